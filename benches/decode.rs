@@ -0,0 +1,138 @@
+//! Decoding, encoding, signing and verification of payment requests: the spec vector, payment
+//! requests with route hints, whose tagged fields make the bit conversions the hottest path, and
+//! batches of them. `cargo bench --bench decode`, or `cargo bench --bench decode -- batch` for a
+//! group. <br>
+//! The inline buffers of the default `smallvec` feature are measured against the heap ones with
+//! `cargo bench --no-default-features --bench decode -- --save-baseline heap` followed by
+//! `cargo bench --bench decode -- --baseline heap`.
+
+#[macro_use]
+extern crate criterion;
+extern crate bolt11;
+extern crate secp256k1;
+
+use bolt11::builder::InvoiceBuilder;
+use bolt11::currency::Currency;
+use bolt11::node_id::NodeId;
+use bolt11::payment_request::PaymentRequest;
+use bolt11::raw::RawInvoice;
+use bolt11::short_channel_id::ShortChannelId;
+use bolt11::tag::ExtraHop;
+use criterion::{black_box, BenchmarkId, Criterion, Throughput};
+use secp256k1::SecretKey;
+
+const TX_REF: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+// Secret key of the payment requests built by the benches.
+fn secret_key() -> SecretKey {
+    SecretKey::parse(&[0x42; 32]).unwrap()
+}
+
+// Payment request with `routes` route hints of `hops` hops each.
+fn route_hints_invoice(routes: u64, hops: u64) -> String {
+    let key = secret_key();
+    let pub_key = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+        .parse::<NodeId>()
+        .unwrap();
+    (0..routes)
+        .fold(
+            InvoiceBuilder::new(Currency::Bitcoin)
+                .timestamp(1496314658)
+                .payment_hash([7u8; 32])
+                .description("bench")
+                .amount_msat(1_000_000),
+            |builder, route| {
+                builder.route_hint(
+                    (0..hops)
+                        .map(|hop| {
+                            let scid = ShortChannelId::from(route * hops + hop + 1);
+                            ExtraHop::new(pub_key, scid, 1000, 100, 40)
+                        })
+                        .collect(),
+                )
+            },
+        )
+        .build_signed_with(|message| secp256k1::sign(message, &key))
+        .unwrap()
+}
+
+// The payment requests of the decode and encode groups, by name.
+fn invoices() -> Vec<(&'static str, String)> {
+    vec![
+        ("spec vector", TX_REF.to_owned()),
+        ("10 route hints", route_hints_invoice(10, 1)),
+        ("20 route hops", route_hints_invoice(5, 4)),
+    ]
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, invoice) in invoices() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &invoice, |b, invoice| {
+            b.iter(|| PaymentRequest::decode(black_box(invoice)).unwrap())
+        });
+    }
+    group.bench_function("raw payment hash and amount", |b| {
+        b.iter(|| {
+            let raw = RawInvoice::parse(black_box(TX_REF)).unwrap();
+            (raw.payment_hash().unwrap().unwrap(), raw.amount().unwrap())
+        })
+    });
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for (name, invoice) in invoices() {
+        let payment_request = PaymentRequest::decode(&invoice).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(name), &payment_request, |b, pr| {
+            b.iter(|| black_box(pr).encode().unwrap())
+        });
+    }
+    let payment_request = PaymentRequest::decode(&route_hints_invoice(5, 4)).unwrap();
+    let mut encoded = String::new();
+    group.bench_function("write 20 route hops to a reused string", |b| {
+        b.iter(|| {
+            encoded.clear();
+            black_box(&payment_request).write_to(&mut encoded).unwrap()
+        })
+    });
+    group.finish();
+}
+
+// Signature work of a decoding, libsecp256k1 keeps its contexts in precomputed statics so
+// nothing is set up per call.
+fn signature(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signature");
+    let payment_request = PaymentRequest::decode(TX_REF).unwrap();
+    let node_id = payment_request.node_id.clone();
+    let key = secret_key();
+    group.bench_function("sign", |b| {
+        b.iter(|| black_box(&payment_request).sign(&key).unwrap())
+    });
+    group.bench_function("recover payee public key", |b| {
+        b.iter(|| black_box(&payment_request).recover_payee_pub_key().unwrap())
+    });
+    group.bench_function("verify", |b| {
+        b.iter(|| black_box(&payment_request).verify_signature(&node_id))
+    });
+    group.finish();
+}
+
+fn batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    for &(size, routes) in &[(100usize, 0u64), (1000, 0), (100, 10)] {
+        let invoice = route_hints_invoice(routes, 1);
+        let invoices = vec![invoice.as_str(); size];
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(
+            BenchmarkId::new(format!("decode_batch {} route hints", routes), size),
+            &invoices,
+            |b, invoices| b.iter(|| PaymentRequest::decode_batch(black_box(invoices))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, encode, signature, batch);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+#![no_main]
+use bolt11::amount::Amount;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(amount) = std::str::from_utf8(data) {
+        if let Ok(msat) = Amount::decode(amount) {
+            assert_eq!(Amount::decode(&Amount::encode(msat)).unwrap(), msat);
+        }
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+use bolt11::tag::ExtraHop;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ExtraHop::parse(data);
+    if let Ok(hops) = ExtraHop::parse_all(data.to_vec()) {
+        let packed = hops
+            .iter()
+            .map(|hop| hop.pack().expect("a parsed hop can be packed"))
+            .collect::<Vec<_>>()
+            .concat();
+        assert_eq!(packed, data);
+    }
+});
@@ -0,0 +1,22 @@
+#![no_main]
+use bolt11::payment_request::PaymentRequest;
+use bolt11::raw::RawInvoice;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = match std::str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+    if let Ok(raw) = RawInvoice::parse(input) {
+        let _ = raw.currency();
+        let _ = raw.amount();
+        let _ = raw.tags();
+        let _ = raw.decode();
+    }
+    let _ = PaymentRequest::decode_lenient(input);
+    if let Ok(payment_request) = PaymentRequest::decode(input) {
+        let encoded = payment_request.encode().expect("a decoded payment request can be encoded");
+        assert_eq!(PaymentRequest::decode(&encoded).unwrap(), payment_request);
+    }
+});
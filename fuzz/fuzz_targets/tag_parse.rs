@@ -0,0 +1,13 @@
+#![no_main]
+use bolt11::tag::Tag;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // values above 31 are rejected, not only the bech32 alphabet is fed in
+    if let Ok(tag) = Tag::parse(data) {
+        let encoded = tag.to_vec_u5().expect("a parsed tag can be encoded");
+        assert_eq!(Tag::parse(&encoded).expect("an encoded tag can be parsed"), tag);
+    }
+    let _ = Tag::parse_all(data);
+    let _ = Tag::parse_all_lenient(data);
+});
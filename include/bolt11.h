@@ -0,0 +1,267 @@
+#ifndef BOLT11_H
+#define BOLT11_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Status of a call, `BOLT11_OK` or the kind of the error.
+ */
+typedef enum Bolt11Status {
+  /**
+   * Success.
+   */
+  BOLT11_OK = 0,
+  /**
+   * Null pointer argument.
+   */
+  BOLT11_NULL_POINTER = 1,
+  /**
+   * Input string that is not UTF-8.
+   */
+  BOLT11_INVALID_UTF8 = 2,
+  /**
+   * Index out of range of an accessor.
+   */
+  BOLT11_OUT_OF_RANGE = 3,
+  /**
+   * Invalid bech32 string or checksum.
+   */
+  BOLT11_INVALID_BECH32 = 10,
+  /**
+   * Invalid payment request structure or length.
+   */
+  BOLT11_INVALID_FORMAT = 11,
+  /**
+   * Invalid amount.
+   */
+  BOLT11_INVALID_AMOUNT = 12,
+  /**
+   * Malformed tagged field.
+   */
+  BOLT11_INVALID_TAG = 13,
+  /**
+   * Invalid or unrecoverable signature.
+   */
+  BOLT11_INVALID_SIGNATURE = 14,
+  /**
+   * Invalid value, e.g. a description containing a nul character.
+   */
+  BOLT11_INVALID_VALUE = 15,
+  /**
+   * Payment request for an unexpected network.
+   */
+  BOLT11_WRONG_NETWORK = 16,
+  /**
+   * Payment request exceeding a size limit.
+   */
+  BOLT11_LIMIT_EXCEEDED = 17,
+  /**
+   * Payment request violating the BOLT #11 rules.
+   */
+  BOLT11_VALIDATION_FAILED = 18,
+  /**
+   * Any other error.
+   */
+  BOLT11_UNKNOWN = 99,
+} Bolt11Status;
+
+/**
+ * Storage of a decoded payment request, opaque to C.
+ */
+typedef struct Bolt11Inner Bolt11Inner;
+
+/**
+ * Decoded payment request.
+ */
+typedef struct Bolt11Invoice {
+  /**
+   * Prefix, e.g. `lnbc`, nul terminated.
+   */
+  const char *prefix;
+  /**
+   * Whether the payment request has an amount.
+   */
+  bool has_amount;
+  /**
+   * Amount in millisatoshis, 0 if missing.
+   */
+  uint64_t amount_msat;
+  /**
+   * Request timestamp (UNIX format).
+   */
+  uint64_t timestamp;
+  /**
+   * Expiry in seconds, 3600 if not specified.
+   */
+  uint64_t expiry;
+  /**
+   * min_final_cltv_expiry, 18 if not specified.
+   */
+  uint64_t min_final_cltv_expiry;
+  /**
+   * Compressed node id of the payee.
+   */
+  uint8_t payee[33];
+  /**
+   * Whether the payment request has a payment hash.
+   */
+  bool has_payment_hash;
+  /**
+   * Payment hash, zeroes if missing.
+   */
+  uint8_t payment_hash[32];
+  /**
+   * Description, nul terminated, null if missing.
+   */
+  const char *description;
+  /**
+   * Whether the payment request has a description hash.
+   */
+  bool has_description_hash;
+  /**
+   * Description hash, zeroes if missing.
+   */
+  uint8_t description_hash[32];
+  /**
+   * Signature, 64 bytes followed by the recovery id.
+   */
+  uint8_t signature[65];
+  /**
+   * Number of private routes, see `bolt11_route_hop`.
+   */
+  size_t routes_count;
+  /**
+   * Number of fallback addresses, see `bolt11_fallback`.
+   */
+  size_t fallbacks_count;
+  /**
+   * Storage of the library, not to be used.
+   */
+  struct Bolt11Inner *inner;
+} Bolt11Invoice;
+
+/**
+ * Hop of a private route.
+ */
+typedef struct Bolt11Hop {
+  /**
+   * Compressed node id of the start of the channel.
+   */
+  uint8_t node_id[33];
+  /**
+   * Short channel id as a 64-bit integer.
+   */
+  uint64_t short_channel_id;
+  /**
+   * Base fee in millisatoshis.
+   */
+  uint32_t fee_base_msat;
+  /**
+   * Proportional fee in millionths.
+   */
+  uint32_t fee_proportional_millionths;
+  /**
+   * Channel's cltv expiry delta.
+   */
+  uint16_t cltv_expiry_delta;
+} Bolt11Hop;
+
+/**
+ * Fallback address as a witness version and program, the program is owned by the payment
+ * request.
+ */
+typedef struct Bolt11Fallback {
+  /**
+   * Witness version, 17 for P2PKH and 18 for P2SH.
+   */
+  uint8_t version;
+  /**
+   * Witness program or hash.
+   */
+  const uint8_t *program;
+  /**
+   * Length of the program.
+   */
+  size_t program_len;
+} Bolt11Fallback;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Decode a payment request. <br>
+ * *Note*: a panic of the decoding is caught and returns `BOLT11_UNKNOWN`, it doesn't unwind
+ * into C.
+ * # Arguments
+ * `invoice` Nul terminated payment request, with or without the `lightning:` scheme.<br>
+ * `out` Set to the decoded payment request on success, to be released with `bolt11_free`.
+ * # Safety
+ * `invoice` must be a nul terminated string and `out` a valid pointer, or null.
+ */
+enum Bolt11Status bolt11_decode(const char *invoice, struct Bolt11Invoice **out);
+
+/**
+ * Release a payment request returned by `bolt11_decode`, does nothing if it's null.
+ * # Safety
+ * `invoice` must come from `bolt11_decode` and not be used afterwards.
+ */
+void bolt11_free(struct Bolt11Invoice *invoice);
+
+/**
+ * Number of hops of a private route, 0 if the route doesn't exist.
+ * # Arguments
+ * `invoice` Decoded payment request.<br>
+ * `route` Index of the route, below `routes_count`.
+ * # Safety
+ * `invoice` must come from `bolt11_decode`, or be null.
+ */
+size_t bolt11_route_hops_count(const struct Bolt11Invoice *invoice, size_t route);
+
+/**
+ * Read a hop of a private route.
+ * # Arguments
+ * `invoice` Decoded payment request.<br>
+ * `route` Index of the route, below `routes_count`.<br>
+ * `hop` Index of the hop, below `bolt11_route_hops_count`.<br>
+ * `out` Set to the hop on success.
+ * # Safety
+ * `invoice` must come from `bolt11_decode` and `out` be a valid pointer, or null.
+ */
+enum Bolt11Status bolt11_route_hop(const struct Bolt11Invoice *invoice,
+                                   size_t route,
+                                   size_t hop,
+                                   struct Bolt11Hop *out);
+
+/**
+ * Read a fallback address.
+ * # Arguments
+ * `invoice` Decoded payment request.<br>
+ * `index` Index of the fallback address, below `fallbacks_count`.<br>
+ * `out` Set to the fallback address on success.
+ * # Safety
+ * `invoice` must come from `bolt11_decode` and `out` be a valid pointer, or null.
+ */
+enum Bolt11Status bolt11_fallback(const struct Bolt11Invoice *invoice,
+                                  size_t index,
+                                  struct Bolt11Fallback *out);
+
+/**
+ * Static, nul terminated, description of a status, the one of `BOLT11_UNKNOWN` for a code which
+ * isn't a status.
+ * # Arguments
+ * `status` Status code, e.g. returned by `bolt11_decode`.
+ */
+const char *bolt11_status_message(int status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BOLT11_H */
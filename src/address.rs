@@ -0,0 +1,373 @@
+//! Conversion of the fallback on-chain addresses between the `f` field, a version followed by a
+//! hash, and the address strings of the Bitcoin networks.
+
+use base58check::{FromBase58Check, ToBase58Check};
+use bech32::{Bech32, Variant};
+use currency::Currency;
+use types::Error;
+use utils::{Padding, U5Conversions, U8Conversions};
+#[cfg(feature = "bitcoin")]
+use bitcoin;
+
+/// Version of the `f` field holding a P2PKH address.
+pub const P2PKH_VERSION: u8 = 17;
+
+/// Version of the `f` field holding a P2SH address.
+pub const P2SH_VERSION: u8 = 18;
+
+/// Highest segwit witness version, the witness versions are the `f` field versions 0 to 16.
+pub const MAX_WITNESS_VERSION: u8 = 16;
+
+/// Check the length of a segwit witness program: 20 (P2WPKH) or 32 (P2WSH) bytes for the
+/// witness version 0, 2 to 40 bytes for the later ones (BIP-0141).
+///
+/// # Arguments
+/// `version` the witness version, 0 to 16.
+/// `program` the witness program.
+pub fn check_witness_program(version: u8, program: &[u8]) -> Result<(), Error> {
+    match (version, program.len()) {
+        (0, 20) | (0, 32) => Ok(()),
+        (0, len) => Err(Error::InvalidLength(format!(
+            "segwit v0 witness program must be 20 or 32 bytes, got {}",
+            len
+        ))),
+        (1..=MAX_WITNESS_VERSION, 2..=40) => Ok(()),
+        (1..=MAX_WITNESS_VERSION, len) => Err(Error::InvalidLength(format!(
+            "segwit v{} witness program must be 2 to 40 bytes, got {}",
+            version, len
+        ))),
+        _ => Err(Error::InvalidValue(format!(
+            "invalid witness version {}",
+            version
+        ))),
+    }
+}
+
+/// Check the length of the hash of a fallback address against its version: 20 bytes for
+/// P2PKH and P2SH, a valid witness program for the segwit versions.
+///
+/// # Arguments
+/// `version` the `f` field version.
+/// `hash` the address hash, or witness program.
+pub fn check_fallback_address(version: u8, hash: &[u8]) -> Result<(), Error> {
+    match version {
+        P2PKH_VERSION | P2SH_VERSION if hash.len() == 20 => Ok(()),
+        P2PKH_VERSION | P2SH_VERSION => Err(Error::InvalidLength(format!(
+            "P2PKH and P2SH address hash must be 20 bytes, got {}",
+            hash.len()
+        ))),
+        _ => check_witness_program(version, hash),
+    }
+}
+
+/// Return the output script paying to a fallback address, its scriptPubKey.
+///
+/// # Arguments
+/// `version` the `f` field version.
+/// `hash` the address hash, or witness program.
+pub fn script_pubkey(version: u8, hash: &[u8]) -> Result<Vec<u8>, Error> {
+    check_fallback_address(version, hash)?;
+    Ok(match version {
+        // OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+        P2PKH_VERSION => [&[0x76, 0xa9, 0x14][..], hash, &[0x88, 0xac]].concat(),
+        // OP_HASH160 <hash> OP_EQUAL
+        P2SH_VERSION => [&[0xa9, 0x14][..], hash, &[0x87]].concat(),
+        // OP_0 or OP_1 to OP_16, followed by the witness program
+        0 => [&[0x00, hash.len() as u8][..], hash].concat(),
+        _ => [&[0x50 + version, hash.len() as u8][..], hash].concat(),
+    })
+}
+
+/// Address parameters of a Bitcoin network.
+struct Network {
+    /// Base58check prefix of the P2PKH addresses.
+    p2pkh_prefix: u8,
+    /// Base58check prefix of the P2SH addresses.
+    p2sh_prefix: u8,
+    /// Human readable part of the segwit addresses.
+    bech32_hrp: &'static str,
+}
+
+impl Network {
+    fn from_currency(currency: &Currency) -> Result<Network, Error> {
+        match *currency {
+            Currency::Bitcoin => Ok(Network {
+                p2pkh_prefix: 0,
+                p2sh_prefix: 5,
+                bech32_hrp: "bc",
+            }),
+            Currency::Testnet | Currency::Signet => Ok(Network {
+                p2pkh_prefix: 111,
+                p2sh_prefix: 196,
+                bech32_hrp: "tb",
+            }),
+            Currency::Regtest => Ok(Network {
+                p2pkh_prefix: 111,
+                p2sh_prefix: 196,
+                bech32_hrp: "bcrt",
+            }),
+            Currency::Custom(prefix) => Err(Error::InvalidValue(format!(
+                "unknown address format for prefix {}",
+                prefix
+            ))),
+        }
+    }
+}
+
+/// Return the checksum variant of the segwit addresses of the witness version, bech32 for the
+/// version 0 and bech32m for the later ones (BIP-0350).
+fn witness_variant(version: u8) -> Variant {
+    match version {
+        0 => Variant::Bech32,
+        _ => Variant::Bech32m,
+    }
+}
+
+/// Convert a fallback address to the address string of the network.
+///
+/// # Arguments
+/// `version` the `f` field version; valid values are: <br>
+///               - 17 (P2PKH) <br>
+///               - 18 (P2SH) <br>
+///               - 0 to 16 (segwit witness version) <br>
+/// `hash` the address hash, or witness program.
+/// `currency` the network of the address.
+pub fn to_address(version: u8, hash: &[u8], currency: &Currency) -> Result<String, Error> {
+    let network = Network::from_currency(currency)?;
+    check_fallback_address(version, hash)?;
+    match version {
+        P2PKH_VERSION => Ok(hash.to_base58check(network.p2pkh_prefix)),
+        P2SH_VERSION => Ok(hash.to_base58check(network.p2sh_prefix)),
+        _ => Bech32 {
+            hrp: network.bech32_hrp.to_owned(),
+            data: [vec![version], hash.to_vec().to_u5_vec(Padding::Add)?].concat(),
+        }.to_string_variant(witness_variant(version))
+            .map_err(Error::from),
+    }
+}
+
+/// Convert an address string of the network to a fallback address, returning its `f` field
+/// version and hash.
+///
+/// # Arguments
+/// `address` base58check P2PKH or P2SH address, or bech32/bech32m segwit address.
+/// `currency` the network the address must belong to.
+pub fn from_address(address: &str, currency: &Currency) -> Result<(u8, Vec<u8>), Error> {
+    let network = Network::from_currency(currency)?;
+    if let Ok((prefix, hash)) = address.from_base58check() {
+        let version = match prefix {
+            p if p == network.p2pkh_prefix => P2PKH_VERSION,
+            p if p == network.p2sh_prefix => P2SH_VERSION,
+            _ => return Err(Error::WrongNetwork(format!(
+                "address {} is not an address of prefix {}",
+                address,
+                currency.prefix()
+            ))),
+        };
+        check_fallback_address(version, &hash)?;
+        return Ok((version, hash));
+    }
+
+    let (Bech32 { hrp, data }, variant) = Bech32::from_string_variant(address.to_owned())?;
+    if hrp != network.bech32_hrp {
+        return Err(Error::WrongNetwork(format!(
+            "address {} is not an address of prefix {}",
+            address,
+            currency.prefix()
+        )));
+    }
+    let (&version, program) = data.split_first()
+        .ok_or_else(|| Error::InvalidLength("empty segwit address".to_owned()))?;
+    if version > MAX_WITNESS_VERSION {
+        return Err(Error::InvalidValue(format!(
+            "invalid witness version {}",
+            version
+        )));
+    }
+    if variant != witness_variant(version) {
+        return Err(Error::InvalidFormat(format!(
+            "segwit v{} address with a {:?} checksum",
+            version, variant
+        )));
+    }
+    let program = program.to_vec().to_u8_vec(Padding::Strict)?;
+    check_witness_program(version, &program)?;
+    Ok((version, program))
+}
+
+/// Return the `bitcoin` network of the currency.
+#[cfg(feature = "bitcoin")]
+fn bitcoin_network(currency: &Currency) -> Result<bitcoin::Network, Error> {
+    match *currency {
+        Currency::Bitcoin => Ok(bitcoin::Network::Bitcoin),
+        Currency::Testnet => Ok(bitcoin::Network::Testnet),
+        Currency::Signet => Ok(bitcoin::Network::Signet),
+        Currency::Regtest => Ok(bitcoin::Network::Regtest),
+        Currency::Custom(prefix) => Err(Error::InvalidValue(format!(
+            "unknown address format for prefix {}",
+            prefix
+        ))),
+    }
+}
+
+/// Convert a fallback address to a `bitcoin` address of the network.
+///
+/// # Arguments
+/// `version` the `f` field version.
+/// `hash` the address hash, or witness program.
+/// `currency` the network of the address.
+#[cfg(feature = "bitcoin")]
+pub fn to_bitcoin_address(
+    version: u8,
+    hash: &[u8],
+    currency: &Currency,
+) -> Result<bitcoin::Address, Error> {
+    let network = bitcoin_network(currency)?;
+    to_address(version, hash, currency)?
+        .parse::<bitcoin::Address<bitcoin::address::NetworkUnchecked>>()
+        .map_err(|e| Error::InvalidValue(e.to_string()))?
+        .require_network(network)
+        .map_err(|e| Error::WrongNetwork(e.to_string()))
+}
+
+/// Convert a `bitcoin` address to a fallback address, returning its `f` field version and hash.
+///
+/// # Arguments
+/// `address` the `bitcoin` address.
+/// `currency` the network the address must belong to.
+#[cfg(feature = "bitcoin")]
+pub fn from_bitcoin_address(
+    address: &bitcoin::Address,
+    currency: &Currency,
+) -> Result<(u8, Vec<u8>), Error> {
+    from_address(&address.to_string(), currency)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::{from_hex, to_hex};
+
+    #[test]
+    fn base58_addresses() {
+        let hash = from_hex("04b61f7dc1ea0dc99424464cc4064dc564d91e89").unwrap();
+
+        assert_eq!(
+            to_address(P2PKH_VERSION, &hash, &Currency::Bitcoin).unwrap(),
+            "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T"
+        );
+        assert_eq!(
+            from_address("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T", &Currency::Bitcoin).unwrap(),
+            (P2PKH_VERSION, hash.to_owned())
+        );
+        let testnet = to_address(P2SH_VERSION, &hash, &Currency::Testnet).unwrap();
+        assert_eq!(
+            from_address(&testnet, &Currency::Signet).unwrap(),
+            (P2SH_VERSION, hash.to_owned())
+        );
+        assert!(matches!(
+            from_address(&testnet, &Currency::Bitcoin),
+            Err(Error::WrongNetwork(_))
+        ));
+        assert!(to_address(P2PKH_VERSION, &hash, &Currency::Custom("lnltc")).is_err());
+        assert!(to_address(19, &hash, &Currency::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn witness_program_length() {
+        assert!(check_witness_program(0, &[0u8; 20]).is_ok());
+        assert!(check_witness_program(0, &[0u8; 32]).is_ok());
+        assert!(check_witness_program(0, &[0u8; 21]).is_err());
+        assert!(check_witness_program(1, &[0u8; 2]).is_ok());
+        assert!(check_witness_program(16, &[0u8; 40]).is_ok());
+        assert!(check_witness_program(1, &[0u8; 1]).is_err());
+        assert!(check_witness_program(1, &[0u8; 41]).is_err());
+        assert!(check_witness_program(17, &[0u8; 20]).is_err());
+        assert!(to_address(1, &[0u8; 41], &Currency::Bitcoin).is_err());
+        assert!(check_fallback_address(P2SH_VERSION, &[0u8; 20]).is_ok());
+        assert!(check_fallback_address(P2PKH_VERSION, &[0u8; 32]).is_err());
+        assert!(to_address(P2PKH_VERSION, &[0u8; 19], &Currency::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn segwit_addresses() {
+        // BIP-0173 and BIP-0350 test vectors
+        let vectors = vec![
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                Currency::Bitcoin,
+                0,
+                "751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                Currency::Testnet,
+                0,
+                "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                Currency::Bitcoin,
+                1,
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+            ("bc1sw50qgdz25j", Currency::Bitcoin, 16, "751e"),
+            (
+                "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+                Currency::Regtest,
+                0,
+                "751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+        ];
+        for (address, currency, version, hash) in vectors {
+            let hash = from_hex(hash).unwrap();
+
+            assert_eq!(to_address(version, &hash, &currency).unwrap(), address);
+            assert_eq!(
+                from_address(address, &currency).unwrap(),
+                (version, hash.to_owned())
+            );
+        }
+
+        assert!(matches!(
+            from_address(
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+                &Currency::Testnet
+            ),
+            Err(Error::WrongNetwork(_))
+        ));
+        // checksum variant not matching the witness version
+        for &(version, variant) in &[(0, Variant::Bech32m), (1, Variant::Bech32)] {
+            let address = Bech32 {
+                hrp: "bc".to_owned(),
+                data: [vec![version], [0u8; 32].to_u5_vec(Padding::Add).unwrap()].concat(),
+            }.to_string_variant(variant)
+                .unwrap();
+
+            assert!(matches!(
+                from_address(&address, &Currency::Bitcoin),
+                Err(Error::InvalidFormat(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn script_pubkeys() {
+        let hash = from_hex("04b61f7dc1ea0dc99424464cc4064dc564d91e89").unwrap();
+
+        assert_eq!(
+            to_hex(&script_pubkey(P2PKH_VERSION, &hash).unwrap()),
+            "76a91404b61f7dc1ea0dc99424464cc4064dc564d91e8988ac"
+        );
+        assert_eq!(
+            to_hex(&script_pubkey(P2SH_VERSION, &hash).unwrap()),
+            "a91404b61f7dc1ea0dc99424464cc4064dc564d91e8987"
+        );
+        assert_eq!(
+            to_hex(&script_pubkey(0, &hash).unwrap()),
+            "001404b61f7dc1ea0dc99424464cc4064dc564d91e89"
+        );
+        assert_eq!(script_pubkey(1, &[0u8; 32]).unwrap()[..2], [0x51, 0x20]);
+        assert!(script_pubkey(0, &[0u8; 21]).is_err());
+    }
+}
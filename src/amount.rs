@@ -1,4 +1,14 @@
-use types::Error;
+//! Payment request amounts.
+
+use types::{AmountError, Error};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Maximum amount in millisatoshis, the 21 million bitcoin supply.
+pub const MAX_AMOUNT_MSAT: u64 = 2_100_000_000_000_000_000;
+
+/// Amount in millisatoshis.
+///
 /// Bitcoin subunits
 /// The following **multiplier** letters are defined:
 ///
@@ -7,19 +17,157 @@ use types::Error;
 /// 'n' (nano): multiply by 0.000000001
 /// 'p' (pico): multiply by 0.000000000001
 ///
-pub struct Amount;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Amount {
+    msat: u64,
+}
+
+/// Units an amount can be expressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// Bitcoin, 10^11 millisatoshis.
+    Btc,
+    /// Satoshi, 1000 millisatoshis.
+    Sat,
+    /// Millisatoshi.
+    MilliSat,
+}
+
+impl Unit {
+    /// Number of decimals of the unit in millisatoshis.
+    fn decimals(&self) -> usize {
+        match *self {
+            Unit::Btc => 11,
+            Unit::Sat => 3,
+            Unit::MilliSat => 0,
+        }
+    }
+
+    /// Symbol of the unit.
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            Unit::Btc => "BTC",
+            Unit::Sat => "sat",
+            Unit::MilliSat => "msat",
+        }
+    }
+}
 
 impl Amount {
-    /// the unit allowing for the shortest representation possible
-    fn unit(amount: u64) -> char {
-        match amount * 10 {
-            pico if pico % 1000 > 0 => 'p',
-            pico if pico % 1000_000 > 0 => 'n',
-            pico if pico % 1000_000_000 > 0 => 'u',
-            _ => 'm',
+    /// Create an amount from millisatoshis.
+    pub fn from_msat(msat: u64) -> Amount {
+        Amount { msat }
+    }
+
+    /// Create an amount from satoshis, `None` if it overflows.
+    pub fn from_sat(sat: u64) -> Option<Amount> {
+        sat.checked_mul(1000).map(Amount::from_msat)
+    }
+
+    /// Create an amount from bitcoins, `None` if it overflows.
+    pub fn from_btc(btc: u64) -> Option<Amount> {
+        btc.checked_mul(100_000_000_000).map(Amount::from_msat)
+    }
+
+    /// Return the amount in millisatoshis.
+    pub fn msat(&self) -> u64 {
+        self.msat
+    }
+
+    /// Return the amount in satoshis, rounded down.
+    pub fn sat(&self) -> u64 {
+        self.msat / 1000
+    }
+
+    /// Return the amount in bitcoins, rounded down.
+    pub fn btc(&self) -> u64 {
+        self.msat / 100_000_000_000
+    }
+
+    /// Add the amounts, `None` if it overflows.
+    pub fn checked_add(&self, other: Amount) -> Option<Amount> {
+        self.msat.checked_add(other.msat).map(Amount::from_msat)
+    }
+
+    /// Subtract the amounts, `None` if it is negative.
+    pub fn checked_sub(&self, other: Amount) -> Option<Amount> {
+        self.msat.checked_sub(other.msat).map(Amount::from_msat)
+    }
+
+    /// Multiply the amount, `None` if it overflows.
+    pub fn checked_mul(&self, factor: u64) -> Option<Amount> {
+        self.msat.checked_mul(factor).map(Amount::from_msat)
+    }
+
+    /// Return a value displaying the exact amount in the unit, followed by its symbol,
+    /// e.g. `0.0025 BTC`.
+    /// # Arguments
+    /// `unit` Unit to display the amount in.
+    pub fn display(&self, unit: Unit) -> AmountDisplay {
+        AmountDisplay {
+            amount: *self,
+            unit,
         }
     }
 
+    /// Return the amount in bitcoin as a plain decimal string, without exponent, symbol nor
+    /// trailing zeroes, e.g. `0.000123` as in the BIP-21 URIs.
+    pub fn to_plain_btc_string(&self) -> String {
+        Amount::to_decimal_string(self.msat, Unit::Btc.decimals())
+    }
+
+    /// Check that the amount in millisatoshis doesn't exceed the bitcoin supply.
+    /// # Arguments
+    /// `amount` Amount in millisatoshis.
+    pub fn check_supply(amount: u64) -> Result<u64, Error> {
+        if amount <= MAX_AMOUNT_MSAT {
+            Ok(amount)
+        } else {
+            Err(AmountError::ExceedsSupply(amount).into())
+        }
+    }
+
+    /// Format the millisatoshis as a plain decimal number with the given decimals, without
+    /// trailing zeroes.
+    fn to_decimal_string(amount: u64, decimals: usize) -> String {
+        let scale = 10u64.pow(decimals as u32);
+        let fraction = format!("{:0width$}", amount % scale, width = decimals);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            (amount / scale).to_string()
+        } else {
+            format!("{}.{}", amount / scale, fraction)
+        }
+    }
+}
+
+/// Helper displaying an amount in a given unit, created by `Amount::display`.
+#[derive(Debug, Clone, Copy)]
+pub struct AmountDisplay {
+    amount: Amount,
+    unit: Unit,
+}
+
+impl fmt::Display for AmountDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = Amount::to_decimal_string(self.amount.msat, self.unit.decimals());
+        write!(f, "{} {}", value, self.unit.symbol())
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.display(Unit::MilliSat))
+    }
+}
+
+impl From<MilliSatoshi> for Amount {
+    fn from(amount: MilliSatoshi) -> Amount {
+        Amount::from_msat(amount.0)
+    }
+}
+
+impl Amount {
     /// Given an encoded amount, convert it into millisatoshis
     /// BOLT #11:
     /// A reader SHOULD fail if `amount` contains a non-digit, or is followed by
@@ -27,17 +175,7 @@ impl Amount {
     /// # Arguments
     /// * `amount` - A string that holds the amount to shorten
     pub fn decode(amount: &str) -> Result<u64, Error> {
-        match amount.chars().last() {
-            Some(a) if a == 'p' => amount[..amount.len() - 1].parse::<u64>().map(|v| v / 10),
-            Some(a) if a == 'n' => amount[..amount.len() - 1].parse::<u64>().map(|v| v * 100),
-            Some(a) if a == 'u' => amount[..amount.len() - 1]
-                .parse::<u64>()
-                .map(|v| v * 100_000),
-            Some(a) if a == 'm' => amount[..amount.len() - 1]
-                .parse::<u64>()
-                .map(|v| v * 100_000_000),
-            _ => amount.parse::<u64>(),
-        }.map_err(Error::ParseIntErr)
+        MilliSatoshi::from_bolt11(amount).map(|amount| amount.0)
     }
 
     /// Given an amount in Bitcoin, shorten it
@@ -46,13 +184,159 @@ impl Amount {
     /// A writer MUST encode `amount` as a positive decimal integer with no
     /// leading zeroes, SHOULD use the shortest representation possible.
     pub fn encode(amount: u64) -> String {
-        match amount {
-            amt if Amount::unit(amt) == 'p' => format!("{}p", amt * 10),
-            amt if Amount::unit(amt) == 'n' => format!("{}n", amt / 100),
-            amt if Amount::unit(amt) == 'u' => format!("{}u", amt / 100_000),
-            amt if Amount::unit(amt) == 'm' => format!("{}m", amt / 100_000_000),
-            amt => amt.to_string(),
+        MilliSatoshi(amount).to_bolt11()
+    }
+
+    /// Parse an amount in bitcoin written as a plain decimal number, e.g. `0.000123` as in the
+    /// BIP-21 URIs, and return it in millisatoshis.
+    /// # Arguments
+    /// * `amount` - Decimal amount in bitcoin, with at most 11 decimals.
+    pub fn from_plain_btc_string(amount: &str) -> Result<u64, Error> {
+        let decimals = Unit::Btc.decimals();
+        let (integer, fraction) = match amount.find('.') {
+            Some(index) => (&amount[..index], &amount[index + 1..]),
+            None => (amount, ""),
+        };
+        let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if integer.is_empty() || !digits(integer) || !digits(fraction) {
+            return Err(AmountError::InvalidFormat(amount.to_owned()).into());
+        }
+        if fraction.len() > decimals {
+            return Err(AmountError::SubMillisatoshiPrecision.into());
         }
+        let msat = format!("{}{:0<width$}", integer, fraction, width = decimals)
+            .parse::<u64>()
+            .map_err(|_| AmountError::TooLarge(amount.to_owned()))?;
+        Amount::check_supply(msat)
+    }
+}
+
+/// Exact amount in millisatoshis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MilliSatoshi(pub u64);
+
+impl MilliSatoshi {
+    /// Parse an encoded amount, an integer followed by an optional multiplier, in bitcoin if
+    /// there is none.
+    /// # Arguments
+    /// * `amount` - Encoded amount, e.g. `2500u`.
+    pub fn from_bolt11(amount: &str) -> Result<MilliSatoshi, Error> {
+        let (value, multiplier) = match amount.chars().last() {
+            Some(unit) if !unit.is_ascii_digit() => (
+                &amount[..amount.len() - unit.len_utf8()],
+                Some(Multiplier::try_from(unit)?),
+            ),
+            _ => (amount, None),
+        };
+        // a positive decimal integer with no leading zeroes, `parse` would accept a sign
+        match value.chars().next() {
+            Some(first) if first != '0' && value.chars().all(|c| c.is_ascii_digit()) => (),
+            _ => return Err(AmountError::InvalidFormat(amount.to_owned()).into()),
+        }
+        // pico-bitcoin amounts may not fit in a u64
+        let value = value
+            .parse::<u128>()
+            .map_err(|_| AmountError::TooLarge(amount.to_owned()))?;
+        let pico_btc = value.checked_mul(multiplier.map_or(PICO_BTC_PER_BTC, |m| m.pico_btc()));
+        match pico_btc {
+            // 1 pico-bitcoin is a tenth of millisatoshi
+            Some(pico_btc) if pico_btc % 10 != 0 => Err(AmountError::SubMillisatoshiPrecision.into()),
+            Some(pico_btc) if pico_btc / 10 <= u128::from(u64::MAX) => {
+                Amount::check_supply((pico_btc / 10) as u64).map(MilliSatoshi)
+            }
+            _ => Err(AmountError::TooLarge(amount.to_owned()).into()),
+        }
+    }
+
+    /// Encode the amount with the shortest representation possible.
+    pub fn to_bolt11(&self) -> String {
+        let mut encoded = String::new();
+        self.write_bolt11(&mut encoded).expect("writing to a String can't fail");
+        encoded
+    }
+
+    /// Write the amount with the shortest representation possible, without allocating.
+    /// # Arguments
+    /// `writer` sink of the encoded amount, e.g. a `fmt::Formatter`.
+    pub fn write_bolt11<W: fmt::Write>(&self, writer: &mut W) -> fmt::Result {
+        let pico_btc = u128::from(self.0) * 10;
+        // whole bitcoins are shortest without multiplier
+        if pico_btc != 0 && pico_btc % PICO_BTC_PER_BTC == 0 {
+            return write!(writer, "{}", pico_btc / PICO_BTC_PER_BTC);
+        }
+        // the largest multiplier dividing the amount
+        let multiplier = Multiplier::all()
+            .iter()
+            .find(|multiplier| pico_btc % multiplier.pico_btc() == 0)
+            .cloned()
+            .unwrap_or(Multiplier::Pico);
+        write!(writer, "{}{}", pico_btc / multiplier.pico_btc(), multiplier.to_char())
+    }
+}
+
+/// Number of pico-bitcoins in a bitcoin, the unit of amounts without multiplier.
+const PICO_BTC_PER_BTC: u128 = 1_000_000_000_000;
+
+/// Multiplier letter following an encoded amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplier {
+    /// `m` (milli): multiply by 0.001
+    Milli,
+    /// `u` (micro): multiply by 0.000001
+    Micro,
+    /// `n` (nano): multiply by 0.000000001
+    Nano,
+    /// `p` (pico): multiply by 0.000000000001
+    Pico,
+}
+
+impl Multiplier {
+    /// Return all the multipliers, from the largest to the smallest.
+    pub fn all() -> &'static [Multiplier] {
+        &[
+            Multiplier::Milli,
+            Multiplier::Micro,
+            Multiplier::Nano,
+            Multiplier::Pico,
+        ]
+    }
+
+    /// Return the letter of the multiplier.
+    pub fn to_char(&self) -> char {
+        match *self {
+            Multiplier::Milli => 'm',
+            Multiplier::Micro => 'u',
+            Multiplier::Nano => 'n',
+            Multiplier::Pico => 'p',
+        }
+    }
+
+    /// Return the number of pico-bitcoins the multiplier stands for.
+    pub fn pico_btc(&self) -> u128 {
+        match *self {
+            Multiplier::Milli => 1_000_000_000,
+            Multiplier::Micro => 1_000_000,
+            Multiplier::Nano => 1_000,
+            Multiplier::Pico => 1,
+        }
+    }
+}
+
+impl TryFrom<char> for Multiplier {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Multiplier, Error> {
+        Multiplier::all()
+            .iter()
+            .find(|multiplier| multiplier.to_char() == c)
+            .cloned()
+            .ok_or(Error::AmountError(AmountError::UnknownMultiplier(c)))
+    }
+}
+
+impl From<u64> for MilliSatoshi {
+    fn from(amount: u64) -> MilliSatoshi {
+        MilliSatoshi(amount)
     }
 }
 
@@ -70,11 +354,16 @@ mod test {
         assert_eq!(Some('n'), Amount::encode(1000).chars().last());
         assert_eq!(Some('u'), Amount::encode(100_000).chars().last());
         assert_eq!(Some('n'), Amount::encode(101_000).chars().last());
-        assert_eq!(Some('u'), Amount::encode(1155_400_000).chars().last());
+        assert_eq!(Some('u'), Amount::encode(1_155_400_000).chars().last());
 
         assert_eq!(Some('m'), Amount::encode(100_000_000).chars().last());
-        assert_eq!(Some('m'), Amount::encode(1000_000_000).chars().last());
-        assert_eq!(Some('m'), Amount::encode(100_000_000_000).chars().last());
+        assert_eq!(Some('m'), Amount::encode(1_000_000_000).chars().last());
+        assert_eq!(Some('m'), Amount::encode(10_000_000_000).chars().last());
+        assert_eq!(Some('m'), Amount::encode(100_100_000_000).chars().last());
+
+        assert_eq!("1", Amount::encode(100_000_000_000));
+        assert_eq!("20", Amount::encode(2_000_000_000_000));
+        assert_eq!(Amount::decode("20").unwrap(), 2_000_000_000_000);
     }
     #[test]
     fn decode() {
@@ -83,4 +372,151 @@ mod test {
         assert_eq!(100_000_000u64, Amount::decode("1000000n").unwrap());
         assert_eq!(100_000_000u64, Amount::decode("1000000000p").unwrap());
     }
+
+    #[test]
+    fn millisatoshi() {
+        let max = MilliSatoshi(MAX_AMOUNT_MSAT);
+
+        assert_eq!(max.to_bolt11(), "21000000");
+        assert_eq!(MilliSatoshi::from_bolt11(&max.to_bolt11()).unwrap(), max);
+        assert_eq!(
+            MilliSatoshi(u64::MAX).to_bolt11(),
+            "184467440737095516150p"
+        );
+        assert_eq!(
+            MilliSatoshi::from_bolt11("2100000000m").unwrap(),
+            MilliSatoshi(210_000_000_000_000_000)
+        );
+        assert_eq!(
+            MilliSatoshi::from_bolt11("20").unwrap(),
+            MilliSatoshi(2_000_000_000_000)
+        );
+        assert_eq!(MilliSatoshi::from(2_500_000_000).to_bolt11(), "25m");
+        assert!(MilliSatoshi::from_bolt11("184467440737095516160p").is_err());
+        assert!(MilliSatoshi::from_bolt11("200000000000m").is_err());
+        assert!(MilliSatoshi::from_bolt11("1x").is_err());
+        assert!(MilliSatoshi::from_bolt11("m").is_err());
+    }
+
+    #[test]
+    fn multiplier() {
+        for multiplier in Multiplier::all() {
+            assert_eq!(Multiplier::try_from(multiplier.to_char()).unwrap(), *multiplier);
+        }
+        assert_eq!(Multiplier::Milli.pico_btc(), 1000 * Multiplier::Micro.pico_btc());
+        assert!(Multiplier::try_from('x').is_err());
+        assert!(Multiplier::try_from('M').is_err());
+        assert!(Amount::decode("1x").is_err());
+        assert!(Amount::decode("1é").is_err());
+    }
+
+    #[test]
+    fn strict_decode() {
+        for amount in &[
+            "0123m", "0m", "0", "+1m", "-1m", "1.5m", "1e3m", "1x", "1mm", "m", "", " 1m", "1 m",
+        ] {
+            assert!(Amount::decode(amount).is_err(), "{}", amount);
+        }
+        assert_eq!(Amount::decode("10m").unwrap(), 1_000_000_000);
+    }
+
+    #[test]
+    fn bitcoin_supply() {
+        assert_eq!(Amount::check_supply(MAX_AMOUNT_MSAT).unwrap(), MAX_AMOUNT_MSAT);
+        assert!(matches!(
+            Amount::check_supply(MAX_AMOUNT_MSAT + 1),
+            Err(Error::AmountError(AmountError::ExceedsSupply(_)))
+        ));
+        assert!(matches!(
+            Amount::decode("21000000001m"),
+            Err(Error::AmountError(AmountError::ExceedsSupply(2_100_000_000_100_000_000)))
+        ));
+        assert!(matches!(
+            Amount::decode("184467440737095516150p"),
+            Err(Error::AmountError(AmountError::ExceedsSupply(_)))
+        ));
+    }
+
+    #[test]
+    fn sub_millisatoshi_precision() {
+        assert_eq!(Amount::decode("2500000000p").unwrap(), 250_000_000);
+        assert!(matches!(
+            Amount::decode("2500000001p"),
+            Err(Error::AmountError(AmountError::SubMillisatoshiPrecision))
+        ));
+        assert!(matches!(
+            MilliSatoshi::from_bolt11("1p"),
+            Err(Error::AmountError(AmountError::SubMillisatoshiPrecision))
+        ));
+    }
+
+    #[test]
+    fn amount_units() {
+        let amount = Amount::from_sat(250_000).unwrap();
+
+        assert_eq!(amount.msat(), 250_000_000);
+        assert_eq!(amount.sat(), 250_000);
+        assert_eq!(amount.btc(), 0);
+        assert_eq!(Amount::from_btc(21).unwrap().sat(), 2_100_000_000);
+        assert_eq!(Amount::from_msat(1500).sat(), 1);
+        assert_eq!(Amount::from_sat(u64::MAX), None);
+        assert_eq!(Amount::from_btc(u64::MAX), None);
+        assert_eq!(Amount::from(MilliSatoshi(42)), Amount::from_msat(42));
+    }
+
+    #[test]
+    fn amount_checked_arithmetic() {
+        let one = Amount::from_msat(1);
+        let max = Amount::from_msat(u64::MAX);
+
+        assert_eq!(one.checked_add(one), Some(Amount::from_msat(2)));
+        assert_eq!(max.checked_add(one), None);
+        assert_eq!(one.checked_sub(one), Some(Amount::from_msat(0)));
+        assert_eq!(one.checked_sub(max), None);
+        assert_eq!(one.checked_mul(3), Some(Amount::from_msat(3)));
+        assert_eq!(max.checked_mul(2), None);
+    }
+
+    #[test]
+    fn amount_display() {
+        let amount = Amount::from_msat(250_000_500);
+
+        assert_eq!(amount.display(Unit::Btc).to_string(), "0.002500005 BTC");
+        assert_eq!(amount.display(Unit::Sat).to_string(), "250000.5 sat");
+        assert_eq!(amount.display(Unit::MilliSat).to_string(), "250000500 msat");
+        assert_eq!(amount.to_string(), "250000500 msat");
+        assert_eq!(Amount::from_sat(1000).unwrap().display(Unit::Sat).to_string(), "1000 sat");
+    }
+
+    #[test]
+    fn plain_btc_string() {
+        let plain = |amount| {
+            Amount::from_msat(Amount::decode(amount).unwrap()).to_plain_btc_string()
+        };
+
+        assert_eq!(plain("10p"), "0.00000000001");
+        assert_eq!(plain("123u"), "0.000123");
+        assert_eq!(plain("1m"), "0.001");
+        assert_eq!(plain("21000m"), "21");
+        assert_eq!(plain("1500m"), "1.5");
+    }
+
+    #[test]
+    fn from_plain_btc_string() {
+        assert_eq!(Amount::from_plain_btc_string("0.000123").unwrap(), 12_300_000);
+        assert_eq!(Amount::from_plain_btc_string("21").unwrap(), 2_100_000_000_000);
+        assert_eq!(Amount::from_plain_btc_string("1.").unwrap(), 100_000_000_000);
+        assert_eq!(Amount::from_plain_btc_string("0.00000000001").unwrap(), 1);
+        assert!(matches!(
+            Amount::from_plain_btc_string("0.000000000001"),
+            Err(Error::AmountError(AmountError::SubMillisatoshiPrecision))
+        ));
+        assert!(Amount::from_plain_btc_string(".5").is_err());
+        assert!(Amount::from_plain_btc_string("-1").is_err());
+        assert!(Amount::from_plain_btc_string("1e3").is_err());
+        assert!(matches!(
+            Amount::from_plain_btc_string("21000001"),
+            Err(Error::AmountError(AmountError::ExceedsSupply(_)))
+        ));
+    }
 }
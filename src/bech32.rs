@@ -63,18 +63,43 @@ pub struct Bech32 {
     pub data: Vec<u8>,
 }
 
+/// Checksum variant, bech32 of BIP-0173 or bech32m of
+/// [BIP-0350](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki)
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Variant {
+    /// Bech32, used by the payment requests and the segwit v0 addresses
+    Bech32,
+    /// Bech32m, used by the segwit v1+ addresses
+    Bech32m,
+}
+
+impl Variant {
+    /// Constant the polymod of a valid checksum equals to
+    fn constant(&self) -> u32 {
+        match *self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => BECH32M_CONST,
+        }
+    }
+}
+
 type EncodeResult = Result<String, Error>;
 type DecodeResult = Result<Bech32, Error>;
 
 impl Bech32 {
     /// Encode as a string
     pub fn to_string(&self) -> EncodeResult {
-        if self.hrp.len() < 1 {
+        self.to_string_variant(Variant::Bech32)
+    }
+
+    /// Encode as a string with the checksum variant
+    pub fn to_string_variant(&self, variant: Variant) -> EncodeResult {
+        if self.hrp.is_empty() {
             return Err(Error::InvalidLength);
         }
         let hrp_bytes: Vec<u8> = self.hrp.clone().into_bytes();
         let mut combined: Vec<u8> = self.data.clone();
-        combined.extend_from_slice(&create_checksum(&hrp_bytes, &self.data));
+        combined.extend_from_slice(&create_checksum_variant(&hrp_bytes, &self.data, variant));
         let mut encoded: String = format!("{}{}", self.hrp, SEP);
         for p in combined {
             if p >= 32 {
@@ -88,6 +113,14 @@ impl Bech32 {
     // btcutil, allowing strings to be more than 90 characters.
     /// Decode from a string
     pub fn from_string(s: String) -> DecodeResult {
+        match Bech32::from_string_variant(s)? {
+            (b, Variant::Bech32) => Ok(b),
+            (_, Variant::Bech32m) => Err(Error::InvalidChecksum),
+        }
+    }
+
+    /// Decode from a string either checksum variant, returning the variant found
+    pub fn from_string_variant(s: String) -> Result<(Bech32, Variant), Error> {
         // Ensure overall length is within bounds
         let len: usize = s.len();
         // The maximum allowed length for a bech32 string is 90. It must also
@@ -107,7 +140,7 @@ impl Bech32 {
         let parts: Vec<&str> = s.rsplitn(2, SEP).collect();
         let raw_hrp = parts[1];
         let raw_data = parts[0];
-        if raw_hrp.len() < 1 || raw_data.len() < 6 {
+        if raw_hrp.is_empty() || raw_data.len() < 6 {
             return Err(Error::InvalidLength);
         }
 
@@ -116,16 +149,16 @@ impl Bech32 {
         let mut hrp_bytes: Vec<u8> = Vec::new();
         for b in raw_hrp.bytes() {
             // Valid subset of ASCII
-            if b < 33 || b > 126 {
+            if !(33..=126).contains(&b) {
                 return Err(Error::InvalidChar(b));
             }
             let mut c = b;
             // Lowercase
-            if b >= b'a' && b <= b'z' {
+            if b.is_ascii_lowercase() {
                 has_lower = true;
             }
             // Uppercase
-            if b >= b'A' && b <= b'Z' {
+            if b.is_ascii_uppercase() {
                 has_upper = true;
                 // Convert to lowercase
                 c = b + (b'a' - b'A');
@@ -137,7 +170,7 @@ impl Bech32 {
         let mut data_bytes: Vec<u8> = Vec::new();
         for b in raw_data.bytes() {
             // Aphanumeric only
-            if !((b >= b'0' && b <= b'9') || (b >= b'A' && b <= b'Z') || (b >= b'a' && b <= b'z')) {
+            if !b.is_ascii_alphanumeric() {
                 return Err(Error::InvalidChar(b));
             }
             // Excludes these characters: [1,b,i,o]
@@ -145,12 +178,12 @@ impl Bech32 {
                 return Err(Error::InvalidChar(b));
             }
             // Lowercase
-            if b >= b'a' && b <= b'z' {
+            if b.is_ascii_lowercase() {
                 has_lower = true;
             }
             let mut c = b;
             // Uppercase
-            if b >= b'A' && b <= b'Z' {
+            if b.is_ascii_uppercase() {
                 has_upper = true;
                 // Convert to lowercase
                 c = b + (b'a' - b'A');
@@ -164,41 +197,93 @@ impl Bech32 {
         }
 
         // Ensure checksum
-        if !verify_checksum(&hrp_bytes, &data_bytes) {
-            return Err(Error::InvalidChecksum);
-        }
+        let variant = match verify_checksum(&hrp_bytes, &data_bytes) {
+            Some(variant) => variant,
+            None => return Err(Error::InvalidChecksum),
+        };
 
         // Remove checksum from data payload
         let dbl: usize = data_bytes.len();
         data_bytes.truncate(dbl - 6);
 
-        Ok(Bech32 {
-            hrp: String::from_utf8(hrp_bytes).unwrap(),
-            data: data_bytes,
-        })
+        Ok((
+            Bech32 {
+                hrp: String::from_utf8(hrp_bytes).unwrap(),
+                data: data_bytes,
+            },
+            variant,
+        ))
+    }
+}
+
+/// Check a bech32 string without copying it, returning its human-readable part and its data
+/// part without the checksum, in the case of the input. <br>
+/// The errors are those of `Bech32::from_string`.
+pub fn check_str(s: &str) -> Result<(&str, &str), Error> {
+    if s.len() < 8 {
+        return Err(Error::InvalidLength);
+    }
+    let (raw_hrp, raw_data) = s.rsplit_once(SEP).ok_or(Error::MissingSeparator)?;
+    if raw_hrp.is_empty() || raw_data.len() < 6 {
+        return Err(Error::InvalidLength);
+    }
+    if let Some(b) = raw_hrp.bytes().find(|b| !(33..=126).contains(b)) {
+        return Err(Error::InvalidChar(b));
+    }
+    if let Some(b) = raw_data.bytes().find(|b| char_value(*b).is_none()) {
+        return Err(Error::InvalidChar(b));
+    }
+    if s.bytes().any(|b| b.is_ascii_lowercase()) && s.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(Error::MixedCase);
     }
+    if polymod_str(raw_hrp, raw_data) != Variant::Bech32.constant() {
+        return Err(Error::InvalidChecksum);
+    }
+    Ok((raw_hrp, &raw_data[..raw_data.len() - 6]))
+}
+
+/// Checksum of the expanded human-readable part followed by the data part, both in either case,
+/// without copying them, the data characters being in the charset
+pub fn polymod_str(raw_hrp: &str, raw_data: &str) -> u32 {
+    let hrp = raw_hrp.bytes().map(|b| b.to_ascii_lowercase());
+    hrp.clone()
+        .map(|b| b >> 5)
+        .chain(Some(0))
+        .chain(hrp.map(|b| b & 0x1f))
+        .chain(raw_data.bytes().map(|b| char_value(b).unwrap_or_default()))
+        .fold(1, polymod_step)
 }
+
 /// verify checksum
-pub fn create_checksum(hrp: &Vec<u8>, data: &Vec<u8>) -> Vec<u8> {
+pub fn create_checksum(hrp: &[u8], data: &[u8]) -> Vec<u8> {
+    create_checksum_variant(hrp, data, Variant::Bech32)
+}
+
+/// create checksum of the variant
+pub fn create_checksum_variant(hrp: &[u8], data: &[u8], variant: Variant) -> Vec<u8> {
     let mut values: Vec<u8> = hrp_expand(hrp);
     values.extend_from_slice(data);
     // Pad with 6 zeros
     values.extend_from_slice(&[0u8; 6]);
-    let plm: u32 = polymod(values) ^ 1;
+    let plm: u32 = polymod(values) ^ variant.constant();
     let mut checksum: Vec<u8> = Vec::new();
     for p in 0..6 {
-        checksum.push(((plm >> 5 * (5 - p)) & 0x1f) as u8);
+        checksum.push(((plm >> (5 * (5 - p))) & 0x1f) as u8);
     }
     checksum
 }
 
-fn verify_checksum(hrp: &Vec<u8>, data: &Vec<u8>) -> bool {
+fn verify_checksum(hrp: &[u8], data: &[u8]) -> Option<Variant> {
     let mut exp = hrp_expand(hrp);
     exp.extend_from_slice(data);
-    polymod(exp) == 1u32
+    match polymod(exp) {
+        1u32 => Some(Variant::Bech32),
+        BECH32M_CONST => Some(Variant::Bech32m),
+        _ => None,
+    }
 }
 
-fn hrp_expand(hrp: &Vec<u8>) -> Vec<u8> {
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
     let mut v: Vec<u8> = Vec::new();
     for b in hrp {
         v.push(*b >> 5);
@@ -211,20 +296,30 @@ fn hrp_expand(hrp: &Vec<u8>) -> Vec<u8> {
 }
 
 fn polymod(values: Vec<u8>) -> u32 {
-    let mut chk: u32 = 1;
-    let mut b: u8;
-    for v in values {
-        b = (chk >> 25) as u8;
-        chk = (chk & 0x1ffffff) << 5 ^ (v as u32);
-        for i in 0..5 {
-            if (b >> i) & 1 == 1 {
-                chk ^= GEN[i]
-            }
+    values.into_iter().fold(1, polymod_step)
+}
+
+/// Feed one 5-bit value to the checksum, starting from 1, e.g. to check it without allocating
+pub fn polymod_step(chk: u32, value: u8) -> u32 {
+    let b = (chk >> 25) as u8;
+    let mut chk = (chk & 0x1ffffff) << 5 ^ (value as u32);
+    for (i, generator) in GEN.iter().enumerate() {
+        if (b >> i) & 1 == 1 {
+            chk ^= generator
         }
     }
     chk
 }
 
+/// Value of a data character in either case, None if it's not in the character set
+pub const fn char_value(c: u8) -> Option<u8> {
+    if c >= 128 || CHARSET_REV[c as usize] < 0 {
+        None
+    } else {
+        Some(CHARSET_REV[c as usize] as u8)
+    }
+}
+
 /// Human-readable part and data part separator
 const SEP: char = '1';
 
@@ -234,8 +329,8 @@ pub const CHARSET: [char; 32] = [
     'n', '5', '4', 'k', 'h', 'c', 'e', '6', 'm', 'u', 'a', '7', 'l',
 ];
 
-// Reverse character set. Maps ASCII byte -> CHARSET index on [0,31]
-const CHARSET_REV: [i8; 128] = [
+/// Reverse character set. Maps ASCII byte -> CHARSET index on [0,31], -1 outside of the set
+pub const CHARSET_REV: [i8; 128] = [
     -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
     -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
     15, -1, 10, 17, 21, 20, 26, 30, 7, 5, -1, -1, -1, -1, -1, -1, -1, 29, -1, 24, 13, 25, 9, 8, 23,
@@ -247,6 +342,9 @@ const CHARSET_REV: [i8; 128] = [
 /// Generator coefficients
 const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
 
+/// Bech32m checksum constant
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
 /// Error types for Bech32 encoding / decoding
 #[derive(PartialEq, Debug)]
 pub enum Error {
@@ -305,12 +403,8 @@ mod tests {
         );
         for s in strings {
             let decode_result = Bech32::from_string(s.to_string());
-            if !decode_result.is_ok() {
-                panic!(
-                    "Did not decode: {:?} Reason: {:?}",
-                    s,
-                    decode_result.unwrap_err()
-                );
+            if let Err(ref e) = decode_result {
+                panic!("Did not decode: {:?} Reason: {:?}", s, e);
             }
             assert!(decode_result.is_ok());
             let encode_result = decode_result.unwrap().to_string();
@@ -319,6 +413,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn valid_checksum_bech32m() {
+        let strings: Vec<&str> = vec![
+            "A1LQFN3A",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ];
+        for s in strings {
+            let (b, variant) = Bech32::from_string_variant(s.to_string()).unwrap();
+            assert_eq!(variant, Variant::Bech32m);
+            assert_eq!(
+                b.to_string_variant(Variant::Bech32m).unwrap(),
+                s.to_lowercase()
+            );
+            assert_eq!(
+                Bech32::from_string(s.to_string()),
+                Err(Error::InvalidChecksum)
+            );
+        }
+    }
+
     #[test]
     fn invalid() {
         let pairs: Vec<(&str, Error)> = vec![
@@ -0,0 +1,301 @@
+//! Command line tool decoding, encoding and verifying payment requests, built with the `cli`
+//! feature.
+//!
+//! ```text
+//! bolt11 decode [--json] <invoice>
+//! bolt11 decode --batch < invoices.txt
+//! bolt11 encode --key <secret key> --payment-hash <hash> (--description <text> | --description-hash <hash>)
+//!               [--prefix lnbc] [--amount 2500u] [--expiry <seconds>] [--min-final-cltv <blocks>]
+//!               [--timestamp <seconds>]
+//! bolt11 verify <invoice> <node id>
+//! ```
+
+extern crate bolt11;
+extern crate secp256k1;
+#[macro_use]
+extern crate serde_json;
+
+use bolt11::amount::Amount;
+use bolt11::builder::InvoiceBuilder;
+use bolt11::currency::Currency;
+use bolt11::hashes::{DescriptionHash, PaymentHash};
+use bolt11::node_id::NodeId;
+use bolt11::payment_request::PaymentRequest;
+use secp256k1::SecretKey;
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str = "usage:
+  bolt11 decode [--json] <invoice>
+  bolt11 decode --batch < invoices.txt
+  bolt11 encode --key <secret key> --payment-hash <hash> (--description <text> | --description-hash <hash>)
+                [--prefix lnbc] [--amount 2500u] [--expiry <seconds>] [--min-final-cltv <blocks>]
+                [--timestamp <seconds>]
+  bolt11 verify <invoice> <node id>";
+
+/// Failure of a command, printed to stderr except `Invalid`.
+#[derive(Debug, PartialEq)]
+enum Failure {
+    /// Invalid command line, the usage is printed too.
+    Usage(String),
+    /// The command failed.
+    Error(String),
+    /// The signature doesn't match, prints `invalid` to stdout like `valid` and exits with 1.
+    Invalid,
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    match run(&args) {
+        Ok(ref output) if output.is_empty() => {}
+        Ok(output) => println!("{}", output),
+        Err(Failure::Usage(message)) => {
+            eprintln!("{}\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(Failure::Error(message)) => {
+            eprintln!("error: {}", message);
+            process::exit(1);
+        }
+        Err(Failure::Invalid) => {
+            println!("invalid");
+            process::exit(1);
+        }
+    }
+}
+
+// Helper for main, run the command and return its output.
+fn run(args: &[String]) -> Result<String, Failure> {
+    let (command, args) = args
+        .split_first()
+        .ok_or_else(|| Failure::Usage("missing command".to_owned()))?;
+    match command.as_str() {
+        "decode" => decode(args),
+        "encode" => encode(args),
+        "verify" => verify(args),
+        "-h" | "--help" | "help" => Ok(USAGE.to_owned()),
+        _ => Err(Failure::Usage(format!("unknown command {}", command))),
+    }
+}
+
+// Helper for run, `bolt11 decode [--json] <invoice>` or `bolt11 decode --batch`.
+fn decode(args: &[String]) -> Result<String, Failure> {
+    let (json, invoice) = match args {
+        [flag] if flag == "--batch" => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            decode_batch(stdin.lock(), stdout.lock()).map_err(error)?;
+            return Ok(String::new());
+        }
+        [flag, invoice] if flag == "--json" => (true, invoice),
+        [invoice] => (false, invoice),
+        _ => return Err(Failure::Usage("decode takes an invoice".to_owned())),
+    };
+    let invoice = invoice.parse::<PaymentRequest>().map_err(error)?;
+    if json {
+        serde_json::to_string_pretty(&invoice.to_cln()).map_err(error)
+    } else {
+        Ok(invoice.explain().to_string().trim_end().to_owned())
+    }
+}
+
+// Helper for decode, read one invoice per line and write one JSON object per line, the failures
+// are written as `{"invoice": ..., "error": ...}` and don't stop the batch, the invalid UTF-8 of
+// a line being replaced. Blank lines are skipped.
+fn decode_batch<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<()> {
+    let mut bytes = vec![];
+    loop {
+        bytes.clear();
+        if input.read_until(b'\n', &mut bytes)? == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&bytes);
+        let invoice = line.trim();
+        if invoice.is_empty() {
+            continue;
+        }
+        let value = match invoice.parse::<PaymentRequest>() {
+            Ok(pay_request) => serde_json::to_value(pay_request.to_cln())
+                .unwrap_or_else(|e| json!({ "invoice": invoice, "error": e.to_string() })),
+            Err(e) => json!({ "invoice": invoice, "error": e.to_string() }),
+        };
+        writeln!(output, "{}", value)?;
+    }
+    output.flush()
+}
+
+// Helper for run, `bolt11 encode --key <secret key> ...`.
+fn encode(args: &[String]) -> Result<String, Failure> {
+    let mut key = None;
+    let mut payment_hash = None;
+    let mut description = None;
+    let mut description_hash = None;
+    let mut prefix = "lnbc".to_owned();
+    let mut amount = None;
+    let mut expiry = None;
+    let mut min_final_cltv = None;
+    let mut timestamp = None;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| Failure::Usage(format!("missing value of {}", flag)))?;
+        match flag.as_str() {
+            "--key" => key = Some(parse_secret_key(value)?),
+            "--payment-hash" => payment_hash = Some(value.parse::<PaymentHash>().map_err(error)?),
+            "--description" => description = Some(value.to_owned()),
+            "--description-hash" => {
+                description_hash = Some(value.parse::<DescriptionHash>().map_err(error)?)
+            }
+            "--prefix" => prefix = value.to_owned(),
+            "--amount" => amount = Some(Amount::decode(value).map_err(error)?),
+            "--expiry" => expiry = Some(parse_number(flag, value)?),
+            "--min-final-cltv" => min_final_cltv = Some(parse_number(flag, value)?),
+            "--timestamp" => timestamp = Some(parse_number(flag, value)?),
+            _ => return Err(Failure::Usage(format!("unknown option {}", flag))),
+        }
+    }
+    let key = key.ok_or_else(|| Failure::Usage("missing --key".to_owned()))?;
+    let payment_hash =
+        payment_hash.ok_or_else(|| Failure::Usage("missing --payment-hash".to_owned()))?;
+    let currency = Currency::from_prefix(&prefix)
+        .ok_or_else(|| Failure::Usage(format!("invalid prefix {}", prefix)))?;
+
+    let builder = InvoiceBuilder::new(currency).payment_hash(payment_hash);
+    let mut builder = match (description, description_hash) {
+        (Some(description), None) => builder.description(&description),
+        (None, Some(hash)) => builder.description_hash(hash),
+        _ => {
+            return Err(Failure::Usage(
+                "either --description or --description-hash is required".to_owned(),
+            ))
+        }
+    };
+    if let Some(amount) = amount {
+        builder = builder.amount_msat(amount);
+    }
+    if let Some(seconds) = expiry {
+        builder = builder.expiry(Duration::from_secs(seconds));
+    }
+    if let Some(blocks) = min_final_cltv {
+        builder = builder.min_final_cltv_expiry(blocks);
+    }
+    if let Some(timestamp) = timestamp {
+        builder = builder.timestamp(timestamp);
+    }
+    builder
+        .build_signed_with(|message| secp256k1::sign(message, &key))
+        .map_err(error)
+}
+
+// Helper for run, `bolt11 verify <invoice> <node id>`.
+fn verify(args: &[String]) -> Result<String, Failure> {
+    let (invoice, node_id) = match args {
+        [invoice, node_id] => (invoice, node_id),
+        _ => return Err(Failure::Usage("verify takes an invoice and a node id".to_owned())),
+    };
+    let invoice = invoice.parse::<PaymentRequest>().map_err(error)?;
+    let pub_key = node_id
+        .parse::<NodeId>()
+        .and_then(|node_id| node_id.to_public_key())
+        .map_err(error)?;
+    if invoice.verify_signature(&pub_key) {
+        Ok("valid".to_owned())
+    } else {
+        Err(Failure::Invalid)
+    }
+}
+
+// Helper for encode, a 32 bytes hex encoded secret key.
+fn parse_secret_key(value: &str) -> Result<SecretKey, Failure> {
+    let hash = value
+        .parse::<PaymentHash>()
+        .map_err(|_| Failure::Usage("the key must be 32 bytes hex encoded".to_owned()))?;
+    SecretKey::parse(hash.as_bytes()).map_err(|e| Failure::Error(format!("invalid key: {:?}", e)))
+}
+
+// Helper for encode, a decimal option value.
+fn parse_number(flag: &str, value: &str) -> Result<u64, Failure> {
+    value
+        .parse()
+        .map_err(|_| Failure::Usage(format!("invalid value of {}: {}", flag, value)))
+}
+
+// Helper for the commands, a failure from an error.
+fn error<E: ToString>(e: E) -> Failure {
+    Failure::Error(e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TX_REF: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+    const NODE_ID: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+    const SEC_KEY: &str = "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734";
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn decode_and_verify() {
+        assert!(run(&args(&["decode", TX_REF])).unwrap().contains("1 cup coffee"));
+        assert!(run(&args(&["decode", "--json", TX_REF]))
+            .unwrap()
+            .contains("\"amount_msat\": \"250000000msat\""));
+        assert_eq!(run(&args(&["verify", TX_REF, NODE_ID])).unwrap(), "valid");
+        assert_eq!(
+            run(&args(&["verify", TX_REF, &NODE_ID.replacen("03", "02", 1)])),
+            Err(Failure::Invalid)
+        );
+        assert!(matches!(run(&args(&["decode"])), Err(Failure::Usage(_))));
+        assert!(matches!(run(&args(&["decode", "lnbc1"])), Err(Failure::Error(_))));
+    }
+
+    #[test]
+    fn batch() {
+        let mut input = format!("{}\n\nlnbc1\n", TX_REF).into_bytes();
+        // a line which isn't UTF-8
+        input.extend_from_slice(&[0xff, 0xfe, b'\n']);
+        input.extend_from_slice(format!("  {}  ", TX_REF.to_uppercase()).as_bytes());
+        let mut output = vec![];
+        decode_batch(&input[..], &mut output).unwrap();
+        let lines = String::from_utf8(output).unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].contains("\"amount_msat\":\"250000000msat\""));
+        assert!(lines[1].starts_with("{\"error\":"));
+        assert!(lines[1].contains("\"invoice\":\"lnbc1\""));
+        assert!(lines[2].contains("\"invoice\":\"\u{fffd}\u{fffd}\""));
+        assert_eq!(lines[3], lines[0]);
+    }
+
+    #[test]
+    fn encode() {
+        let encoded = run(&args(&[
+            "encode",
+            "--key",
+            SEC_KEY,
+            "--payment-hash",
+            "0001020304050607080900010203040506070809000102030405060708090102",
+            "--description",
+            "1 cup coffee",
+            "--amount",
+            "2500u",
+            "--expiry",
+            "60",
+            "--timestamp",
+            "1496314658",
+        ])).unwrap();
+
+        assert_eq!(encoded, TX_REF);
+        assert!(matches!(
+            run(&args(&["encode", "--key", SEC_KEY])),
+            Err(Failure::Usage(_))
+        ));
+    }
+}
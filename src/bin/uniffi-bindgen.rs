@@ -0,0 +1,8 @@
+//! Kotlin and Swift bindings generator, built with the `uniffi-cli` feature, see the `mobile`
+//! module.
+
+extern crate uniffi;
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
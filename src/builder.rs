@@ -0,0 +1,768 @@
+//! Fluent construction of payment requests.
+
+use currency::Currency;
+use features::Features;
+use hashes::{DescriptionHash, PaymentHash, PaymentSecret};
+use payment_request::UnsignedInvoice;
+#[cfg(feature = "async")]
+use signer::AsyncInvoiceSigner;
+use signer::InvoiceSigner;
+#[cfg(feature = "async")]
+use types::SignError;
+use node_id::NodeId;
+use tag::{ChannelPolicy, ExtraHop, Tag};
+use timestamp::Timestamp;
+use types::Error;
+use amount::Amount;
+use secp256k1;
+use secp256k1::{Message, RecoveryId, Signature};
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::marker::PhantomData;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Builder of a private route, from the payer side to the payee.
+///
+/// # Examples
+///
+/// ```
+/// use bolt11::builder::RouteHintBuilder;
+/// use bolt11::tag::ChannelPolicy;
+///
+/// let policy = ChannelPolicy {
+///     fee_base_msat: 1000,
+///     fee_proportional_millionths: 100,
+///     cltv_expiry_delta: 40,
+/// };
+/// let path = RouteHintBuilder::new()
+///     .hop(
+///         "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255".parse().unwrap(),
+///         "66051:263430:1800",
+///         &policy,
+///     )
+///     .unwrap()
+///     .build();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RouteHintBuilder {
+    path: Vec<ExtraHop>,
+}
+
+impl RouteHintBuilder {
+    /// Create an empty route.
+    pub fn new() -> RouteHintBuilder {
+        RouteHintBuilder::default()
+    }
+
+    /// Add the next hop of the route.
+    /// # Arguments
+    /// `pub_key` Public key of the node at the start of the channel. <br>
+    /// `short_channel_id` Channel ID of the channel, formatted as `block:tx:output` or `BxTxO`.
+    /// <br>
+    /// `policy` Routing policy of the channel.
+    pub fn hop(
+        self,
+        pub_key: NodeId,
+        short_channel_id: &str,
+        policy: &ChannelPolicy,
+    ) -> Result<RouteHintBuilder, Error> {
+        Ok(self.extra_hop(ExtraHop::from_policy(
+            pub_key,
+            short_channel_id.parse()?,
+            policy,
+        )))
+    }
+
+    /// Add the next hop of the route.
+    /// # Arguments
+    /// `hop` Extra hop.
+    pub fn extra_hop(mut self, hop: ExtraHop) -> RouteHintBuilder {
+        self.path.push(hop);
+        self
+    }
+
+    /// Return the extra hops of the route, for `InvoiceBuilder::route_hint`.
+    pub fn build(self) -> Vec<ExtraHop> {
+        self.path
+    }
+}
+
+/// Marker of a builder without payment hash.
+#[derive(Debug, Clone)]
+pub struct HashNotSet;
+/// Marker of a builder with a payment hash.
+#[derive(Debug, Clone)]
+pub struct HashSet;
+/// Marker of a builder without description nor description hash.
+#[derive(Debug, Clone)]
+pub struct DescNotSet;
+/// Marker of a builder with either a description or a description hash.
+#[derive(Debug, Clone)]
+pub struct DescSet;
+
+/// Builder of signed payment requests. <br>
+/// *Note*: the payment hash and exactly one of the description or the description hash are
+/// required, a payment request missing them fails to compile.
+///
+/// # Examples
+///
+/// ```
+/// extern crate bolt11;
+/// extern crate secp256k1;
+///
+/// use bolt11::builder::InvoiceBuilder;
+/// use bolt11::currency::Currency;
+/// use bolt11::signer::SoftwareSigner;
+///
+/// # fn main() {
+/// let secret_key = secp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+///
+/// let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+///     .amount_msat(250_000_000)
+///     .payment_hash([0u8; 32])
+///     .description("1 cup coffee")
+///     .build_signed(SoftwareSigner::new(secret_key));
+/// # }
+/// ```
+///
+/// A payment request without payment hash can't be signed:
+///
+/// ```compile_fail
+/// extern crate bolt11;
+/// extern crate secp256k1;
+///
+/// use bolt11::builder::InvoiceBuilder;
+/// use bolt11::currency::Currency;
+/// use bolt11::signer::SoftwareSigner;
+///
+/// # fn main() {
+/// let secret_key = secp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+///
+/// let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+///     .description("1 cup coffee")
+///     .build_signed(SoftwareSigner::new(secret_key));
+/// # }
+/// ```
+///
+/// Neither can a payment request with both a description and a description hash:
+///
+/// ```compile_fail
+/// extern crate bolt11;
+///
+/// use bolt11::builder::InvoiceBuilder;
+/// use bolt11::currency::Currency;
+///
+/// # fn main() {
+/// let builder = InvoiceBuilder::new(Currency::Bitcoin)
+///     .description("1 cup coffee")
+///     .description_hash([0u8; 32]);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct InvoiceBuilder<H, D> {
+    currency: Currency,
+    amount: Option<u64>,
+    timestamp: Option<u64>,
+    tags: Vec<Tag>,
+    phantom: PhantomData<(H, D)>,
+}
+
+impl InvoiceBuilder<HashNotSet, DescNotSet> {
+    /// Create a builder of payment requests for the currency.
+    /// # Arguments
+    /// `currency` Currency of the payment request.
+    pub fn new(currency: Currency) -> InvoiceBuilder<HashNotSet, DescNotSet> {
+        InvoiceBuilder {
+            currency,
+            amount: None,
+            timestamp: None,
+            tags: vec![],
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<H, D> InvoiceBuilder<H, D> {
+    /// Set the amount.
+    /// # Arguments
+    /// `amount` Amount in millisatoshis.
+    pub fn amount_msat(mut self, amount: u64) -> InvoiceBuilder<H, D> {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Set the amount, it can be omitted for donations where the payer chooses the amount.
+    /// # Arguments
+    /// `amount` Amount to pay.
+    pub fn amount(mut self, amount: Amount) -> InvoiceBuilder<H, D> {
+        self.amount = Some(amount.msat());
+        self
+    }
+
+    /// Set the creation timestamp, defaults to the current time.
+    /// # Arguments
+    /// `timestamp` Seconds since epoch (UNIX format).
+    pub fn timestamp(mut self, timestamp: u64) -> InvoiceBuilder<H, D> {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Set the expiry time.
+    /// # Arguments
+    /// `expiry` Time after the creation timestamp, in seconds precision.
+    pub fn expiry(self, expiry: Duration) -> InvoiceBuilder<H, D> {
+        self.set_tag(Tag::Expiry {
+            seconds: expiry.as_secs(),
+        })
+    }
+
+    /// Set the min_final_cltv_expiry.
+    /// # Arguments
+    /// `blocks` min_final_cltv_expiry, in blocks.
+    pub fn min_final_cltv_expiry(self, blocks: u64) -> InvoiceBuilder<H, D> {
+        self.set_tag(Tag::MinFinalCltvExpiry { blocks })
+    }
+
+    /// Add a private route, it can be called once per route.
+    /// # Arguments
+    /// `path` Extra hops of the route.
+    pub fn route_hint(mut self, path: Vec<ExtraHop>) -> InvoiceBuilder<H, D> {
+        self.tags.push(Tag::RoutingInfo { path });
+        self
+    }
+
+    /// Add a fallback on-chain address.
+    /// # Arguments
+    /// `version` Address version, 17 (pubkey hash), 18 (script hash) or a witness version. <br>
+    /// `hash` Address hash.
+    pub fn fallback(mut self, version: u8, hash: Vec<u8>) -> InvoiceBuilder<H, D> {
+        self.tags.push(Tag::FallbackAddress { version, hash });
+        self
+    }
+
+    /// Set the features supported or required for receiving the payment.
+    /// # Arguments
+    /// `bits` Feature bit vector.
+    pub fn features(self, bits: Features) -> InvoiceBuilder<H, D> {
+        self.set_tag(Tag::Features { bits })
+    }
+
+    /// Set the payment metadata to be sent in the onion.
+    /// # Arguments
+    /// `bytes` Opaque metadata bytes.
+    pub fn payment_metadata(self, bytes: Vec<u8>) -> InvoiceBuilder<H, D> {
+        self.set_tag(Tag::PaymentMetadata { bytes })
+    }
+
+    /// Set the payment secret, required by the `payment_secret` feature.
+    /// # Arguments
+    /// `secret` Secret given to the payer only, e.g. `[u8; 32]`.
+    pub fn payment_secret<T: Into<PaymentSecret>>(self, secret: T) -> InvoiceBuilder<H, D> {
+        self.set_tag(Tag::PaymentSecret {
+            secret: secret.into(),
+        })
+    }
+
+    // Helper for the setters of the fields appearing once, replaces the field if it is set.
+    fn set_tag(mut self, tag: Tag) -> InvoiceBuilder<H, D> {
+        match self.tags.iter().position(|t| t.tag_char() == tag.tag_char()) {
+            Some(index) => self.tags[index] = tag,
+            None => self.tags.push(tag),
+        }
+        self
+    }
+
+    // Move to another state of the builder.
+    fn set<H2, D2>(self) -> InvoiceBuilder<H2, D2> {
+        InvoiceBuilder {
+            currency: self.currency,
+            amount: self.amount,
+            timestamp: self.timestamp,
+            tags: self.tags,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D> InvoiceBuilder<HashNotSet, D> {
+    /// Set the payment hash.
+    /// # Arguments
+    /// `hash` SHA256 of the payment preimage, e.g. `[u8; 32]`.
+    pub fn payment_hash<T: Into<PaymentHash>>(mut self, hash: T) -> InvoiceBuilder<HashSet, D> {
+        self.tags.push(Tag::PaymentHash { hash: hash.into() });
+        self.set()
+    }
+}
+
+impl<H> InvoiceBuilder<H, DescNotSet> {
+    /// Set the description.
+    /// # Arguments
+    /// `description` Short description of purpose of payment.
+    pub fn description(mut self, description: &str) -> InvoiceBuilder<H, DescSet> {
+        self.tags.push(Tag::Description {
+            description: description.to_owned(),
+        });
+        self.set()
+    }
+
+    /// Set the description hash.
+    /// # Arguments
+    /// `hash` SHA256 of the description of purpose of payment, e.g. `[u8; 32]`.
+    pub fn description_hash<T: Into<DescriptionHash>>(
+        mut self,
+        hash: T,
+    ) -> InvoiceBuilder<H, DescSet> {
+        self.tags.push(Tag::DescriptionHash { hash: hash.into() });
+        self.set()
+    }
+
+    /// Set the description hash to the SHA256 of a description too long for the `d` field, e.g.
+    /// the metadata of an LNURL-pay service, which is then given to the payer out of band.
+    /// # Arguments
+    /// `description` Description of purpose of payment.
+    pub fn description_hashed(self, description: &str) -> InvoiceBuilder<H, DescSet> {
+        self.description_hash(DescriptionHash::from_description(description))
+    }
+}
+
+impl InvoiceBuilder<HashSet, DescSet> {
+    /// Return the payment request to be signed outside of the crate, see `UnsignedInvoice`.
+    pub fn build_unsigned(self) -> Result<UnsignedInvoice, Error> {
+        if let Some(amount) = self.amount {
+            Amount::check_supply(amount)?;
+        }
+        let timestamp = match self.timestamp {
+            Some(timestamp) => timestamp,
+            None => Timestamp::now()?,
+        };
+        Ok(UnsignedInvoice::new(
+            self.currency.prefix().to_owned(),
+            self.amount,
+            timestamp,
+            self.tags,
+        ))
+    }
+
+    /// Sign the payment request and return it encoded.
+    /// # Arguments
+    /// `signer` Signer of the node, e.g. a `SoftwareSigner` or a KMS backed `InvoiceSigner`.
+    pub fn build_signed<S: InvoiceSigner>(self, signer: S) -> Result<String, Error> {
+        self.build_unsigned()?.sign_with(&signer)?.encode()
+    }
+
+    /// Sign the payment request with a signing function and return it encoded.
+    /// # Arguments
+    /// `sign` Signing function of the message, e.g. `secp256k1::sign` with the node key.
+    pub fn build_signed_with<F>(self, sign: F) -> Result<String, Error>
+    where
+        F: FnOnce(&Message) -> Result<(Signature, RecoveryId), secp256k1::Error>,
+    {
+        let unsigned = self.build_unsigned()?;
+        let (hash, _, _) = unsigned.signing_data()?;
+        let (signature, recovery_id) = sign(&Message::parse(&hash))?;
+
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.serialize());
+        bytes[64] = recovery_id.serialize();
+        unsigned.attach_signature(bytes)?.encode()
+    }
+
+    /// Sign the payment request without blocking and return a future of it encoded. <br>
+    /// *Note*: only built with the `async` feature, the future is not tied to a runtime.
+    /// # Arguments
+    /// `signer` Signer of the node, e.g. a client of a network HSM.
+    #[cfg(feature = "async")]
+    pub fn build_signed_async<S: AsyncInvoiceSigner>(self, signer: S) -> BuildSigned<S::Future> {
+        let state = self.build_unsigned().and_then(|unsigned| {
+            let (hash, _, _) = unsigned.signing_data()?;
+            let signature = Box::pin(signer.sign_recoverable_async(&hash));
+            Ok((unsigned, signature))
+        });
+        BuildSigned { state: Some(state) }
+    }
+}
+
+/// Future of `InvoiceBuilder::build_signed_async`, resolving to the encoded payment request.
+#[cfg(feature = "async")]
+pub struct BuildSigned<F> {
+    state: Option<Result<Signing<F>, Error>>,
+}
+
+// Helper for BuildSigned, the payment request waiting for the future of its signature.
+#[cfg(feature = "async")]
+type Signing<F> = (UnsignedInvoice, Pin<Box<F>>);
+
+#[cfg(feature = "async")]
+impl<F> Future for BuildSigned<F>
+where
+    F: Future<Output = Result<(RecoveryId, [u8; 64]), SignError>>,
+{
+    type Output = Result<String, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<String, Error>> {
+        let signed = match self.state {
+            Some(Ok((_, ref mut signature))) => match signature.as_mut().poll(cx) {
+                Poll::Ready(signed) => Some(signed),
+                Poll::Pending => return Poll::Pending,
+            },
+            _ => None,
+        };
+        match (self.state.take(), signed) {
+            (Some(Ok((unsigned, _))), Some(signed)) => Poll::Ready(
+                signed
+                    .map_err(Error::from)
+                    .and_then(|(recovery_id, signature)| {
+                        let mut bytes = [0u8; 65];
+                        bytes[..64].copy_from_slice(&signature);
+                        bytes[64] = recovery_id.serialize();
+                        unsigned.attach_signature(bytes)
+                    })
+                    .and_then(|payment_request| payment_request.encode()),
+            ),
+            (Some(Err(e)), _) => Poll::Ready(Err(e)),
+            _ => panic!("BuildSigned polled after completion"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use payment_request::PaymentRequest;
+    use signer::SoftwareSigner;
+    use short_channel_id::ShortChannelId;
+    use types::{AmountError, SignError};
+    use utils::from_hex;
+
+    lazy_static!{
+         static ref SEC_KEY: secp256k1::SecretKey = {
+            let key = from_hex(
+                "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734",
+            ).unwrap()
+                .iter()
+                .enumerate()
+                .fold([0u8; 32], |mut acc, (index, item)| {
+                    acc[index] = *item;
+                    acc
+                });
+            secp256k1::SecretKey::parse(&key).unwrap()
+         };
+    }
+
+    #[test]
+    fn build_signed() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut payment_hash = [0u8; 32];
+        payment_hash.copy_from_slice(&from_hex(
+            "0001020304050607080900010203040506070809000102030405060708090102",
+        ).unwrap());
+
+        let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+            .amount_msat(250_000_000)
+            .timestamp(1496314658)
+            .payment_hash(payment_hash)
+            .description("1 cup coffee")
+            .expiry(Duration::from_secs(60))
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+
+        assert_eq!(payment_request, tx_ref);
+    }
+
+    #[test]
+    fn build_signed_payment_secret() {
+        let tx_ref = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qq\
+        qsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77\
+        nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam3\
+        0g4vgpfna3rh";
+        let payment_hash: PaymentHash =
+            "0001020304050607080900010203040506070809000102030405060708090102".parse().unwrap();
+        let mut features = Features::new();
+        features.set_bit(Features::VAR_ONION_OPTIN);
+        features.set_bit(Features::PAYMENT_SECRET);
+
+        let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+            .amount_msat(250_000_000)
+            .timestamp(1496314658)
+            .payment_secret([0x11; 32])
+            .payment_hash(payment_hash)
+            .description("1 cup coffee")
+            .expiry(Duration::from_secs(60))
+            .features(features)
+            .build_signed(SoftwareSigner::new(SEC_KEY.clone()))
+            .unwrap();
+
+        assert_eq!(payment_request, tx_ref);
+    }
+
+    #[test]
+    fn build_unsigned() {
+        let unsigned = InvoiceBuilder::new(Currency::Bitcoin)
+            .amount_msat(250_000_000)
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("1 cup coffee")
+            .build_unsigned()
+            .unwrap();
+        let (hash, hrp, _) = unsigned.signing_data().unwrap();
+        let (signature, recovery_id) = secp256k1::sign(&Message::parse(&hash), &SEC_KEY).unwrap();
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.serialize());
+        bytes[64] = recovery_id.serialize();
+
+        assert_eq!(hrp, "lnbc2500u");
+        assert_eq!(
+            unsigned.attach_signature(bytes).unwrap().encode().unwrap(),
+            InvoiceBuilder::new(Currency::Bitcoin)
+                .amount_msat(250_000_000)
+                .timestamp(1496314658)
+                .payment_hash([0u8; 32])
+                .description("1 cup coffee")
+                .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn setters_replace_fields() {
+        let builder = InvoiceBuilder::new(Currency::Bitcoin)
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("1 cup coffee");
+        let twice = builder
+            .clone()
+            .expiry(Duration::from_secs(30))
+            .min_final_cltv_expiry(9)
+            .expiry(Duration::from_secs(60))
+            .min_final_cltv_expiry(12)
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+        let once = builder
+            .expiry(Duration::from_secs(60))
+            .min_final_cltv_expiry(12)
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+        let payment_request = PaymentRequest::decode(&twice).unwrap();
+
+        assert_eq!(twice, once);
+        assert_eq!(payment_request.expiry(), Some(60));
+        assert_eq!(payment_request.min_final_cltv_expiry_field(), Some(12));
+        assert_eq!(payment_request.tags.len(), 4);
+    }
+
+    #[test]
+    fn description_hashed() {
+        let metadata = "[[\"text/plain\",\"lnurl-pay\"]]".repeat(20);
+        let payment_request = PaymentRequest::decode(
+            &InvoiceBuilder::new(Currency::Bitcoin)
+                .timestamp(1496314658)
+                .payment_hash([0u8; 32])
+                .description_hashed(&metadata)
+                .build_signed(SoftwareSigner::new(SEC_KEY.clone()))
+                .unwrap(),
+        ).unwrap();
+
+        assert_eq!(
+            payment_request.description_hash(),
+            Some(DescriptionHash::from_description(&metadata))
+        );
+        assert!(payment_request.check_description(&metadata));
+        assert!(!payment_request.check_description("lnurl-pay"));
+    }
+
+    #[test]
+    fn build_signed_with_signer() {
+        struct Kms;
+
+        impl InvoiceSigner for Kms {
+            fn sign_recoverable(
+                &self,
+                _: &[u8; 32],
+            ) -> Result<(secp256k1::RecoveryId, [u8; 64]), SignError> {
+                Err(SignError::Rejected("policy".to_owned()))
+            }
+        }
+
+        let builder = InvoiceBuilder::new(Currency::Bitcoin)
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("1 cup coffee");
+
+        assert_eq!(
+            builder.clone().build_signed(SoftwareSigner::new(SEC_KEY.clone())).unwrap(),
+            builder
+                .clone()
+                .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+                .unwrap()
+        );
+        assert!(matches!(
+            builder.build_signed(Kms),
+            Err(Error::Signer(SignError::Rejected(_)))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn build_signed_async() {
+        use signer::AsyncInvoiceSigner;
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::ptr;
+        use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+        // Signer answering on the second poll, as a network HSM would.
+        struct Hsm;
+
+        struct Pending(u8, [u8; 32]);
+
+        impl Future for Pending {
+            type Output = Result<(secp256k1::RecoveryId, [u8; 64]), SignError>;
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+                self.0 += 1;
+                if self.0 < 2 {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                Poll::Ready(SoftwareSigner::new(SEC_KEY.clone()).sign_recoverable(&self.1))
+            }
+        }
+
+        impl AsyncInvoiceSigner for Hsm {
+            type Future = Pending;
+
+            fn sign_recoverable_async(&self, msg: &[u8; 32]) -> Pending {
+                Pending(0, *msg)
+            }
+        }
+
+        fn noop(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+            let waker = unsafe { Waker::from_raw(clone(ptr::null())) };
+            let mut cx = Context::from_waker(&waker);
+            let mut future = Box::pin(future);
+            let mut polls = 1;
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return (output, polls);
+                }
+                polls += 1;
+            }
+        }
+
+        let builder = InvoiceBuilder::new(Currency::Bitcoin)
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("1 cup coffee");
+
+        let (payment_request, polls) = block_on(builder.clone().build_signed_async(Hsm));
+        assert_eq!(polls, 2);
+        assert_eq!(
+            payment_request.unwrap(),
+            builder.clone().build_signed(SoftwareSigner::new(SEC_KEY.clone())).unwrap()
+        );
+
+        let signer = SoftwareSigner::new(SEC_KEY.clone());
+        let (payment_request, polls) = block_on(
+            builder
+                .amount_msat(u64::MAX)
+                .build_signed_async(&signer),
+        );
+        assert_eq!(polls, 1);
+        assert!(matches!(payment_request, Err(Error::AmountError(_))));
+    }
+
+    #[test]
+    fn build_signed_without_amount() {
+        let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("donation")
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+        let decoded = PaymentRequest::decode(&payment_request).unwrap();
+
+        assert!(payment_request.starts_with("lnbc1"));
+        assert_eq!(decoded.amount(), None);
+
+        let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+            .amount(Amount::from_sat(1000).unwrap())
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("1 cup coffee")
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+
+        assert!(payment_request.starts_with("lnbc10u1"));
+    }
+
+    #[test]
+    fn build_signed_exceeding_supply() {
+        let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+            .amount_msat(::amount::MAX_AMOUNT_MSAT + 1)
+            .payment_hash([0u8; 32])
+            .description("1 cup coffee")
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY));
+
+        assert!(matches!(payment_request, Err(Error::AmountError(AmountError::ExceedsSupply(_)))));
+    }
+
+    #[test]
+    fn build_signed_description_hash() {
+        let payment_request = InvoiceBuilder::new(Currency::Testnet)
+            .timestamp(1496314658)
+            .description_hash([1u8; 32])
+            .payment_hash([0u8; 32])
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+        let decoded = PaymentRequest::decode(&payment_request).unwrap();
+
+        assert_eq!(decoded.prefix, "lntb");
+        assert_eq!(decoded.description_hash(), Some(DescriptionHash::from([1u8; 32])));
+        assert_eq!(decoded.payment_hash(), Some(PaymentHash::from([0u8; 32])));
+    }
+
+    #[test]
+    fn build_signed_route_hint() {
+        let pub_key = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+            .parse::<NodeId>()
+            .unwrap();
+        let policy = ChannelPolicy {
+            fee_base_msat: 1,
+            fee_proportional_millionths: 20,
+            cltv_expiry_delta: 3,
+        };
+        let path = RouteHintBuilder::new()
+            .hop(pub_key, "66051:263430:1800", &policy)
+            .unwrap()
+            .extra_hop(ExtraHop::new(pub_key, ShortChannelId::from(1), 2, 30, 4))
+            .build();
+        assert!(
+            RouteHintBuilder::new()
+                .hop(pub_key, "66051:263430", &policy)
+                .is_err()
+        );
+
+        let payment_request = InvoiceBuilder::new(Currency::Bitcoin)
+            .timestamp(1496314658)
+            .payment_hash([0u8; 32])
+            .description("coffee")
+            .route_hint(path.clone())
+            .build_signed_with(|message| secp256k1::sign(message, &SEC_KEY))
+            .unwrap();
+        let decoded = PaymentRequest::decode(&payment_request).unwrap();
+
+        assert_eq!(decoded.route_hints(), vec![path]);
+    }
+}
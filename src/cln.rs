@@ -0,0 +1,297 @@
+//! View of a payment request with the shape of Core Lightning's `decodepay` output, see
+//! `PaymentRequest::to_cln`. <br>
+//! *Note*: with the `serde` feature the views serialize to the JSON of `decodepay`, the missing
+//! optional fields being left out.
+
+use address;
+use currency::Currency;
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use tag::{ExtraHop, Tag};
+use utils::to_hex;
+
+/// Decoded payment request, `decodepay`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DecodePay {
+    /// Currency part of the prefix, e.g. `bc`.
+    pub currency: String,
+    /// Request timestamp (UNIX format).
+    pub created_at: u64,
+    /// Expiry in seconds, 3600 if not specified.
+    pub expiry: u64,
+    /// Node id of the payee, hex encoded.
+    pub payee: String,
+    /// Amount in millisatoshis, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub msatoshi: Option<u64>,
+    /// Amount in millisatoshis followed by `msat`, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub amount_msat: Option<String>,
+    /// Description, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub description: Option<String>,
+    /// Description hash, hex encoded, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub description_hash: Option<String>,
+    /// min_final_cltv_expiry, 18 if not specified.
+    pub min_final_cltv_expiry: u64,
+    /// Feature bits, hex encoded big-endian bytes, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub features: Option<String>,
+    /// Fallback addresses, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub fallbacks: Option<Vec<Fallback>>,
+    /// Private routes, one per routing tag, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub routes: Option<Vec<Vec<Route>>>,
+    /// Payment metadata, hex encoded, if any.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub payment_metadata: Option<String>,
+    /// Payment hash, hex encoded.
+    pub payment_hash: String,
+    /// DER encoded signature, hex encoded.
+    pub signature: String,
+}
+
+/// Fallback address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fallback {
+    /// Type of the address, e.g. `P2WPKH`, missing for the unknown witness programs.
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "type", skip_serializing_if = "Option::is_none", default)
+    )]
+    pub address_type: Option<String>,
+    /// Address of the network, if it can be converted.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none", default))]
+    pub addr: Option<String>,
+    /// Output script, hex encoded.
+    pub hex: String,
+}
+
+/// Hop of a private route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Route {
+    /// Node id of the start of the channel, hex encoded.
+    pub pubkey: String,
+    /// Short channel id, e.g. `504x1x0`.
+    pub short_channel_id: String,
+    /// Base fee in millisatoshis.
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths.
+    pub fee_proportional_millionths: u32,
+    /// Channel's cltv expiry delta.
+    pub cltv_expiry_delta: u16,
+}
+
+impl DecodePay {
+    /// Create the Core Lightning view of a payment request.
+    /// # Arguments
+    /// `payment_request` Payment request to convert.
+    pub fn new(payment_request: &PaymentRequest) -> DecodePay {
+        let currency = payment_request.currency();
+        let fallbacks = payment_request
+            .tags
+            .iter()
+            .filter_map(|tag| match *tag {
+                Tag::FallbackAddress { version, ref hash } => {
+                    Fallback::new(version, hash, currency.as_ref())
+                }
+                _ => None,
+            })
+            .collect::<Vec<Fallback>>();
+        let routes = payment_request
+            .route_hints()
+            .iter()
+            .map(|path| path.iter().map(Route::new).collect())
+            .collect::<Vec<Vec<Route>>>();
+        DecodePay {
+            currency: payment_request
+                .prefix
+                .trim_start_matches("ln")
+                .to_owned(),
+            created_at: payment_request.timestamp,
+            expiry: payment_request.expiry_seconds(),
+            payee: NodeId::from(&payment_request.node_id).to_string(),
+            msatoshi: payment_request.amount,
+            amount_msat: payment_request.amount.map(|amount| format!("{}msat", amount)),
+            description: payment_request
+                .tags
+                .iter()
+                .filter_map(|tag| match *tag {
+                    Tag::Description { ref description } => Some(description.to_owned()),
+                    _ => None,
+                })
+                .next(),
+            description_hash: payment_request
+                .description_hash()
+                .map(|hash| hash.to_string()),
+            min_final_cltv_expiry: payment_request.min_final_cltv_expiry(),
+            features: payment_request
+                .features()
+                .map(|features| to_hex(&features.to_bytes())),
+            fallbacks: if fallbacks.is_empty() {
+                None
+            } else {
+                Some(fallbacks)
+            },
+            routes: if routes.is_empty() {
+                None
+            } else {
+                Some(routes)
+            },
+            payment_metadata: payment_request
+                .payment_metadata()
+                .map(|bytes| to_hex(&bytes)),
+            payment_hash: payment_request
+                .payment_hash()
+                .map(|hash| hash.to_string())
+                .unwrap_or_default(),
+            signature: to_hex(&der_signature(&payment_request.signature)),
+        }
+    }
+}
+
+impl Fallback {
+    /// Describe a fallback address, None if the hash doesn't match the version.
+    /// # Arguments
+    /// `version` The `f` field version.
+    /// `hash` The address hash, or witness program.
+    /// `currency` Currency of the payment request, to convert the address.
+    pub fn new(version: u8, hash: &[u8], currency: Option<&Currency>) -> Option<Fallback> {
+        let script = address::script_pubkey(version, hash).ok()?;
+        let address_type = match (version, hash.len()) {
+            (address::P2PKH_VERSION, _) => Some("P2PKH"),
+            (address::P2SH_VERSION, _) => Some("P2SH"),
+            (0, 20) => Some("P2WPKH"),
+            (0, 32) => Some("P2WSH"),
+            (1, 32) => Some("P2TR"),
+            _ => None,
+        };
+        Some(Fallback {
+            address_type: address_type.map(str::to_owned),
+            addr: currency.and_then(|currency| address::to_address(version, hash, currency).ok()),
+            hex: to_hex(&script),
+        })
+    }
+}
+
+impl Route {
+    /// Create the Core Lightning view of an extra hop.
+    /// # Arguments
+    /// `hop` Extra hop of a routing tag.
+    pub fn new(hop: &ExtraHop) -> Route {
+        Route {
+            pubkey: hop.pub_key.to_string(),
+            short_channel_id: hop.short_channel_id.to_string(),
+            fee_base_msat: hop.fee_base_msat,
+            fee_proportional_millionths: hop.fee_proportional_millionths,
+            cltv_expiry_delta: hop.cltv_expiry_delta,
+        }
+    }
+}
+
+// Helper for DecodePay::new, DER encoding of the 64 bytes compact signature, the recovery id
+// being dropped; empty if the signature is missing.
+fn der_signature(signature: &[u8]) -> Vec<u8> {
+    if signature.len() < 64 {
+        return vec![];
+    }
+    let der_integer = |bytes: &[u8]| {
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len() - 1);
+        let value = &bytes[start..];
+        let padding = if value[0] & 0x80 != 0 { vec![0u8] } else { vec![] };
+        [vec![0x02, (padding.len() + value.len()) as u8], padding, value.to_vec()].concat()
+    };
+    let integers = [der_integer(&signature[..32]), der_integer(&signature[32..64])].concat();
+    [vec![0x30, integers.len() as u8], integers].concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use short_channel_id::ShortChannelId;
+
+    #[test]
+    fn decode_pay() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let decode_pay = pay_request.to_cln();
+
+        assert_eq!(
+            decode_pay,
+            DecodePay {
+                currency: "bc".to_owned(),
+                created_at: 1496314658,
+                expiry: 60,
+                payee: "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+                    .to_owned(),
+                msatoshi: Some(250_000_000),
+                amount_msat: Some("250000000msat".to_owned()),
+                description: Some("1 cup coffee".to_owned()),
+                description_hash: None,
+                min_final_cltv_expiry: 18,
+                features: None,
+                fallbacks: None,
+                routes: None,
+                payment_metadata: None,
+                payment_hash: "0001020304050607080900010203040506070809000102030405060708090102"
+                    .to_owned(),
+                signature: decode_pay.signature.to_owned(),
+            }
+        );
+        assert!(decode_pay.signature.starts_with("30"));
+        assert!(decode_pay.signature.contains(&to_hex(&pay_request.signature[33..64])));
+    }
+
+    #[test]
+    fn fallbacks_and_routes() {
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+            58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+            9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+            qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+            f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+            dhhwkj";
+        let decode_pay = PaymentRequest::decode(tx_ref).unwrap().to_cln();
+
+        assert_eq!(decode_pay.description, None);
+        assert_eq!(
+            decode_pay.fallbacks,
+            Some(vec![Fallback {
+                address_type: Some("P2PKH".to_owned()),
+                addr: Some("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned()),
+                hex: "76a91404b61f7dc1ea0dc99424464cc4064dc564d91e8988ac".to_owned(),
+            }])
+        );
+        let routes = decode_pay.routes.unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(
+            routes[0][1],
+            Route {
+                pubkey: "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+                    .to_owned(),
+                short_channel_id: ShortChannelId::from(217304205466536202).to_string(),
+                fee_base_msat: 2,
+                fee_proportional_millionths: 30,
+                cltv_expiry_delta: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn der_signatures() {
+        let mut signature = [0u8; 65];
+        signature[31] = 1;
+        signature[32] = 0x80;
+
+        let der = der_signature(&signature);
+
+        assert_eq!(der[..5], [0x30, 0x26, 0x02, 0x01, 0x01]);
+        assert_eq!(der[5..9], [0x02, 0x21, 0x00, 0x80]);
+        assert_eq!(der.len(), 0x28);
+        assert!(der_signature(&[]).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+//! Currencies (networks) a payment request can be issued for.
+
+/// Currency prefix of a payment request, `ln` + BIP-0173 currency prefix. <br>
+/// *Note*: currencies are equal when their prefixes are, a custom currency with the prefix of a
+/// Bitcoin one being that currency.
+#[derive(Debug, Clone, Copy)]
+pub enum Currency {
+    /// `lnbc` Bitcoin mainnet.
+    Bitcoin,
+    /// `lntb` Bitcoin testnet.
+    Testnet,
+    /// `lntbs` Bitcoin signet.
+    Signet,
+    /// `lnbcrt` Bitcoin regtest.
+    Regtest,
+    /// Currency of another chain, holding its full prefix, e.g. `lnltc` for Litecoin, see
+    /// `Currency::custom`. Decoding its payment requests requires registering it in
+    /// `ParseOptions::custom_currencies`.
+    Custom(&'static str),
+}
+
+impl Currency {
+    /// Return all the Bitcoin currencies.
+    pub fn all() -> &'static [Currency] {
+        &[
+            Currency::Bitcoin,
+            Currency::Testnet,
+            Currency::Signet,
+            Currency::Regtest,
+        ]
+    }
+
+    /// Create the currency of another chain, `None` if the prefix isn't `ln` followed by the
+    /// BIP-0173 prefix of the chain in lowercase letters. The prefix of a Bitcoin currency
+    /// returns that currency.
+    /// # Arguments
+    /// `prefix` Payment request prefix, e.g. `lnltc`.
+    pub fn custom(prefix: &'static str) -> Option<Currency> {
+        match Currency::from_prefix(prefix) {
+            Some(currency) => Some(currency),
+            None if prefix.len() > 2 && prefix.starts_with("ln")
+                && prefix.chars().all(|c| c.is_ascii_lowercase()) =>
+            {
+                Some(Currency::Custom(prefix))
+            }
+            None => None,
+        }
+    }
+
+    /// Return the payment request prefix of the currency.
+    pub fn prefix(&self) -> &'static str {
+        match *self {
+            Currency::Bitcoin => "lnbc",
+            Currency::Testnet => "lntb",
+            Currency::Signet => "lntbs",
+            Currency::Regtest => "lnbcrt",
+            Currency::Custom(prefix) => prefix,
+        }
+    }
+
+    /// Return the Bitcoin currency matching the payment request prefix if any.
+    /// # Arguments
+    /// `prefix` Payment request prefix, e.g. `lnbc`.
+    pub fn from_prefix(prefix: &str) -> Option<Currency> {
+        Currency::all()
+            .iter()
+            .find(|currency| currency.prefix() == prefix)
+            .cloned()
+    }
+
+    /// Return the Bitcoin currency of the human readable part of a payment request if any, the
+    /// prefix is followed by the amount, which starts with a digit.
+    /// # Arguments
+    /// `hrp` Human readable part, e.g. `lnbcrt2500u`.
+    pub fn from_hrp(hrp: &str) -> Option<Currency> {
+        Currency::from_hrp_with(hrp, &[])
+    }
+
+    /// Return the currency of the human readable part of a payment request if any, among the
+    /// Bitcoin currencies and the custom ones, see `from_hrp`.
+    /// # Arguments
+    /// `hrp` Human readable part, e.g. `lnltc2500u`. <br>
+    /// `custom` Currencies of other chains, e.g. from `ParseOptions::custom_currencies`.
+    pub fn from_hrp_with(hrp: &str, custom: &[Currency]) -> Option<Currency> {
+        let prefix = hrp.split(|c: char| c.is_ascii_digit()).next().unwrap_or_default();
+        Currency::all()
+            .iter()
+            .chain(custom)
+            .find(|currency| currency.prefix() == prefix)
+            .cloned()
+    }
+}
+
+impl PartialEq for Currency {
+    fn eq(&self, other: &Currency) -> bool {
+        self.prefix() == other.prefix()
+    }
+}
+
+impl Eq for Currency {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn all_currencies() {
+        let currencies = Currency::all();
+
+        assert!(currencies.contains(&Currency::Bitcoin));
+        assert!(currencies.contains(&Currency::Testnet));
+        assert_eq!(Currency::Bitcoin.prefix(), "lnbc");
+        assert_eq!(Currency::Testnet.prefix(), "lntb");
+        assert_eq!(Currency::Signet.prefix(), "lntbs");
+        assert_eq!(Currency::Regtest.prefix(), "lnbcrt");
+    }
+
+    #[test]
+    fn from_prefix() {
+        for currency in Currency::all() {
+            assert_eq!(Currency::from_prefix(currency.prefix()), Some(*currency));
+        }
+        assert_eq!(Currency::from_prefix("lnxx"), None);
+        assert_eq!(Currency::from_prefix("ln"), None);
+        assert_eq!(Currency::from_prefix("bc"), None);
+        assert_eq!(Currency::from_prefix("lnb1"), None);
+    }
+
+    #[test]
+    fn from_hrp() {
+        assert_eq!(Currency::from_hrp("lnbc"), Some(Currency::Bitcoin));
+        assert_eq!(Currency::from_hrp("lnbc2500u"), Some(Currency::Bitcoin));
+        assert_eq!(Currency::from_hrp("lntb20m"), Some(Currency::Testnet));
+        assert_eq!(Currency::from_hrp("lntbs"), Some(Currency::Signet));
+        assert_eq!(Currency::from_hrp("lntbs1m"), Some(Currency::Signet));
+        assert_eq!(Currency::from_hrp("lnbcrt2500u"), Some(Currency::Regtest));
+        assert_eq!(Currency::from_hrp("lnltc2500u"), None);
+        assert_eq!(Currency::from_hrp("ln2500u"), None);
+
+        let litecoin = Currency::custom("lnltc").unwrap();
+        assert_eq!(
+            Currency::from_hrp_with("lnltc2500u", &[litecoin]),
+            Some(Currency::Custom("lnltc"))
+        );
+        assert_eq!(Currency::from_hrp_with("lnbc2500u", &[litecoin]), Some(Currency::Bitcoin));
+        assert_eq!(Currency::from_hrp_with("lnsb2500u", &[litecoin]), None);
+    }
+
+    #[test]
+    fn custom() {
+        assert_eq!(Currency::custom("lnltc"), Some(Currency::Custom("lnltc")));
+        assert_eq!(Currency::custom("lnltc").unwrap().prefix(), "lnltc");
+        assert!(matches!(Currency::custom("lnbc"), Some(Currency::Bitcoin)));
+        assert_eq!(Currency::Custom("lnbc"), Currency::Bitcoin);
+        assert_ne!(Currency::Custom("lnltc"), Currency::Bitcoin);
+        assert_eq!(Currency::custom("ln"), None);
+        assert_eq!(Currency::custom("bc"), None);
+        assert_eq!(Currency::custom("lnLTC"), None);
+        assert_eq!(Currency::custom("lnb1"), None);
+    }
+}
@@ -0,0 +1,232 @@
+//! Streaming encoding of payment requests. <br>
+//! `Bech32Writer` writes the 5-bits values straight to a `fmt::Write` sink as bech32 characters
+//! and computes the checksum as it goes, instead of building the data part, the checksum input
+//! and the encoded string in intermediate vectors. <br>
+//! *Note*: see `PaymentRequest::write_to` and `PaymentRequest::write_to_io` to encode a payment
+//! request.
+
+use bech32::{polymod_step, CHARSET};
+use types::Error;
+use utils::U5;
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+
+/// Length of the checksum in u5.
+const CHECKSUM_LEN: usize = 6;
+
+/// Writer of a bech32 string, the human readable part is written on creation and the checksum
+/// by `finish`.
+pub struct Bech32Writer<'a, W: fmt::Write + 'a> {
+    writer: &'a mut W,
+    chk: u32,
+    acc: u32,
+    bits: u32,
+}
+
+impl<'a, W: fmt::Write> Bech32Writer<'a, W> {
+    /// Write the human readable part and the separator.
+    /// # Arguments
+    /// `hrp` Human readable part, formatted twice to compute the checksum without copying it.
+    /// `writer` Sink of the bech32 string.
+    pub fn new<H: fmt::Display>(
+        hrp: &H,
+        writer: &'a mut W,
+    ) -> Result<Bech32Writer<'a, W>, fmt::Error> {
+        write!(writer, "{}1", hrp)?;
+        // the high bits of the characters, a zero, then their low bits
+        let mut high = HrpChecksum { chk: 1, high: true };
+        write!(high, "{}", hrp)?;
+        let mut low = HrpChecksum {
+            chk: polymod_step(high.chk, 0),
+            high: false,
+        };
+        write!(low, "{}", hrp)?;
+        Ok(Bech32Writer {
+            writer,
+            chk: low.chk,
+            acc: 0,
+            bits: 0,
+        })
+    }
+
+    /// Write a 5-bits value.
+    pub fn write_u5(&mut self, value: U5) -> fmt::Result {
+        debug_assert!(value < 32, "invalid 5-bits value {}", value);
+        self.chk = polymod_step(self.chk, value);
+        self.writer.write_char(CHARSET[(value & 31) as usize])
+    }
+
+    /// Write 5-bits values.
+    pub fn write_u5_slice(&mut self, values: &[U5]) -> fmt::Result {
+        values.iter().try_for_each(|value| self.write_u5(*value))
+    }
+
+    /// Write the 5-bits values of a number, big-endian, on the given number of values.
+    pub fn write_number(&mut self, value: u64, len: usize) -> fmt::Result {
+        (0..len).rev().try_for_each(|i| {
+            self.write_u5(value.checked_shr(5 * i as u32).unwrap_or(0) as U5 & 31)
+        })
+    }
+
+    /// Write bytes converted to 5-bits values, the trailing bits are kept until the next bytes or
+    /// `flush_bytes`.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> fmt::Result {
+        for byte in bytes {
+            self.acc = (self.acc << 8 | u32::from(*byte)) & 0xfff;
+            self.bits += 8;
+            while self.bits >= 5 {
+                self.bits -= 5;
+                let value = (self.acc >> self.bits) as U5 & 31;
+                self.write_u5(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete the trailing bits of the written bytes with zero bits.
+    pub fn flush_bytes(&mut self) -> fmt::Result {
+        if self.bits > 0 {
+            let value = (self.acc << (5 - self.bits)) as U5 & 31;
+            self.bits = 0;
+            self.acc = 0;
+            self.write_u5(value)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the trailing bits and write the bech32 checksum.
+    pub fn finish(mut self) -> fmt::Result {
+        self.flush_bytes()?;
+        let chk = (0..CHECKSUM_LEN).fold(self.chk, |chk, _| polymod_step(chk, 0)) ^ 1;
+        (0..CHECKSUM_LEN)
+            .map(|i| (chk >> (5 * (CHECKSUM_LEN - 1 - i))) as U5 & 31)
+            .try_for_each(|value| self.writer.write_char(CHARSET[value as usize]))
+    }
+}
+
+/// Number of 5-bits values of bytes, the last one completed with zero bits.
+pub fn u5_len(bytes_len: usize) -> usize {
+    (bytes_len * 8).div_ceil(5)
+}
+
+/// Number of 5-bits values of a number without leading zeroes, 0 for 0.
+pub fn number_u5_len(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(5)
+}
+
+// Helper for Bech32Writer::new, feed the high or the low bits of the characters to the checksum.
+struct HrpChecksum {
+    chk: u32,
+    high: bool,
+}
+
+impl fmt::Write for HrpChecksum {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let high = self.high;
+        self.chk = s
+            .bytes()
+            .fold(self.chk, |chk, b| polymod_step(chk, if high { b >> 5 } else { b & 31 }));
+        Ok(())
+    }
+}
+
+/// Adapter of an `io::Write` sink to `fmt::Write`, keeping the io error that stopped the writing.
+/// <br>
+/// *Note*: every character is written on its own, wrap unbuffered sinks in a `BufWriter`.
+pub struct IoWriter<'a, W: io::Write + 'a> {
+    inner: &'a mut W,
+    /// Error of the last failed write.
+    pub error: Option<io::Error>,
+}
+
+impl<'a, W: io::Write> IoWriter<'a, W> {
+    /// Wrap an io sink.
+    pub fn new(inner: &'a mut W) -> IoWriter<'a, W> {
+        IoWriter { inner, error: None }
+    }
+
+    /// Return the io error of a failed write if any, otherwise map the formatting error.
+    pub fn into_error(self, error: Error) -> Error {
+        self.error.map(Error::IOErr).unwrap_or(error)
+    }
+}
+
+impl<'a, W: io::Write> fmt::Write for IoWriter<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Error of a sink refusing the encoded payment request.
+pub fn write_error(_: fmt::Error) -> Error {
+    Error::IOErr(io::Error::other("the sink failed to write the payment request"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bech32::Bech32;
+    use timestamp::Timestamp;
+    use utils::{convert_bits, Padding};
+
+    #[test]
+    fn writer_matches_bech32() {
+        let bytes = [0xffu8, 0x00, 0x5a, 0x3c, 0x01, 0x80, 0x7e];
+        let mut encoded = String::new();
+        {
+            let mut writer = Bech32Writer::new(&"lnbc1m", &mut encoded).unwrap();
+            writer.write_u5_slice(&[0, 31]).unwrap();
+            writer.write_number(1496314658, 7).unwrap();
+            writer.write_bytes(&bytes).unwrap();
+            writer.finish().unwrap();
+        }
+        let data = [
+            vec![0, 31],
+            Timestamp::encode(1496314658).unwrap(),
+            convert_bits(&bytes, 8, 5, Padding::Add).unwrap(),
+        ];
+        let expected = Bech32 {
+            hrp: "lnbc1m".to_owned(),
+            data: data.concat(),
+        };
+        assert_eq!(expected.to_string().unwrap(), encoded);
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(52, u5_len(32));
+        assert_eq!(53, u5_len(33));
+        assert_eq!(0, u5_len(0));
+        assert_eq!(0, number_u5_len(0));
+        assert_eq!(1, number_u5_len(31));
+        assert_eq!(2, number_u5_len(32));
+        assert_eq!(13, number_u5_len(u64::MAX));
+    }
+
+    #[test]
+    fn io_writer() {
+        let mut out = Vec::new();
+        {
+            let mut sink = IoWriter::new(&mut out);
+            Bech32Writer::new(&"bc", &mut sink).unwrap().finish().unwrap();
+        }
+        let empty = Bech32 {
+            hrp: "bc".to_owned(),
+            data: vec![],
+        };
+        assert_eq!(empty.to_string().unwrap().into_bytes(), out);
+
+        let mut full = [0u8; 2];
+        let mut slice = &mut full[..];
+        let mut sink = IoWriter::new(&mut slice);
+        assert!(Bech32Writer::new(&"bc", &mut sink).is_err());
+        match sink.into_error(Error::InvalidPadding) {
+            Error::IOErr(e) => assert_eq!(io::ErrorKind::WriteZero, e.kind()),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+}
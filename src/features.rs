@@ -0,0 +1,190 @@
+//! BOLT 9 feature bits of a payment request.
+
+use utils::U5;
+
+/// Feature bit vector of the `9` tagged field. <br>
+/// *Note*: the bits are stored as 5-bits words, big-endian, the last word holds the bits 0 to 4.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Features {
+    words: Vec<U5>,
+}
+
+impl Features {
+    /// `var_onion_optin` Requires variable-length routing onion payloads.
+    pub const VAR_ONION_OPTIN: usize = 8;
+    /// `payment_secret` Requires the payment secret field.
+    pub const PAYMENT_SECRET: usize = 14;
+    /// `basic_mpp` Requires support for multi-part payments.
+    pub const BASIC_MPP: usize = 16;
+    /// `option_payment_metadata` Requires the payment metadata to be sent in the onion.
+    pub const PAYMENT_METADATA: usize = 48;
+
+    /// Create an empty feature bit vector.
+    pub fn new() -> Features {
+        Features::default()
+    }
+
+    /// Create the feature bit vector of the features known to this crate.
+    pub fn known() -> Features {
+        let mut features = Features::new();
+        for bit in &[
+            Features::VAR_ONION_OPTIN,
+            Features::PAYMENT_SECRET,
+            Features::BASIC_MPP,
+            Features::PAYMENT_METADATA,
+        ] {
+            features.set_bit(*bit);
+        }
+        features
+    }
+
+    /// Create a feature bit vector from its 5-bits words, big-endian.
+    /// # Arguments
+    /// `words` 5-bits words of the `9` tagged field.
+    pub fn from_words(words: Vec<U5>) -> Features {
+        Features { words }
+    }
+
+    /// Return the 5-bits words of the feature bit vector, big-endian.
+    pub fn to_words(&self) -> Vec<U5> {
+        self.words.to_owned()
+    }
+
+    /// Borrow the 5-bits words of the feature bit vector, big-endian.
+    pub fn words(&self) -> &[U5] {
+        &self.words
+    }
+
+    /// Return the feature bit vector as bytes, big-endian, as in the BOLT 9 messages.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bits = self.bits();
+        let len = bits.last().map(|bit| bit / 8 + 1).unwrap_or_default();
+        let mut bytes = vec![0u8; len];
+        for bit in bits {
+            bytes[len - 1 - bit / 8] |= 1 << (bit % 8);
+        }
+        bytes
+    }
+
+    /// Set the bit, extending the vector when needed.
+    /// # Arguments
+    /// `bit` Feature bit to set, e.g. `Features::PAYMENT_SECRET`.
+    pub fn set_bit(&mut self, bit: usize) {
+        let word = bit / 5;
+        if word >= self.words.len() {
+            let missing = word + 1 - self.words.len();
+            self.words.splice(0..0, vec![0u8; missing]);
+        }
+        let index = self.words.len() - 1 - word;
+        self.words[index] |= 1 << (bit % 5);
+    }
+
+    /// Check if the bit is set.
+    /// # Arguments
+    /// `bit` Feature bit to check.
+    pub fn is_set(&self, bit: usize) -> bool {
+        match self.words.len().checked_sub(1 + bit / 5) {
+            Some(index) => (self.words[index] >> (bit % 5)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    /// Check if the feature is set, either as required (even bit) or optional (odd bit).
+    /// # Arguments
+    /// `bit` Either bit of the feature, e.g. `Features::BASIC_MPP`.
+    pub fn has_feature(&self, bit: usize) -> bool {
+        self.is_set(bit & !1) || self.is_set(bit | 1)
+    }
+
+    /// Return the bits set, in ascending order.
+    pub fn bits(&self) -> Vec<usize> {
+        (0..self.words.len() * 5).filter(|&bit| self.is_set(bit)).collect()
+    }
+
+    /// Return the required bits, the even ones, whose feature is unknown, in ascending
+    /// order. <br>
+    /// *Note*: a feature is known if either its even or its odd bit is set in `known`.
+    /// # Arguments
+    /// `known` Features known to the payer, e.g. `Features::known()`.
+    pub fn unknown_required_bits(&self, known: &Features) -> Vec<usize> {
+        self.bits()
+            .into_iter()
+            .filter(|&bit| bit % 2 == 0 && !known.is_set(bit) && !known.is_set(bit + 1))
+            .collect()
+    }
+
+    /// Check if a required bit, an even one, is set for a feature unknown to the payer, in
+    /// which case the payment must not be attempted ("it's OK to be odd").
+    /// # Arguments
+    /// `known` Features known to the payer, e.g. `Features::known()`.
+    pub fn requires_unknown_bits(&self, known: &Features) -> bool {
+        !self.unknown_required_bits(known).is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_bit() {
+        let mut features = Features::new();
+        features.set_bit(Features::VAR_ONION_OPTIN);
+        features.set_bit(Features::PAYMENT_SECRET);
+
+        assert_eq!(features.to_words(), vec![16, 8, 0]);
+        assert!(features.is_set(Features::VAR_ONION_OPTIN));
+        assert!(features.is_set(Features::PAYMENT_SECRET));
+        assert!(!features.is_set(Features::BASIC_MPP));
+        assert!(!features.is_set(100));
+        assert_eq!(features.bits(), vec![8, 14]);
+
+        features.set_bit(Features::BASIC_MPP + 1);
+
+        assert!(features.has_feature(Features::BASIC_MPP));
+        assert!(features.has_feature(Features::PAYMENT_SECRET + 1));
+        assert!(!features.has_feature(18));
+
+        features.set_bit(Features::BASIC_MPP);
+
+        assert_eq!(features.to_words(), vec![6, 16, 8, 0]);
+        assert_eq!(features.bits(), vec![8, 14, 16, 17]);
+        assert_eq!(features.to_bytes(), vec![0x03, 0x41, 0x00]);
+        assert!(Features::new().to_bytes().is_empty());
+    }
+
+    #[test]
+    fn requires_unknown_bits() {
+        let mut features = Features::new();
+        features.set_bit(Features::PAYMENT_SECRET);
+        features.set_bit(Features::BASIC_MPP + 1);
+        features.set_bit(21);
+
+        assert!(!features.requires_unknown_bits(&Features::known()));
+
+        features.set_bit(20);
+
+        assert!(features.requires_unknown_bits(&Features::known()));
+        assert_eq!(features.unknown_required_bits(&Features::known()), vec![20]);
+        assert_eq!(
+            features.unknown_required_bits(&Features::new()),
+            vec![Features::PAYMENT_SECRET, 20]
+        );
+
+        // the odd bit is enough to know the feature
+        let mut known = Features::new();
+        known.set_bit(15);
+        known.set_bit(21);
+        assert!(!features.requires_unknown_bits(&known));
+    }
+
+    #[test]
+    fn from_words() {
+        // leading zero words are kept
+        let features = Features::from_words(vec![0, 16, 16, 0]);
+
+        assert_eq!(features.bits(), vec![9, 14]);
+        assert_eq!(features.to_words(), vec![0, 16, 16, 0]);
+    }
+}
@@ -0,0 +1,486 @@
+//! C interface, behind the `ffi` feature, see `include/bolt11.h` generated by
+//! `cbindgen --config cbindgen.toml --output include/bolt11.h`. The library to link is built with
+//! `cargo rustc --release --features ffi --crate-type cdylib`, or `staticlib`. <br>
+//! *Note*: the decoded payment requests are owned by the library and must be released with
+//! `bolt11_free`, the strings and byte arrays they point to live as long as them. The error codes
+//! are stable, new ones are only appended.
+
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+use tag::{ExtraHop, Tag};
+use types::Error;
+
+/// Status of a call, `BOLT11_OK` or the kind of the error.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bolt11Status {
+    /// Success.
+    Bolt11Ok = 0,
+    /// Null pointer argument.
+    Bolt11NullPointer = 1,
+    /// Input string that is not UTF-8.
+    Bolt11InvalidUtf8 = 2,
+    /// Index out of range of an accessor.
+    Bolt11OutOfRange = 3,
+    /// Invalid bech32 string or checksum.
+    Bolt11InvalidBech32 = 10,
+    /// Invalid payment request structure or length.
+    Bolt11InvalidFormat = 11,
+    /// Invalid amount.
+    Bolt11InvalidAmount = 12,
+    /// Malformed tagged field.
+    Bolt11InvalidTag = 13,
+    /// Invalid or unrecoverable signature.
+    Bolt11InvalidSignature = 14,
+    /// Invalid value, e.g. a description containing a nul character.
+    Bolt11InvalidValue = 15,
+    /// Payment request for an unexpected network.
+    Bolt11WrongNetwork = 16,
+    /// Payment request exceeding a size limit.
+    Bolt11LimitExceeded = 17,
+    /// Payment request violating the BOLT #11 rules.
+    Bolt11ValidationFailed = 18,
+    /// Any other error.
+    Bolt11Unknown = 99,
+}
+
+/// Decoded payment request.
+#[repr(C)]
+pub struct Bolt11Invoice {
+    /// Prefix, e.g. `lnbc`, nul terminated.
+    pub prefix: *const c_char,
+    /// Whether the payment request has an amount.
+    pub has_amount: bool,
+    /// Amount in millisatoshis, 0 if missing.
+    pub amount_msat: u64,
+    /// Request timestamp (UNIX format).
+    pub timestamp: u64,
+    /// Expiry in seconds, 3600 if not specified.
+    pub expiry: u64,
+    /// min_final_cltv_expiry, 18 if not specified.
+    pub min_final_cltv_expiry: u64,
+    /// Compressed node id of the payee.
+    pub payee: [u8; 33],
+    /// Whether the payment request has a payment hash.
+    pub has_payment_hash: bool,
+    /// Payment hash, zeroes if missing.
+    pub payment_hash: [u8; 32],
+    /// Description, nul terminated, null if missing.
+    pub description: *const c_char,
+    /// Whether the payment request has a description hash.
+    pub has_description_hash: bool,
+    /// Description hash, zeroes if missing.
+    pub description_hash: [u8; 32],
+    /// Signature, 64 bytes followed by the recovery id.
+    pub signature: [u8; 65],
+    /// Number of private routes, see `bolt11_route_hop`.
+    pub routes_count: usize,
+    /// Number of fallback addresses, see `bolt11_fallback`.
+    pub fallbacks_count: usize,
+    /// Storage of the library, not to be used.
+    pub inner: *mut Bolt11Inner,
+}
+
+/// Hop of a private route.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bolt11Hop {
+    /// Compressed node id of the start of the channel.
+    pub node_id: [u8; 33],
+    /// Short channel id as a 64-bit integer.
+    pub short_channel_id: u64,
+    /// Base fee in millisatoshis.
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths.
+    pub fee_proportional_millionths: u32,
+    /// Channel's cltv expiry delta.
+    pub cltv_expiry_delta: u16,
+}
+
+/// Fallback address as a witness version and program, the program is owned by the payment
+/// request.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bolt11Fallback {
+    /// Witness version, 17 for P2PKH and 18 for P2SH.
+    pub version: u8,
+    /// Witness program or hash.
+    pub program: *const u8,
+    /// Length of the program.
+    pub program_len: usize,
+}
+
+/// Storage of a decoded payment request, opaque to C.
+pub struct Bolt11Inner {
+    prefix: CString,
+    description: Option<CString>,
+    routes: Vec<Vec<ExtraHop>>,
+    fallbacks: Vec<(u8, Vec<u8>)>,
+}
+
+/// Every status, to read a status code given by C.
+const STATUSES: [Bolt11Status; 14] = [
+    Bolt11Status::Bolt11Ok,
+    Bolt11Status::Bolt11NullPointer,
+    Bolt11Status::Bolt11InvalidUtf8,
+    Bolt11Status::Bolt11OutOfRange,
+    Bolt11Status::Bolt11InvalidBech32,
+    Bolt11Status::Bolt11InvalidFormat,
+    Bolt11Status::Bolt11InvalidAmount,
+    Bolt11Status::Bolt11InvalidTag,
+    Bolt11Status::Bolt11InvalidSignature,
+    Bolt11Status::Bolt11InvalidValue,
+    Bolt11Status::Bolt11WrongNetwork,
+    Bolt11Status::Bolt11LimitExceeded,
+    Bolt11Status::Bolt11ValidationFailed,
+    Bolt11Status::Bolt11Unknown,
+];
+
+impl Bolt11Status {
+    // Helper for bolt11_status_message, the status of a code, `Bolt11Unknown` if there is none,
+    // C being able to pass any int.
+    fn from_code(code: c_int) -> Bolt11Status {
+        STATUSES
+            .iter()
+            .cloned()
+            .find(|status| *status as c_int == code)
+            .unwrap_or(Bolt11Status::Bolt11Unknown)
+    }
+
+    // Helper for the functions, the status of an error.
+    fn from_error(error: &Error) -> Bolt11Status {
+        match *error {
+            Error::Bech32Error(_) | Error::InvalidPadding | Error::InvalidInputValue(_) => {
+                Bolt11Status::Bolt11InvalidBech32
+            }
+            Error::InvalidFormat(_) | Error::InvalidLength(_) => Bolt11Status::Bolt11InvalidFormat,
+            Error::AmountError(_) | Error::ParseFloatErr(_) | Error::ParseIntErr(_) => {
+                Bolt11Status::Bolt11InvalidAmount
+            }
+            Error::TagError { .. } | Error::TrailingRouteHintBytes(_) => {
+                Bolt11Status::Bolt11InvalidTag
+            }
+            Error::SignatureError(_) => Bolt11Status::Bolt11InvalidSignature,
+            Error::InvalidValue(_) | Error::InvalidParameter(_) => {
+                Bolt11Status::Bolt11InvalidValue
+            }
+            Error::FromUTF8Err(_) => Bolt11Status::Bolt11InvalidUtf8,
+            Error::WrongNetwork(_) => Bolt11Status::Bolt11WrongNetwork,
+            Error::LimitExceeded(..) => Bolt11Status::Bolt11LimitExceeded,
+            Error::Validation(_) => Bolt11Status::Bolt11ValidationFailed,
+            Error::IOErr(_) | Error::Signer(_) => Bolt11Status::Bolt11Unknown,
+        }
+    }
+}
+
+impl Bolt11Invoice {
+    // Helper for bolt11_decode, the C representation of a payment request.
+    fn new(payment_request: &PaymentRequest) -> Result<Bolt11Invoice, Error> {
+        let to_c_string = |s: String| {
+            CString::new(s).map_err(|e| Error::InvalidValue(format!("nul character: {}", e)))
+        };
+        let description = payment_request
+            .tags
+            .iter()
+            .filter_map(|tag| match *tag {
+                Tag::Description { ref description } => Some(description.to_owned()),
+                _ => None,
+            })
+            .next()
+            .map(to_c_string)
+            .map_or(Ok(None), |description| description.map(Some))?;
+        let inner = Box::new(Bolt11Inner {
+            prefix: to_c_string(payment_request.prefix.to_owned())?,
+            description,
+            routes: payment_request.route_hints(),
+            fallbacks: payment_request
+                .tags
+                .iter()
+                .filter_map(|tag| match *tag {
+                    Tag::FallbackAddress { version, ref hash } => Some((version, hash.to_owned())),
+                    _ => None,
+                })
+                .collect(),
+        });
+        let payment_hash = payment_request.payment_hash();
+        let description_hash = payment_request.description_hash();
+        let mut signature = [0u8; 65];
+        if payment_request.signature.len() == signature.len() {
+            signature.copy_from_slice(&payment_request.signature);
+        }
+        Ok(Bolt11Invoice {
+            prefix: inner.prefix.as_ptr(),
+            has_amount: payment_request.amount.is_some(),
+            amount_msat: payment_request.amount.unwrap_or_default(),
+            timestamp: payment_request.timestamp,
+            expiry: payment_request.expiry_seconds(),
+            min_final_cltv_expiry: payment_request.min_final_cltv_expiry(),
+            payee: *NodeId::from(&payment_request.node_id).as_bytes(),
+            has_payment_hash: payment_hash.is_some(),
+            payment_hash: payment_hash.map_or([0u8; 32], |hash| *hash.as_bytes()),
+            description: inner
+                .description
+                .as_ref()
+                .map_or(ptr::null(), |description| description.as_ptr()),
+            has_description_hash: description_hash.is_some(),
+            description_hash: description_hash.map_or([0u8; 32], |hash| *hash.as_bytes()),
+            signature,
+            routes_count: inner.routes.len(),
+            fallbacks_count: inner.fallbacks.len(),
+            inner: Box::into_raw(inner),
+        })
+    }
+}
+
+/// Decode a payment request. <br>
+/// *Note*: a panic of the decoding is caught and returns `BOLT11_UNKNOWN`, it doesn't unwind
+/// into C.
+/// # Arguments
+/// `invoice` Nul terminated payment request, with or without the `lightning:` scheme.<br>
+/// `out` Set to the decoded payment request on success, to be released with `bolt11_free`.
+/// # Safety
+/// `invoice` must be a nul terminated string and `out` a valid pointer, or null.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_decode(
+    invoice: *const c_char,
+    out: *mut *mut Bolt11Invoice,
+) -> Bolt11Status {
+    if invoice.is_null() || out.is_null() {
+        return Bolt11Status::Bolt11NullPointer;
+    }
+    *out = ptr::null_mut();
+    let invoice = match CStr::from_ptr(invoice).to_str() {
+        Ok(invoice) => invoice,
+        Err(_) => return Bolt11Status::Bolt11InvalidUtf8,
+    };
+    let decoded = panic::catch_unwind(|| {
+        invoice
+            .parse::<PaymentRequest>()
+            .and_then(|payment_request| Bolt11Invoice::new(&payment_request))
+    });
+    match decoded {
+        Ok(Ok(decoded)) => {
+            *out = Box::into_raw(Box::new(decoded));
+            Bolt11Status::Bolt11Ok
+        }
+        Ok(Err(e)) => Bolt11Status::from_error(&e),
+        Err(_) => Bolt11Status::Bolt11Unknown,
+    }
+}
+
+/// Release a payment request returned by `bolt11_decode`, does nothing if it's null.
+/// # Safety
+/// `invoice` must come from `bolt11_decode` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_free(invoice: *mut Bolt11Invoice) {
+    if invoice.is_null() {
+        return;
+    }
+    let invoice = Box::from_raw(invoice);
+    if !invoice.inner.is_null() {
+        drop(Box::from_raw(invoice.inner));
+    }
+}
+
+/// Number of hops of a private route, 0 if the route doesn't exist.
+/// # Arguments
+/// `invoice` Decoded payment request.<br>
+/// `route` Index of the route, below `routes_count`.
+/// # Safety
+/// `invoice` must come from `bolt11_decode`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_route_hops_count(
+    invoice: *const Bolt11Invoice,
+    route: usize,
+) -> usize {
+    inner(invoice)
+        .and_then(|inner| inner.routes.get(route))
+        .map_or(0, Vec::len)
+}
+
+/// Read a hop of a private route.
+/// # Arguments
+/// `invoice` Decoded payment request.<br>
+/// `route` Index of the route, below `routes_count`.<br>
+/// `hop` Index of the hop, below `bolt11_route_hops_count`.<br>
+/// `out` Set to the hop on success.
+/// # Safety
+/// `invoice` must come from `bolt11_decode` and `out` be a valid pointer, or null.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_route_hop(
+    invoice: *const Bolt11Invoice,
+    route: usize,
+    hop: usize,
+    out: *mut Bolt11Hop,
+) -> Bolt11Status {
+    if out.is_null() {
+        return Bolt11Status::Bolt11NullPointer;
+    }
+    let inner = match inner(invoice) {
+        Some(inner) => inner,
+        None => return Bolt11Status::Bolt11NullPointer,
+    };
+    match inner.routes.get(route).and_then(|path| path.get(hop)) {
+        Some(extra_hop) => {
+            *out = Bolt11Hop {
+                node_id: *extra_hop.pub_key.as_bytes(),
+                short_channel_id: u64::from(extra_hop.short_channel_id),
+                fee_base_msat: extra_hop.fee_base_msat,
+                fee_proportional_millionths: extra_hop.fee_proportional_millionths,
+                cltv_expiry_delta: extra_hop.cltv_expiry_delta,
+            };
+            Bolt11Status::Bolt11Ok
+        }
+        None => Bolt11Status::Bolt11OutOfRange,
+    }
+}
+
+/// Read a fallback address.
+/// # Arguments
+/// `invoice` Decoded payment request.<br>
+/// `index` Index of the fallback address, below `fallbacks_count`.<br>
+/// `out` Set to the fallback address on success.
+/// # Safety
+/// `invoice` must come from `bolt11_decode` and `out` be a valid pointer, or null.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_fallback(
+    invoice: *const Bolt11Invoice,
+    index: usize,
+    out: *mut Bolt11Fallback,
+) -> Bolt11Status {
+    if out.is_null() {
+        return Bolt11Status::Bolt11NullPointer;
+    }
+    let inner = match inner(invoice) {
+        Some(inner) => inner,
+        None => return Bolt11Status::Bolt11NullPointer,
+    };
+    match inner.fallbacks.get(index) {
+        Some(&(version, ref program)) => {
+            *out = Bolt11Fallback {
+                version,
+                program: program.as_ptr(),
+                program_len: program.len(),
+            };
+            Bolt11Status::Bolt11Ok
+        }
+        None => Bolt11Status::Bolt11OutOfRange,
+    }
+}
+
+/// Static, nul terminated, description of a status, the one of `BOLT11_UNKNOWN` for a code which
+/// isn't a status.
+/// # Arguments
+/// `status` Status code, e.g. returned by `bolt11_decode`.
+#[no_mangle]
+pub extern "C" fn bolt11_status_message(status: c_int) -> *const c_char {
+    let message: &'static [u8] = match Bolt11Status::from_code(status) {
+        Bolt11Status::Bolt11Ok => b"ok\0",
+        Bolt11Status::Bolt11NullPointer => b"null pointer\0",
+        Bolt11Status::Bolt11InvalidUtf8 => b"invalid utf-8\0",
+        Bolt11Status::Bolt11OutOfRange => b"index out of range\0",
+        Bolt11Status::Bolt11InvalidBech32 => b"invalid bech32\0",
+        Bolt11Status::Bolt11InvalidFormat => b"invalid format\0",
+        Bolt11Status::Bolt11InvalidAmount => b"invalid amount\0",
+        Bolt11Status::Bolt11InvalidTag => b"invalid tagged field\0",
+        Bolt11Status::Bolt11InvalidSignature => b"invalid signature\0",
+        Bolt11Status::Bolt11InvalidValue => b"invalid value\0",
+        Bolt11Status::Bolt11WrongNetwork => b"wrong network\0",
+        Bolt11Status::Bolt11LimitExceeded => b"limit exceeded\0",
+        Bolt11Status::Bolt11ValidationFailed => b"validation failed\0",
+        Bolt11Status::Bolt11Unknown => b"unknown error\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+// Helper for the accessors, the storage of a payment request.
+unsafe fn inner<'a>(invoice: *const Bolt11Invoice) -> Option<&'a Bolt11Inner> {
+    invoice.as_ref().and_then(|invoice| invoice.inner.as_ref())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let tx_ref = CString::new(
+            "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+             58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+             9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+             qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+             f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+             dhhwkj",
+        ).unwrap();
+        let mut invoice = ptr::null_mut();
+
+        unsafe {
+            assert_eq!(bolt11_decode(tx_ref.as_ptr(), &mut invoice), Bolt11Status::Bolt11Ok);
+            let decoded = &*invoice;
+            assert_eq!(CStr::from_ptr(decoded.prefix).to_str().unwrap(), "lnbc");
+            assert!(decoded.has_amount);
+            assert_eq!(decoded.amount_msat, 2_000_000_000);
+            assert!(decoded.description.is_null());
+            assert!(decoded.has_description_hash);
+            assert_eq!(decoded.routes_count, 1);
+            assert_eq!(bolt11_route_hops_count(invoice, 0), 2);
+
+            let mut hop = Bolt11Hop {
+                node_id: [0u8; 33],
+                short_channel_id: 0,
+                fee_base_msat: 0,
+                fee_proportional_millionths: 0,
+                cltv_expiry_delta: 0,
+            };
+            assert_eq!(bolt11_route_hop(invoice, 0, 1, &mut hop), Bolt11Status::Bolt11Ok);
+            assert_eq!(hop.short_channel_id, 217304205466536202);
+            assert_eq!(
+                bolt11_route_hop(invoice, 0, 2, &mut hop),
+                Bolt11Status::Bolt11OutOfRange
+            );
+
+            let mut fallback = Bolt11Fallback {
+                version: 0,
+                program: ptr::null(),
+                program_len: 0,
+            };
+            assert_eq!(bolt11_fallback(invoice, 0, &mut fallback), Bolt11Status::Bolt11Ok);
+            assert_eq!((fallback.version, fallback.program_len), (17, 20));
+            bolt11_free(invoice);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut invoice = ptr::null_mut();
+        let invalid = CString::new("lnbc1qqqqqq").unwrap();
+
+        unsafe {
+            assert_eq!(
+                bolt11_decode(ptr::null(), &mut invoice),
+                Bolt11Status::Bolt11NullPointer
+            );
+            assert_eq!(
+                bolt11_decode(invalid.as_ptr(), &mut invoice),
+                Bolt11Status::Bolt11InvalidBech32
+            );
+            assert!(invoice.is_null());
+            assert_eq!(bolt11_route_hops_count(ptr::null(), 0), 0);
+            assert_eq!(
+                CStr::from_ptr(bolt11_status_message(Bolt11Status::Bolt11InvalidBech32 as c_int)),
+                CStr::from_bytes_with_nul(b"invalid bech32\0").unwrap()
+            );
+            for code in &[-1, 4, 19, 1000] {
+                assert_eq!(
+                    CStr::from_ptr(bolt11_status_message(*code)),
+                    CStr::from_bytes_with_nul(b"unknown error\0").unwrap()
+                );
+            }
+            bolt11_free(ptr::null_mut());
+        }
+    }
+}
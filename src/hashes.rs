@@ -0,0 +1,244 @@
+//! Fixed-size hashes and secrets of a payment request.
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+#[cfg(feature = "rand")]
+use rand::{OsRng, Rng};
+use types::Error;
+use utils::{from_hex, to_hex};
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Define a 32 bytes newtype, built from exactly 32 bytes and shown as hex.
+macro_rules! hash_newtype {
+    ($name:ident, $desc:expr) => {
+        impl $name {
+            /// Create it from a byte slice, which must be 32 bytes.
+            /// # Arguments
+            /// `bytes` The 32 bytes.
+            pub fn from_slice(bytes: &[u8]) -> Result<$name, Error> {
+                if bytes.len() != 32 {
+                    return Err(Error::InvalidLength(format!("{} must be 32 bytes", $desc)));
+                }
+                let mut array = [0u8; 32];
+                array.copy_from_slice(bytes);
+                Ok($name(array))
+            }
+
+            /// Return the 32 bytes.
+            pub fn as_bytes(&self) -> &[u8; 32] {
+                &self.0
+            }
+
+            /// Return the 32 bytes as a vector.
+            pub fn to_vec(&self) -> Vec<u8> {
+                self.0.to_vec()
+            }
+        }
+
+        impl From<[u8; 32]> for $name {
+            fn from(bytes: [u8; 32]) -> $name {
+                $name(bytes)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", to_hex(&self.0))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<$name, Error> {
+                if s.len() != 64 {
+                    return Err(Error::InvalidLength(format!("{} must be 32 bytes", $desc)));
+                }
+                $name::from_slice(&from_hex(s)?)
+            }
+        }
+    };
+}
+
+/// 256-bit SHA256 payment hash, the `p` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PaymentHash(pub [u8; 32]);
+
+hash_newtype!(PaymentHash, "payment hash");
+
+/// 256-bit SHA256 hash of the description, the `h` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DescriptionHash(pub [u8; 32]);
+
+hash_newtype!(DescriptionHash, "description hash");
+
+impl DescriptionHash {
+    /// Hash a description too long for the `d` field, e.g. the metadata of an LNURL-pay service,
+    /// which is then given to the payer out of band.
+    /// # Arguments
+    /// `description` Description of purpose of payment.
+    pub fn from_description(description: &str) -> DescriptionHash {
+        DescriptionHash(sha256(description.as_bytes()))
+    }
+}
+
+/// 256-bit secret preventing forwarding nodes from probing the payee, the `s` field. <br>
+/// *Note*: not `Copy`, so that the copies of the secret are explicit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PaymentSecret(pub [u8; 32]);
+
+hash_newtype!(PaymentSecret, "payment secret");
+
+/// Clear the secret, which is also done when it is dropped. <br>
+/// *Note*: only built with the `zeroize` feature, the clones of the secret are cleared when they
+/// are dropped, but not the bytes it was built from.
+#[cfg(feature = "zeroize")]
+impl Zeroize for PaymentSecret {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for PaymentSecret {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for PaymentSecret {}
+
+/// 256-bit preimage of the payment hash, revealed to the payer when the payment settles. <br>
+/// *Note*: not `Copy`, so that the copies of the preimage are explicit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PaymentPreimage(pub [u8; 32]);
+
+hash_newtype!(PaymentPreimage, "payment preimage");
+
+impl PaymentPreimage {
+    /// Return the payment hash of the preimage, its SHA256.
+    pub fn payment_hash(&self) -> PaymentHash {
+        PaymentHash(sha256(&self.0))
+    }
+
+    /// Generate a preimage with the random number generator of the operating system. <br>
+    /// *Note*: only built with the `rand` feature.
+    #[cfg(feature = "rand")]
+    pub fn random() -> Result<PaymentPreimage, Error> {
+        let mut bytes = [0u8; 32];
+        OsRng::new()?.fill_bytes(&mut bytes);
+        Ok(PaymentPreimage(bytes))
+    }
+}
+
+/// Clear the preimage, which is also done when it is dropped. <br>
+/// *Note*: only built with the `zeroize` feature, the clones of the preimage are cleared when they
+/// are dropped, but not the bytes it was built from.
+#[cfg(feature = "zeroize")]
+impl Zeroize for PaymentPreimage {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for PaymentPreimage {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for PaymentPreimage {}
+
+// Helper for the hashes computed by the crate.
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    let mut hasher = Sha256::new();
+    hasher.input(bytes);
+    hasher.result(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_slice() {
+        let hash = PaymentHash::from_slice(&[1u8; 32]).unwrap();
+
+        assert_eq!(hash, PaymentHash::from([1u8; 32]));
+        assert_eq!(hash.as_bytes(), &[1u8; 32]);
+        assert_eq!(hash.to_vec(), vec![1u8; 32]);
+        assert!(matches!(
+            DescriptionHash::from_slice(&[1u8; 31]),
+            Err(Error::InvalidLength(_))
+        ));
+        assert!(PaymentSecret::from_slice(&[1u8; 33]).is_err());
+    }
+
+    #[test]
+    fn hex() {
+        let hex = "0001020304050607080900010203040506070809000102030405060708090102";
+        let hash = hex.parse::<PaymentHash>().unwrap();
+
+        assert_eq!(hash.as_bytes()[31], 2);
+        assert_eq!(hash.to_string(), hex);
+        assert!("0001".parse::<PaymentHash>().is_err());
+        assert!(hex[1..].parse::<PaymentHash>().is_err());
+        assert!(hex.replace("00", "zz").parse::<DescriptionHash>().is_err());
+    }
+
+    #[test]
+    fn description_hash() {
+        // SHA256 of the description of the BOLT 11 appendix examples
+        let description = "One piece of chocolate cake, one icecream cone, one pickle, one slice \
+                           of swiss cheese, one slice of salami, one lollypop, one piece of \
+                           cherry pie, one sausage, one cupcake, and one slice of watermelon";
+        assert_eq!(
+            DescriptionHash::from_description(description).to_string(),
+            "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1"
+        );
+    }
+
+    #[test]
+    fn preimage() {
+        let preimage = PaymentPreimage::from([0u8; 32]);
+
+        assert_eq!(
+            preimage.payment_hash().to_string(),
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925"
+        );
+        assert!(PaymentPreimage::from([1u8; 32]).payment_hash() != preimage.payment_hash());
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        fn zeroize_on_drop<T: ZeroizeOnDrop>(_: &T) {}
+
+        let mut secret = PaymentSecret::from([7u8; 32]);
+        zeroize_on_drop(&secret);
+        secret.zeroize();
+        assert_eq!(secret, PaymentSecret::from([0u8; 32]));
+
+        let mut preimage = PaymentPreimage::from([7u8; 32]);
+        zeroize_on_drop(&preimage);
+        preimage.zeroize();
+        assert_eq!(preimage, PaymentPreimage::from([0u8; 32]));
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn random_preimage() {
+        let preimage = PaymentPreimage::random().unwrap();
+
+        assert!(preimage != PaymentPreimage::random().unwrap());
+        assert!(preimage != PaymentPreimage::from([0u8; 32]));
+    }
+}
@@ -0,0 +1,145 @@
+//! Conversions with the types of the `lightning-invoice` crate, behind the `ldk-interop` feature,
+//! e.g. to migrate incrementally or to cross-check the parsing. <br>
+//! *Note*: the payment requests are converted through their bech32 encoding, so they must be
+//! signed, and both crates check them.
+
+use bitcoin::secp256k1::PublicKey as LdkPublicKey;
+use lightning_invoice::{Bolt11Invoice, RouteHint as LdkRouteHint, RouteHintHop, RoutingFees};
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use short_channel_id::ShortChannelId;
+use tag::{ExtraHop, Tag};
+use types::Error;
+use std::convert::TryFrom;
+
+impl<'a> TryFrom<&'a PaymentRequest> for Bolt11Invoice {
+    type Error = Error;
+
+    fn try_from(payment_request: &'a PaymentRequest) -> Result<Bolt11Invoice, Error> {
+        payment_request
+            .encode()?
+            .parse::<Bolt11Invoice>()
+            .map_err(|e| Error::InvalidFormat(format!("lightning-invoice: {}", e)))
+    }
+}
+
+impl<'a> TryFrom<&'a Bolt11Invoice> for PaymentRequest {
+    type Error = Error;
+
+    fn try_from(invoice: &'a Bolt11Invoice) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode(&invoice.to_string())
+    }
+}
+
+impl<'a> TryFrom<&'a ExtraHop> for RouteHintHop {
+    type Error = Error;
+
+    fn try_from(hop: &'a ExtraHop) -> Result<RouteHintHop, Error> {
+        Ok(RouteHintHop {
+            src_node_id: LdkPublicKey::from_slice(hop.pub_key.as_bytes())
+                .map_err(|e| Error::InvalidValue(format!("node id {}: {}", hop.pub_key, e)))?,
+            short_channel_id: u64::from(hop.short_channel_id),
+            fees: RoutingFees {
+                base_msat: hop.fee_base_msat,
+                proportional_millionths: hop.fee_proportional_millionths,
+            },
+            cltv_expiry_delta: hop.cltv_expiry_delta,
+            htlc_minimum_msat: None,
+            htlc_maximum_msat: None,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a RouteHintHop> for ExtraHop {
+    type Error = Error;
+
+    fn try_from(hop: &'a RouteHintHop) -> Result<ExtraHop, Error> {
+        Ok(ExtraHop::new(
+            NodeId::from_slice(&hop.src_node_id.serialize())?,
+            ShortChannelId::from(hop.short_channel_id),
+            hop.fees.base_msat,
+            hop.fees.proportional_millionths,
+            hop.cltv_expiry_delta,
+        ))
+    }
+}
+
+impl<'a> TryFrom<&'a Tag> for LdkRouteHint {
+    type Error = Error;
+
+    /// Convert a routing info tag, fails for the other tags.
+    fn try_from(tag: &'a Tag) -> Result<LdkRouteHint, Error> {
+        match *tag {
+            Tag::RoutingInfo { ref path } => Ok(LdkRouteHint(
+                path.iter()
+                    .map(RouteHintHop::try_from)
+                    .collect::<Result<Vec<RouteHintHop>, Error>>()?,
+            )),
+            _ => Err(Error::InvalidParameter(format!(
+                "{} field is not a routing info",
+                tag.tag_char()
+            ))),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a LdkRouteHint> for Tag {
+    type Error = Error;
+
+    fn try_from(route_hint: &'a LdkRouteHint) -> Result<Tag, Error> {
+        Ok(Tag::RoutingInfo {
+            path: route_hint
+                .0
+                .iter()
+                .map(ExtraHop::try_from)
+                .collect::<Result<Vec<ExtraHop>, Error>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // lightning-invoice rejects the payment requests without a payment secret
+    const TX_REF: &str = "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5\
+        qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk9\
+        8klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u\
+        7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y\
+        00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzq9qrsgqdfjcdk6w3ak5pca9hwfwfh63zrrz06wwfya0ydlzpgzxkn5x\
+        agsqz7x9j4jwe7yj7vaf2k9lqsdk45kts2fd0fkr28am0u4w95tt2nsq76cqw0";
+
+    #[test]
+    fn invoice() {
+        let pay_request = PaymentRequest::decode(TX_REF).unwrap();
+        let invoice = Bolt11Invoice::try_from(&pay_request).unwrap();
+
+        assert_eq!(invoice.amount_milli_satoshis(), pay_request.amount);
+        assert_eq!(
+            invoice.payment_hash().to_string(),
+            pay_request.payment_hash().unwrap().to_string()
+        );
+        assert_eq!(
+            invoice.payment_secret().0,
+            *pay_request.payment_secret().unwrap().as_bytes()
+        );
+        assert_eq!(PaymentRequest::try_from(&invoice).unwrap(), pay_request);
+    }
+
+    #[test]
+    fn route_hints() {
+        let pay_request = PaymentRequest::decode(TX_REF).unwrap();
+        let invoice = Bolt11Invoice::try_from(&pay_request).unwrap();
+        let routing_info = pay_request
+            .tags
+            .iter()
+            .find(|tag| tag.tag_char() == 'r')
+            .unwrap();
+
+        let route_hint = LdkRouteHint::try_from(routing_info).unwrap();
+
+        assert_eq!(invoice.route_hints(), vec![route_hint.to_owned()]);
+        assert_eq!(Tag::try_from(&route_hint).unwrap(), *routing_info);
+        assert!(LdkRouteHint::try_from(&Tag::Expiry { seconds: 60 }).is_err());
+    }
+}
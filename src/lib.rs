@@ -10,24 +10,92 @@
 //! [Lightning BOLT11 Specification](https://github.com/lightningnetwork/lightning-rfc/blob/master/11-payment-encoding.md)
 //! for providing a minimal QR-code-ready format for requesting lightning payments.
 
+#[cfg(feature = "testing")]
+extern crate arbitrary;
 extern crate base58check;
-extern crate bitcoin_bech32;
+#[cfg(feature = "bitcoin")]
+extern crate bitcoin;
 extern crate byteorder;
 extern crate crypto;
 extern crate hex;
 extern crate itertools;
+#[cfg(feature = "wasm")]
+extern crate js_sys;
+#[cfg(test)]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "ldk-interop")]
+extern crate lightning_invoice;
 extern crate num;
+#[cfg(feature = "proto")]
+extern crate prost;
+#[cfg(feature = "testing")]
+extern crate proptest;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 extern crate secp256k1;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "wasm")]
+extern crate serde_wasm_bindgen;
+#[cfg(feature = "smallvec")]
+extern crate smallvec;
+#[cfg(feature = "mobile")]
+extern crate uniffi;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
 
 #[macro_use]
 mod macros;
-mod timestamp;
 mod utils;
-mod amount;
 mod bech32;
+#[cfg(feature = "serde")]
+mod serde_utils;
 
 pub mod types;
+pub mod address;
+pub mod amount;
+pub mod builder;
+pub mod cln;
+pub mod currency;
+pub mod encoder;
+pub mod features;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod hashes;
+#[cfg(feature = "ldk-interop")]
+pub mod ldk;
+pub mod lnd;
+#[cfg(feature = "mobile")]
+pub mod mobile;
+pub mod node_id;
+pub mod options;
+#[cfg(feature = "proto")]
+pub mod proto;
+pub mod raw;
+pub mod redact;
+pub mod short_channel_id;
+pub mod signature;
+pub mod signer;
 pub mod tag;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timestamp;
 pub mod payment_request;
+pub mod report;
+pub mod uri;
+pub mod view;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "mobile")]
+uniffi::setup_scaffolding!();
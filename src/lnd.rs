@@ -0,0 +1,282 @@
+//! View of a payment request with the shape of lnd's `decodepayreq` response (`lnrpc.PayReq`),
+//! see `PaymentRequest::to_lnd`. <br>
+//! *Note*: with the `serde` feature the views serialize to the JSON of lnd's REST API, the 64-bit
+//! integers being strings and the bytes base64.
+
+use features::Features;
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use tag::{ExtraHop, Tag};
+use std::collections::BTreeMap;
+
+/// Decoded payment request, `lnrpc.PayReq`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PayReq {
+    /// Node id of the payee, hex encoded.
+    pub destination: String,
+    /// Payment hash, hex encoded, empty if missing.
+    pub payment_hash: String,
+    /// Amount in satoshis, rounded down, `"0"` for any amount.
+    pub num_satoshis: String,
+    /// Request timestamp (UNIX format).
+    pub timestamp: String,
+    /// Expiry in seconds, 3600 if not specified.
+    pub expiry: String,
+    /// Description, empty if missing.
+    pub description: String,
+    /// Description hash, hex encoded, empty if missing.
+    pub description_hash: String,
+    /// First fallback address, empty if missing.
+    pub fallback_addr: String,
+    /// min_final_cltv_expiry, 18 if not specified.
+    pub cltv_expiry: String,
+    /// Route hints, one per routing tag.
+    pub route_hints: Vec<RouteHint>,
+    /// Payment secret, empty if missing.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_utils::base64"))]
+    pub payment_addr: Vec<u8>,
+    /// Amount in millisatoshis, `"0"` for any amount.
+    pub num_msat: String,
+    /// Feature bits, by bit number.
+    pub features: BTreeMap<u32, Feature>,
+}
+
+/// Private route, `lnrpc.RouteHint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RouteHint {
+    /// Hops of the route.
+    pub hop_hints: Vec<HopHint>,
+}
+
+/// Hop of a private route, `lnrpc.HopHint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HopHint {
+    /// Node id of the start of the channel, hex encoded.
+    pub node_id: String,
+    /// Short channel id as a 64-bit integer.
+    pub chan_id: String,
+    /// Base fee in millisatoshis.
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths.
+    pub fee_proportional_millionths: u32,
+    /// Channel's cltv expiry delta.
+    pub cltv_expiry_delta: u32,
+}
+
+/// Feature bit, `lnrpc.Feature`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Feature {
+    /// Name of the feature, empty if unknown.
+    pub name: String,
+    /// Is the bit even.
+    pub is_required: bool,
+    /// Is the feature known to this crate.
+    pub is_known: bool,
+}
+
+impl PayReq {
+    /// Create the lnd view of a payment request.
+    /// # Arguments
+    /// `payment_request` Payment request to convert.
+    pub fn new(payment_request: &PaymentRequest) -> PayReq {
+        let amount = payment_request.amount.unwrap_or_default();
+        PayReq {
+            destination: NodeId::from(&payment_request.node_id).to_string(),
+            payment_hash: payment_request
+                .payment_hash()
+                .map(|hash| hash.to_string())
+                .unwrap_or_default(),
+            num_satoshis: (amount / 1000).to_string(),
+            timestamp: payment_request.timestamp.to_string(),
+            expiry: payment_request.expiry_seconds().to_string(),
+            description: payment_request
+                .tags
+                .iter()
+                .filter_map(|tag| match *tag {
+                    Tag::Description { ref description } => Some(description.to_owned()),
+                    _ => None,
+                })
+                .next()
+                .unwrap_or_default(),
+            description_hash: payment_request
+                .description_hash()
+                .map(|hash| hash.to_string())
+                .unwrap_or_default(),
+            fallback_addr: payment_request.fallback_address().unwrap_or_default(),
+            cltv_expiry: payment_request.min_final_cltv_expiry().to_string(),
+            route_hints: payment_request
+                .route_hints()
+                .iter()
+                .map(|path| RouteHint {
+                    hop_hints: path.iter().map(HopHint::new).collect(),
+                })
+                .collect(),
+            payment_addr: payment_request
+                .payment_secret()
+                .map(|secret| secret.to_vec())
+                .unwrap_or_default(),
+            num_msat: amount.to_string(),
+            features: payment_request
+                .features()
+                .map(|features| {
+                    features
+                        .bits()
+                        .into_iter()
+                        .map(|bit| (bit as u32, Feature::new(bit)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl HopHint {
+    /// Create the lnd view of an extra hop.
+    /// # Arguments
+    /// `hop` Extra hop of a routing tag.
+    pub fn new(hop: &ExtraHop) -> HopHint {
+        HopHint {
+            node_id: hop.pub_key.to_string(),
+            chan_id: u64::from(hop.short_channel_id).to_string(),
+            fee_base_msat: hop.fee_base_msat,
+            fee_proportional_millionths: hop.fee_proportional_millionths,
+            cltv_expiry_delta: u32::from(hop.cltv_expiry_delta),
+        }
+    }
+}
+
+impl Feature {
+    /// Describe a feature bit with lnd's names.
+    /// # Arguments
+    /// `bit` Feature bit.
+    pub fn new(bit: usize) -> Feature {
+        Feature {
+            name: feature_name(bit).unwrap_or_default().to_owned(),
+            is_required: bit & 1 == 0,
+            is_known: Features::known().is_set(bit & !1),
+        }
+    }
+}
+
+// Helper for Feature::new, the names lnd gives to the feature bits of a payment request.
+fn feature_name(bit: usize) -> Option<&'static str> {
+    match bit & !1 {
+        Features::VAR_ONION_OPTIN => Some("tlv-onion"),
+        Features::PAYMENT_SECRET => Some("payment-addr"),
+        Features::BASIC_MPP => Some("multi-path-payments"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pay_req() {
+        let tx_ref = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qq\
+            qsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0\
+            rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuv\
+            en09sam30g4vgpfna3rh";
+        let pay_req = PaymentRequest::decode(tx_ref).unwrap().to_lnd();
+        let mut features = BTreeMap::new();
+        features.insert(8, Feature::new(Features::VAR_ONION_OPTIN));
+        features.insert(14, Feature::new(Features::PAYMENT_SECRET));
+
+        assert_eq!(
+            pay_req,
+            PayReq {
+                destination: "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+                    .to_owned(),
+                payment_hash: "0001020304050607080900010203040506070809000102030405060708090102"
+                    .to_owned(),
+                num_satoshis: "250000".to_owned(),
+                timestamp: "1496314658".to_owned(),
+                expiry: "60".to_owned(),
+                description: "1 cup coffee".to_owned(),
+                description_hash: String::new(),
+                fallback_addr: String::new(),
+                cltv_expiry: "18".to_owned(),
+                route_hints: vec![],
+                payment_addr: vec![0x11; 32],
+                num_msat: "250000000".to_owned(),
+                features,
+            }
+        );
+    }
+
+    #[test]
+    fn route_hints() {
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjm\
+            dan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9y\
+            q20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqa\
+            fqxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr\
+            5aj9f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh\
+            8z3g2qqdhhwkj";
+        let pay_req = PaymentRequest::decode(tx_ref).unwrap().to_lnd();
+
+        assert_eq!(pay_req.fallback_addr, "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T");
+        assert_eq!(pay_req.description, "");
+        assert_eq!(
+            pay_req.description_hash,
+            "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1"
+        );
+        assert_eq!(
+            pay_req.route_hints,
+            vec![RouteHint {
+                hop_hints: vec![
+                    HopHint {
+                        node_id: "029e03a901b85534ff1e92c43c74431f7c\
+                                  e72046060fcf7a95c37e148f78c77255"
+                            .to_owned(),
+                        chan_id: "72623859790382856".to_owned(),
+                        fee_base_msat: 1,
+                        fee_proportional_millionths: 20,
+                        cltv_expiry_delta: 3,
+                    },
+                    HopHint {
+                        node_id: "039e03a901b85534ff1e92c43c74431f7c\
+                                  e72046060fcf7a95c37e148f78c77255"
+                            .to_owned(),
+                        chan_id: "217304205466536202".to_owned(),
+                        fee_base_msat: 2,
+                        fee_proportional_millionths: 30,
+                        cltv_expiry_delta: 4,
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn features() {
+        assert_eq!(
+            Feature::new(Features::PAYMENT_SECRET),
+            Feature {
+                name: "payment-addr".to_owned(),
+                is_required: true,
+                is_known: true,
+            }
+        );
+        assert_eq!(
+            Feature::new(Features::BASIC_MPP + 1),
+            Feature {
+                name: "multi-path-payments".to_owned(),
+                is_required: false,
+                is_known: true,
+            }
+        );
+        assert_eq!(
+            Feature::new(101),
+            Feature {
+                name: String::new(),
+                is_required: false,
+                is_known: false,
+            }
+        );
+    }
+}
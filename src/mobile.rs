@@ -0,0 +1,492 @@
+//! Kotlin and Swift bindings generated by UniFFI, behind the `mobile` feature, from the library
+//! built with `cargo rustc --release --features mobile --crate-type cdylib`, e.g.
+//! `cargo run --features uniffi-cli --bin uniffi-bindgen -- generate --library
+//! target/release/libbolt11.so --language kotlin --out-dir out`. <br>
+//! *Note*: the decoded payment requests are plain records, the builder is an object whose
+//! setters can be chained from the foreign language, and the signing can be left to a foreign
+//! `MessageSigner`, e.g. backed by the keystore or the secure enclave.
+
+use builder;
+use currency::Currency;
+use hashes::{DescriptionHash, PaymentHash};
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use secp256k1::{self, Message, RecoveryId, SecretKey, Signature};
+use short_channel_id::ShortChannelId;
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{error, fmt};
+use tag::{ExtraHop, Tag};
+use types::Error;
+use utils::to_hex;
+
+/// Error of the bindings, `Bolt11Exception` in Kotlin and `Bolt11Error` in Swift.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Error)]
+pub enum Bolt11Error {
+    /// Payment request that can't be decoded.
+    Decode {
+        /// Description of the error.
+        message: String,
+    },
+    /// Payment request violating the BOLT #11 rules.
+    Validation {
+        /// Description of the error.
+        message: String,
+    },
+    /// Invalid builder field or signing failure.
+    Encode {
+        /// Description of the error.
+        message: String,
+    },
+}
+
+/// Decoded payment request.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Invoice {
+    /// Prefix, e.g. `lnbc`.
+    pub prefix: String,
+    /// Amount in millisatoshis, if any.
+    pub amount_msat: Option<u64>,
+    /// Request timestamp (UNIX format).
+    pub timestamp: u64,
+    /// Expiry in seconds, 3600 if not specified.
+    pub expiry: u64,
+    /// min_final_cltv_expiry, 18 if not specified.
+    pub min_final_cltv_expiry: u64,
+    /// Node id of the payee, hex encoded.
+    pub payee: String,
+    /// Payment hash, hex encoded, if any.
+    pub payment_hash: Option<String>,
+    /// Description, if any.
+    pub description: Option<String>,
+    /// Description hash, hex encoded, if any.
+    pub description_hash: Option<String>,
+    /// Fallback addresses that can be converted for the network.
+    pub fallback_addresses: Vec<String>,
+    /// Private routes, one per routing tag.
+    pub route_hints: Vec<Vec<RouteHop>>,
+    /// Feature bits, hex encoded big-endian bytes, if any.
+    pub features: Option<String>,
+    /// Signature, 64 bytes followed by the recovery id.
+    pub signature: Vec<u8>,
+}
+
+/// Hop of a private route.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct RouteHop {
+    /// Node id of the start of the channel, hex encoded.
+    pub node_id: String,
+    /// Short channel id as a 64-bit integer.
+    pub short_channel_id: u64,
+    /// Base fee in millisatoshis.
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths.
+    pub fee_proportional_millionths: u32,
+    /// Channel's cltv expiry delta.
+    pub cltv_expiry_delta: u16,
+}
+
+/// Signing function implemented in the foreign language.
+#[uniffi::export(with_foreign)]
+pub trait MessageSigner: Send + Sync {
+    /// Sign the 32 bytes message, return the 64 bytes compact signature followed by the recovery
+    /// id.
+    fn sign(&self, message: Vec<u8>) -> Result<Vec<u8>, Bolt11Error>;
+}
+
+/// Builder of payment requests, the setters can be called in any order.
+#[derive(Debug, uniffi::Object)]
+pub struct InvoiceBuilder {
+    fields: Mutex<BuilderFields>,
+}
+
+// Helper for InvoiceBuilder, the fields set so far.
+#[derive(Debug, Clone)]
+struct BuilderFields {
+    currency: Currency,
+    amount: Option<u64>,
+    timestamp: Option<u64>,
+    payment_hash: Option<PaymentHash>,
+    description: Option<String>,
+    description_hash: Option<DescriptionHash>,
+    expiry: Option<u64>,
+    min_final_cltv_expiry: Option<u64>,
+    route_hints: Vec<Vec<ExtraHop>>,
+}
+
+impl fmt::Display for Bolt11Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Bolt11Error::Decode { ref message } => write!(f, "decode error: {}", message),
+            Bolt11Error::Validation { ref message } => write!(f, "validation error: {}", message),
+            Bolt11Error::Encode { ref message } => write!(f, "encode error: {}", message),
+        }
+    }
+}
+
+impl error::Error for Bolt11Error {}
+
+impl From<uniffi::UnexpectedUniFFICallbackError> for Bolt11Error {
+    fn from(e: uniffi::UnexpectedUniFFICallbackError) -> Bolt11Error {
+        Bolt11Error::Encode { message: e.reason }
+    }
+}
+
+impl<'a> From<&'a PaymentRequest> for Invoice {
+    fn from(payment_request: &'a PaymentRequest) -> Invoice {
+        Invoice {
+            prefix: payment_request.prefix.to_owned(),
+            amount_msat: payment_request.amount,
+            timestamp: payment_request.timestamp,
+            expiry: payment_request.expiry_seconds(),
+            min_final_cltv_expiry: payment_request.min_final_cltv_expiry(),
+            payee: NodeId::from(&payment_request.node_id).to_string(),
+            payment_hash: payment_request.payment_hash().map(|hash| hash.to_string()),
+            description: payment_request
+                .tags
+                .iter()
+                .filter_map(|tag| match *tag {
+                    Tag::Description { ref description } => Some(description.to_owned()),
+                    _ => None,
+                })
+                .next(),
+            description_hash: payment_request
+                .description_hash()
+                .map(|hash| hash.to_string()),
+            fallback_addresses: payment_request
+                .currency()
+                .map(|currency| payment_request.fallback_addresses(&currency))
+                .unwrap_or_default(),
+            route_hints: payment_request
+                .route_hints()
+                .iter()
+                .map(|path| path.iter().map(RouteHop::from).collect())
+                .collect(),
+            features: payment_request
+                .features()
+                .map(|features| to_hex(&features.to_bytes())),
+            signature: payment_request.signature.to_owned(),
+        }
+    }
+}
+
+impl<'a> From<&'a ExtraHop> for RouteHop {
+    fn from(hop: &'a ExtraHop) -> RouteHop {
+        RouteHop {
+            node_id: hop.pub_key.to_string(),
+            short_channel_id: u64::from(hop.short_channel_id),
+            fee_base_msat: hop.fee_base_msat,
+            fee_proportional_millionths: hop.fee_proportional_millionths,
+            cltv_expiry_delta: hop.cltv_expiry_delta,
+        }
+    }
+}
+
+/// Decode a payment request.
+/// # Arguments
+/// `invoice` Payment request, with or without the `lightning:` scheme.
+#[uniffi::export]
+pub fn decode_invoice(invoice: String) -> Result<Invoice, Bolt11Error> {
+    let payment_request = invoice.parse::<PaymentRequest>().map_err(decode_error)?;
+    Ok(Invoice::from(&payment_request))
+}
+
+/// Decode a payment request and check the BOLT #11 rules, e.g. before paying it.
+/// # Arguments
+/// `invoice` Payment request, with or without the `lightning:` scheme.
+#[uniffi::export]
+pub fn validate_invoice(invoice: String) -> Result<Invoice, Bolt11Error> {
+    let payment_request = invoice.parse::<PaymentRequest>().map_err(decode_error)?;
+    payment_request
+        .validate()
+        .map_err(|e| Bolt11Error::Validation {
+            message: e.to_string(),
+        })?;
+    payment_request
+        .validate_features()
+        .map_err(|violations| Bolt11Error::Validation {
+            message: violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join(", "),
+        })?;
+    Ok(Invoice::from(&payment_request))
+}
+
+#[uniffi::export]
+impl InvoiceBuilder {
+    /// Create a builder of payment requests.
+    /// # Arguments
+    /// `prefix` Prefix of the currency, e.g. `lnbc`.
+    #[uniffi::constructor]
+    pub fn new(prefix: String) -> Result<Arc<InvoiceBuilder>, Bolt11Error> {
+        let currency = Currency::from_prefix(&prefix).ok_or_else(|| Bolt11Error::Encode {
+            message: format!("invalid prefix {}", prefix),
+        })?;
+        Ok(Arc::new(InvoiceBuilder {
+            fields: Mutex::new(BuilderFields {
+                currency,
+                amount: None,
+                timestamp: None,
+                payment_hash: None,
+                description: None,
+                description_hash: None,
+                expiry: None,
+                min_final_cltv_expiry: None,
+                route_hints: vec![],
+            }),
+        }))
+    }
+
+    /// Set the amount in millisatoshis.
+    pub fn amount_msat(self: Arc<Self>, amount: u64) -> Arc<InvoiceBuilder> {
+        self.update(|fields| fields.amount = Some(amount));
+        self
+    }
+
+    /// Set the timestamp (UNIX format), now if not set.
+    pub fn timestamp(self: Arc<Self>, timestamp: u64) -> Arc<InvoiceBuilder> {
+        self.update(|fields| fields.timestamp = Some(timestamp));
+        self
+    }
+
+    /// Set the payment hash, hex encoded.
+    pub fn payment_hash(self: Arc<Self>, hash: String) -> Result<Arc<InvoiceBuilder>, Bolt11Error> {
+        let hash = hash.parse::<PaymentHash>().map_err(encode_error)?;
+        self.update(|fields| fields.payment_hash = Some(hash));
+        Ok(self)
+    }
+
+    /// Set the description, replacing the description hash.
+    pub fn description(self: Arc<Self>, description: String) -> Arc<InvoiceBuilder> {
+        self.update(|fields| {
+            fields.description = Some(description);
+            fields.description_hash = None;
+        });
+        self
+    }
+
+    /// Set the description hash, hex encoded, replacing the description.
+    pub fn description_hash(
+        self: Arc<Self>,
+        hash: String,
+    ) -> Result<Arc<InvoiceBuilder>, Bolt11Error> {
+        let hash = hash.parse::<DescriptionHash>().map_err(encode_error)?;
+        self.update(|fields| {
+            fields.description_hash = Some(hash);
+            fields.description = None;
+        });
+        Ok(self)
+    }
+
+    /// Set the expiry in seconds.
+    pub fn expiry(self: Arc<Self>, seconds: u64) -> Arc<InvoiceBuilder> {
+        self.update(|fields| fields.expiry = Some(seconds));
+        self
+    }
+
+    /// Set the min_final_cltv_expiry in blocks.
+    pub fn min_final_cltv_expiry(self: Arc<Self>, blocks: u64) -> Arc<InvoiceBuilder> {
+        self.update(|fields| fields.min_final_cltv_expiry = Some(blocks));
+        self
+    }
+
+    /// Add a private route.
+    pub fn route_hint(
+        self: Arc<Self>,
+        hops: Vec<RouteHop>,
+    ) -> Result<Arc<InvoiceBuilder>, Bolt11Error> {
+        let path = hops
+            .iter()
+            .map(|hop| {
+                Ok(ExtraHop::new(
+                    hop.node_id.parse::<NodeId>()?,
+                    ShortChannelId::from(hop.short_channel_id),
+                    hop.fee_base_msat,
+                    hop.fee_proportional_millionths,
+                    hop.cltv_expiry_delta,
+                ))
+            })
+            .collect::<Result<Vec<ExtraHop>, Error>>()
+            .map_err(encode_error)?;
+        self.update(|fields| fields.route_hints.push(path));
+        Ok(self)
+    }
+
+    /// Sign the payment request with the node key and return it encoded.
+    /// # Arguments
+    /// `secret_key` Secret key of the node, 32 bytes.
+    pub fn build_signed(&self, secret_key: Vec<u8>) -> Result<String, Bolt11Error> {
+        if secret_key.len() != 32 {
+            return Err(Bolt11Error::Encode {
+                message: format!("the secret key must be 32 bytes, got {}", secret_key.len()),
+            });
+        }
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&secret_key);
+        let secret_key = SecretKey::parse(&bytes).map_err(|e| encode_error(Error::from(e)))?;
+        self.build(|message| secp256k1::sign(message, &secret_key))
+            .map_err(encode_error)
+    }
+
+    /// Sign the payment request with a foreign signer and return it encoded.
+    /// # Arguments
+    /// `signer` Signing function, e.g. backed by the keystore.
+    pub fn build_signed_with(&self, signer: Arc<dyn MessageSigner>) -> Result<String, Bolt11Error> {
+        // the error of the signer, reported instead of the secp256k1 one
+        let failure = RefCell::new(None);
+        self.build(|message| {
+            sign_with(signer.as_ref(), message).map_err(|e| {
+                *failure.borrow_mut() = Some(e);
+                secp256k1::Error::InvalidSignature
+            })
+        }).map_err(|e| failure.borrow_mut().take().unwrap_or_else(|| encode_error(e)))
+    }
+}
+
+impl InvoiceBuilder {
+    // Helper for the setters, update the fields.
+    fn update<F: FnOnce(&mut BuilderFields)>(&self, f: F) {
+        let mut fields = self.fields.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut fields)
+    }
+
+    // Helper for build_signed and build_signed_with, build the payment request with the typed
+    // builder.
+    fn build<F>(&self, sign: F) -> Result<String, Error>
+    where
+        F: FnOnce(&Message) -> Result<(Signature, RecoveryId), secp256k1::Error>,
+    {
+        let fields = self
+            .fields
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .to_owned();
+        let payment_hash = fields
+            .payment_hash
+            .ok_or_else(|| Error::InvalidParameter("missing payment hash".to_owned()))?;
+        let builder = builder::InvoiceBuilder::new(fields.currency).payment_hash(payment_hash);
+        let mut builder = match (fields.description, fields.description_hash) {
+            (Some(description), _) => builder.description(&description),
+            (None, Some(hash)) => builder.description_hash(hash),
+            (None, None) => {
+                return Err(Error::InvalidParameter(
+                    "either a description or a description hash is required".to_owned(),
+                ))
+            }
+        };
+        if let Some(amount) = fields.amount {
+            builder = builder.amount_msat(amount);
+        }
+        if let Some(timestamp) = fields.timestamp {
+            builder = builder.timestamp(timestamp);
+        }
+        if let Some(seconds) = fields.expiry {
+            builder = builder.expiry(Duration::from_secs(seconds));
+        }
+        if let Some(blocks) = fields.min_final_cltv_expiry {
+            builder = builder.min_final_cltv_expiry(blocks);
+        }
+        for path in fields.route_hints {
+            builder = builder.route_hint(path);
+        }
+        builder.build_signed_with(sign)
+    }
+}
+
+// Helper for build_signed_with, call the foreign signer.
+fn sign_with(
+    signer: &dyn MessageSigner,
+    message: &Message,
+) -> Result<(Signature, RecoveryId), Bolt11Error> {
+    let signed = signer.sign(message.serialize().to_vec())?;
+    if signed.len() != 65 {
+        return Err(Bolt11Error::Encode {
+            message: format!("the signature must be 65 bytes, got {}", signed.len()),
+        });
+    }
+    let mut bytes = [0u8; 64];
+    bytes.copy_from_slice(&signed[..64]);
+    let recovery_id =
+        RecoveryId::parse(signed[64]).map_err(|e| encode_error(Error::from(e)))?;
+    Ok((Signature::parse(&bytes), recovery_id))
+}
+
+// Helper for the functions, a decode error from an error.
+fn decode_error(e: Error) -> Bolt11Error {
+    Bolt11Error::Decode {
+        message: e.to_string(),
+    }
+}
+
+// Helper for the builder, an encode error from an error.
+fn encode_error(e: Error) -> Bolt11Error {
+    Bolt11Error::Encode {
+        message: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TX_REF: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+    const SEC_KEY: &str = "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734";
+
+    // Signer of the tests, signing with the key of the test vectors.
+    struct KeySigner;
+
+    impl MessageSigner for KeySigner {
+        fn sign(&self, message: Vec<u8>) -> Result<Vec<u8>, Bolt11Error> {
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(&message);
+            let key = SEC_KEY.parse::<PaymentHash>().unwrap();
+            let key = SecretKey::parse(key.as_bytes()).unwrap();
+            let (signature, recovery_id) = secp256k1::sign(&Message::parse(&bytes), &key).unwrap();
+            let mut signed = signature.serialize().to_vec();
+            signed.push(recovery_id.serialize());
+            Ok(signed)
+        }
+    }
+
+    fn builder() -> Arc<InvoiceBuilder> {
+        InvoiceBuilder::new("lnbc".to_owned())
+            .unwrap()
+            .payment_hash(
+                "0001020304050607080900010203040506070809000102030405060708090102".to_owned(),
+            )
+            .unwrap()
+            .description("1 cup coffee".to_owned())
+            .amount_msat(250_000_000)
+            .expiry(60)
+            .timestamp(1496314658)
+    }
+
+    #[test]
+    fn decode() {
+        let invoice = decode_invoice(TX_REF.to_owned()).unwrap();
+
+        assert_eq!(invoice.amount_msat, Some(250_000_000));
+        assert_eq!(invoice.expiry, 60);
+        assert_eq!(invoice.description, Some("1 cup coffee".to_owned()));
+        assert_eq!(validate_invoice(TX_REF.to_owned()), Ok(invoice));
+        assert!(matches!(
+            decode_invoice("lnbc1".to_owned()),
+            Err(Bolt11Error::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn build() {
+        let secret_key = SEC_KEY.parse::<PaymentHash>().unwrap().as_bytes().to_vec();
+
+        assert_eq!(builder().build_signed(secret_key).unwrap(), TX_REF);
+        assert_eq!(builder().build_signed_with(Arc::new(KeySigner)).unwrap(), TX_REF);
+        assert!(matches!(
+            InvoiceBuilder::new("lnbc".to_owned()).unwrap().build_signed(vec![1; 32]),
+            Err(Bolt11Error::Encode { .. })
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+//! Node ids, the compressed public keys of the lightning nodes.
+
+use secp256k1::curve::{Affine, Field};
+use secp256k1::PublicKey;
+use types::Error;
+use utils::{from_hex, to_hex};
+use std::fmt;
+use std::str::FromStr;
+
+/// 33 bytes compressed public key of a node, starting with 0x02 or 0x03.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId([u8; 33]);
+
+impl NodeId {
+    /// Create a node id from a byte slice, which must be a 33 bytes compressed public key.
+    /// # Arguments
+    /// `bytes` The 33 bytes.
+    pub fn from_slice(bytes: &[u8]) -> Result<NodeId, Error> {
+        if bytes.len() != 33 {
+            return Err(Error::InvalidLength("node id must be 33 bytes".to_owned()));
+        }
+        if bytes[0] != 0x02 && bytes[0] != 0x03 {
+            return Err(Error::InvalidValue(format!(
+                "invalid node id prefix {:#04x}",
+                bytes[0]
+            )));
+        }
+        let mut array = [0u8; 33];
+        array.copy_from_slice(bytes);
+        Ok(NodeId(array))
+    }
+
+    /// Return the 33 bytes.
+    pub fn as_bytes(&self) -> &[u8; 33] {
+        &self.0
+    }
+
+    /// Return the 33 bytes as a vector.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    /// Decompress the node id into a public key, fails if the x coordinate is not on the curve.
+    pub fn to_public_key(&self) -> Result<PublicKey, Error> {
+        let mut x_bytes = [0u8; 32];
+        x_bytes.copy_from_slice(&self.0[1..]);
+        let mut x = Field::default();
+        let mut point = Affine::default();
+        if !x.set_b32(&x_bytes) || !point.set_xo_var(&x, self.0[0] == 0x03) {
+            return Err(Error::InvalidValue(format!("node id {} is not on the curve", self)));
+        }
+        point.x.normalize_var();
+        point.y.normalize_var();
+        let mut uncompressed = [0u8; 65];
+        uncompressed[0] = 0x04;
+        uncompressed[1..33].copy_from_slice(&point.x.b32());
+        uncompressed[33..].copy_from_slice(&point.y.b32());
+        PublicKey::parse(&uncompressed).map_err(Error::SignatureError)
+    }
+}
+
+impl<'a> From<&'a PublicKey> for NodeId {
+    fn from(pub_key: &'a PublicKey) -> NodeId {
+        // the uncompressed form is 0x04 followed by the x and y coordinates
+        let uncompressed = pub_key.serialize();
+        let mut compressed = [0u8; 33];
+        compressed[0] = 0x02 | (uncompressed[64] & 1);
+        compressed[1..].copy_from_slice(&uncompressed[1..33]);
+        NodeId(compressed)
+    }
+}
+
+impl fmt::Debug for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodeId({})", self)
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", to_hex(&self.0))
+    }
+}
+
+impl FromStr for NodeId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<NodeId, Error> {
+        if s.len() != 66 {
+            return Err(Error::InvalidLength("node id must be 33 bytes".to_owned()));
+        }
+        NodeId::from_slice(&from_hex(s)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1::SecretKey;
+
+    const NODE_ID: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+
+    #[test]
+    fn from_slice() {
+        let node_id = NodeId::from_slice(&from_hex(NODE_ID).unwrap()).unwrap();
+
+        assert_eq!(node_id.to_vec(), from_hex(NODE_ID).unwrap());
+        assert_eq!(node_id.as_bytes()[0], 0x03);
+        assert!(matches!(
+            NodeId::from_slice(&[2u8; 32]),
+            Err(Error::InvalidLength(_))
+        ));
+        assert!(matches!(
+            NodeId::from_slice(&[4u8; 33]),
+            Err(Error::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn from_public_key() {
+        let sec_key = SecretKey::parse(&[
+            0xe1, 0x26, 0xf6, 0x8f, 0x7e, 0xaf, 0xcc, 0x8b, 0x74, 0xf5, 0x4d, 0x26, 0x9f, 0xe2,
+            0x06, 0xbe, 0x71, 0x50, 0x00, 0xf9, 0x4d, 0xac, 0x06, 0x7d, 0x1c, 0x04, 0xa8, 0xca,
+            0x3b, 0x2d, 0xb7, 0x34,
+        ]).unwrap();
+        let pub_key = PublicKey::from_secret_key(&sec_key);
+
+        assert_eq!(NodeId::from(&pub_key).to_string(), NODE_ID);
+        assert_eq!(NodeId::from(&pub_key).to_public_key().unwrap(), pub_key);
+
+        let even = NODE_ID.replacen("03", "02", 1).parse::<NodeId>().unwrap();
+        assert_eq!(NodeId::from(&even.to_public_key().unwrap()), even);
+        let mut off_curve = [0xffu8; 33];
+        off_curve[0] = 0x02;
+        let off_curve = NodeId::from_slice(&off_curve).unwrap();
+        assert!(matches!(off_curve.to_public_key(), Err(Error::InvalidValue(_))));
+    }
+
+    #[test]
+    fn hex() {
+        let node_id = NODE_ID.parse::<NodeId>().unwrap();
+
+        assert_eq!(node_id.to_string(), NODE_ID);
+        assert_eq!(format!("{:?}", node_id), format!("NodeId({})", NODE_ID));
+        assert!(NODE_ID[2..].parse::<NodeId>().is_err());
+        assert!(NODE_ID.replacen("03", "04", 1).parse::<NodeId>().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Options of the payment request decoding.
+
+use currency::Currency;
+use std::fmt;
+
+/// Default maximum length of the encoded payment request, the capacity of a QR code.
+pub const DEFAULT_MAX_INVOICE_LEN: usize = 7089;
+
+/// Default maximum number of hops of each routing info field, the route hints given to the
+/// payer.
+pub const DEFAULT_MAX_HOPS_PER_ROUTE: usize = 20;
+
+/// Default maximum number of hops of all the routing info fields together. <br>
+/// *Note*: a routing info field holds at most 12 hops, this limit bounds the route hints of a
+/// payment request with several routing info fields.
+pub const DEFAULT_MAX_ROUTE_HOPS: usize = 20;
+
+/// Size limit of a payment request, see `ParseOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Length of the encoded payment request.
+    InvoiceLength,
+    /// Number of hops of all the routing info fields together.
+    RouteHops,
+    /// Number of hops of a routing info field.
+    HopsPerRoute,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Limit::InvoiceLength => write!(f, "payment request length"),
+            Limit::RouteHops => write!(f, "number of route hops"),
+            Limit::HopsPerRoute => write!(f, "number of hops per route"),
+        }
+    }
+}
+
+/// Handling of the signatures with a high `s`, see `signature::Signature::is_low_s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighS {
+    /// Accept them as they are, they verify like their low `s` opposite.
+    Accept,
+    /// Reject them, e.g. to identify a payment request by its encoding.
+    Reject,
+    /// Replace them by their low `s` opposite, the encoding of the payment request changing.
+    Normalize,
+}
+
+/// Strictness of the payment request decoding. <br>
+/// *Note*: when a field appears several times the accessors of `PaymentRequest` use the first
+/// one, as BOLT #11 readers do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Record the malformed tagged fields as `Tag::Skipped` rather than failing.
+    pub lenient_tags: bool,
+    /// Fail when a mandatory field, `p`, `d` or `h`, appears more than once.
+    pub reject_duplicate_tags: bool,
+    /// Fail when the amount is not written with its shortest representation, e.g. `2500000n`
+    /// rather than `2500u`.
+    pub strict_amount: bool,
+    /// Accept the payment requests requiring feature bits unknown to this crate, i.e. unknown
+    /// even bits of the `9` field.
+    pub allow_unknown_even_features: bool,
+    /// Maximum length of the encoded payment request, if any, defaults to 7089.
+    pub max_invoice_len: Option<usize>,
+    /// Maximum number of hops of all the routing info fields together, if any, defaults to 20.
+    pub max_route_hops: Option<usize>,
+    /// Maximum number of hops of each routing info field, if any, defaults to 20.
+    pub max_hops_per_route: Option<usize>,
+    /// Handling of the signatures with a high `s`, accepted by default.
+    pub high_s: HighS,
+    /// Currencies of other chains accepted besides the Bitcoin ones, see `Currency::custom`,
+    /// none by default: a payment request with an unknown prefix fails.
+    pub custom_currencies: Vec<Currency>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            lenient_tags: false,
+            reject_duplicate_tags: false,
+            strict_amount: false,
+            allow_unknown_even_features: true,
+            max_invoice_len: Some(DEFAULT_MAX_INVOICE_LEN),
+            max_route_hops: Some(DEFAULT_MAX_ROUTE_HOPS),
+            max_hops_per_route: Some(DEFAULT_MAX_HOPS_PER_ROUTE),
+            high_s: HighS::Accept,
+            custom_currencies: Vec::new(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Options recording the malformed tagged fields rather than failing, to show as much as
+    /// possible of a payment request.
+    pub fn lenient() -> ParseOptions {
+        ParseOptions {
+            lenient_tags: true,
+            ..ParseOptions::default()
+        }
+    }
+
+    /// Strict options for the payers: duplicate mandatory fields and unknown even feature bits
+    /// are rejected, as BOLT #11 requires of the readers, and so are the non-shortest amounts,
+    /// which BOLT #11 only asks the writers to avoid (SHOULD).
+    pub fn strict() -> ParseOptions {
+        ParseOptions {
+            reject_duplicate_tags: true,
+            strict_amount: true,
+            allow_unknown_even_features: false,
+            ..ParseOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_options() {
+        let options = ParseOptions::default();
+
+        assert!(!options.lenient_tags);
+        assert!(!options.strict_amount);
+        assert!(options.allow_unknown_even_features);
+        assert_eq!(options.max_invoice_len, Some(7089));
+        assert_eq!(options.max_route_hops, Some(20));
+        assert_eq!(options.max_hops_per_route, Some(20));
+        assert_eq!(options.high_s, HighS::Accept);
+        assert!(options.custom_currencies.is_empty());
+        assert!(ParseOptions::lenient().allow_unknown_even_features);
+        assert!(!ParseOptions::strict().allow_unknown_even_features);
+    }
+}
@@ -1,41 +1,83 @@
 //! Represents a decoded or to be encoded payment request
 
-use bech32::{Bech32, create_checksum as bech32_checksum, CHARSET};
+use bech32::{Bech32, CHARSET};
 use tag::{ExtraHop, Tag};
-use timestamp::Timestamp;
-use types::Error;
-use utils::{U5, U5Conversions, U8Conversions};
+use timestamp::{Clock, Timestamp};
+use types::{AmountError, Error, ValidationError, Warning};
+use utils::{Padding, U5, U5Conversions, U8Conversions};
 use secp256k1;
 use secp256k1::{Message, PublicKey, RecoveryId, SecretKey, Signature};
 use crypto::sha2::Sha256;
 use crypto::digest::Digest;
-use amount::Amount;
+use address;
+use amount::{Amount, MilliSatoshi};
+use cln::DecodePay;
+use currency::Currency;
+use encoder;
+use encoder::{Bech32Writer, IoWriter};
+use features::Features;
+use hashes::{DescriptionHash, PaymentHash, PaymentPreimage, PaymentSecret};
+use node_id::NodeId;
+use options::{HighS, Limit, ParseOptions};
+#[cfg(feature = "proto")]
+use proto;
+use lnd::PayReq;
+use report::InvoiceReport;
+use redact::Redacted;
+use signature;
+use signer::InvoiceSigner;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use uri;
+use uri::{LightningUri, UnifiedUri};
+use std::convert::TryFrom;
 use std::fmt;
-use base58check::*;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
 use itertools::Itertools;
-use bitcoin_bech32::WitnessProgram;
-use bitcoin_bech32::constants::Network;
-use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+
+/// Expiry time in seconds used when the payment request has no expiry tag.
+pub const DEFAULT_EXPIRY_SECONDS: u64 = 3600;
+
+/// Minimum CLTV expiry in blocks used when the payment request has no min_final_cltv_expiry
+/// tag.
+pub const DEFAULT_MIN_FINAL_CLTV_EXPIRY: u64 = 18;
+
+/// Number of u5 used by the timestamp.
+const TIMESTAMP_LENGTH: usize = 7;
+
+/// Characters of the alphanumeric mode of the QR codes.
+const QR_ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Version of the binary encoding of `to_bytes`.
+pub const BINARY_FORMAT_VERSION: u8 = 1;
 
 /// Lightning Payment Request
 /// *see* [Lightning RFC](https://github.com/lightningnetwork/lightning-rfc/blob/master/11-payment-encoding.md)
 ///
 /// Represents a decoded or to be encoded payment request.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PaymentRequest {
     /// Specifies what network this Lightning payment request is meant for
     /// lnbc for bitcoin, lntb for bitcoin testnet.
     pub prefix: String,
     /// Amount to pay in millisatoshis. Donation addresses often don't have an associated amount,
     /// so amount is optional in that case.
+    #[cfg_attr(feature = "serde", serde(default, with = "::serde_utils::amount"))]
     pub amount: Option<u64>,
     /// Request timestamp (UNIX format).
     pub timestamp: u64,
     /// Id of the node emitting the payment request.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_utils::public_key"))]
     pub node_id: PublicKey,
     /// Payment tags; must include a single PaymentHash tag.
     pub tags: Vec<Tag>,
-    /// Request signature that will be checked against node id.
+    /// Request signature that will be checked against node id, 64 bytes followed by the
+    /// recovery id.
+    #[cfg_attr(feature = "serde", serde(with = "::serde_utils::hex"))]
     pub signature: Vec<u8>,
 }
 
@@ -57,6 +99,124 @@ impl PaymentRequest {
     /// `input` The encoded payment request.
     ///
     pub fn decode(input: &str) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode_with_message(input).map(|(payment_request, _)| payment_request)
+    }
+
+    /// Decode payment requests independently, e.g. historical payment requests for analytics,
+    /// returning the results in the order of the input. With the `parallel` feature, they are
+    /// decoded on the rayon thread pool. <br>
+    /// *Note*: the signature recovery doesn't create a secp256k1 context per payment request,
+    /// libsecp256k1 keeps its precomputed tables in statics shared by all the threads.
+    ///
+    /// # Params
+    /// `invoices` The encoded payment requests.
+    pub fn decode_batch(invoices: &[&str]) -> Vec<Result<PaymentRequest, Error>> {
+        #[cfg(feature = "parallel")]
+        let invoices = invoices.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let invoices = invoices.iter();
+        invoices.map(|invoice| PaymentRequest::decode(invoice)).collect()
+    }
+
+    /// Decode the provided encoded payment request, returning it along with the message that was
+    /// signed: the human readable part followed by the data part, padded to a byte boundary.
+    ///
+    /// # Params
+    /// `input` The encoded payment request.
+    pub fn decode_with_message(input: &str) -> Result<(PaymentRequest, Vec<u8>), Error> {
+        PaymentRequest::decode_with(input, &ParseOptions::default())
+    }
+
+    /// Decode the provided encoded payment request, recording the malformed tagged fields as
+    /// `Tag::Skipped` rather than failing, so the rest of the payment request can still be shown.
+    ///
+    /// # Params
+    /// `input` The encoded payment request.
+    pub fn decode_lenient(input: &str) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode_with_options(input, &ParseOptions::lenient())
+    }
+
+    /// Decode the provided encoded payment request leniently, see `decode_lenient`, reporting
+    /// the issues which didn't prevent its decoding: unknown and skipped fields, duplicate
+    /// fields, amount issues and BOLT #11 rule violations.
+    ///
+    /// # Params
+    /// `input` The encoded payment request.
+    pub fn read_with_warnings(input: &str) -> Result<DecodedInvoice, Error> {
+        let invoice = PaymentRequest::decode_lenient(input)?;
+        let mut warnings = invoice.warnings();
+
+        // the amount as written, between the prefix and the separator
+        let hrp = input.rsplit_once('1').map_or("", |(hrp, _)| hrp).to_lowercase();
+        match (hrp.get(invoice.prefix.len()..), invoice.amount) {
+            (Some(amount), Some(msat)) if Amount::encode(msat) != amount => {
+                warnings.push(Warning::NonShortestAmount(amount.to_owned()))
+            }
+            _ => (),
+        }
+        Ok(DecodedInvoice { invoice, warnings })
+    }
+
+    /// Return the issues of the payment request which don't prevent its decoding: unknown and
+    /// skipped fields, duplicate fields, sub-satoshi amount and BOLT #11 rule violations.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = self.tags
+            .iter()
+            .filter_map(|tag| match *tag {
+                Tag::UnknownTag { .. } => Some(Warning::UnknownField(tag.tag_char())),
+                Tag::Skipped { ref reason, .. } => Some(Warning::SkippedField {
+                    tag: tag.tag_char(),
+                    reason: reason.to_owned(),
+                }),
+                _ => None,
+            })
+            .collect::<Vec<Warning>>();
+        for tag_char in &['p', 'd', 'h'] {
+            let count = self.tags
+                .iter()
+                .filter(|tag| !matches!(*tag, &Tag::Skipped{..}) && tag.tag_char() == *tag_char)
+                .count();
+            if count > 1 {
+                warnings.push(Warning::DuplicateField(*tag_char));
+            }
+        }
+        match self.amount {
+            Some(msat) if msat % 1000 != 0 => warnings.push(Warning::SubSatoshiAmount(msat)),
+            _ => (),
+        }
+        if let Err(e) = self.validate() {
+            warnings.push(Warning::Validation(e));
+        }
+        for result in [self.validate_features(), self.validate_fallback_addresses()] {
+            if let Err(violations) = result {
+                warnings.extend(violations.into_iter().map(Warning::Validation));
+            }
+        }
+        warnings
+    }
+
+    /// Decode the provided encoded payment request with the given strictness.
+    ///
+    /// # Params
+    /// `input` The encoded payment request. <br>
+    /// `options` Strictness of the decoding.
+    pub fn decode_with_options(
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode_with(input, options).map(|(payment_request, _)| payment_request)
+    }
+
+    // Helper for the decoding functions.
+    fn decode_with(
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<(PaymentRequest, Vec<u8>), Error> {
+        if let Some(max_invoice_len) = options.max_invoice_len {
+            if input.len() > max_invoice_len {
+                return Err(Error::LimitExceeded(Limit::InvoiceLength, max_invoice_len));
+            }
+        }
         let Bech32 { hrp, mut data } = Bech32::from_string(input.to_owned())?;
 
         match data.len() {
@@ -65,29 +225,80 @@ impl PaymentRequest {
                 "data is too short to decode".to_owned(),
             )),
             len => {
-                let signature_bytes = data.split_off(len - 104).to_u8_vec(false)?;
+                let signature_bytes = data.split_off(len - 104).to_u8_vec(Padding::Strict)?;
 
-                let message = PaymentRequest::parse_message(&hrp, &data.to_u8_vec(true)?);
+                let signed_bytes = [hrp.as_bytes(), &data.to_u8_vec(Padding::Add)?].concat();
+                let message =
+                    secp256k1::Message::parse(&PaymentRequest::sha256_hasher(&signed_bytes));
 
-                let timestamp = Timestamp::decode(&data.drain(..7).collect::<Vec<_>>());
-                let tags = Tag::parse_all(&data)?;
+                // the timestamp precedes the tagged fields
+                let tagged_fields = data.split_off(TIMESTAMP_LENGTH);
+                let timestamp = Timestamp::decode(&data);
+                let tags = if options.lenient_tags {
+                    Tag::parse_all_lenient(&tagged_fields)
+                } else {
+                    Tag::parse_all(&tagged_fields)
+                };
+                // the tagged fields follow the separator and the timestamp
+                let tags = tags.map_err(|e| {
+                    PaymentRequest::locate_tag_error(e, hrp.len() + 1 + TIMESTAMP_LENGTH)
+                })?;
+                if options.reject_duplicate_tags {
+                    PaymentRequest::check_duplicate_tags(&tags)?;
+                }
+                PaymentRequest::check_route_hops(&tags, options)?;
+                if !options.allow_unknown_even_features {
+                    PaymentRequest::check_unknown_even_features(&tags)?;
+                }
 
+                let parsed = signature::Signature::from_slice(&signature_bytes)?;
+                let signature_bytes = match options.high_s {
+                    HighS::Reject if !parsed.is_low_s() => {
+                        return Err(Error::InvalidValue("the signature has a high s".to_owned()))
+                    }
+                    HighS::Normalize => parsed.normalize_s().to_bytes().to_vec(),
+                    _ => signature_bytes,
+                };
                 let (recovery_id, signature) = PaymentRequest::parse_signature(&signature_bytes)?;
 
                 let node_id = secp256k1::recover(&message, &signature, &recovery_id)?;
 
-                let prefix = hrp[..4].to_owned();
-                let amount = hrp.get(4..).and_then(|u| Amount::decode(u).ok());
+                let prefix = Currency::from_hrp_with(&hrp, &options.custom_currencies)
+                    .ok_or(Error::InvalidValue(format!("invalid prefix {}", hrp)))?
+                    .prefix()
+                    .to_owned();
+                let amount = match hrp.get(prefix.len()..) {
+                    Some(amount) if !amount.is_empty() => {
+                        let msat = Amount::decode(amount)?;
+                        if options.strict_amount && Amount::encode(msat) != amount {
+                            return Err(AmountError::NotShortest(amount.to_owned()).into());
+                        }
+                        Some(msat)
+                    }
+                    _ => None,
+                };
                 let valid_signature = secp256k1::verify(&message, &signature, &node_id);
-                if valid_signature {
-                    Ok(PaymentRequest {
+                let payee_node_id = tags.iter()
+                    .filter_map(|v| match *v {
+                        Tag::PayeeNodeId { ref pub_key } => Some(pub_key),
+                        _ => None,
+                    })
+                    .next();
+                // the signature must be from the payee node when it is provided
+                let valid_payee_node_id = match payee_node_id {
+                    Some(pub_key) => *pub_key == NodeId::from(&node_id),
+                    None => true,
+                };
+                if valid_signature && valid_payee_node_id {
+                    let payment_request = PaymentRequest {
                         prefix,
                         amount,
                         timestamp,
                         node_id,
                         tags,
                         signature: signature_bytes,
-                    })
+                    };
+                    Ok((payment_request, signed_bytes))
                 } else {
                     Err(Error::SignatureError(secp256k1::Error::InvalidSignature))
                 }
@@ -95,45 +306,267 @@ impl PaymentRequest {
         }
     }
 
-    /// Returns the encoded representation of a bech32 payment request.
+    // Helper for decode_with, converting the span of a tag error from u5 of the tagged fields to
+    // characters of the payment request.
+    fn locate_tag_error(error: Error, fields_start: usize) -> Error {
+        match error {
+            Error::TagError {
+                tag,
+                span: Some(span),
+                kind,
+            } => Error::TagError {
+                tag,
+                span: Some(span.start + fields_start..span.end + fields_start),
+                kind,
+            },
+            error => error,
+        }
+    }
+
+    /// Returns the encoded representation of a bech32 payment request. The payment request must
+    /// be signed.
     pub fn encode(&self) -> Result<String, Error> {
-        let hr_amount = self.amount.map_or(String::new(), |a| Amount::encode(a));
-        let mut hrp = self.prefix.to_owned() + &hr_amount;
-        let stream = [self.stream(), self.signature.to_u5_vec(true)?].concat();
+        let mut encoded = String::new();
+        self.write_to(&mut encoded)?;
+        Ok(encoded)
+    }
+
+    /// Write the encoded payment request to a sink, converting the fields straight to bech32
+    /// characters and computing the checksum as they are written, without the intermediate
+    /// vectors of `encode`. The payment request must be signed.
+    /// # Arguments
+    /// `writer` Sink of the payment request, e.g. a `String` reused between payment requests.
+    /// <br>
+    /// *Note*: on error, the sink may hold the beginning of the payment request.
+    pub fn write_to<W: fmt::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if self.signature.len() != 65 {
+            return Err(Error::InvalidLength(
+                "the signature length must be 65 bytes".to_owned(),
+            ));
+        }
+        let timestamp = Timestamp::check(self.timestamp)?;
+        let hrp = Hrp {
+            prefix: &self.prefix,
+            amount: self.amount,
+        };
+        let mut bech32 = Bech32Writer::new(&hrp, writer).map_err(encoder::write_error)?;
+        bech32.write_number(timestamp, 7).map_err(encoder::write_error)?;
+        for tag in &self.tags {
+            tag.write_u5(&mut bech32)?;
+        }
+        bech32
+            .write_bytes(&self.signature)
+            .and_then(|_| bech32.finish())
+            .map_err(encoder::write_error)
+    }
+
+    /// Write the encoded payment request to an io sink, see `write_to`.
+    /// # Arguments
+    /// `writer` Sink of the payment request, preferably buffered as the characters are written
+    /// one at a time.
+    pub fn write_to_io<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut sink = IoWriter::new(writer);
+        self.write_to(&mut sink).map_err(|e| sink.into_error(e))
+    }
+
+    /// Returns the `lightning:` URI of the payment request, see `LightningUri` to add query
+    /// parameters.
+    pub fn to_uri(&self) -> Result<String, Error> {
+        Ok(format!("{}{}", uri::LIGHTNING_SCHEME, self.encode()?))
+    }
+
+    /// Returns a BIP-21 unified URI offering both an on-chain address and this payment request,
+    /// see `UnifiedUri` to add a label, message or other parameters.
+    ///
+    /// # Arguments
+    /// `address` On-chain address.
+    pub fn to_unified_uri(&self, address: &str) -> Result<String, Error> {
+        UnifiedUri::new(address, self.to_owned()).to_uri()
+    }
+
+    /// Returns the encoded payment request in uppercase, which bech32 allows, so it fits the
+    /// alphanumeric mode of the QR codes.
+    pub fn encode_uppercase(&self) -> Result<String, Error> {
+        self.encode().map(|encoded| encoded.to_uppercase())
+    }
+
+    /// Return the data in the case taking the least space in a QR code: uppercase if all its
+    /// characters then belong to the QR alphanumeric set, e.g. a payment request or a
+    /// `lightning:` URI, unchanged otherwise, e.g. a URI with query parameters, which must then
+    /// use the byte mode anyway.
+    ///
+    /// # Arguments
+    /// `data` Payment request or URI to put in a QR code.
+    pub fn qr_optimal_case(data: &str) -> String {
+        let uppercase = data.to_uppercase();
+        if uppercase.chars().all(|c| QR_ALPHANUMERIC.contains(c)) {
+            uppercase
+        } else {
+            data.to_owned()
+        }
+    }
+
+    /// Return a compact binary encoding of the payment request, e.g. to store decoded payment
+    /// requests without their bech32 string. <br>
+    /// The encoding is: the version `BINARY_FORMAT_VERSION`, the prefix length and prefix, a
+    /// flags byte (bit 0: amount present), the amount (u64), the timestamp (u64), the 33 bytes
+    /// node id, the signature length and signature, the number of u5 of the tagged fields (u32)
+    /// and the tagged fields packed in bytes; the integers are big-endian.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        if self.prefix.len() > 255 || self.signature.len() > 255 {
+            return Err(Error::InvalidLength(
+                "the prefix and the signature must be at most 255 bytes".to_owned(),
+            ));
+        }
+        let tagged_fields = self.tags
+            .iter()
+            .map(|tag| tag.to_vec_u5())
+            .collect::<Result<Vec<Vec<U5>>, Error>>()?
+            .concat();
+        let mut bytes = vec![BINARY_FORMAT_VERSION, self.prefix.len() as u8];
+        bytes.extend(self.prefix.as_bytes());
+        bytes.push(self.amount.is_some() as u8);
+        if let Some(amount) = self.amount {
+            bytes.write_u64::<BigEndian>(amount)?;
+        }
+        bytes.write_u64::<BigEndian>(self.timestamp)?;
+        bytes.extend(NodeId::from(&self.node_id).as_bytes().iter());
+        bytes.push(self.signature.len() as u8);
+        bytes.extend(&self.signature);
+        bytes.write_u32::<BigEndian>(tagged_fields.len() as u32)?;
+        bytes.extend(tagged_fields.to_u8_vec(Padding::Add)?);
+        Ok(bytes)
+    }
+
+    /// Decode the binary encoding of `to_bytes`.
+    /// # Params
+    /// `bytes` The encoded payment request.
+    pub fn from_bytes(bytes: &[u8]) -> Result<PaymentRequest, Error> {
+        let mut reader = BinaryReader { bytes, position: 0 };
+        let version = reader.read(1)?[0];
+        if version != BINARY_FORMAT_VERSION {
+            return Err(Error::InvalidValue(format!(
+                "unsupported binary format version {}",
+                version
+            )));
+        }
+        let prefix_len = reader.read(1)?[0] as usize;
+        let prefix = String::from_utf8(reader.read(prefix_len)?.to_vec())?;
+        let amount = match reader.read(1)?[0] {
+            0 => None,
+            1 => Some(BigEndian::read_u64(reader.read(8)?)),
+            flags => {
+                return Err(Error::InvalidValue(format!("invalid flags {:#04x}", flags)));
+            }
+        };
+        let timestamp = BigEndian::read_u64(reader.read(8)?);
+        let node_id = NodeId::from_slice(reader.read(33)?)?.to_public_key()?;
+        let signature_len = reader.read(1)?[0] as usize;
+        let signature = reader.read(signature_len)?.to_vec();
+        let u5_len = BigEndian::read_u32(reader.read(4)?) as usize;
+        let tagged_len = u5_len
+            .checked_mul(5)
+            .map(|bits| bits.div_ceil(8))
+            .ok_or_else(|| Error::InvalidLength(format!("{} tagged field values", u5_len)))?;
+        let mut tagged_fields = reader.read(tagged_len)?.to_vec().to_u5_vec(Padding::Add)?;
+        if tagged_fields[u5_len..].iter().any(|&u5| u5 != 0) {
+            return Err(Error::InvalidPadding);
+        }
+        tagged_fields.truncate(u5_len);
+        if reader.position != bytes.len() {
+            return Err(Error::InvalidLength(format!(
+                "{} unexpected trailing bytes",
+                bytes.len() - reader.position
+            )));
+        }
+        Ok(PaymentRequest {
+            prefix,
+            amount,
+            timestamp,
+            node_id,
+            tags: Tag::parse_all_lenient(&tagged_fields)?,
+            signature,
+        })
+    }
 
-        let checksum = bech32_checksum(&hrp.as_bytes().to_vec(), &stream);
-        let stream_sum = [stream, checksum]
-            .concat()
+    /// Return the number of characters each section of the encoded payment request takes. Tags
+    /// are identified by their bech32 character. <br>
+    /// Fail as `encode` does if a tag can't be encoded.
+    pub fn size_breakdown(&self) -> Result<Vec<(String, usize)>, Error> {
+        let hrp = self.prefix.to_owned() + &self.amount.map(Amount::encode).unwrap_or_default();
+        let mut sections = vec![
+            ("hrp".to_owned(), hrp.len()),
+            ("separator".to_owned(), 1),
+            ("timestamp".to_owned(), TIMESTAMP_LENGTH),
+        ];
+        let tags = self.tags
             .iter()
-            .map(|i| CHARSET[*i as usize])
-            .collect::<String>();
-        hrp.push_str("1");
-        hrp.push_str(&stream_sum);
-        Ok(hrp)
+            .map(Tag::to_vec_u5)
+            .collect::<Result<Vec<_>, Error>>()?;
+        sections.extend(
+            tags.into_iter()
+                .map(|bytes| (CHARSET[bytes[0] as usize].to_string(), bytes.len())),
+        );
+        sections.push(("signature".to_owned(), self.signature.to_u5_vec(Padding::Add)?.len()));
+        sections.push(("checksum".to_owned(), 6));
+        Ok(sections)
+    }
+
+    /// Return a breakdown of the payment request describing every field, the signed message
+    /// hash and the signature components, e.g. for debugging tools.
+    pub fn explain(&self) -> InvoiceReport {
+        InvoiceReport::new(self)
+    }
+
+    /// Return this payment request formatted with the customer data redacted, e.g. to be logged,
+    /// see `Redacted`.
+    pub fn redacted<'a>(&'a self) -> Redacted<'a, PaymentRequest> {
+        Redacted(self)
+    }
+
+    /// Return the view of this payment request with the shape of lnd's `decodepayreq`
+    /// response, e.g. to serialize it as lnd's JSON with the `serde` feature.
+    pub fn to_lnd(&self) -> PayReq {
+        PayReq::new(self)
+    }
+
+    /// Return the view of this payment request with the shape of Core Lightning's `decodepay`
+    /// output, e.g. to serialize it as its JSON with the `serde` feature.
+    pub fn to_cln(&self) -> DecodePay {
+        DecodePay::new(self)
+    }
+
+    /// Return the protobuf message of this payment request, compatible with lnd's `PayReq`.
+    #[cfg(feature = "proto")]
+    pub fn to_proto(&self) -> Result<proto::PayReq, Error> {
+        proto::PayReq::new(self)
+    }
+
+    /// Rebuild a payment request from its protobuf message.
+    /// # Arguments
+    /// `message` Protobuf message, see `to_proto`.
+    #[cfg(feature = "proto")]
+    pub fn from_proto(message: &proto::PayReq) -> Result<PaymentRequest, Error> {
+        message.to_payment_request()
     }
 
     /// Return the hash of this payment request.
     pub fn hash(&self) -> Result<Vec<u8>, Error> {
-        let amount = self.amount.map_or(String::new(), |a| Amount::encode(a));
-        let bytes = (self.prefix.to_owned() + &amount).as_bytes().to_vec();
-
-        Ok(
-            PaymentRequest::sha256_hasher(&[bytes, self.stream().to_u8_vec(false)?].concat())
-                .to_vec(),
-        )
+        self.message().map(|message| message.serialize().to_vec())
     }
 
-    /// Return a new PaymentRequest signed with the provided secret key.
+    /// Return a new PaymentRequest signed with the provided secret key, the node id is updated
+    /// to the public key of the signer.
     /// # Params
     /// `secret_key` The secret key used to sign the payment request.
     pub fn sign(&self, secret_key: &SecretKey) -> Result<PaymentRequest, Error> {
-        let hrp = self.prefix.to_owned() + &self.amount.map(Amount::encode).unwrap_or_default();
-        let message = PaymentRequest::parse_message(&hrp, &self.stream().to_u8_vec(true)?);
+        let message = self.message()?;
         match secp256k1::sign(&message, secret_key) {
             Ok((signature, recovery_id)) => {
                 let mut signed = self.clone();
                 let mut bytes = signature.serialize().to_vec();
                 bytes.push(recovery_id.serialize());
+                signed.node_id = PublicKey::from_secret_key(secret_key);
                 signed.signature = bytes;
                 Ok(signed)
             }
@@ -141,6 +574,145 @@ impl PaymentRequest {
         }
     }
 
+    /// Return the currency of the payment request if it is a Bitcoin one, see `check_network`
+    /// for the custom currencies.
+    pub fn currency(&self) -> Option<Currency> {
+        Currency::from_prefix(&self.prefix)
+    }
+
+    /// Check that the payment request is meant for the expected currency.
+    /// # Arguments
+    /// `expected` The currency the payment request must be for.
+    pub fn check_network(&self, expected: &Currency) -> Result<(), Error> {
+        if self.prefix == expected.prefix() {
+            Ok(())
+        } else {
+            Err(Error::WrongNetwork(format!(
+                "expected {} payment request, found {}",
+                expected.prefix(),
+                self.prefix
+            )))
+        }
+    }
+
+    /// Recover the public key of the payee node from the signature. The recovered key must match
+    /// the payee node id tag when present.
+    pub fn recover_payee_pub_key(&self) -> Result<PublicKey, Error> {
+        let (recovery_id, signature) = PaymentRequest::parse_signature(&self.signature)?;
+        let pub_key = secp256k1::recover(&self.message()?, &signature, &recovery_id)?;
+        if self.matches_payee_node_id(&pub_key) {
+            Ok(pub_key)
+        } else {
+            Err(Error::InvalidValue(
+                "the signature does not match the payee node id".to_owned(),
+            ))
+        }
+    }
+
+    /// Check that the payment request is signed by the provided public key, and that the key
+    /// matches the payee node id tag when present.
+    /// # Params
+    /// `pub_key` Public key of the payee node.
+    pub fn verify_signature(&self, pub_key: &PublicKey) -> bool {
+        match (
+            self.message(),
+            PaymentRequest::parse_signature(&self.signature),
+        ) {
+            (Ok(message), Ok((_, signature))) => {
+                secp256k1::verify(&message, &signature, pub_key)
+                    && self.matches_payee_node_id(pub_key)
+            }
+            _ => false,
+        }
+    }
+
+    /// Check the payment request holds exactly one of the description or the description
+    /// hash. <br>
+    /// *Note*: `InvoiceBuilder` enforces it when the payment request is built.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let description = self.tags
+            .iter()
+            .any(|tag| matches!(*tag, Tag::Description{..}));
+        match (description, self.description_hash().is_some()) {
+            (false, false) => Err(ValidationError::MissingDescription),
+            (true, true) => Err(ValidationError::DescriptionAndHash),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the payment request can be paid by a payer knowing the given features: it must be
+    /// valid and must not require a feature unknown to the payer.
+    /// # Arguments
+    /// `known_features` Features known to the payer, e.g. `Features::known()`.
+    pub fn validate_for_payment(&self, known_features: &Features) -> Result<(), ValidationError> {
+        self.validate()?;
+        match self.features() {
+            Some(ref features) if features.requires_unknown_bits(known_features) => Err(
+                ValidationError::UnknownRequiredFeatures(
+                    features.unknown_required_bits(known_features),
+                ),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the features against each other and against the tagged fields, returning all the
+    /// violations rather than the first one: `basic_mpp` depends on `payment_secret`, which
+    /// depends on `var_onion_optin` and requires the `s` field.
+    pub fn validate_features(&self) -> Result<(), Vec<ValidationError>> {
+        let features = self.features().unwrap_or_default();
+        let mut violations = vec![];
+        for &(feature, dependency) in &[
+            (Features::BASIC_MPP, Features::PAYMENT_SECRET),
+            (Features::PAYMENT_SECRET, Features::VAR_ONION_OPTIN),
+        ] {
+            if features.has_feature(feature) && !features.has_feature(dependency) {
+                violations.push(ValidationError::MissingFeatureDependency {
+                    feature,
+                    dependency,
+                });
+            }
+        }
+        if features.has_feature(Features::PAYMENT_SECRET) && self.payment_secret().is_none() {
+            violations.push(ValidationError::MissingPaymentSecret);
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Check the hash length of every fallback address against its version, e.g. 20 bytes for
+    /// P2PKH, returning all the mismatches. <br>
+    /// *Note*: these are warnings, the payment can still be made over lightning.
+    pub fn validate_fallback_addresses(&self) -> Result<(), Vec<ValidationError>> {
+        let violations = self.tags
+            .iter()
+            .filter_map(|tag| match *tag {
+                Tag::FallbackAddress { version, ref hash } => {
+                    address::check_fallback_address(version, hash)
+                        .err()
+                        .map(|_| ValidationError::InvalidFallbackAddress {
+                            version,
+                            len: hash.len(),
+                        })
+                }
+                _ => None,
+            })
+            .collect::<Vec<ValidationError>>();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Return the amount to pay, `None` for donations where the payer chooses the amount.
+    pub fn amount(&self) -> Option<Amount> {
+        self.amount.map(Amount::from_msat)
+    }
+
     /// Update the payment amount.
     pub fn update_amount(&mut self, amount: Option<u64>) {
         self.amount = amount;
@@ -151,12 +723,53 @@ impl PaymentRequest {
         self.node_id = node_id;
     }
 
+    /// Return the recovery id of the signature, its last byte.
+    pub fn recovery_id(&self) -> Option<u8> {
+        self.signature.get(64).cloned()
+    }
+
+    /// Return the signature, failing if it is malformed, e.g. to check whether its `s` is low.
+    pub fn signature(&self) -> Result<signature::Signature, Error> {
+        signature::Signature::from_slice(&self.signature)
+    }
+
+    /// Check that the preimage is the one of the payment hash, the settlement check of the
+    /// receivers.
+    /// # Params
+    /// `preimage` Preimage revealed by the payment.
+    pub fn matches_preimage(&self, preimage: &PaymentPreimage) -> bool {
+        self.payment_hash() == Some(preimage.payment_hash())
+    }
+
     /// Return the payment hash.
-    pub fn payment_hash(&self) -> Option<Vec<u8>> {
+    pub fn payment_hash(&self) -> Option<PaymentHash> {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::PaymentHash { hash } => Some(hash),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// Return the payment secret, from the `s` tag. <br>
+    /// *Note*: the secret is borrowed, it is not `Copy`.
+    pub fn payment_secret(&self) -> Option<&PaymentSecret> {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::PaymentSecret { ref secret } => Some(secret),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// Return the compressed public key of the payee node, from the `n` tag.
+    pub fn payee_node_id(&self) -> Option<NodeId> {
         self.tags
             .iter()
             .filter_map(|v| match *v {
-                Tag::PaymentHash { ref hash } => Some(hash.to_owned()),
+                Tag::PayeeNodeId { pub_key } => Some(pub_key),
                 _ => None,
             })
             .next()
@@ -190,26 +803,48 @@ impl PaymentRequest {
     /// *Note*: must be used if and only if description is not used (Replaces description).
     /// # Params
     /// `hash` 256-bit description of purpose of payment (SHA256).
-    pub fn update_description_hash(&mut self, hash: Vec<u8>) {
+    pub fn update_description_hash(&mut self, hash: DescriptionHash) {
         let mut tags = self.filter_description();
         tags.push(Tag::DescriptionHash { hash });
         self.tags = tags;
     }
 
-    /// Return the extra routing info.
+    /// Return the extra routing info of all the routing tags.
     pub fn routing_info(&self) -> Vec<ExtraHop> {
-        Itertools::flatten(
-            self.tags
-                .iter()
-                .filter_map(|v| match *v {
-                    Tag::RoutingInfo { ref path } => Some(path.to_owned()),
-                    _ => None,
-                })
-         ).collect_vec()
+        self.route_hint_hops().cloned().collect_vec()
+    }
+
+    /// Iterate over the extra hops of all the routing tags, route after route.
+    pub fn route_hint_hops<'a>(&'a self) -> impl Iterator<Item = &'a ExtraHop> + 'a {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::RoutingInfo { ref path } => Some(path),
+                _ => None,
+            })
+            .flat_map(|path| path.iter())
+    }
+
+    /// Return the extra routing info of each routing tag, including empty ones.
+    pub fn route_hints(&self) -> Vec<Vec<ExtraHop>> {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::RoutingInfo { ref path } => Some(path.to_owned()),
+                _ => None,
+            })
+            .collect_vec()
+    }
+
+    /// Return the min_final_cltv_expiry, defaulting to 18 blocks if not specified. <br>
+    /// *Note*: see `min_final_cltv_expiry_field` for the value of the `c` field only.
+    pub fn min_final_cltv_expiry(&self) -> u64 {
+        self.min_final_cltv_expiry_field()
+            .unwrap_or(DEFAULT_MIN_FINAL_CLTV_EXPIRY)
     }
 
-    /// Return the min_final_cltv_expiry if any.
-    pub fn min_final_cltv_expiry(&self) -> Option<u64> {
+    /// Return the min_final_cltv_expiry of the `c` field if any.
+    pub fn min_final_cltv_expiry_field(&self) -> Option<u64> {
         self.tags
             .iter()
             .filter_map(|v| match *v {
@@ -219,6 +854,12 @@ impl PaymentRequest {
             .next()
     }
 
+    /// Return the min_final_cltv_expiry, defaulting to 18 blocks if not specified.
+    #[deprecated(note = "use `min_final_cltv_expiry`, which returns the default too")]
+    pub fn min_final_cltv_expiry_delta(&self) -> u64 {
+        self.min_final_cltv_expiry()
+    }
+
     /// Update the min_final_cltv_expiry.
     /// # Params
     /// `blocks` Minimum CLTV expiry for incoming HTLC
@@ -232,7 +873,8 @@ impl PaymentRequest {
         self.tags = tags;
     }
 
-    /// Return the payment request expiry if any.
+    /// Return the payment request expiry if any. <br>
+    /// *Note*: see `expiry_seconds` for the value to use when paying.
     pub fn expiry(&self) -> Option<u64> {
         self.tags
             .iter()
@@ -243,6 +885,11 @@ impl PaymentRequest {
             .next()
     }
 
+    /// Return the payment request expiry, defaulting to 3600 seconds if not specified.
+    pub fn expiry_seconds(&self) -> u64 {
+        self.expiry().unwrap_or(DEFAULT_EXPIRY_SECONDS)
+    }
+
     /// Update the expiry data for this payment request.
     /// # Arguments
     /// `seconds` Expiry time in seconds.
@@ -256,12 +903,103 @@ impl PaymentRequest {
         self.tags = tags;
     }
 
+    /// Return the seconds remaining until this payment request expires, negative if it has
+    /// already expired. <br>
+    /// *Note*: expiry defaults to 3600 seconds if not specified.
+    /// # Arguments
+    /// `now_unix` Current time (UNIX format).
+    pub fn seconds_until_expiry(&self, now_unix: u64) -> i64 {
+        self.expires_at() as i64 - now_unix as i64
+    }
+
+    /// Return the time this payment request expires at (UNIX format). <br>
+    /// *Note*: expiry defaults to 3600 seconds if not specified.
+    pub fn expires_at(&self) -> u64 {
+        self.timestamp.saturating_add(self.expiry_seconds())
+    }
+
+    /// Check if this payment request has expired, according to the system time.
+    pub fn is_expired(&self) -> Result<bool, Error> {
+        Ok(Timestamp::now()? >= self.expires_at())
+    }
+
+    /// Check if this payment request has expired, according to the clock.
+    /// # Arguments
+    /// `clock` Source of the current time, e.g. `SystemClock`.
+    pub fn is_expired_with<C: Clock>(&self, clock: &C) -> bool {
+        clock.now() >= self.expires_at()
+    }
+
+    /// Return the time remaining until this payment request expires, according to the clock,
+    /// zero if it has already expired.
+    /// # Arguments
+    /// `clock` Source of the current time, e.g. `SystemClock`.
+    pub fn duration_until_expiry_with<C: Clock>(&self, clock: &C) -> Duration {
+        Duration::from_secs(self.expires_at().saturating_sub(clock.now()))
+    }
+
+    /// Return the time remaining until this payment request expires, according to the system
+    /// time, zero if it has already expired.
+    pub fn duration_until_expiry(&self) -> Result<Duration, Error> {
+        Ok(Duration::from_secs(
+            self.expires_at().saturating_sub(Timestamp::now()?),
+        ))
+    }
+
+    /// Check that every feature required by this payment request is supported by the node. <br>
+    /// *Note*: required features are those with an even bit set, a node supports the feature
+    /// if it sets either the even or the odd bit.
+    /// # Arguments
+    /// `node_features` Node features bitfield (big-endian).
+    pub fn is_payable_with_features(&self, node_features: &[u8]) -> bool {
+        let supports = |bit: usize| match node_features.len().checked_sub(1 + bit / 8) {
+            Some(index) => (node_features[index] >> (bit % 8)) & 1 == 1,
+            None => false,
+        };
+        self.features()
+            .map(|features| features.bits())
+            .unwrap_or_default()
+            .iter()
+            .filter(|&bit| bit % 2 == 0)
+            .all(|&bit| supports(bit) || supports(bit + 1))
+    }
+
+    /// Return the features supported or required for receiving this payment if any.
+    pub fn features(&self) -> Option<Features> {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::Features { ref bits } => Some(bits.to_owned()),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// Return the payment metadata to be sent in the onion if any.
+    pub fn payment_metadata(&self) -> Option<Vec<u8>> {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::PaymentMetadata { ref bytes } => Some(bytes.to_owned()),
+                _ => None,
+            })
+            .next()
+    }
+
+    /// Check that the description given out of band, e.g. the metadata of an LNURL-pay service,
+    /// is the one of the description hash.
+    /// # Params
+    /// `description` Description of purpose of payment.
+    pub fn check_description(&self, description: &str) -> bool {
+        self.description_hash() == Some(DescriptionHash::from_description(description))
+    }
+
     /// Return the description hash if any.
-    pub fn description_hash(&self) -> Option<Vec<u8>> {
+    pub fn description_hash(&self) -> Option<DescriptionHash> {
         self.tags
             .iter()
             .filter_map(|v| match *v {
-                Tag::DescriptionHash { ref hash } => Some(hash.to_owned()),
+                Tag::DescriptionHash { hash } => Some(hash),
                 _ => None,
             })
             .next()
@@ -269,63 +1007,44 @@ impl PaymentRequest {
 
     /// Return the fallback address if any. It could be a script address, pubkey address, ..
     pub fn fallback_address(&self) -> Option<String> {
-        // encode fallback address
-        fn bech_address(version: u8, program: Vec<u8>, network: Network) -> Option<String> {
-            let witness_program = WitnessProgram {
-                version,
-                program,
-                network,
-            };
-            witness_program.to_address().ok()
-        }
-        // prefix used in fallback closure
-        let prefix = &self.prefix;
-
-        let fallback = |tag: &Tag| -> Option<String> {
-            match *tag {
-                Tag::FallbackAddress {
-                    version: 17,
-                    ref hash,
-                } if prefix == "lnbc" =>
-                {
-                    // 0 PubkeyAddress
-                    Some(hash.to_base58check(0))
-                }
-                Tag::FallbackAddress {
-                    version: 18,
-                    ref hash,
-                } if prefix == "lnbc" =>
-                {
-                    // 5 ScriptAddress
-                    Some(hash.as_slice().to_base58check(5))
-                }
-                Tag::FallbackAddress {
-                    version: 17,
-                    ref hash,
-                } if prefix == "lntb" =>
-                {
-                    // 111 PubkeyAddressTestnet
-                    Some(hash.as_slice().to_base58check(111))
-                }
-                Tag::FallbackAddress {
-                    version: 18,
-                    ref hash,
-                } if prefix == "lntb" =>
-                {
-                    // 196 ScriptAddressTestnet
-                    Some(hash.as_slice().to_base58check(196))
-                }
-                Tag::FallbackAddress { version, ref hash } if prefix == "lnbc" => {
-                    bech_address(version, hash.to_owned(), Network::Bitcoin)
-                }
-                Tag::FallbackAddress { version, ref hash } if prefix == "lntb" => {
-                    bech_address(version, hash.to_owned(), Network::Testnet)
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::FallbackAddress { version, ref hash } => {
+                    self.fallback_to_address(version, hash).ok()
                 }
                 _ => None,
-            }
-        };
+            })
+            .next()
+    }
+
+    /// Return all the fallback addresses converted to addresses of the network, in the order of
+    /// the `f` fields. <br>
+    /// *Note*: the fallback addresses that can't be converted, e.g. with an invalid hash length,
+    /// are left out, see `validate_fallback_addresses`.
+    ///
+    /// # Arguments
+    /// `currency` the network of the addresses.
+    pub fn fallback_addresses(&self, currency: &Currency) -> Vec<String> {
+        self.tags
+            .iter()
+            .filter_map(|tag| tag.fallback_address(currency))
+            .filter_map(Result::ok)
+            .collect()
+    }
 
-        self.tags.iter().filter_map(fallback).next()
+    /// Return the first fallback address converted to an address for the network of this payment
+    /// request, or None if there is no fallback address.
+    pub fn primary_fallback_address(&self) -> Option<Result<String, Error>> {
+        self.tags
+            .iter()
+            .filter_map(|v| match *v {
+                Tag::FallbackAddress { version, ref hash } => {
+                    Some(self.fallback_to_address(version, hash))
+                }
+                _ => None,
+            })
+            .next()
     }
 
     /// Update the fallback address.
@@ -335,6 +1054,7 @@ impl PaymentRequest {
     ///               - 17 (pubkey hash) <br>
     ///               - 18 (script hash) <br>
     ///               - 0 (segwit hash: p2wpkh (20 bytes) or p2wsh (32 bytes)) <br>
+    ///               - 1 to 16 (segwit witness program of 2 to 40 bytes, e.g. p2tr) <br>
     /// `hash` the address hash.
     pub fn update_fallback_address(&mut self, version: u8, hash: Vec<u8>) {
         let mut tags = self.tags
@@ -346,6 +1066,15 @@ impl PaymentRequest {
         self.tags = tags
     }
 
+    /// Add a fallback address, keeping the existing ones.
+    ///
+    /// # Arguments
+    /// `version` the address version, see `update_fallback_address`.
+    /// `hash` the address hash.
+    pub fn add_fallback_address(&mut self, version: u8, hash: Vec<u8>) {
+        self.tags.push(Tag::FallbackAddress { version, hash });
+    }
+
     /// Create a new PaymentRequest.
     ///
     /// # Arguments
@@ -357,12 +1086,15 @@ impl PaymentRequest {
     /// `fallback_address` Fallback on chain address.
     /// `expiry_seconds` Expiry time of the payment request.
     /// `extra_hops` Extra routing information.
-    /// `timestamp` Request timestamp.
+    /// `timestamp` Request timestamp, defaults to the current time.
     /// `min_final_cltv_expiry` min_final_cltv_expiry.
+    ///
+    /// *Note*: signing is deterministic (RFC 6979), so providing a fixed `timestamp` yields a
+    /// byte-stable payment request.
     pub fn new(
         prefix: String,
         amount: Option<u64>,
-        payment_hash: Vec<u8>,
+        payment_hash: PaymentHash,
         secret_key: &SecretKey,
         description: String,
         fallback_address: Option<String>,
@@ -371,6 +1103,9 @@ impl PaymentRequest {
         timestamp: Option<u64>,
         min_final_cltv_expiry: Option<u64>,
     ) -> Result<PaymentRequest, Error> {
+        if let Some(amount) = amount {
+            Amount::check_supply(amount)?;
+        }
         let mut tags = vec![
             Tag::PaymentHash { hash: payment_hash },
             Tag::Description { description },
@@ -378,48 +1113,135 @@ impl PaymentRequest {
         if let Some(seconds) = expiry_seconds {
             tags.push(Tag::Expiry { seconds })
         }
-        if extra_hops.len() > 0 {
+        if !extra_hops.is_empty() {
             tags.push(Tag::RoutingInfo { path: extra_hops })
         }
 
-        if let Some(tag) = fallback_address.and_then(PaymentRequest::tag_from_fallback_address) {
+        if let Some(tag) = fallback_address
+            .and_then(|address| PaymentRequest::tag_from_fallback_address(&address, &prefix))
+        {
             tags.push(tag)
         }
 
-        let time = timestamp.unwrap_or({
-            let start = SystemTime::now();
-            let time = start
-                .duration_since(UNIX_EPOCH)
-                .map_err(|_| Error::InvalidValue("invalid system time".to_owned()))?;
-            time.as_secs() * 1000 + time.subsec_nanos() as u64 / 1_000_000
-        });
+        let time = match timestamp {
+            Some(time) => time,
+            None => Timestamp::now()?,
+        };
 
         if let Some(blocks) = min_final_cltv_expiry {
             tags.push(Tag::MinFinalCltvExpiry { blocks })
         }
 
         let pay = PaymentRequest {
-            prefix: prefix,
+            prefix,
             amount,
             timestamp: time,
-            node_id: secp256k1::PublicKey::from_secret_key(&secret_key),
+            node_id: secp256k1::PublicKey::from_secret_key(secret_key),
             tags,
             signature: Vec::new(),
         };
-        pay.sign(&secret_key)
+        pay.sign(secret_key)
+    }
+
+    /// Create a new signed donation PaymentRequest, without amount, for bitcoin mainnet and
+    /// timestamped with the current time.
+    ///
+    /// # Arguments
+    /// `payment_hash` SHA256 payment hash.
+    /// `description` Short description of purpose of payment.
+    /// `secret_key` Secret key.
+    pub fn new_donation(
+        payment_hash: [u8; 32],
+        description: &str,
+        secret_key: &[u8; 32],
+    ) -> Result<PaymentRequest, Error> {
+        PaymentRequest::new(
+            Currency::Bitcoin.prefix().to_owned(),
+            None,
+            PaymentHash::from(payment_hash),
+            &SecretKey::parse(secret_key)?,
+            description.to_owned(),
+            None,
+            None,
+            Vec::new(),
+            None,
+            None,
+        )
     }
 
     /// A representation of this payment request, without its signature, as a bit stream.
     /// This is what will be signed
-    fn stream(&self) -> Vec<U5> {
-        //Result<Vec<u8>, Error> {
+    fn stream(&self) -> Result<Vec<U5>, Error> {
         let bytes = self.tags
             .iter()
-            .flat_map(|tag| tag.to_vec_u5())
-            .collect_vec()
+            .map(Tag::to_vec_u5)
+            .collect::<Result<Vec<_>, Error>>()?
             .concat();
-        [Timestamp::encode(self.timestamp), bytes].concat()
+        Ok([Timestamp::encode(self.timestamp)?, bytes].concat())
+    }
+    /// The signed message, the hash of the human readable part followed by the data part.
+    fn message(&self) -> Result<Message, Error> {
+        let hrp = self.prefix.to_owned() + &self.amount.map(Amount::encode).unwrap_or_default();
+        Ok(PaymentRequest::parse_message(&hrp, &self.stream()?.to_u8_vec(Padding::Add)?))
+    }
+
+    /// Check that the mandatory fields appear at most once.
+    fn check_duplicate_tags(tags: &[Tag]) -> Result<(), Error> {
+        for tag_char in &['p', 'd', 'h'] {
+            let count = tags.iter()
+                .filter(|tag| !matches!(*tag, &Tag::Skipped{..}) && tag.tag_char() == *tag_char)
+                .count();
+            if count > 1 {
+                return Err(Error::InvalidFormat(format!("duplicate {} field", tag_char)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the number of hops of each routing info field and of all of them together.
+    fn check_route_hops(tags: &[Tag], options: &ParseOptions) -> Result<(), Error> {
+        let routes = tags.iter()
+            .filter_map(|tag| match *tag {
+                Tag::RoutingInfo { ref path } => Some(path.len()),
+                _ => None,
+            })
+            .collect_vec();
+        match options.max_hops_per_route {
+            Some(max) if routes.iter().any(|&hops| hops > max) => {
+                Err(Error::LimitExceeded(Limit::HopsPerRoute, max))
+            }
+            _ => match options.max_route_hops {
+                Some(max) if routes.iter().sum::<usize>() > max => {
+                    Err(Error::LimitExceeded(Limit::RouteHops, max))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    /// Check the feature bits required by the payment request, the even ones, are known.
+    fn check_unknown_even_features(tags: &[Tag]) -> Result<(), Error> {
+        for tag in tags {
+            if let Tag::Features { ref bits } = *tag {
+                if let Some(&bit) = bits.unknown_required_bits(&Features::known()).first() {
+                    return Err(Error::InvalidFormat(format!(
+                        "unknown required feature bit {}",
+                        bit
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the public key against the payee node id tag, if any.
+    fn matches_payee_node_id(&self, pub_key: &PublicKey) -> bool {
+        match self.payee_node_id() {
+            Some(payee_node_id) => payee_node_id == NodeId::from(pub_key),
+            None => true,
+        }
     }
+
     /// Remove the payment description
     fn filter_description(&self) -> Vec<Tag> {
         self.tags
@@ -448,42 +1270,214 @@ impl PaymentRequest {
         hash
     }
 
-    /// Parse the signature, the signature must be 65 bytes
+    /// Parse the signature, the signature must be 65 bytes with `r`, `s` and the recovery id in
+    /// range.
     fn parse_signature(bytes: &[u8]) -> Result<(RecoveryId, Signature), Error> {
-        if bytes.len() == 65 {
-            //  the recovery id is the last byte of the signature
-            let recovery_id = secp256k1::RecoveryId::parse(bytes[64])?;
-            let sig_raw = bytes[..64].iter().enumerate().fold(
-                [0u8; 64],
-                |mut acc, (index, item)| {
-                    acc[index] = *item;
-                    acc
-                },
-            );
-            let signature = secp256k1::Signature::parse(&sig_raw);
-            Ok((recovery_id, signature))
-        } else {
-            Err(Error::InvalidLength(
-                "the length must be 65 bytes".to_owned(),
-            ))
-        }
+        signature::Signature::from_slice(bytes).map(|signature| signature.to_secp256k1())
+    }
+
+    /// Convert a fallback address to an address for the network of this payment request.
+    fn fallback_to_address(&self, version: u8, hash: &[u8]) -> Result<String, Error> {
+        let currency = Currency::from_prefix(&self.prefix)
+            .ok_or(Error::InvalidValue(format!("unknown prefix {}", self.prefix)))?;
+        address::to_address(version, hash, &currency)
     }
 
     // get tag from fallback adress
-    fn tag_from_fallback_address(address: String) -> Option<Tag> {
-        match address.from_base58check() {
-            Ok((version, hash)) => match version {
-                0 | 111 => Some(Tag::FallbackAddress { version: 17, hash }),
-                5 | 196 => Some(Tag::FallbackAddress { version: 18, hash }),
-                _ => None,
-            },
-            _ => match WitnessProgram::from_address(address.to_owned()) {
-                Ok(witness) => Some(Tag::FallbackAddress {
-                    version: witness.version,
-                    hash: witness.program,
-                }),
-                _ => None,
-            },
+    fn tag_from_fallback_address(address: &str, prefix: &str) -> Option<Tag> {
+        Currency::from_prefix(prefix)
+            .and_then(|currency| address::from_address(address, &currency).ok())
+            .map(|(version, hash)| Tag::FallbackAddress { version, hash })
+    }
+}
+
+// Helper for write_to, the human readable part written without concatenating its parts.
+struct Hrp<'a> {
+    prefix: &'a str,
+    amount: Option<u64>,
+}
+
+impl<'a> fmt::Display for Hrp<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.prefix)?;
+        match self.amount {
+            Some(amount) => MilliSatoshi(amount).write_bolt11(f),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Encode the payment request, as `encode` does. <br>
+/// *Note*: never fails, a payment request which can't be encoded, e.g. unsigned or with fields
+/// changed to values out of range, is written as `<invalid payment request: error>`, `encode`
+/// returns the error.
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.encode() {
+            Ok(encoded) => f.write_str(&encoded),
+            Err(e) => write!(f, "<invalid payment request: {}>", e),
+        }
+    }
+}
+
+/// Decode a payment request, in lowercase or uppercase, or a `lightning:` URI whose query
+/// parameters are ignored, see `LightningUri`.
+impl FromStr for PaymentRequest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<PaymentRequest, Error> {
+        let s = s.trim();
+        match uri::strip_scheme(s, uri::LIGHTNING_SCHEME) {
+            Some(_) => s.parse::<LightningUri>().map(|uri| uri.invoice),
+            None => PaymentRequest::decode(s),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for PaymentRequest {
+    type Error = Error;
+
+    fn try_from(s: &'a str) -> Result<PaymentRequest, Error> {
+        s.parse()
+    }
+}
+
+/// Reader of the binary encoding, see `PaymentRequest::from_bytes`.
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+    /// Read the next `len` bytes.
+    fn read(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = match self.position.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => {
+                return Err(Error::InvalidLength(format!(
+                    "truncated binary payment request, expected {} bytes at {}",
+                    len, self.position
+                )))
+            }
+        };
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+}
+
+/// Payment request decoded along with the issues which didn't prevent its decoding, see
+/// `PaymentRequest::read_with_warnings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInvoice {
+    /// Decoded payment request.
+    pub invoice: PaymentRequest,
+    /// Issues found in the payment request.
+    pub warnings: Vec<Warning>,
+}
+
+/// Payment request waiting for a signature made outside of the crate, e.g. by an HSM, a remote
+/// signer or an air-gapped device: `signing_data` gives what to sign and `attach_signature` turns
+/// the signature into a payment request, the private key never being handed to the crate. <br>
+/// *Note*: see `InvoiceBuilder::build_unsigned`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsignedInvoice {
+    /// Prefix of the currency, e.g. `lnbc`.
+    pub prefix: String,
+    /// Amount to pay in millisatoshis.
+    pub amount: Option<u64>,
+    /// Request timestamp (UNIX format).
+    pub timestamp: u64,
+    /// Payment tags.
+    pub tags: Vec<Tag>,
+}
+
+impl UnsignedInvoice {
+    /// Create an unsigned payment request.
+    /// # Params
+    /// `prefix` Prefix of the currency, e.g. `lnbc`.
+    /// `amount` Amount to pay in millisatoshis.
+    /// `timestamp` Request timestamp (UNIX format).
+    /// `tags` Payment tags.
+    pub fn new(
+        prefix: String,
+        amount: Option<u64>,
+        timestamp: u64,
+        tags: Vec<Tag>,
+    ) -> UnsignedInvoice {
+        UnsignedInvoice {
+            prefix,
+            amount,
+            timestamp,
+            tags,
+        }
+    }
+
+    /// Return the human readable part, the prefix followed by the amount.
+    pub fn hrp(&self) -> String {
+        Hrp {
+            prefix: &self.prefix,
+            amount: self.amount,
+        }.to_string()
+    }
+
+    /// Return the data part without the signature, the timestamp followed by the tags, as u5.
+    pub fn data_part(&self) -> Result<Vec<U5>, Error> {
+        let tags = self.tags
+            .iter()
+            .map(|tag| tag.to_vec_u5())
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok([Timestamp::encode(self.timestamp)?, tags.concat()].concat())
+    }
+
+    /// Return what the signer needs: the SHA256 to sign, of the human readable part followed by
+    /// the data part padded to bytes, then the human readable part and the data part for the
+    /// signers checking what they sign.
+    pub fn signing_data(&self) -> Result<([u8; 32], String, Vec<U5>), Error> {
+        let hrp = self.hrp();
+        let data = self.data_part()?;
+        let message = [hrp.as_bytes(), &data.to_u8_vec(Padding::Add)?].concat();
+        let hash = PaymentRequest::sha256_hasher(&message);
+        Ok((hash, hrp, data))
+    }
+
+    /// Return the payment request signed by the signer, see `attach_signature`.
+    /// # Params
+    /// `signer` Signer of the node, e.g. a `SoftwareSigner` or a KMS backed signer.
+    pub fn sign_with<S: InvoiceSigner + ?Sized>(&self, signer: &S) -> Result<PaymentRequest, Error> {
+        let (hash, _, _) = self.signing_data()?;
+        let (recovery_id, signature) = signer.sign_recoverable(&hash)?;
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature);
+        bytes[64] = recovery_id.serialize();
+        self.attach_signature(bytes)
+    }
+
+    /// Return the payment request signed with the signature, the node id being recovered from
+    /// it. <br>
+    /// *Note*: fails if the recovery id is invalid or if the recovered key doesn't match the
+    /// payee node id tag.
+    /// # Params
+    /// `signature` Compact signature of the hash of `signing_data`, 64 bytes followed by the
+    /// recovery id.
+    pub fn attach_signature(&self, signature: [u8; 65]) -> Result<PaymentRequest, Error> {
+        let (hash, _, _) = self.signing_data()?;
+        let (recovery_id, parsed) = PaymentRequest::parse_signature(&signature)?;
+        let node_id = secp256k1::recover(&Message::parse(&hash), &parsed, &recovery_id)?;
+        let payment_request = PaymentRequest {
+            prefix: self.prefix.to_owned(),
+            amount: self.amount,
+            timestamp: self.timestamp,
+            node_id,
+            tags: self.tags.to_owned(),
+            signature: signature.to_vec(),
+        };
+        if payment_request.matches_payee_node_id(&payment_request.node_id) {
+            Ok(payment_request)
+        } else {
+            Err(Error::InvalidValue(
+                "the signature does not match the payee node id".to_owned(),
+            ))
         }
     }
 }
@@ -491,7 +1485,7 @@ impl PaymentRequest {
 /// PaymentRequest description
 enum Description {
     Tag(String),
-    HashTag(Vec<u8>),
+    HashTag(DescriptionHash),
 }
 
 impl Description {
@@ -499,7 +1493,7 @@ impl Description {
     pub fn new(tag: &Tag) -> Option<Description> {
         match *tag {
             Tag::Description { ref description } => Some(Description::Tag(description.to_owned())),
-            Tag::DescriptionHash { ref hash } => Some(Description::HashTag(hash.to_owned())),
+            Tag::DescriptionHash { hash } => Some(Description::HashTag(hash)),
             _ => None,
         }
     }
@@ -509,7 +1503,7 @@ impl fmt::Display for Description {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Description::Tag(ref description) => write!(f, "{}", description),
-            Description::HashTag(ref hash) => write!(f, "{}", ::utils::to_hex(&hash)),
+            Description::HashTag(ref hash) => write!(f, "{}", hash),
         }
     }
 }
@@ -518,6 +1512,8 @@ impl fmt::Display for Description {
 mod test {
     use super::*;
     use utils::from_hex;
+    use short_channel_id::ShortChannelId;
+    use timestamp::{FixedClock, SystemClock};
 
     lazy_static!{
          static ref SEC_KEY: secp256k1::SecretKey = {
@@ -559,21 +1555,148 @@ mod test {
         assert_eq!(pay_request.fallback_address(), fallback_address)
     }
 
+    #[test]
+    fn check_description() {
+        let description = "One piece of chocolate cake, one icecream cone, one pickle, one slice \
+                           of swiss cheese, one slice of salami, one lollypop, one piece of \
+                           cherry pie, one sausage, one cupcake, and one slice of watermelon";
+        let mut pay_request = PaymentRequest::decode(
+            "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6q\
+             qdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqscc6gd6ql3jrc5yzme8v4ntcewwz5cnw92tz0pc8qcuufvq7k\
+             hhr8wpald05e92xw006sq94mg8v2ndf4sefvf9sygkshp5zfem29trqq2yxxz7",
+        ).unwrap();
+
+        assert!(pay_request.check_description(description));
+        assert!(!pay_request.check_description("1 cup coffee"));
+        pay_request.update_description("1 cup coffee".to_owned());
+        assert!(!pay_request.check_description("1 cup coffee"));
+    }
+
+    #[test]
+    fn matches_preimage() {
+        let preimage = PaymentPreimage::from([7u8; 32]);
+        let mut pay_request = PaymentRequest::decode(
+            "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3\
+             k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3c\
+             h9zw97j25emudupq63nyw24cg27h2rspfj9srp",
+        ).unwrap();
+
+        assert!(!pay_request.matches_preimage(&preimage));
+        pay_request.tags[0] = Tag::PaymentHash {
+            hash: preimage.payment_hash(),
+        };
+        assert!(pay_request.matches_preimage(&preimage));
+        assert!(!pay_request.matches_preimage(&PaymentPreimage::from([8u8; 32])));
+        pay_request.tags.remove(0);
+        assert!(!pay_request.matches_preimage(&preimage));
+    }
+
+    #[test]
+    fn high_s_signature() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let low = pay_request.signature().unwrap();
+        assert!(low.is_low_s());
+
+        // the opposite signature, n - s with the other recovery id, verifies with the same key
+        let mut malleated = pay_request.clone();
+        malleated.signature = low.negate_s().to_bytes().to_vec();
+        let encoded = malleated.encode().unwrap();
+
+        assert!(!malleated.signature().unwrap().is_low_s());
+        assert_eq!(malleated.signature().unwrap().normalize_s(), low);
+        assert_eq!(PaymentRequest::decode(&encoded).unwrap(), malleated);
+
+        let options = ParseOptions {
+            high_s: HighS::Reject,
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            PaymentRequest::decode_with_options(&encoded, &options),
+            Err(Error::InvalidValue(_))
+        ));
+        assert!(PaymentRequest::decode_with_options(tx_ref, &options).is_ok());
+
+        let options = ParseOptions {
+            high_s: HighS::Normalize,
+            ..ParseOptions::default()
+        };
+        let normalized = PaymentRequest::decode_with_options(&encoded, &options).unwrap();
+        assert_eq!(normalized, pay_request);
+        assert_eq!(normalized.encode().unwrap(), tx_ref);
+
+        // out of range r, reduced by libsecp256k1 before
+        let mut overflow = pay_request.clone();
+        for byte in overflow.signature[..32].iter_mut() {
+            *byte = 0xff;
+        }
+        assert!(matches!(
+            PaymentRequest::decode(&overflow.encode().unwrap()),
+            Err(Error::SignatureError(secp256k1::Error::InvalidSignature))
+        ));
+    }
+
+    #[test]
+    fn unsigned_invoice() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let unsigned = UnsignedInvoice::new(
+            pay_request.prefix.to_owned(),
+            pay_request.amount,
+            pay_request.timestamp,
+            pay_request.tags.to_owned(),
+        );
+
+        let (hash, hrp, data) = unsigned.signing_data().unwrap();
+        assert_eq!(hash.to_vec(), pay_request.hash().unwrap());
+        assert_eq!(hrp, "lnbc2500u");
+        assert_eq!(data, pay_request.stream().unwrap());
+
+        // signed outside of the crate
+        let (signature, recovery_id) = secp256k1::sign(&Message::parse(&hash), &SEC_KEY).unwrap();
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.serialize());
+        bytes[64] = recovery_id.serialize();
+
+        let signed = unsigned.attach_signature(bytes).unwrap();
+        assert_eq!(signed, pay_request);
+        assert_eq!(signed.encode().unwrap(), tx_ref);
+
+        // signed by another node than the payee
+        let mut with_payee = unsigned.clone();
+        with_payee.tags.push(Tag::PayeeNodeId {
+            pub_key: NodeId::from_slice(&[2u8; 33]).unwrap(),
+        });
+        let (hash, _, _) = with_payee.signing_data().unwrap();
+        let (signature, recovery_id) = secp256k1::sign(&Message::parse(&hash), &SEC_KEY).unwrap();
+        bytes[..64].copy_from_slice(&signature.serialize());
+        bytes[64] = recovery_id.serialize();
+        assert!(with_payee.attach_signature(bytes).is_err());
+
+        bytes[64] = 4;
+        assert!(unsigned.attach_signature(bytes).is_err());
+    }
+
     #[test]
     fn test_send_using_payment_hash() {
         // Please make a donation of any amount using payment_hash 0001020304050607080900010203040506070809000102030405060708090102 to me @03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad
         let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
         kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
         n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
         assert!(pay_request.amount.is_none());
         assert_eq!(pay_request.payment_hash().unwrap(), payment_hash);
-        assert_eq!(pay_request.timestamp, 1496_314_658u64);
+        assert_eq!(pay_request.timestamp, 1_496_314_658u64);
         assert!(pay_request.node_id.eq(&PUB_KEY));
         assert_eq!(
             pay_request.description().unwrap(),
@@ -598,8 +1721,9 @@ mod test {
         let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
         q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
         3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
@@ -628,13 +1752,14 @@ mod test {
             "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqh\
              p58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqscc6gd6ql3jrc5yzme8v4ntcewwz5cnw9\
              2tz0pc8qcuufvq7khhr8wpald05e92xw006sq94mg8v2ndf4sefvf9sygkshp5zfem29trqq2yxxz7";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000_000_000u64));
+        assert_eq!(pay_request.amount, Some(2_000_000_000u64));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
         assert!(pay_request.node_id.eq(&PUB_KEY));
@@ -657,13 +1782,14 @@ mod test {
             jmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3x9et2e20v6pu37c5d9vax37wxq72un98k6\
             vcx9fz94w0qf237cm2rqv9pmn5lnexfvf5579slr4zq3u8kmczecytdx0xg9rwzngp7e6guwqpqlhssu04sucpnz4\
             axcv2dstmknqq6jsk2l";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lntb");
-        assert_eq!(pay_request.amount, Some(2000_000_000u64));
+        assert_eq!(pay_request.amount, Some(2_000_000_000u64));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
         assert!(pay_request.node_id.eq(&PUB_KEY));
@@ -693,27 +1819,28 @@ mod test {
             qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
             f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
             dhhwkj";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         let routing_info = Tag::RoutingInfo {
             path: vec![
                 ExtraHop {
-                    pub_key: from_hex(
-                        "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                    ).unwrap(),
-                    short_channel_id: 72623859790382856u64,
+                    pub_key: "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+                        .parse::<NodeId>()
+                        .unwrap(),
+                    short_channel_id: ShortChannelId::from(72623859790382856),
                     fee_base_msat: 1,
                     fee_proportional_millionths: 20,
                     cltv_expiry_delta: 3,
                 },
                 ExtraHop {
-                    pub_key: from_hex(
-                        "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                    ).unwrap(),
-                    short_channel_id: 217304205466536202u64,
+                    pub_key: "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+                        .parse::<NodeId>()
+                        .unwrap(),
+                    short_channel_id: ShortChannelId::from(217304205466536202),
                     fee_base_msat: 2,
                     fee_proportional_millionths: 30,
                     cltv_expiry_delta: 4,
@@ -722,7 +1849,7 @@ mod test {
         };
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000_000_000u64));
+        assert_eq!(pay_request.amount, Some(2_000_000_000u64));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
         assert!(pay_request.node_id.eq(&PUB_KEY));
@@ -755,8 +1882,9 @@ mod test {
                 8yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfppj3a24vwu6r8ejrss3axul8rxldph2q7z9kk\
                 822r8plup77n9yq5ep2dfpcydrjwzxs0la84v3tfw43t3vqhek7f05m6uf8lmfkjn7zv7enn76sq65d8u9lxav2pl6\
                 x3xnc2ww3lqpagnh0u";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
@@ -787,8 +1915,9 @@ mod test {
                 yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfppqw508d6qejxtdg4y5r3zarvary0c5xw7kknt\
                 6zz5vxa8yh8jrnlkl63dah48yh6eupakk87fjdcnwqfcyt7snnpuz7vp83txauq4c60sys3xyucesxjf46yqnpplj\
                 0saq36a554cp9wt865";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
@@ -820,8 +1949,9 @@ mod test {
             jmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfp4qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4x\
             j0gdcccefvpysxf3qvnjha2auylmwrltv2pkp2t22uy8ura2xsdwhq5nm7s574xva47djmnj2xeycsu7u5v8929mvu\
             ux43j0cqhhf32wfyn2th0sv4t9x55sppz5we8";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
@@ -852,8 +1982,9 @@ mod test {
             p58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfp4qrp33g0q5c5txsp9arysrx4k6zdkfs4nc\
             e4xj0gdcccefvpysxf3q90qkf3gd7fcqs0ewr7t3xf72ptmc4n38evg0xhy4p64nlg7hgrmq6g997tkrvezs8afs0\
             x0y8v4vs8thwsk6knkvdfvfa7wmhhpcsxcqw0ny48";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
@@ -870,7 +2001,7 @@ mod test {
             pay_request.fallback_address(),
             Some("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3".to_owned())
         );
-        assert_eq!(pay_request.min_final_cltv_expiry(), Some(12));
+        assert_eq!(pay_request.min_final_cltv_expiry_field(), Some(12));
         assert_eq!(pay_request.tags.len(), 4);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
@@ -885,8 +2016,9 @@ mod test {
         let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqyp\
         qdpquwpc4curk03c9wlrswe78q4eyqc7d8d0xqzpuyk0sg5g70me25alkluzd2x62aysf2pyy8edtjeevuv4p2d5p7\
         6r4zkmneet7uvyakky2zr4cusd45tftc9c5fh0nnqpnl2jfll544esqchsrny";
-        let payment_hash =
-            from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap();
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
 
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
@@ -917,15 +2049,15 @@ mod test {
 
         let new_pay_request = PaymentRequest::new(
             pay_request.prefix.clone(),
-            pay_request.amount.clone(),
+            pay_request.amount,
             pay_request.payment_hash().unwrap(),
             &SEC_KEY,
             pay_request.description().unwrap().to_string(),
             pay_request.fallback_address(),
             pay_request.expiry(),
             pay_request.routing_info(),
-            Some(pay_request.timestamp.clone()),
-            pay_request.min_final_cltv_expiry(),
+            Some(pay_request.timestamp),
+            pay_request.min_final_cltv_expiry_field(),
         ).unwrap();
 
         assert_eq!(pay_request, new_pay_request);
@@ -940,17 +2072,1211 @@ mod test {
 
         let new_pay_request = PaymentRequest::new(
             pay_request.prefix.clone(),
-            pay_request.amount.clone(),
+            pay_request.amount,
             pay_request.payment_hash().unwrap(),
             &SEC_KEY,
             pay_request.description().unwrap(),
             pay_request.fallback_address(),
             pay_request.expiry(),
             pay_request.routing_info(),
-            Some(pay_request.timestamp.clone()),
-            pay_request.min_final_cltv_expiry(),
+            Some(pay_request.timestamp),
+            pay_request.min_final_cltv_expiry_field(),
         ).unwrap();
 
         assert_eq!(pay_request, new_pay_request);
     }
+
+    #[test]
+    fn test_new_payment_with_fixed_timestamp() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
+
+        let pay_request = PaymentRequest::new(
+            "lnbc".to_owned(),
+            None,
+            payment_hash,
+            &SEC_KEY,
+            "Please consider supporting this project".to_owned(),
+            None,
+            None,
+            vec![],
+            Some(1496314658),
+            None,
+        ).unwrap();
+
+        assert_eq!(pay_request.encode().unwrap(), tx_ref);
+
+        // the timestamp must fit 35 bits
+        let mut pay_request = pay_request;
+        pay_request.timestamp = Timestamp::MAX + 1;
+        assert!(matches!(pay_request.encode(), Err(Error::InvalidValue(_))));
+    }
+
+    #[test]
+    fn test_hash_is_signed_message() {
+        // the data part is 450 bits long and is padded to a byte boundary before hashing
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        let hash = pay_request.hash().unwrap().iter().enumerate().fold(
+            [0u8; 32],
+            |mut acc, (index, item)| {
+                acc[index] = *item;
+                acc
+            },
+        );
+        let (recovery_id, signature) =
+            PaymentRequest::parse_signature(&pay_request.signature).unwrap();
+        let message = secp256k1::Message::parse(&hash);
+
+        assert_eq!(
+            secp256k1::recover(&message, &signature, &recovery_id).unwrap(),
+            *PUB_KEY
+        );
+    }
+
+    #[test]
+    fn test_size_breakdown() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        let sections = pay_request.size_breakdown().unwrap();
+
+        assert_eq!(
+            sections,
+            vec![
+                ("hrp".to_owned(), 9),
+                ("separator".to_owned(), 1),
+                ("timestamp".to_owned(), 7),
+                ("p".to_owned(), 55),
+                ("d".to_owned(), 23),
+                ("x".to_owned(), 5),
+                ("signature".to_owned(), 104),
+                ("checksum".to_owned(), 6),
+            ]
+        );
+        assert_eq!(
+            sections.iter().map(|&(_, len)| len).sum::<usize>(),
+            tx_ref.len()
+        );
+
+        // a tag which can't be encoded isn't left out of the breakdown
+        let mut pay_request = pay_request;
+        pay_request.tags.push(Tag::Description {
+            description: "a".repeat(700),
+        });
+        assert!(pay_request.size_breakdown().is_err());
+        assert!(pay_request.stream().is_err());
+    }
+
+    #[test]
+    fn test_primary_fallback_address() {
+        // On mainnet, with Fallback (p2wpkh) address bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58\
+                yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfppqw508d6qejxtdg4y5r3zarvary0c5xw7kknt\
+                6zz5vxa8yh8jrnlkl63dah48yh6eupakk87fjdcnwqfcyt7snnpuz7vp83txauq4c60sys3xyucesxjf46yqnpplj\
+                0saq36a554cp9wt865";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(
+            pay_request.primary_fallback_address().unwrap().unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert!(pay_request.primary_fallback_address().is_none());
+    }
+
+    #[test]
+    fn test_tampered_amount() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let Bech32 { data, .. } = Bech32::from_string(tx_ref.to_owned()).unwrap();
+        // same data part with a valid checksum for the new amount
+        let tampered = Bech32 {
+            hrp: "lnbc2400u".to_owned(),
+            data: data.clone(),
+        }.to_string()
+            .unwrap();
+
+        match PaymentRequest::decode(&tampered) {
+            Ok(pay_request) => assert!(pay_request.node_id.ne(&PUB_KEY)),
+            Err(e) => assert!(matches!(e, Error::SignatureError(_))),
+        }
+
+        let invalid_amount = Bech32 {
+            hrp: "lnbc2500x".to_owned(),
+            data,
+        }.to_string()
+            .unwrap();
+
+        assert!(PaymentRequest::decode(&invalid_amount).is_err());
+    }
+
+    #[test]
+    fn test_new_donation() {
+        let payment_hash = [7u8; 32];
+        let pay_request =
+            PaymentRequest::new_donation(payment_hash, "donation", &SEC_KEY.serialize()).unwrap();
+
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded.prefix, "lnbc");
+        assert!(decoded.amount.is_none());
+        assert_eq!(decoded.payment_hash(), Some(PaymentHash::from(payment_hash)));
+        assert_eq!(decoded.description(), Some("donation".to_owned()));
+        assert!(decoded.node_id.eq(&PUB_KEY));
+    }
+
+    #[test]
+    fn test_timestamp_precedes_tags() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let Bech32 { data, .. } = Bech32::from_string(tx_ref.to_owned()).unwrap();
+        let tagged_fields = data[TIMESTAMP_LENGTH..data.len() - 104].to_vec();
+
+        assert_eq!(Timestamp::decode(&data), 1496314658);
+        // payment hash tag type and 52 u5 length
+        assert_eq!(tagged_fields[..3].to_vec(), vec![1, 1, 20]);
+        assert_eq!(
+            Tag::parse_all(&tagged_fields).unwrap(),
+            PaymentRequest::decode(tx_ref).unwrap().tags
+        );
+    }
+
+    #[test]
+    fn test_empty_routing_info() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        pay_request.tags.push(Tag::RoutingInfo { path: vec![] });
+        let pay_request = pay_request.sign(&SEC_KEY).unwrap();
+
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.route_hints(), vec![vec![]]);
+        assert!(decoded.routing_info().is_empty());
+    }
+
+    #[test]
+    fn test_multiple_routing_info() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pub_key = NodeId::from(&*PUB_KEY);
+        let first = vec![
+            ExtraHop::new(pub_key, ShortChannelId::from(1), 1, 20, 3),
+            ExtraHop::new(pub_key, ShortChannelId::from(2), 2, 30, 4),
+        ];
+        let second = vec![ExtraHop::new(pub_key, ShortChannelId::from(3), 3, 40, 5)];
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        pay_request.tags.push(Tag::RoutingInfo {
+            path: first.clone(),
+        });
+        pay_request.tags.push(Tag::RoutingInfo {
+            path: second.clone(),
+        });
+        let pay_request = pay_request.sign(&SEC_KEY).unwrap();
+
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded.route_hints(), vec![first.clone(), second.clone()]);
+        assert_eq!(
+            decoded
+                .route_hint_hops()
+                .map(|hop| u64::from(hop.short_channel_id))
+                .collect_vec(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(decoded.routing_info(), [first, second].concat());
+    }
+
+    #[test]
+    fn test_check_network() {
+        let tx_ref = "lntb20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58y\
+            jmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3x9et2e20v6pu37c5d9vax37wxq72un98k6\
+            vcx9fz94w0qf237cm2rqv9pmn5lnexfvf5579slr4zq3u8kmczecytdx0xg9rwzngp7e6guwqpqlhssu04sucpnz4\
+            axcv2dstmknqq6jsk2l";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.currency(), Some(Currency::Testnet));
+        assert!(pay_request.check_network(&Currency::Testnet).is_ok());
+        assert!(matches!(
+            pay_request.check_network(&Currency::Bitcoin),
+            Err(Error::WrongNetwork(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_with_message() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+        let (pay_request, message) = PaymentRequest::decode_with_message(tx_ref).unwrap();
+
+        assert!(message.starts_with(b"lnbc2500u"));
+        let (recovery_id, signature) =
+            PaymentRequest::parse_signature(&pay_request.signature).unwrap();
+        let message = secp256k1::Message::parse(&PaymentRequest::sha256_hasher(&message));
+        assert_eq!(
+            secp256k1::recover(&message, &signature, &recovery_id).unwrap(),
+            pay_request.node_id
+        );
+    }
+
+    #[test]
+    fn test_is_payable_with_features() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert!(pay_request.is_payable_with_features(&[]));
+
+        // requires feature bit 14 and supports feature bit 9
+        pay_request.tags.push(Tag::UnknownTag {
+            tag: 5,
+            bytes: vec![16, 16, 0],
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+        let pay_request = PaymentRequest::decode(&encoded).unwrap();
+
+        assert!(!pay_request.is_payable_with_features(&[]));
+        assert!(!pay_request.is_payable_with_features(&[0x02, 0x00]));
+        assert!(pay_request.is_payable_with_features(&[0x40, 0x00]));
+        assert!(pay_request.is_payable_with_features(&[0x80, 0x00]));
+    }
+
+    #[test]
+    fn test_recovery_id() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.signature.len(), 65);
+        assert_eq!(pay_request.recovery_id(), Some(pay_request.signature[64]));
+        assert!(pay_request.recovery_id().unwrap() < 4);
+    }
+
+    #[test]
+    fn test_decode_short_prefix() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let Bech32 { data, .. } = Bech32::from_string(tx_ref.to_owned()).unwrap();
+        let short_prefix = Bech32 {
+            hrp: "ln".to_owned(),
+            data,
+        }.to_string()
+            .unwrap();
+
+        assert!(PaymentRequest::decode(&short_prefix).is_err());
+    }
+
+    #[test]
+    fn test_sign() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut unsigned = PaymentRequest::decode(tx_ref).unwrap();
+        unsigned.signature = Vec::new();
+
+        assert!(unsigned.encode().is_err());
+
+        let secret_key = secp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+        let signed = unsigned.sign(&secret_key).unwrap();
+        let decoded = PaymentRequest::decode(&signed.encode().unwrap()).unwrap();
+
+        assert_eq!(signed.signature.len(), 65);
+        assert_eq!(signed.node_id, PublicKey::from_secret_key(&secret_key));
+        assert_eq!(decoded, signed);
+    }
+
+    #[test]
+    fn test_recover_payee_pub_key() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let other_key = secp256k1::PublicKey::from_secret_key(
+            &secp256k1::SecretKey::parse(&[1u8; 32]).unwrap(),
+        );
+
+        assert_eq!(pay_request.recover_payee_pub_key().unwrap(), *PUB_KEY);
+        assert!(pay_request.verify_signature(&PUB_KEY));
+        assert!(!pay_request.verify_signature(&other_key));
+
+        pay_request.update_amount(Some(240_000_000));
+
+        assert!(pay_request.recover_payee_pub_key().ok() != Some(PUB_KEY.clone()));
+        assert!(!pay_request.verify_signature(&PUB_KEY));
+    }
+
+    #[test]
+    fn test_payee_node_id() {
+        let payment_hash = [1u8; 32];
+        let pub_key = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+        let mut pay_request = PaymentRequest::new(
+            "lnbc".to_owned(),
+            None,
+            PaymentHash::from(payment_hash),
+            &SEC_KEY,
+            "coffee".to_owned(),
+            None,
+            None,
+            vec![],
+            Some(1_496_314_658),
+            None,
+        ).unwrap();
+        pay_request.tags.push(Tag::PayeeNodeId {
+            pub_key: pub_key.parse().unwrap(),
+        });
+        let signed = pay_request.sign(&SEC_KEY).unwrap();
+        let decoded = PaymentRequest::decode(&signed.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded.payee_node_id(), Some(pub_key.parse().unwrap()));
+        assert!(decoded.verify_signature(&PUB_KEY));
+        assert_eq!(decoded.recover_payee_pub_key().unwrap(), *PUB_KEY);
+
+        let mut other = signed.clone();
+        other.tags.pop();
+        other.tags.push(Tag::PayeeNodeId {
+            pub_key: NodeId::from_slice(&[2u8; 33]).unwrap(),
+        });
+        let other = other.sign(&SEC_KEY).unwrap();
+
+        assert!(!other.verify_signature(&PUB_KEY));
+        assert!(other.recover_payee_pub_key().is_err());
+        assert!(PaymentRequest::decode(&other.encode().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_payment_metadata() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.payment_metadata(), None);
+
+        pay_request.tags.push(Tag::PaymentMetadata {
+            bytes: from_hex("01fafaf0").unwrap(),
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+        let decoded = PaymentRequest::decode(&encoded).unwrap();
+
+        assert_eq!(decoded.payment_metadata(), from_hex("01fafaf0").ok());
+        assert_eq!(decoded.encode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn test_regtest_payment() {
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
+        let fallback_address = "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".to_owned();
+
+        let pay_request = PaymentRequest::new(
+            Currency::Regtest.prefix().to_owned(),
+            Some(250_000_000),
+            payment_hash,
+            &SEC_KEY,
+            "1 cup coffee".to_owned(),
+            Some(fallback_address.clone()),
+            None,
+            vec![],
+            Some(1496314658),
+            None,
+        ).unwrap();
+        let encoded = pay_request.encode().unwrap();
+        let decoded = PaymentRequest::decode(&encoded).unwrap();
+
+        assert!(encoded.starts_with("lnbcrt2500u1"));
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.currency(), Some(Currency::Regtest));
+        assert_eq!(decoded.amount, Some(250_000_000));
+        assert_eq!(decoded.fallback_address(), Some(fallback_address));
+    }
+
+    #[test]
+    fn test_taproot_fallback_address() {
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
+        let fallback_address =
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".to_owned();
+
+        let pay_request = PaymentRequest::new(
+            Currency::Bitcoin.prefix().to_owned(),
+            Some(250_000_000),
+            payment_hash,
+            &SEC_KEY,
+            "1 cup coffee".to_owned(),
+            Some(fallback_address.clone()),
+            None,
+            vec![],
+            Some(1496314658),
+            None,
+        ).unwrap();
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.fallback_address(), Some(fallback_address));
+    }
+
+    #[test]
+    fn test_multiple_fallback_addresses() {
+        let mut pay_request = PaymentRequest::decode(
+            "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp",
+        ).unwrap();
+        let p2pkh = from_hex("04b61f7dc1ea0dc99424464cc4064dc564d91e89").unwrap();
+        let p2wpkh = from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        pay_request.add_fallback_address(17, p2pkh);
+        pay_request.add_fallback_address(0, p2wpkh);
+        let signed = pay_request.sign(&SEC_KEY).unwrap();
+        let decoded = PaymentRequest::decode(&signed.encode().unwrap()).unwrap();
+
+        assert_eq!(
+            decoded.fallback_addresses(&Currency::Bitcoin),
+            vec![
+                "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned(),
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned(),
+            ]
+        );
+        assert!(decoded.validate_fallback_addresses().is_ok());
+
+        pay_request.add_fallback_address(18, vec![0u8; 32]);
+
+        assert_eq!(pay_request.fallback_addresses(&Currency::Bitcoin).len(), 2);
+        assert_eq!(
+            pay_request.validate_fallback_addresses(),
+            Err(vec![
+                ValidationError::InvalidFallbackAddress {
+                    version: 18,
+                    len: 32,
+                },
+            ])
+        );
+
+        // a witness program of the wrong length is a warning rather than a decoding error
+        pay_request.add_fallback_address(0, vec![0u8; 25]);
+        let signed = pay_request.sign(&SEC_KEY).unwrap();
+        let decoded = PaymentRequest::read_with_warnings(&signed.encode().unwrap()).unwrap();
+        assert_eq!(decoded.invoice, signed);
+        assert!(decoded.warnings.contains(&Warning::Validation(
+            ValidationError::InvalidFallbackAddress {
+                version: 0,
+                len: 25,
+            }
+        )));
+    }
+
+    #[test]
+    fn test_custom_currency() {
+        let payment_hash = "0001020304050607080900010203040506070809000102030405060708090102"
+            .parse::<PaymentHash>()
+            .unwrap();
+        let currency = Currency::custom("lnltc").unwrap();
+
+        let pay_request = PaymentRequest::new(
+            currency.prefix().to_owned(),
+            Some(250_000_000),
+            payment_hash,
+            &SEC_KEY,
+            "1 cup coffee".to_owned(),
+            None,
+            None,
+            vec![],
+            Some(1496314658),
+            None,
+        ).unwrap();
+        let encoded = pay_request.encode().unwrap();
+        let options = ParseOptions {
+            custom_currencies: vec![currency],
+            ..ParseOptions::default()
+        };
+        let decoded = PaymentRequest::decode_with_options(&encoded, &options).unwrap();
+
+        assert!(encoded.starts_with("lnltc2500u1"));
+        assert!(matches!(
+            PaymentRequest::decode(&encoded),
+            Err(Error::InvalidValue(_))
+        ));
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.currency(), None);
+        assert!(decoded.check_network(&currency).is_ok());
+        assert!(decoded.check_network(&Currency::Bitcoin).is_err());
+    }
+
+    #[test]
+    fn test_amount() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.amount(), Amount::from_sat(250_000));
+
+        pay_request.update_amount(None);
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        assert!(encoded.starts_with("lnbc1"));
+        assert_eq!(PaymentRequest::decode(&encoded).unwrap().amount(), None);
+    }
+
+    #[test]
+    fn test_unknown_tag_round_trip() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        // `v` tag with trailing bits that don't make a byte
+        pay_request.tags.push(Tag::UnknownTag {
+            tag: 12,
+            bytes: vec![31, 0, 17],
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+        let decoded = PaymentRequest::decode(&encoded).unwrap();
+
+        assert_eq!(decoded.encode().unwrap(), encoded);
+        assert!(decoded.verify_signature(&PUB_KEY));
+    }
+
+    #[test]
+    fn test_decode_lenient() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        // malformed description
+        pay_request.tags.push(Tag::UnknownTag {
+            tag: 13,
+            bytes: vec![31, 30],
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        assert!(PaymentRequest::decode(&encoded).is_err());
+
+        let decoded = PaymentRequest::decode_lenient(&encoded).unwrap();
+
+        assert!(matches!(
+            decoded.tags.last(),
+            Some(&Tag::Skipped { tag: 13, .. })
+        ));
+        assert_eq!(
+            decoded.description(),
+            Some("Please consider supporting this project".to_owned())
+        );
+        assert_eq!(decoded.encode().unwrap(), encoded);
+        assert!(decoded.verify_signature(&PUB_KEY));
+    }
+
+    #[test]
+    fn test_duplicate_tags() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let payment_hash = pay_request.payment_hash().unwrap();
+        pay_request.tags.push(Tag::PaymentHash {
+            hash: PaymentHash::from([0u8; 32]),
+        });
+        pay_request.tags.push(Tag::Description {
+            description: "second".to_owned(),
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        // the first occurrence is used
+        let decoded = PaymentRequest::decode(&encoded).unwrap();
+        assert_eq!(decoded.payment_hash(), Some(payment_hash));
+        assert_eq!(
+            decoded.description(),
+            Some("Please consider supporting this project".to_owned())
+        );
+
+        match PaymentRequest::decode_with_options(&encoded, &ParseOptions::strict()) {
+            Err(Error::InvalidFormat(e)) => assert_eq!(e, "duplicate p field"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(PaymentRequest::decode_with_options(tx_ref, &ParseOptions::strict()).is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.validate(), Ok(()));
+
+        pay_request.tags.push(Tag::DescriptionHash {
+            hash: DescriptionHash::from([0u8; 32]),
+        });
+        assert_eq!(
+            pay_request.validate(),
+            Err(ValidationError::DescriptionAndHash)
+        );
+
+        pay_request.tags = pay_request.filter_description();
+        assert_eq!(
+            pay_request.validate(),
+            Err(ValidationError::MissingDescription)
+        );
+
+        pay_request.update_description_hash(DescriptionHash::from([0u8; 32]));
+        assert_eq!(pay_request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_decode_with_options() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxx\
+        atsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch\
+        9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        assert!(PaymentRequest::decode_with_options(tx_ref, &ParseOptions::strict()).is_ok());
+
+        let options = ParseOptions {
+            max_invoice_len: Some(tx_ref.len() - 1),
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            PaymentRequest::decode_with_options(tx_ref, &options),
+            Err(Error::LimitExceeded(Limit::InvoiceLength, _))
+        ));
+
+        // 2500u written as 2500000n
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let hrp = "lnbc2500000n".to_owned();
+        let data = pay_request.stream().unwrap();
+        let message = PaymentRequest::parse_message(&hrp, &data.to_u8_vec(Padding::Add).unwrap());
+        let (signature, recovery_id) = secp256k1::sign(&message, &SEC_KEY).unwrap();
+        let mut signature_bytes = signature.serialize().to_vec();
+        signature_bytes.push(recovery_id.serialize());
+        let non_shortest = Bech32 {
+            hrp,
+            data: [data, signature_bytes.to_u5_vec(Padding::Add).unwrap()].concat(),
+        }.to_string()
+            .unwrap();
+        assert!(PaymentRequest::decode(&non_shortest).is_ok());
+        assert!(matches!(
+            PaymentRequest::decode_with_options(&non_shortest, &ParseOptions::strict()),
+            Err(Error::AmountError(AmountError::NotShortest(_)))
+        ));
+
+        // 20 bitcoins, shortest without multiplier
+        let mut whole_btc = PaymentRequest::decode(tx_ref).unwrap();
+        whole_btc.amount = Some(2_000_000_000_000);
+        let encoded = whole_btc.sign(&SEC_KEY).unwrap().encode().unwrap();
+        assert!(encoded.starts_with("lnbc201"));
+        assert_eq!(
+            PaymentRequest::decode_with_options(&encoded, &ParseOptions::strict()).unwrap().amount,
+            whole_btc.amount
+        );
+
+        // unknown even feature bit 20 and a 2 hops route
+        let mut features = Features::new();
+        features.set_bit(20);
+        pay_request.tags.push(Tag::Features { bits: features });
+        pay_request.tags.push(Tag::RoutingInfo {
+            path: vec![
+                ExtraHop {
+                    pub_key: NodeId::from(&*PUB_KEY),
+                    short_channel_id: ShortChannelId::from(1),
+                    fee_base_msat: 1,
+                    fee_proportional_millionths: 20,
+                    cltv_expiry_delta: 3,
+                };
+                2
+            ],
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        assert!(PaymentRequest::decode(&encoded).is_ok());
+        assert!(matches!(
+            PaymentRequest::decode_with_options(&encoded, &ParseOptions::strict()),
+            Err(Error::InvalidFormat(_))
+        ));
+        let options = ParseOptions {
+            max_route_hops: Some(1),
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            PaymentRequest::decode_with_options(&encoded, &options),
+            Err(Error::LimitExceeded(Limit::RouteHops, 1))
+        ));
+        let options = ParseOptions {
+            max_hops_per_route: Some(1),
+            ..ParseOptions::default()
+        };
+        assert!(matches!(
+            PaymentRequest::decode_with_options(&encoded, &options),
+            Err(Error::LimitExceeded(Limit::HopsPerRoute, 1))
+        ));
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let hop = ExtraHop::new(NodeId::from(&*PUB_KEY), ShortChannelId::from(1), 1, 20, 3);
+        // a routing info field holds at most 12 hops
+        pay_request.tags.push(Tag::RoutingInfo {
+            path: vec![hop.clone(); 12],
+        });
+        pay_request.tags.push(Tag::RoutingInfo {
+            path: vec![hop.clone(); 8],
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        assert_eq!(PaymentRequest::decode(&encoded).unwrap().routing_info().len(), 20);
+
+        pay_request.tags.push(Tag::RoutingInfo {
+            path: vec![hop.clone()],
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        assert!(matches!(
+            PaymentRequest::decode(&encoded),
+            Err(Error::LimitExceeded(Limit::RouteHops, 20))
+        ));
+
+        // 8 routes of 12 hops take more than 7089 characters
+        for _ in 0..8 {
+            pay_request.tags.push(Tag::RoutingInfo {
+                path: vec![hop.clone(); 12],
+            });
+        }
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+
+        assert!(encoded.len() > 7089);
+        assert!(matches!(
+            PaymentRequest::decode(&encoded),
+            Err(Error::LimitExceeded(Limit::InvoiceLength, 7089))
+        ));
+        let options = ParseOptions {
+            max_invoice_len: None,
+            max_route_hops: None,
+            ..ParseOptions::default()
+        };
+        assert!(PaymentRequest::decode_with_options(&encoded, &options).is_ok());
+    }
+
+    #[test]
+    fn test_validate_for_payment() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxx\
+        atsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch\
+        9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.validate_for_payment(&Features::new()), Ok(()));
+
+        let mut features = Features::new();
+        features.set_bit(Features::PAYMENT_SECRET);
+        features.set_bit(23);
+        pay_request.tags.push(Tag::Features { bits: features });
+
+        assert_eq!(pay_request.validate_for_payment(&Features::known()), Ok(()));
+        assert_eq!(
+            pay_request.validate_for_payment(&Features::new()),
+            Err(ValidationError::UnknownRequiredFeatures(vec![
+                Features::PAYMENT_SECRET,
+            ]))
+        );
+
+        pay_request.tags = pay_request.filter_description();
+        assert_eq!(
+            pay_request.validate_for_payment(&Features::known()),
+            Err(ValidationError::MissingDescription)
+        );
+    }
+
+    #[test]
+    fn test_validate_features() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxx\
+        atsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch\
+        9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.validate_features(), Ok(()));
+
+        let mut features = Features::new();
+        features.set_bit(Features::BASIC_MPP + 1);
+        pay_request.tags.push(Tag::Features { bits: features });
+
+        assert_eq!(
+            pay_request.validate_features(),
+            Err(vec![ValidationError::MissingFeatureDependency {
+                feature: Features::BASIC_MPP,
+                dependency: Features::PAYMENT_SECRET,
+            }])
+        );
+
+        let mut features = Features::new();
+        features.set_bit(Features::BASIC_MPP);
+        features.set_bit(Features::PAYMENT_SECRET);
+        pay_request.tags.pop();
+        pay_request.tags.push(Tag::Features { bits: features });
+
+        assert_eq!(
+            pay_request.validate_features(),
+            Err(vec![
+                ValidationError::MissingFeatureDependency {
+                    feature: Features::PAYMENT_SECRET,
+                    dependency: Features::VAR_ONION_OPTIN,
+                },
+                ValidationError::MissingPaymentSecret,
+            ])
+        );
+
+        let mut features = Features::new();
+        features.set_bit(Features::BASIC_MPP);
+        features.set_bit(Features::PAYMENT_SECRET);
+        features.set_bit(Features::VAR_ONION_OPTIN + 1);
+        pay_request.tags.pop();
+        pay_request.tags.push(Tag::Features { bits: features });
+        pay_request.tags.push(Tag::PaymentSecret {
+            secret: PaymentSecret::from([0x11; 32]),
+        });
+
+        assert_eq!(pay_request.validate_features(), Ok(()));
+    }
+
+    #[test]
+    fn test_decode_error_span() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let Bech32 { hrp, data } = Bech32::from_string(tx_ref.to_owned()).unwrap();
+        // description which is not valid UTF-8 right after the timestamp
+        let invalid = Bech32 {
+            hrp,
+            data: [
+                data[..TIMESTAMP_LENGTH].to_vec(),
+                vec![13, 0, 2, 31, 31],
+                data[TIMESTAMP_LENGTH..].to_vec(),
+            ].concat(),
+        }.to_string()
+            .unwrap();
+
+        let error = PaymentRequest::decode(&invalid).unwrap_err();
+
+        assert!(matches!(error, Error::TagError { tag: 'd', .. }));
+        assert_eq!(error.span(), Some(17..22));
+        assert_eq!(&invalid[error.span().unwrap()], "dqzll");
+    }
+
+    #[test]
+    fn test_read_with_warnings() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let decoded = PaymentRequest::read_with_warnings(tx_ref).unwrap();
+
+        assert_eq!(decoded.invoice, PaymentRequest::decode(tx_ref).unwrap());
+        assert!(decoded.warnings.is_empty());
+
+        let mut pay_request = decoded.invoice;
+        pay_request.amount = Some(250_000_001);
+        pay_request.tags.push(Tag::UnknownTag {
+            tag: 2,
+            bytes: vec![1, 2],
+        });
+        pay_request.tags.push(Tag::DescriptionHash {
+            hash: DescriptionHash([0u8; 32]),
+        });
+        let encoded = pay_request.sign(&SEC_KEY).unwrap().encode().unwrap();
+        let decoded = PaymentRequest::read_with_warnings(&encoded).unwrap();
+
+        assert_eq!(
+            decoded.warnings,
+            vec![
+                Warning::UnknownField('z'),
+                Warning::SubSatoshiAmount(250_000_001),
+                Warning::Validation(ValidationError::DescriptionAndHash),
+            ]
+        );
+
+        // 2500000n rather than 2500u
+        let Bech32 { data, .. } = Bech32::from_string(tx_ref.to_owned()).unwrap();
+        let mut signature_data = data.clone();
+        let data = signature_data.drain(..data.len() - 104).collect::<Vec<u8>>();
+        let signed_bytes = [b"lnbc2500000n".to_vec(), data.to_u8_vec(Padding::Add).unwrap()].concat();
+        let message = Message::parse(&PaymentRequest::sha256_hasher(&signed_bytes));
+        let (signature, recovery_id) = secp256k1::sign(&message, &SEC_KEY).unwrap();
+        let mut signature_bytes = signature.serialize().to_vec();
+        signature_bytes.push(recovery_id.serialize());
+        let non_shortest = Bech32 {
+            hrp: "lnbc2500000n".to_owned(),
+            data: [data, signature_bytes.to_u5_vec(Padding::Add).unwrap()].concat(),
+        }.to_string()
+            .unwrap();
+        let decoded = PaymentRequest::read_with_warnings(&non_shortest).unwrap();
+
+        assert_eq!(
+            decoded.warnings,
+            vec![Warning::NonShortestAmount("2500000n".to_owned())]
+        );
+
+        // 20 bitcoins, shortest without multiplier
+        let mut whole_btc = PaymentRequest::decode(tx_ref).unwrap();
+        whole_btc.amount = Some(2_000_000_000_000);
+        let encoded = whole_btc.sign(&SEC_KEY).unwrap().encode().unwrap();
+        assert!(encoded.starts_with("lnbc201"));
+        assert!(PaymentRequest::read_with_warnings(&encoded).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(tx_ref.parse::<PaymentRequest>().unwrap(), pay_request);
+        assert_eq!(pay_request.encode().unwrap(), tx_ref);
+        assert_eq!(format!("{}", pay_request), tx_ref);
+        assert_eq!(format!("{}", pay_request).parse::<PaymentRequest>().unwrap(), pay_request);
+        assert_eq!(PaymentRequest::try_from(tx_ref).unwrap(), pay_request);
+        let mut unsigned = pay_request.clone();
+        unsigned.signature = vec![];
+        assert!(matches!(unsigned.encode(), Err(Error::InvalidLength(_))));
+        assert_eq!(
+            unsigned.to_string(),
+            "<invalid payment request: the signature length must be 65 bytes>"
+        );
+        assert_eq!(
+            format!("lightning:{}", tx_ref).parse::<PaymentRequest>().unwrap(),
+            pay_request
+        );
+        assert_eq!(
+            format!(" LIGHTNING:{}\n", tx_ref.to_uppercase())
+                .parse::<PaymentRequest>()
+                .unwrap(),
+            pay_request
+        );
+        assert!(format!("bitcoin:{}", tx_ref).parse::<PaymentRequest>().is_err());
+        assert!(tx_ref.replacen("lnbc", "LNBC", 1).parse::<PaymentRequest>().is_err());
+    }
+
+    #[test]
+    fn test_uppercase() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let uppercase = pay_request.encode_uppercase().unwrap();
+
+        assert_eq!(uppercase, tx_ref.to_uppercase());
+        assert_eq!(PaymentRequest::decode(&uppercase).unwrap(), pay_request);
+        let mixed_case = tx_ref.replacen("pp5", "PP5", 1);
+        assert!(matches!(
+            PaymentRequest::decode(&mixed_case),
+            Err(Error::Bech32Error(_))
+        ));
+
+        assert_eq!(PaymentRequest::qr_optimal_case(tx_ref), uppercase);
+        assert_eq!(
+            PaymentRequest::qr_optimal_case(&format!("lightning:{}", tx_ref)),
+            format!("LIGHTNING:{}", uppercase)
+        );
+        let uri = format!("bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?lightning={}", tx_ref);
+        assert_eq!(PaymentRequest::qr_optimal_case(&uri), uri);
+    }
+
+    #[test]
+    fn test_decode_batch() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let tampered = tx_ref.replacen("2500u", "2600u", 1);
+        let results = PaymentRequest::decode_batch(&[tx_ref, "lnbc", &tampered, tx_ref]);
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().unwrap().encode().unwrap(), tx_ref);
+        assert!(matches!(results[1], Err(Error::Bech32Error(_))));
+        assert!(results[2].is_err());
+        assert_eq!(results[3].as_ref().unwrap(), results[0].as_ref().unwrap());
+        assert!(PaymentRequest::decode_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_write_to() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        // same output as the data part and the checksum computed on vectors
+        let stream = [
+            pay_request.stream().unwrap(),
+            pay_request.signature.to_u5_vec(Padding::Add).unwrap(),
+        ].concat();
+        let expected = Bech32 {
+            hrp: "lnbc2500u".to_owned(),
+            data: stream,
+        };
+        assert_eq!(expected.to_string().unwrap(), tx_ref);
+
+        let mut encoded = String::with_capacity(tx_ref.len());
+        pay_request.write_to(&mut encoded).unwrap();
+        assert_eq!(encoded, tx_ref);
+
+        let mut bytes = Vec::new();
+        pay_request.write_to_io(&mut bytes).unwrap();
+        assert_eq!(bytes, tx_ref.as_bytes());
+
+        let mut short = [0u8; 10];
+        match pay_request.write_to_io(&mut &mut short[..]) {
+            Err(Error::IOErr(e)) => assert_eq!(e.kind(), io::ErrorKind::WriteZero),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let mut long = pay_request.clone();
+        long.tags.push(Tag::Description {
+            description: "a".repeat(640),
+        });
+        assert!(matches!(
+            long.write_to(&mut String::new()),
+            Err(Error::InvalidLength(_))
+        ));
+    }
+
+    #[test]
+    fn test_binary_encoding() {
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+            58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+            9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+            qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+            f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+            dhhwkj";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let bytes = pay_request.to_bytes().unwrap();
+
+        assert_eq!(bytes[0], BINARY_FORMAT_VERSION);
+        assert!(bytes.len() < tx_ref.len());
+        let decoded = PaymentRequest::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.encode().unwrap(), pay_request.encode().unwrap());
+
+        let donation = PaymentRequest::decode("lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9rn449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w").unwrap();
+        assert_eq!(
+            PaymentRequest::from_bytes(&donation.to_bytes().unwrap()).unwrap(),
+            donation
+        );
+
+        assert!(matches!(
+            PaymentRequest::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::InvalidLength(_))
+        ));
+        assert!(matches!(
+            PaymentRequest::from_bytes(&[bytes.to_owned(), vec![0]].concat()),
+            Err(Error::InvalidLength(_))
+        ));
+        let mut unknown_version = bytes.to_owned();
+        unknown_version[0] = 2;
+        assert!(matches!(
+            PaymentRequest::from_bytes(&unknown_version),
+            Err(Error::InvalidValue(_))
+        ));
+        assert!(PaymentRequest::from_bytes(&[]).is_err());
+        // version, prefix, amount, timestamp, node id and signature before the number of u5 values
+        let u5_len_at = 2 + pay_request.prefix.len() + 9 + 8 + 33 + 1 + pay_request.signature.len();
+        let mut u5_len_max = bytes.to_owned();
+        u5_len_max[u5_len_at..u5_len_at + 4].copy_from_slice(&[0xff; 4]);
+        assert!(matches!(
+            PaymentRequest::from_bytes(&u5_len_max),
+            Err(Error::InvalidLength(_))
+        ));
+    }
+
+    #[test]
+    fn test_seconds_until_expiry() {
+        // expiry of 60 seconds
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.seconds_until_expiry(1496314658 + 10), 50);
+        assert_eq!(pay_request.seconds_until_expiry(1496314658 + 100), -40);
+    }
+
+    #[test]
+    fn test_seconds_until_expiry_default() {
+        // no expiry tag, defaults to 3600 seconds
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.seconds_until_expiry(1496314658), 3600);
+        assert_eq!(pay_request.seconds_until_expiry(1496314658 + 3601), -1);
+    }
+
+    #[test]
+    fn test_default_values() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.expiry(), None);
+        assert_eq!(pay_request.expiry_seconds(), 3600);
+        assert_eq!(pay_request.min_final_cltv_expiry_field(), None);
+        assert_eq!(pay_request.min_final_cltv_expiry(), 18);
+
+        pay_request.update_expiry(60);
+        pay_request.update_min_final_cltv_expiry(12);
+
+        assert_eq!(pay_request.expiry_seconds(), 60);
+        assert_eq!(pay_request.min_final_cltv_expiry(), 12);
+    }
+
+    #[test]
+    fn test_expires_at() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(pay_request.expires_at(), 1496314658 + 60);
+        assert!(pay_request.is_expired().unwrap());
+        assert_eq!(pay_request.duration_until_expiry().unwrap(), Duration::from_secs(0));
+
+        pay_request.timestamp = Timestamp::now().unwrap();
+
+        assert!(!pay_request.is_expired().unwrap());
+        assert!(pay_request.duration_until_expiry().unwrap() <= Duration::from_secs(60));
+        assert!(pay_request.duration_until_expiry().unwrap() > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn test_is_expired_with() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert!(!pay_request.is_expired_with(&FixedClock(1496314658 + 59)));
+        assert!(pay_request.is_expired_with(&FixedClock(1496314658 + 60)));
+        assert!(pay_request.is_expired_with(&SystemClock));
+        assert_eq!(
+            pay_request.duration_until_expiry_with(&FixedClock(1496314658 + 10)),
+            Duration::from_secs(50)
+        );
+        assert_eq!(
+            pay_request.duration_until_expiry_with(&FixedClock(1496314658 + 100)),
+            Duration::from_secs(0)
+        );
+    }
 }
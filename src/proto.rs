@@ -0,0 +1,222 @@
+//! Protobuf messages of a decoded payment request, behind the `proto` feature, see
+//! `proto/bolt11.proto`. <br>
+//! *Note*: the fields 1 to 13 of `PayReq` are those of lnd's `lnrpc.PayReq`, the extra fields keep
+//! the prefix, amount, tagged fields and signature so the payment request can be rebuilt.
+
+use lnd;
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use tag::{ExtraHop, Tag};
+use types::Error;
+use std::collections::BTreeMap;
+
+/// Decoded payment request, `bolt11.PayReq`.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PayReq {
+    /// Node id of the payee, hex encoded.
+    #[prost(string, tag = "1")]
+    pub destination: String,
+    /// Payment hash, hex encoded, empty if missing.
+    #[prost(string, tag = "2")]
+    pub payment_hash: String,
+    /// Amount in satoshis, rounded down, 0 for any amount.
+    #[prost(int64, tag = "3")]
+    pub num_satoshis: i64,
+    /// Request timestamp (UNIX format).
+    #[prost(int64, tag = "4")]
+    pub timestamp: i64,
+    /// Expiry in seconds, 3600 if not specified.
+    #[prost(int64, tag = "5")]
+    pub expiry: i64,
+    /// Description, empty if missing.
+    #[prost(string, tag = "6")]
+    pub description: String,
+    /// Description hash, hex encoded, empty if missing.
+    #[prost(string, tag = "7")]
+    pub description_hash: String,
+    /// First fallback address, empty if missing.
+    #[prost(string, tag = "8")]
+    pub fallback_addr: String,
+    /// min_final_cltv_expiry, 18 if not specified.
+    #[prost(int64, tag = "9")]
+    pub cltv_expiry: i64,
+    /// Route hints, one per routing tag.
+    #[prost(message, repeated, tag = "10")]
+    pub route_hints: Vec<RouteHint>,
+    /// Payment secret, empty if missing.
+    #[prost(bytes = "vec", tag = "11")]
+    pub payment_addr: Vec<u8>,
+    /// Amount in millisatoshis, 0 for any amount.
+    #[prost(int64, tag = "12")]
+    pub num_msat: i64,
+    /// Feature bits, by bit number.
+    #[prost(btree_map = "uint32, message", tag = "13")]
+    pub features: BTreeMap<u32, Feature>,
+    /// Prefix of the payment request, e.g. `lnbc`.
+    #[prost(string, tag = "50")]
+    pub prefix: String,
+    /// Amount in millisatoshis, if any.
+    #[prost(uint64, optional, tag = "51")]
+    pub amount_msat: Option<u64>,
+    /// Tagged fields as u5 values, one per byte, in order.
+    #[prost(bytes = "vec", tag = "52")]
+    pub tagged_fields: Vec<u8>,
+    /// Signature, 64 bytes followed by the recovery id.
+    #[prost(bytes = "vec", tag = "53")]
+    pub signature: Vec<u8>,
+}
+
+/// Private route, `bolt11.RouteHint`.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteHint {
+    /// Hops of the route.
+    #[prost(message, repeated, tag = "1")]
+    pub hop_hints: Vec<HopHint>,
+}
+
+/// Hop of a private route, `bolt11.HopHint`.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HopHint {
+    /// Node id of the start of the channel, hex encoded.
+    #[prost(string, tag = "1")]
+    pub node_id: String,
+    /// Short channel id as a 64-bit integer.
+    #[prost(uint64, tag = "2")]
+    pub chan_id: u64,
+    /// Base fee in millisatoshis.
+    #[prost(uint32, tag = "3")]
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths.
+    #[prost(uint32, tag = "4")]
+    pub fee_proportional_millionths: u32,
+    /// Channel's cltv expiry delta.
+    #[prost(uint32, tag = "5")]
+    pub cltv_expiry_delta: u32,
+}
+
+/// Feature bit, `bolt11.Feature`.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Feature {
+    /// Name of the feature, empty if unknown.
+    #[prost(string, tag = "2")]
+    pub name: String,
+    /// Is the bit even.
+    #[prost(bool, tag = "3")]
+    pub is_required: bool,
+    /// Is the feature known to this crate.
+    #[prost(bool, tag = "4")]
+    pub is_known: bool,
+}
+
+impl PayReq {
+    /// Create the protobuf message of a payment request.
+    /// # Arguments
+    /// `payment_request` Payment request to convert.
+    pub fn new(payment_request: &PaymentRequest) -> Result<PayReq, Error> {
+        let view = lnd::PayReq::new(payment_request);
+        let amount = payment_request.amount.unwrap_or_default();
+        let mut tagged_fields = vec![];
+        for tag in &payment_request.tags {
+            tagged_fields.extend(tag.to_vec_u5()?);
+        }
+        Ok(PayReq {
+            destination: view.destination,
+            payment_hash: view.payment_hash,
+            num_satoshis: (amount / 1000) as i64,
+            timestamp: payment_request.timestamp as i64,
+            expiry: payment_request.expiry_seconds() as i64,
+            description: view.description,
+            description_hash: view.description_hash,
+            fallback_addr: view.fallback_addr,
+            cltv_expiry: payment_request.min_final_cltv_expiry() as i64,
+            route_hints: payment_request
+                .route_hints()
+                .iter()
+                .map(|path| RouteHint {
+                    hop_hints: path.iter().map(HopHint::new).collect(),
+                })
+                .collect(),
+            payment_addr: view.payment_addr,
+            num_msat: amount as i64,
+            features: view.features
+                .into_iter()
+                .map(|(bit, feature)| {
+                    let feature = Feature {
+                        name: feature.name,
+                        is_required: feature.is_required,
+                        is_known: feature.is_known,
+                    };
+                    (bit, feature)
+                })
+                .collect(),
+            prefix: payment_request.prefix.to_owned(),
+            amount_msat: payment_request.amount,
+            tagged_fields,
+            signature: payment_request.signature.to_owned(),
+        })
+    }
+
+    /// Rebuild the payment request from the prefix, amount, timestamp, tagged fields, signature
+    /// and destination of the message. <br>
+    /// *Note*: the other fields are derived from these ones and ignored.
+    pub fn to_payment_request(&self) -> Result<PaymentRequest, Error> {
+        if self.timestamp < 0 {
+            return Err(Error::InvalidValue(format!(
+                "invalid timestamp {}",
+                self.timestamp
+            )));
+        }
+        Ok(PaymentRequest {
+            prefix: self.prefix.to_owned(),
+            amount: self.amount_msat,
+            timestamp: self.timestamp as u64,
+            node_id: self.destination.parse::<NodeId>()?.to_public_key()?,
+            tags: Tag::parse_all_lenient(&self.tagged_fields)?,
+            signature: self.signature.to_owned(),
+        })
+    }
+}
+
+impl HopHint {
+    /// Create the protobuf message of an extra hop.
+    /// # Arguments
+    /// `hop` Extra hop of a routing tag.
+    pub fn new(hop: &ExtraHop) -> HopHint {
+        HopHint {
+            node_id: hop.pub_key.to_string(),
+            chan_id: u64::from(hop.short_channel_id),
+            fee_base_msat: hop.fee_base_msat,
+            fee_proportional_millionths: hop.fee_proportional_millionths,
+            cltv_expiry_delta: u32::from(hop.cltv_expiry_delta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use prost::Message;
+
+    #[test]
+    fn round_trip() {
+        let tx_ref = "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqs\
+            yqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk9\
+            8klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq20q82gphp2nflc7jtzrcazrra7wwgzx\
+            qc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu92d8lr6fvg0r5gv0heeeqgcrqlnm\
+            6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzq9qrsgqdfjcdk6w3ak5pca9hwfwfh63zrrz06wwfya0\
+            ydlzpgzxkn5xagsqz7x9j4jwe7yj7vaf2k9lqsdk45kts2fd0fkr28am0u4w95tt2nsq76cqw0";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let message = pay_request.to_proto().unwrap();
+
+        assert_eq!(message.num_msat, 2_000_000_000);
+        assert_eq!(message.fallback_addr, "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T");
+        assert_eq!(message.route_hints[0].hop_hints[0].chan_id, 72623859790382856);
+        assert_eq!(message.payment_addr, vec![0x11; 32]);
+
+        let bytes = message.encode_to_vec();
+        let decoded = PayReq::decode(bytes.as_slice()).unwrap();
+
+        assert_eq!(decoded, message);
+        assert_eq!(PaymentRequest::from_proto(&decoded).unwrap(), pay_request);
+    }
+}
@@ -0,0 +1,324 @@
+//! Borrowed, lazy decoding of payment requests. <br>
+//! `RawInvoice` only checks the checksum and locates the human readable part, the tagged fields
+//! and the signature in the input, the fields being converted from u5 when their accessor is
+//! called, e.g. to read the payment hash and the amount of many payment requests without
+//! decoding their descriptions and route hints. <br>
+//! *Note*: the signature isn't verified, see `RawInvoice::decode` for the full decoding.
+
+use amount::Amount;
+use bech32;
+use bech32::{char_value, CHARSET};
+use currency::Currency;
+use hashes::PaymentHash;
+use payment_request::PaymentRequest;
+use tag::Tag;
+use types::Error;
+use utils::{FieldBuffer, U5};
+use view::{ByteReader, RawTagView};
+
+/// Length of the timestamp in u5.
+const TIMESTAMP_LEN: usize = 7;
+
+/// Length of the signature in u5.
+const SIGNATURE_LEN: usize = 104;
+
+// Tagged fields of a payment request, stored inline up to the usual number of fields with the
+// `smallvec` feature.
+#[cfg(feature = "smallvec")]
+type Fields<'a> = ::smallvec::SmallVec<[RawTagView<'a>; 10]>;
+#[cfg(not(feature = "smallvec"))]
+type Fields<'a> = Vec<RawTagView<'a>>;
+
+/// Payment request borrowed from its encoding, whose parts are decoded on demand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawInvoice<'a> {
+    input: &'a str,
+    hrp: &'a str,
+    timestamp: &'a str,
+    fields: Fields<'a>,
+    signature: &'a str,
+}
+
+impl<'a> RawInvoice<'a> {
+    /// Check the checksum of a payment request and locate its parts, without converting them.
+    /// # Arguments
+    /// `input` Payment request, all lowercase or all uppercase.
+    pub fn parse(input: &'a str) -> Result<RawInvoice<'a>, Error> {
+        let (hrp, data) = bech32::check_str(input)?;
+        if data.len() < TIMESTAMP_LEN + SIGNATURE_LEN {
+            return Err(Error::InvalidLength(
+                "data is too short to decode".to_owned(),
+            ));
+        }
+        let (timestamp, rest) = data.split_at(TIMESTAMP_LEN);
+        let (mut fields_data, signature) = rest.split_at(rest.len() - SIGNATURE_LEN);
+
+        // the tagged fields follow the separator and the timestamp, as in Tag::parse_all
+        let fields_start = hrp.len() + 1 + TIMESTAMP_LEN;
+        let fields_end = fields_start + fields_data.len();
+        let mut fields = Fields::new();
+        while !fields_data.is_empty() {
+            let header = fields_data.as_bytes();
+            let value = |i: usize| char_value(header[i]).unwrap_or_default() as usize;
+            let tag = CHARSET[value(0)];
+            let position = fields_end - fields_data.len();
+            // the remaining words can't hold a tag type and length, as in Tag::parse_fields
+            if fields_data.len() < 3 {
+                return Err(Error::TagError {
+                    tag,
+                    span: Some(position..fields_end),
+                    kind: Box::new(Error::InvalidFormat("incomplete tagged field".to_owned())),
+                });
+            }
+            let len = value(1) * 32 + value(2);
+            if fields_data.len() < 3 + len {
+                return Err(Error::TagError {
+                    tag,
+                    span: Some(position..fields_end),
+                    kind: Box::new(Error::InvalidLength(format!(
+                        "declared length {} exceeds the data",
+                        len
+                    ))),
+                });
+            }
+            fields.push(RawTagView {
+                tag,
+                data: &fields_data[3..3 + len],
+            });
+            fields_data = &fields_data[3 + len..];
+        }
+
+        Ok(RawInvoice {
+            input,
+            hrp,
+            timestamp,
+            fields,
+            signature,
+        })
+    }
+
+    /// The payment request as given to `parse`.
+    pub fn as_str(&self) -> &'a str {
+        self.input
+    }
+
+    /// Human readable part, the prefix followed by the amount, in the case of the input.
+    pub fn hrp(&self) -> &'a str {
+        self.hrp
+    }
+
+    /// Network of the payment request.
+    pub fn currency(&self) -> Result<Currency, Error> {
+        Currency::from_hrp(&self.hrp.to_ascii_lowercase())
+            .ok_or(Error::InvalidValue(format!("invalid prefix {}", self.hrp)))
+    }
+
+    /// Prefix of the human readable part, e.g. `lnbc`, in the case of the input.
+    pub fn prefix(&self) -> Result<&'a str, Error> {
+        self.currency()
+            .map(|currency| &self.hrp[..currency.prefix().len()])
+    }
+
+    /// Amount in millisatoshis, if any.
+    pub fn amount(&self) -> Result<Option<u64>, Error> {
+        let amount = &self.hrp[self.prefix()?.len()..];
+        if amount.is_empty() {
+            return Ok(None);
+        }
+        Amount::decode(&amount.to_ascii_lowercase()).map(Some)
+    }
+
+    /// Request timestamp (UNIX format).
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+            .bytes()
+            .fold(0, |n, c| n << 5 | u64::from(char_value(c).unwrap_or_default()))
+    }
+
+    /// Tagged fields, in order, not decoded.
+    pub fn fields(&self) -> &[RawTagView<'a>] {
+        &self.fields
+    }
+
+    /// Decode the first tagged field of the type, if any.
+    /// # Arguments
+    /// `tag` Type of the field, e.g. `'p'`.
+    pub fn field(&self, tag: char) -> Option<Result<Tag, Error>> {
+        self.fields
+            .iter()
+            .find(|field| field.tag == tag)
+            .map(RawInvoice::decode_field)
+    }
+
+    /// Decode all the tagged fields, in order.
+    pub fn tags(&self) -> Result<Vec<Tag>, Error> {
+        self.fields.iter().map(RawInvoice::decode_field).collect()
+    }
+
+    /// Payment hash of the `p` field, if any.
+    pub fn payment_hash(&self) -> Option<Result<PaymentHash, Error>> {
+        self.field('p').map(|tag| match tag? {
+            Tag::PaymentHash { hash } => Ok(hash),
+            _ => Err(Error::InvalidLength("invalid payment hash length".to_owned())),
+        })
+    }
+
+    /// Description of the `d` field, if any.
+    pub fn description(&self) -> Option<Result<String, Error>> {
+        self.field('d').map(|tag| match tag? {
+            Tag::Description { description } => Ok(description),
+            _ => Err(Error::InvalidFormat("invalid description".to_owned())),
+        })
+    }
+
+    /// Signature, 64 bytes followed by the recovery id, not verified.
+    pub fn signature(&self) -> [u8; 65] {
+        let mut signature = [0u8; 65];
+        for (byte, value) in signature.iter_mut().zip(ByteReader::new(self.signature)) {
+            *byte = value;
+        }
+        signature
+    }
+
+    /// Decode the whole payment request and verify its signature, see `PaymentRequest::decode`.
+    pub fn decode(&self) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode(self.input)
+    }
+
+    // Helper for the field accessors, convert the field to u5 and parse it.
+    fn decode_field(field: &RawTagView<'a>) -> Result<Tag, Error> {
+        let len = field.len_u5();
+        let header = [
+            char_value(field.tag as u8).unwrap_or_default(),
+            (len / 32) as U5,
+            (len % 32) as U5,
+        ];
+        let data = field.data.bytes().map(|c| char_value(c).unwrap_or_default());
+        let input: FieldBuffer = header.iter().cloned().chain(data).collect();
+        Tag::parse(&input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bech32;
+
+    const TX_REF: &str = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+        58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+        9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+        qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+        f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+        dhhwkj";
+
+    #[test]
+    fn parse() {
+        let raw = RawInvoice::parse(TX_REF).unwrap();
+        let pay_request = PaymentRequest::decode(TX_REF).unwrap();
+
+        assert_eq!(raw.prefix().unwrap(), "lnbc");
+        assert_eq!(raw.currency().unwrap(), Currency::Bitcoin);
+        assert_eq!(raw.amount().unwrap(), pay_request.amount);
+        assert_eq!(raw.timestamp(), pay_request.timestamp);
+        assert_eq!(raw.payment_hash().unwrap().unwrap(), pay_request.payment_hash().unwrap());
+        assert!(raw.description().is_none());
+        assert_eq!(
+            raw.fields().iter().map(|field| field.tag).collect::<String>(),
+            "phfr"
+        );
+        assert_eq!(raw.field('r').unwrap().unwrap(), pay_request.tags[3]);
+        assert_eq!(raw.tags().unwrap(), pay_request.tags);
+        assert_eq!(&raw.signature()[..], &pay_request.signature[..]);
+        assert_eq!(raw.decode().unwrap(), pay_request);
+
+        let uppercase = TX_REF.to_uppercase();
+        let raw = RawInvoice::parse(&uppercase).unwrap();
+        assert_eq!(raw.prefix().unwrap(), "LNBC");
+        assert_eq!(raw.amount().unwrap(), pay_request.amount);
+        assert_eq!(raw.tags().unwrap(), pay_request.tags);
+    }
+
+    #[test]
+    fn lazy_fields() {
+        // a malformed description only fails when it is read
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let raw = RawInvoice::parse(tx_ref).unwrap();
+        assert_eq!(raw.description().unwrap().unwrap(), "1 cup coffee");
+        assert_eq!(raw.amount().unwrap(), Some(250_000_000));
+
+        let field = RawTagView {
+            tag: 'd',
+            data: "lllq",
+        };
+        assert!(matches!(
+            RawInvoice::decode_field(&field),
+            Err(Error::TagError { tag: 'd', .. })
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            RawInvoice::parse(&TX_REF.replacen("dhhwkj", "dhhwkk", 1)),
+            Err(Error::Bech32Error(bech32::Error::InvalidChecksum))
+        ));
+        assert!(matches!(
+            RawInvoice::parse(&TX_REF.replacen("lnbc", "LNbc", 1)),
+            Err(Error::Bech32Error(bech32::Error::MixedCase))
+        ));
+        assert!(matches!(
+            RawInvoice::parse("lnbc"),
+            Err(Error::Bech32Error(bech32::Error::InvalidLength))
+        ));
+        let encode = |data: Vec<U5>| {
+            bech32::Bech32 {
+                hrp: "lnbc".to_owned(),
+                data,
+            }.to_string()
+                .unwrap()
+        };
+        assert!(matches!(
+            RawInvoice::parse(&encode(vec![0; 10])),
+            Err(Error::InvalidLength(_))
+        ));
+        // a `p` field declaring 1023 u5, with 2 u5 before the signature
+        let overflow = encode([vec![0; 7], vec![1, 31, 31, 0, 0], vec![0; 104]].concat());
+        match RawInvoice::parse(&overflow) {
+            Err(Error::TagError { tag, span, .. }) => {
+                assert_eq!(tag, 'p');
+                assert_eq!(span, Some(12..17));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        match PaymentRequest::decode(&overflow) {
+            Err(Error::TagError { span, .. }) => assert_eq!(span, Some(12..17)),
+            result => panic!("unexpected result {:?}", result),
+        }
+        // a trailing `q` before the signature, too short for a tag type and length
+        let mut data = bech32::Bech32::from_string(TX_REF.to_owned()).unwrap().data;
+        let end = data.len() - 104;
+        data.insert(end, 0);
+        let incomplete = bech32::Bech32 {
+            hrp: "lnbc20m".to_owned(),
+            data,
+        }.to_string()
+            .unwrap();
+        let span = Some(8 + end..9 + end);
+        match RawInvoice::parse(&incomplete) {
+            Err(Error::TagError { tag, span: s, .. }) => {
+                assert_eq!(tag, 'q');
+                assert_eq!(s, span);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        match PaymentRequest::decode(&incomplete) {
+            Err(Error::TagError { span: s, .. }) => assert_eq!(s, span),
+            result => panic!("unexpected result {:?}", result),
+        }
+        let raw = RawInvoice::parse(TX_REF).unwrap();
+        assert!(matches!(
+            RawInvoice { hrp: "xxbc20m", ..raw }.amount(),
+            Err(Error::InvalidValue(_))
+        ));
+    }
+}
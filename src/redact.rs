@@ -0,0 +1,162 @@
+//! Redaction of the customer data of payment requests, e.g. for the logs of payment services. <br>
+//! `Redacted` formats a payment request, a tagged field, a preimage or a payment secret as
+//! `Debug` and `Display` do, but with the descriptions, the payment metadata, the payment secrets
+//! and the preimages replaced by their length, e.g. `description: <redacted 12 bytes>`. <br>
+//! *Note*: the description hash, the payment hash and the node id are kept, so the log entries of
+//! a payment can still be matched.
+
+use hashes::{PaymentPreimage, PaymentSecret};
+use payment_request::PaymentRequest;
+use tag::Tag;
+use std::fmt;
+
+/// Wrapper formatting its value with the sensitive fields redacted, see `PaymentRequest::redacted`.
+#[derive(Clone, Copy)]
+pub struct Redacted<'a, T: 'a>(pub &'a T);
+
+// Helper for the redacted fields, shown as their length.
+struct Length(usize);
+
+impl fmt::Debug for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<redacted {} bytes>", self.0)
+    }
+}
+
+impl<'a> fmt::Debug for Redacted<'a, PaymentRequest> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tags = self.0.tags.iter().map(Redacted).collect::<Vec<_>>();
+        f.debug_struct("PaymentRequest")
+            .field("prefix", &self.0.prefix)
+            .field("amount", &self.0.amount)
+            .field("timestamp", &self.0.timestamp)
+            .field("node_id", &self.0.node_id)
+            .field("tags", &tags)
+            .field("signature", &self.0.signature)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Display for Redacted<'a, PaymentRequest> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0.prefix)?;
+        if let Some(amount) = self.0.amount {
+            write!(f, ", amount {} msat", amount)?;
+        }
+        if let Some(hash) = self.0.payment_hash() {
+            write!(f, ", payment hash {}", hash)?;
+        }
+        for tag in &self.0.tags {
+            match *tag {
+                Tag::Description { ref description } => {
+                    write!(f, ", description {:?}", Length(description.len()))?
+                }
+                Tag::DescriptionHash { hash } => write!(f, ", description hash {}", hash)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for Redacted<'a, Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Tag::Description { ref description } => f
+                .debug_struct("Description")
+                .field("description", &Length(description.len()))
+                .finish(),
+            Tag::PaymentMetadata { ref bytes } => f
+                .debug_struct("PaymentMetadata")
+                .field("bytes", &Length(bytes.len()))
+                .finish(),
+            Tag::PaymentSecret { ref secret } => f
+                .debug_struct("PaymentSecret")
+                .field("secret", &Redacted(secret))
+                .finish(),
+            ref tag => fmt::Debug::fmt(tag, f),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Redacted<'a, PaymentPreimage> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PaymentPreimage").field(&Length(32)).finish()
+    }
+}
+
+impl<'a> fmt::Display for Redacted<'a, PaymentPreimage> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", Length(32))
+    }
+}
+
+impl<'a> fmt::Debug for Redacted<'a, PaymentSecret> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PaymentSecret").field(&Length(32)).finish()
+    }
+}
+
+impl<'a> fmt::Display for Redacted<'a, PaymentSecret> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", Length(32))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TX_REF: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xy\
+                          sxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruat\
+                          fhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+    #[test]
+    fn payment_request() {
+        let pay_request = PaymentRequest::decode(TX_REF).unwrap();
+        let debug = format!("{:?}", pay_request.redacted());
+
+        assert!(format!("{:?}", pay_request).contains("1 cup coffee"));
+        assert!(!debug.contains("1 cup coffee"));
+        assert!(debug.contains("Description { description: <redacted 12 bytes> }"));
+        assert!(debug.contains("Expiry { seconds: 60 }"));
+        assert_eq!(
+            pay_request.redacted().to_string(),
+            "lnbc, amount 250000000 msat, payment hash \
+             0001020304050607080900010203040506070809000102030405060708090102, \
+             description <redacted 12 bytes>"
+        );
+    }
+
+    #[test]
+    fn secrets() {
+        let preimage = PaymentPreimage::from([7u8; 32]);
+        let secret = PaymentSecret::from([7u8; 32]);
+
+        assert_eq!(format!("{:?}", Redacted(&preimage)), "PaymentPreimage(<redacted 32 bytes>)");
+        assert_eq!(Redacted(&preimage).to_string(), "<redacted 32 bytes>");
+        assert_eq!(format!("{:?}", Redacted(&secret)), "PaymentSecret(<redacted 32 bytes>)");
+        assert!(!Redacted(&secret).to_string().contains("0707"));
+    }
+
+    #[test]
+    fn tags() {
+        let secret = Tag::PaymentSecret {
+            secret: PaymentSecret::from([7u8; 32]),
+        };
+        let metadata = Tag::PaymentMetadata {
+            bytes: vec![1, 2, 3],
+        };
+
+        assert_eq!(
+            format!("{:?}", Redacted(&secret)),
+            "PaymentSecret { secret: PaymentSecret(<redacted 32 bytes>) }"
+        );
+        assert_eq!(
+            format!("{:?}", Redacted(&metadata)),
+            "PaymentMetadata { bytes: <redacted 3 bytes> }"
+        );
+        let expiry = Tag::Expiry { seconds: 60 };
+        assert_eq!(format!("{:?}", Redacted(&expiry)), format!("{:?}", expiry));
+    }
+}
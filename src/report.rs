@@ -0,0 +1,272 @@
+//! Human readable breakdown of a payment request, e.g. for debugging tools and invoice
+//! inspectors, see `PaymentRequest::explain`.
+
+use amount::Amount;
+use currency::Currency;
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use tag::Tag;
+use utils::to_hex;
+use std::fmt;
+
+/// Description of a tagged field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldReport {
+    /// Type of the field, e.g. `'p'`.
+    pub tag: char,
+    /// Name of the field, e.g. `payment_hash`.
+    pub name: &'static str,
+    /// Number of u5 of the field data, without the type and the length.
+    pub data_len: usize,
+    /// Decoded value of the field.
+    pub value: String,
+    /// Hex encoding of the field bytes, for the hashes, keys, addresses and opaque data.
+    pub hex: Option<String>,
+}
+
+impl FieldReport {
+    /// Describe a tagged field.
+    /// # Arguments
+    /// `tag` Tagged field.
+    /// `currency` Currency of the payment request, to convert the fallback addresses.
+    pub fn new(tag: &Tag, currency: Option<&Currency>) -> FieldReport {
+        let (value, hex) = match *tag {
+            Tag::PaymentHash { ref hash } => (hash.to_string(), Some(hash.to_string())),
+            Tag::PaymentSecret { ref secret } => (secret.to_string(), Some(secret.to_string())),
+            Tag::Description { ref description } => (description.to_owned(), None),
+            Tag::DescriptionHash { ref hash } => (hash.to_string(), Some(hash.to_string())),
+            Tag::FallbackAddress { version, ref hash } => (
+                currency
+                    .and_then(|currency| tag.fallback_address(currency))
+                    .and_then(Result::ok)
+                    .unwrap_or_else(|| format!("version {}", version)),
+                Some(to_hex(hash)),
+            ),
+            Tag::Expiry { seconds } => (format!("{} seconds", seconds), None),
+            Tag::MinFinalCltvExpiry { blocks } => (format!("{} blocks", blocks), None),
+            Tag::RoutingInfo { ref path } => (
+                path.iter()
+                    .map(|hop| {
+                        format!(
+                            "{} via {}: {} msat + {} ppm, cltv delta {}",
+                            hop.pub_key,
+                            hop.short_channel_id,
+                            hop.fee_base_msat,
+                            hop.fee_proportional_millionths,
+                            hop.cltv_expiry_delta
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("; "),
+                path.iter()
+                    .map(|hop| hop.pack().map(|bytes| to_hex(&bytes)))
+                    .collect::<Result<String, _>>()
+                    .ok(),
+            ),
+            Tag::PayeeNodeId { ref pub_key } => (pub_key.to_string(), Some(pub_key.to_string())),
+            Tag::Features { ref bits } => (format!("bits {:?}", bits.bits()), None),
+            Tag::PaymentMetadata { ref bytes } => (to_hex(bytes), Some(to_hex(bytes))),
+            Tag::Skipped { ref reason, .. } => (format!("skipped: {}", reason), None),
+            Tag::UnknownTag { ref bytes, .. } => {
+                (format!("unknown field of {} u5", bytes.len()), None)
+            }
+        };
+        FieldReport {
+            tag: tag.tag_char(),
+            name: tag.name(),
+            data_len: tag.data_len().unwrap_or_default(),
+            value,
+            hex,
+        }
+    }
+}
+
+impl fmt::Display for FieldReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({} u5): {}",
+            self.tag, self.name, self.data_len, self.value
+        )?;
+        match self.hex {
+            Some(ref hex) if *hex != self.value => write!(f, " [{}]", hex),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Breakdown of a payment request, created by `PaymentRequest::explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvoiceReport {
+    /// Prefix of the payment request, e.g. `lnbc`.
+    pub prefix: String,
+    /// Currency of the prefix, if known.
+    pub currency: Option<Currency>,
+    /// Amount to pay in millisatoshis, if any.
+    pub amount: Option<u64>,
+    /// Request timestamp (UNIX format).
+    pub timestamp: u64,
+    /// Tagged fields, in order.
+    pub fields: Vec<FieldReport>,
+    /// Id of the node which signed the payment request, hex encoded compressed public key.
+    pub node_id: String,
+    /// SHA256 hash of the signed message, hex encoded.
+    pub message_hash: Option<String>,
+    /// `r` component of the signature, hex encoded.
+    pub signature_r: Option<String>,
+    /// `s` component of the signature, hex encoded.
+    pub signature_s: Option<String>,
+    /// Recovery id of the signature.
+    pub recovery_id: Option<u8>,
+}
+
+impl InvoiceReport {
+    /// Describe a payment request.
+    /// # Arguments
+    /// `payment_request` Payment request to describe.
+    pub fn new(payment_request: &PaymentRequest) -> InvoiceReport {
+        let currency = payment_request.currency();
+        let signature = payment_request.signature.as_slice();
+        let (signature_r, signature_s) = match signature.len() {
+            65 => (Some(to_hex(&signature[..32])), Some(to_hex(&signature[32..64]))),
+            _ => (None, None),
+        };
+        InvoiceReport {
+            prefix: payment_request.prefix.to_owned(),
+            currency,
+            amount: payment_request.amount,
+            timestamp: payment_request.timestamp,
+            fields: payment_request
+                .tags
+                .iter()
+                .map(|tag| FieldReport::new(tag, currency.as_ref()))
+                .collect(),
+            node_id: NodeId::from(&payment_request.node_id).to_string(),
+            message_hash: payment_request.hash().ok().map(|hash| to_hex(&hash)),
+            signature_r,
+            signature_s,
+            recovery_id: payment_request.recovery_id(),
+        }
+    }
+}
+
+impl fmt::Display for InvoiceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "prefix: {}", self.prefix)?;
+        match self.amount {
+            Some(amount) => writeln!(f, "amount: {}", Amount::from_msat(amount))?,
+            None => writeln!(f, "amount: any")?,
+        }
+        writeln!(f, "timestamp: {}", self.timestamp)?;
+        for field in &self.fields {
+            writeln!(f, "{}", field)?;
+        }
+        writeln!(f, "node id: {}", self.node_id)?;
+        if let Some(ref hash) = self.message_hash {
+            writeln!(f, "message hash: {}", hash)?;
+        }
+        if let (Some(ref r), Some(ref s)) = (&self.signature_r, &self.signature_s) {
+            writeln!(f, "signature r: {}", r)?;
+            writeln!(f, "signature s: {}", s)?;
+        }
+        if let Some(recovery_id) = self.recovery_id {
+            writeln!(f, "recovery id: {}", recovery_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn explain() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let report = pay_request.explain();
+
+        assert_eq!(report.currency, Some(Currency::Bitcoin));
+        assert_eq!(report.amount, Some(250_000_000));
+        assert_eq!(
+            report.fields,
+            vec![
+                FieldReport {
+                    tag: 'p',
+                    name: "payment_hash",
+                    data_len: 52,
+                    value: "0001020304050607080900010203040506070809000102030405060708090102"
+                        .to_owned(),
+                    hex: Some(
+                        "0001020304050607080900010203040506070809000102030405060708090102"
+                            .to_owned(),
+                    ),
+                },
+                FieldReport {
+                    tag: 'd',
+                    name: "description",
+                    data_len: 20,
+                    value: "1 cup coffee".to_owned(),
+                    hex: None,
+                },
+                FieldReport {
+                    tag: 'x',
+                    name: "expiry",
+                    data_len: 2,
+                    value: "60 seconds".to_owned(),
+                    hex: None,
+                },
+            ]
+        );
+        assert_eq!(
+            report.node_id,
+            "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+        );
+        assert_eq!(
+            report.message_hash,
+            pay_request.hash().ok().map(|hash| to_hex(&hash))
+        );
+        assert_eq!(
+            report.signature_r.unwrap() + &report.signature_s.unwrap(),
+            to_hex(&pay_request.signature[..64])
+        );
+        assert_eq!(report.recovery_id, pay_request.recovery_id());
+    }
+
+    #[test]
+    fn display() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9rn449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let report = PaymentRequest::decode(tx_ref).unwrap().explain().to_string();
+
+        assert!(report.starts_with("prefix: lnbc\namount: any\ntimestamp: 1496314658\n"));
+        assert!(report.contains("\nd description (63 u5): Please consider supporting this project\n"));
+        assert!(report.contains("\nrecovery id: "));
+        assert_eq!(report.lines().count(), 10);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let field = FieldReport::new(
+            &Tag::UnknownTag {
+                tag: 2,
+                bytes: vec![1, 2],
+            },
+            None,
+        );
+
+        assert_eq!(field.to_string(), "z unknown (2 u5): unknown field of 2 u5");
+        assert_eq!(
+            FieldReport::new(
+                &Tag::FallbackAddress {
+                    version: 17,
+                    hash: vec![0u8; 20],
+                },
+                None,
+            ).value,
+            "version 17"
+        );
+        assert!(FieldReport::new(&Tag::Expiry { seconds: 60 }, None)
+            .to_string()
+            .ends_with("60 seconds"));
+    }
+}
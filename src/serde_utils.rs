@@ -0,0 +1,187 @@
+//! Serde support, behind the `serde` feature: the byte fields are hex encoded, the hashes, payment
+//! secrets, node ids and short channel ids use their string form and the amounts are decimal
+//! strings of millisatoshis, so they don't lose precision in JSON.
+
+use hashes::{DescriptionHash, PaymentHash, PaymentSecret};
+use node_id::NodeId;
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde::ser::{Serialize, Serializer};
+use short_channel_id::ShortChannelId;
+
+/// Implement Serialize and Deserialize with the Display and FromStr implementations.
+macro_rules! serde_string {
+    ($name:ident) => {
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(DeError::custom)
+            }
+        }
+    };
+}
+
+serde_string!(PaymentHash);
+serde_string!(DescriptionHash);
+serde_string!(PaymentSecret);
+serde_string!(NodeId);
+serde_string!(ShortChannelId);
+
+/// Byte vectors as hex strings, for `#[serde(with = "::serde_utils::hex")]`.
+pub mod hex {
+    use super::*;
+    use utils::{from_hex, to_hex};
+
+    /// Serialize the bytes as a hex string.
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    /// Deserialize the bytes from a hex string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        from_hex(&String::deserialize(deserializer)?).map_err(DeError::custom)
+    }
+}
+
+/// Byte vectors as padded base64 strings, the `bytes` fields of lnd's REST API, for
+/// `#[serde(with = "::serde_utils::base64")]`.
+pub mod base64 {
+    use super::*;
+    use types::Error;
+    use utils::{convert_bits, Padding};
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    /// Serialize the bytes as a base64 string.
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(bytes))
+    }
+
+    /// Deserialize the bytes from a base64 string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        decode(&String::deserialize(deserializer)?).map_err(DeError::custom)
+    }
+
+    // Helper for serialize, the 6-bits values of the bytes padded with `=` to 4 characters.
+    pub(super) fn encode(bytes: &[u8]) -> String {
+        let mut encoded = convert_bits(bytes, 8, 6, Padding::Add)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|value| ALPHABET[value as usize] as char)
+            .collect::<String>();
+        while encoded.len() % 4 != 0 {
+            encoded.push('=');
+        }
+        encoded
+    }
+
+    // Helper for deserialize.
+    fn decode(encoded: &str) -> Result<Vec<u8>, Error> {
+        let values = encoded
+            .trim_end_matches('=')
+            .bytes()
+            .map(|c| ALPHABET.iter().position(|&a| a == c).map(|value| value as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| Error::InvalidFormat("invalid base64 character".to_owned()))?;
+        convert_bits(&values, 6, 8, Padding::Strict)
+    }
+}
+
+/// Optional amounts in millisatoshis as decimal strings.
+pub mod amount {
+    use super::*;
+
+    /// Serialize the amount as a decimal string, or null.
+    pub fn serialize<S: Serializer>(amount: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match *amount {
+            Some(amount) => serializer.collect_str(&amount),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserialize the amount from a decimal string, or null.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(amount) => amount.parse().map(Some).map_err(DeError::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Public keys as compressed hex node ids.
+pub mod public_key {
+    use super::*;
+    use secp256k1::PublicKey;
+
+    /// Serialize the public key as a compressed hex node id.
+    pub fn serialize<S: Serializer>(pub_key: &PublicKey, serializer: S) -> Result<S::Ok, S::Error> {
+        NodeId::from(pub_key).serialize(serializer)
+    }
+
+    /// Deserialize the public key from a compressed hex node id.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PublicKey, D::Error> {
+        NodeId::deserialize(deserializer)?
+            .to_public_key()
+            .map_err(DeError::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::de::value::{Error as ValueError, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    const NODE_ID: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+
+    fn deserializer(s: &str) -> StrDeserializer<ValueError> {
+        s.into_deserializer()
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            NodeId::deserialize(deserializer(NODE_ID)).unwrap(),
+            NODE_ID.parse::<NodeId>().unwrap()
+        );
+        assert_eq!(
+            ShortChannelId::deserialize(deserializer("504x1x0")).unwrap(),
+            ShortChannelId::new(504, 1, 0).unwrap()
+        );
+        assert!(PaymentHash::deserialize(deserializer("0102")).is_err());
+        assert_eq!(
+            PaymentSecret::deserialize(deserializer(&"11".repeat(32))).unwrap(),
+            PaymentSecret::from([0x11; 32])
+        );
+        assert_eq!(hex::deserialize(deserializer("0aff")).unwrap(), vec![0x0a, 0xff]);
+        assert!(hex::deserialize(deserializer("0g")).is_err());
+    }
+
+    #[test]
+    fn base64() {
+        for &(bytes, encoded) in &[
+            (&[][..], ""),
+            (&[0x01][..], "AQ=="),
+            (&[0x01, 0xfa][..], "Afo="),
+            (&[0x01, 0xfa, 0xfa, 0xf0][..], "Afr68A=="),
+        ] {
+            assert_eq!(base64::encode(bytes), encoded);
+            assert_eq!(base64::deserialize(deserializer(encoded)).unwrap(), bytes);
+        }
+        assert!(base64::deserialize(deserializer("A!==")).is_err());
+        assert!(base64::deserialize(deserializer("AR==")).is_err());
+    }
+
+    #[test]
+    fn public_key() {
+        let pub_key = public_key::deserialize(deserializer(NODE_ID)).unwrap();
+
+        assert_eq!(NodeId::from(&pub_key).to_string(), NODE_ID);
+    }
+}
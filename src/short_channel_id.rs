@@ -0,0 +1,141 @@
+//! Short channel ids, the location of the funding output of a channel.
+
+use types::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Short channel id: block height and transaction index of the funding transaction, on 24 bits
+/// each, and output index of the funding output, on 16 bits. <br>
+/// The fields are private so that a short channel id always fits 64 bits, see
+/// `ShortChannelId::new`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShortChannelId {
+    block_height: u32,
+    tx_index: u32,
+    output_index: u16,
+}
+
+impl ShortChannelId {
+    /// Create a short channel id, the block height and transaction index must fit 24 bits.
+    /// # Arguments
+    /// `block_height` Height of the block including the funding transaction. <br>
+    /// `tx_index` Index of the funding transaction in the block. <br>
+    /// `output_index` Index of the funding output in the transaction.
+    pub fn new(block_height: u32, tx_index: u32, output_index: u16) -> Result<ShortChannelId, Error> {
+        if block_height >= 1 << 24 || tx_index >= 1 << 24 {
+            return Err(Error::InvalidValue(format!(
+                "invalid short channel id {}x{}x{}",
+                block_height, tx_index, output_index
+            )));
+        }
+        Ok(ShortChannelId {
+            block_height,
+            tx_index,
+            output_index,
+        })
+    }
+
+    /// Height of the block including the funding transaction.
+    pub fn block_height(&self) -> u32 {
+        self.block_height
+    }
+
+    /// Index of the funding transaction in the block.
+    pub fn tx_index(&self) -> u32 {
+        self.tx_index
+    }
+
+    /// Index of the funding output in the transaction.
+    pub fn output_index(&self) -> u16 {
+        self.output_index
+    }
+}
+
+impl From<u64> for ShortChannelId {
+    fn from(short_channel_id: u64) -> ShortChannelId {
+        ShortChannelId {
+            block_height: (short_channel_id >> 40) as u32,
+            tx_index: ((short_channel_id >> 16) & 0xff_ffff) as u32,
+            output_index: short_channel_id as u16,
+        }
+    }
+}
+
+impl From<ShortChannelId> for u64 {
+    fn from(short_channel_id: ShortChannelId) -> u64 {
+        (u64::from(short_channel_id.block_height) << 40)
+            | (u64::from(short_channel_id.tx_index) << 16)
+            | u64::from(short_channel_id.output_index)
+    }
+}
+
+impl fmt::Display for ShortChannelId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}x{}",
+            self.block_height, self.tx_index, self.output_index
+        )
+    }
+}
+
+impl FromStr for ShortChannelId {
+    type Err = Error;
+
+    /// Parse a short channel id formatted as `BxTxO`, e.g. `812345x1234x0`, or as
+    /// `block:tx:output`.
+    fn from_str(s: &str) -> Result<ShortChannelId, Error> {
+        let separator = if s.contains(':') { ':' } else { 'x' };
+        let parts = s.split(separator)
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| Error::InvalidValue(format!("invalid short channel id {}", s)))?;
+        match parts.as_slice() {
+            &[block_height, tx_index, output_index] if output_index < 1 << 16 => {
+                ShortChannelId::new(block_height, tx_index, output_index as u16)
+            }
+            _ => Err(Error::InvalidValue(format!("invalid short channel id {}", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn u64_conversion() {
+        let short_channel_id = ShortChannelId::from(72623859790382856);
+
+        assert_eq!(
+            short_channel_id,
+            ShortChannelId::new(66051, 263430, 1800).unwrap()
+        );
+        assert_eq!(u64::from(short_channel_id), 72623859790382856);
+        assert_eq!(short_channel_id.block_height(), 66051);
+        assert_eq!(short_channel_id.tx_index(), 263430);
+        assert_eq!(short_channel_id.output_index(), 1800);
+        assert_eq!(ShortChannelId::from(u64::MAX).block_height(), (1 << 24) - 1);
+        assert!(ShortChannelId::new(1 << 24, 0, 0).is_err());
+        assert!(ShortChannelId::new(0, 1 << 24, 0).is_err());
+    }
+
+    #[test]
+    fn display() {
+        let short_channel_id = ShortChannelId::new(812345, 1234, 0).unwrap();
+
+        assert_eq!(short_channel_id.to_string(), "812345x1234x0");
+        assert_eq!(
+            "812345x1234x0".parse::<ShortChannelId>().unwrap(),
+            short_channel_id
+        );
+        assert_eq!(
+            "812345:1234:0".parse::<ShortChannelId>().unwrap(),
+            short_channel_id
+        );
+        assert!("812345x1234".parse::<ShortChannelId>().is_err());
+        assert!("812345x1234x65536".parse::<ShortChannelId>().is_err());
+        assert!("16777216x1234x0".parse::<ShortChannelId>().is_err());
+        assert!("812345xax0".parse::<ShortChannelId>().is_err());
+    }
+}
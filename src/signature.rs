@@ -0,0 +1,214 @@
+//! Compact recoverable signature of a payment request, with the checks of its encoding. <br>
+//! The 65 bytes are `r` and `s`, both in `1..n` where `n` is the order of secp256k1, followed by
+//! the recovery id, in `0..4`. A signature and its opposite, `s` replaced by `n - s`, verify with
+//! the same key, so the processors identifying payment requests by their encoding normalize it to
+//! the low `s`, the one produced by the signers.
+
+use secp256k1;
+use types::Error;
+use utils::to_hex;
+use std::fmt;
+
+/// Order of secp256k1, big-endian.
+const ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Half of the order of secp256k1 rounded down, the highest low `s`, big-endian.
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Compact recoverable signature.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    r: [u8; 32],
+    s: [u8; 32],
+    recovery_id: u8,
+}
+
+impl Signature {
+    /// Parse the 65 bytes of a signature, failing if the length, `r`, `s` or the recovery id is
+    /// out of range rather than reducing them.
+    /// # Arguments
+    /// * `bytes` - `r` and `s`, 32 bytes each, followed by the recovery id.
+    pub fn from_slice(bytes: &[u8]) -> Result<Signature, Error> {
+        if bytes.len() != 65 {
+            return Err(Error::InvalidLength(
+                "the length must be 65 bytes".to_owned(),
+            ));
+        }
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        r.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..64]);
+        if !Signature::in_range(&r) || !Signature::in_range(&s) {
+            return Err(Error::SignatureError(secp256k1::Error::InvalidSignature));
+        }
+        if bytes[64] > 3 {
+            return Err(Error::SignatureError(secp256k1::Error::InvalidRecoveryId));
+        }
+        Ok(Signature {
+            r,
+            s,
+            recovery_id: bytes[64],
+        })
+    }
+
+    /// Check whether `s` is at most half of the order, as produced by the signers.
+    pub fn is_low_s(&self) -> bool {
+        self.s <= HALF_ORDER
+    }
+
+    /// Return the signature with a low `s`, replacing a high `s` by `n - s` and flipping the
+    /// parity bit of the recovery id, the recovered key being the same.
+    pub fn normalize_s(&self) -> Signature {
+        if self.is_low_s() {
+            return *self;
+        }
+        self.negate_s()
+    }
+
+    // Helper for normalize_s and the high `s` fixtures of the tests, the opposite signature,
+    // `n - s` with the other parity bit, recovering the same key.
+    pub(crate) fn negate_s(&self) -> Signature {
+        Signature {
+            r: self.r,
+            s: negate(&self.s),
+            recovery_id: self.recovery_id ^ 1,
+        }
+    }
+
+    /// Return the recovery id, in `0..4`.
+    pub fn recovery_id(&self) -> u8 {
+        self.recovery_id
+    }
+
+    /// Return `r` followed by `s`.
+    pub fn compact(&self) -> [u8; 64] {
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&self.r);
+        compact[32..].copy_from_slice(&self.s);
+        compact
+    }
+
+    /// Return the 65 bytes, `r` and `s` followed by the recovery id.
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&self.compact());
+        bytes[64] = self.recovery_id;
+        bytes
+    }
+
+    /// Convert to the libsecp256k1 types, for the recovery and the verification.
+    pub fn to_secp256k1(&self) -> (secp256k1::RecoveryId, secp256k1::Signature) {
+        (
+            secp256k1::RecoveryId::parse(self.recovery_id).expect("the recovery id is checked"),
+            secp256k1::Signature::parse(&self.compact()),
+        )
+    }
+
+    // Helper for from_slice, a scalar in 1..n.
+    fn in_range(scalar: &[u8; 32]) -> bool {
+        *scalar != [0u8; 32] && *scalar < ORDER
+    }
+}
+
+// Helper for negate_s, n - scalar for a scalar in 1..n.
+fn negate(scalar: &[u8; 32]) -> [u8; 32] {
+    let mut negated = [0u8; 32];
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let difference = i16::from(ORDER[i]) - i16::from(scalar[i]) - borrow;
+        borrow = if difference < 0 { 1 } else { 0 };
+        negated[i] = (difference + (borrow << 8)) as u8;
+    }
+    negated
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signature({})", to_hex(&self.to_bytes()))
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", to_hex(&self.to_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1::{Message, PublicKey, SecretKey};
+
+    #[test]
+    fn low_s() {
+        let key = SecretKey::parse(&[1u8; 32]).unwrap();
+        let message = Message::parse(&[7u8; 32]);
+        let (signature, recovery_id) = secp256k1::sign(&message, &key).unwrap();
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.serialize());
+        bytes[64] = recovery_id.serialize();
+
+        let low = Signature::from_slice(&bytes).unwrap();
+        assert!(low.is_low_s());
+        assert_eq!(low.normalize_s(), low);
+        assert_eq!(low.to_bytes().to_vec(), bytes.to_vec());
+
+        // the opposite signature, n - s
+        let mut high = low;
+        high.s = negate(&low.s);
+        high.recovery_id ^= 1;
+        assert!(!high.is_low_s());
+        assert_eq!(high.normalize_s(), low);
+
+        let (recovery_id, signature) = high.to_secp256k1();
+        assert_eq!(
+            secp256k1::recover(&message, &signature, &recovery_id).unwrap(),
+            PublicKey::from_secret_key(&key)
+        );
+    }
+
+    #[test]
+    fn boundaries() {
+        let mut half = HALF_ORDER;
+        let signature = |s: &[u8; 32]| {
+            let mut bytes = [1u8; 65];
+            bytes[32..64].copy_from_slice(s);
+            bytes[64] = 0;
+            Signature::from_slice(&bytes)
+        };
+
+        assert!(signature(&half).unwrap().is_low_s());
+        half[31] += 1;
+        assert!(!signature(&half).unwrap().is_low_s());
+        assert_eq!(signature(&half).unwrap().normalize_s().s, HALF_ORDER);
+
+        let mut last = ORDER;
+        last[31] -= 1;
+        assert_eq!(signature(&last).unwrap().normalize_s().s[31], 1);
+        assert!(signature(&ORDER).is_err());
+        assert!(signature(&[0u8; 32]).is_err());
+        assert!(signature(&[0xffu8; 32]).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = [1u8; 65];
+        bytes[64] = 4;
+        assert!(matches!(
+            Signature::from_slice(&bytes),
+            Err(Error::SignatureError(secp256k1::Error::InvalidRecoveryId))
+        ));
+        bytes[64] = 3;
+        assert_eq!(Signature::from_slice(&bytes).unwrap().recovery_id(), 3);
+        assert!(matches!(
+            Signature::from_slice(&bytes[..64]),
+            Err(Error::InvalidLength(_))
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+//! Signing of payment requests by pluggable backends, e.g. a vault, a KMS or a remote signer, see
+//! `InvoiceBuilder::build_signed` and `UnsignedInvoice::sign_with`. <br>
+//! *Note*: functions signing a `Message` like `secp256k1::sign` are signers, closures being
+//! rather given to `InvoiceBuilder::build_signed_with`, and `SoftwareSigner` signs with a key held
+//! in memory with the `software-signer` feature, enabled by default.
+
+#[cfg(feature = "software-signer")]
+use node_id::NodeId;
+#[cfg(feature = "software-signer")]
+use secp256k1::{PublicKey, SecretKey};
+use secp256k1;
+use secp256k1::{Message, RecoveryId, Signature};
+#[cfg(feature = "software-signer")]
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(all(feature = "software-signer", feature = "async"))]
+use std::future::{self, Ready};
+use types::SignError;
+#[cfg(all(feature = "software-signer", feature = "zeroize"))]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Backend signing the hash of a payment request with the node key.
+pub trait InvoiceSigner {
+    /// Sign the message with a recoverable signature, returning the recovery id and the compact
+    /// signature, `r` followed by `s`.
+    /// # Arguments
+    /// * `msg` - SHA256 of the payment request, see `UnsignedInvoice::signing_data`.
+    fn sign_recoverable(&self, msg: &[u8; 32]) -> Result<(RecoveryId, [u8; 64]), SignError>;
+}
+
+impl<F> InvoiceSigner for F
+where
+    F: Fn(&Message) -> Result<(Signature, RecoveryId), secp256k1::Error>,
+{
+    fn sign_recoverable(&self, msg: &[u8; 32]) -> Result<(RecoveryId, [u8; 64]), SignError> {
+        let (signature, recovery_id) = self(&Message::parse(msg))?;
+        Ok((recovery_id, signature.serialize()))
+    }
+}
+
+/// Backend signing the hash of a payment request without blocking, e.g. a network HSM or lnd's
+/// `signrpc`, see `InvoiceBuilder::build_signed_async`. <br>
+/// *Note*: only built with the `async` feature.
+#[cfg(feature = "async")]
+pub trait AsyncInvoiceSigner {
+    /// Future of the recovery id and the compact signature.
+    type Future: Future<Output = Result<(RecoveryId, [u8; 64]), SignError>>;
+
+    /// Sign the message with a recoverable signature, see `InvoiceSigner::sign_recoverable`.
+    /// # Arguments
+    /// * `msg` - SHA256 of the payment request, see `UnsignedInvoice::signing_data`.
+    fn sign_recoverable_async(&self, msg: &[u8; 32]) -> Self::Future;
+}
+
+/// Signer holding the node secret key in memory. <br>
+/// *Note*: only built with the `software-signer` feature, the key is not shown by `Debug` and,
+/// with the `zeroize` feature, it is cleared when the signer is dropped. The key is copied into
+/// the signer, the copy of the caller is not cleared.
+#[cfg(feature = "software-signer")]
+#[derive(Clone)]
+pub struct SoftwareSigner {
+    // bytes of the key, libsecp256k1 can't clear a `SecretKey` so one is built for each use
+    secret_key: [u8; 32],
+}
+
+#[cfg(feature = "software-signer")]
+impl SoftwareSigner {
+    /// Create a signer.
+    /// # Arguments
+    /// * `secret_key` - Secret key of the node.
+    pub fn new(secret_key: SecretKey) -> SoftwareSigner {
+        SoftwareSigner {
+            secret_key: secret_key.serialize(),
+        }
+    }
+
+    /// Create a signer from the 32 bytes of the secret key, failing if they are not a valid key.
+    /// # Arguments
+    /// * `secret_key` - Secret key of the node.
+    pub fn from_bytes(secret_key: &[u8; 32]) -> Result<SoftwareSigner, SignError> {
+        SecretKey::parse(secret_key)?;
+        Ok(SoftwareSigner {
+            secret_key: *secret_key,
+        })
+    }
+
+    /// Return the public key of the node.
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(&self.key())
+    }
+
+    // Helper for the signatures and the public key, the key checked by the constructors.
+    fn key(&self) -> SecretKey {
+        SecretKey::parse(&self.secret_key).expect("the secret key is checked")
+    }
+}
+
+#[cfg(feature = "software-signer")]
+impl InvoiceSigner for SoftwareSigner {
+    fn sign_recoverable(&self, msg: &[u8; 32]) -> Result<(RecoveryId, [u8; 64]), SignError> {
+        let (signature, recovery_id) = secp256k1::sign(&Message::parse(msg), &self.key())?;
+        Ok((recovery_id, signature.serialize()))
+    }
+}
+
+#[cfg(feature = "software-signer")]
+impl InvoiceSigner for &SoftwareSigner {
+    fn sign_recoverable(&self, msg: &[u8; 32]) -> Result<(RecoveryId, [u8; 64]), SignError> {
+        (**self).sign_recoverable(msg)
+    }
+}
+
+#[cfg(all(feature = "software-signer", feature = "async"))]
+impl AsyncInvoiceSigner for SoftwareSigner {
+    type Future = Ready<Result<(RecoveryId, [u8; 64]), SignError>>;
+
+    fn sign_recoverable_async(&self, msg: &[u8; 32]) -> Self::Future {
+        future::ready(self.sign_recoverable(msg))
+    }
+}
+
+#[cfg(all(feature = "software-signer", feature = "async"))]
+impl AsyncInvoiceSigner for &SoftwareSigner {
+    type Future = Ready<Result<(RecoveryId, [u8; 64]), SignError>>;
+
+    fn sign_recoverable_async(&self, msg: &[u8; 32]) -> Self::Future {
+        (**self).sign_recoverable_async(msg)
+    }
+}
+
+#[cfg(all(feature = "software-signer", feature = "zeroize"))]
+impl Drop for SoftwareSigner {
+    fn drop(&mut self) {
+        self.secret_key.zeroize();
+    }
+}
+
+#[cfg(all(feature = "software-signer", feature = "zeroize"))]
+impl ZeroizeOnDrop for SoftwareSigner {}
+
+#[cfg(feature = "software-signer")]
+impl fmt::Debug for SoftwareSigner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SoftwareSigner")
+            .field("node_id", &NodeId::from(&self.public_key()).to_string())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1::{PublicKey, SecretKey};
+    use utils::from_hex;
+
+    const SEC_KEY: &str = "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734";
+
+    // A backend which can't be reached.
+    struct OfflineSigner;
+
+    impl InvoiceSigner for OfflineSigner {
+        fn sign_recoverable(&self, _: &[u8; 32]) -> Result<(RecoveryId, [u8; 64]), SignError> {
+            Err(SignError::Unavailable("connection refused".to_owned()))
+        }
+    }
+
+    #[test]
+    fn closure_signer() {
+        let key = secret_key();
+        let closure = |message: &Message| secp256k1::sign(message, &key);
+        let (recovery_id, signature) = closure.sign_recoverable(&[7u8; 32]).unwrap();
+
+        assert_eq!(
+            secp256k1::recover(
+                &Message::parse(&[7u8; 32]),
+                &Signature::parse(&signature),
+                &recovery_id
+            ).unwrap(),
+            PublicKey::from_secret_key(&key)
+        );
+    }
+
+    fn secret_key() -> SecretKey {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&from_hex(SEC_KEY).unwrap());
+        SecretKey::parse(&bytes).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "software-signer")]
+    fn software_signer() {
+        let key = secret_key();
+        let signer = SoftwareSigner::new(key.clone());
+        let closure = |message: &Message| secp256k1::sign(message, &key);
+        let msg = [7u8; 32];
+
+        let (recovery_id, signature) = signer.sign_recoverable(&msg).unwrap();
+        assert_eq!(closure.sign_recoverable(&msg).unwrap(), (recovery_id, signature));
+        assert_eq!(
+            InvoiceSigner::sign_recoverable(&&signer, &msg).unwrap(),
+            (recovery_id, signature)
+        );
+        assert_eq!(signer.public_key(), PublicKey::from_secret_key(&key));
+        assert!(!format!("{:?}", signer).contains(SEC_KEY));
+        assert!(SoftwareSigner::from_bytes(&[0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn failing_signer() {
+        assert_eq!(
+            OfflineSigner.sign_recoverable(&[0u8; 32]),
+            Err(SignError::Unavailable("connection refused".to_owned()))
+        );
+    }
+}
@@ -1,27 +1,56 @@
 //! PaymentRequest tagged fields.
 
+use address;
+use bech32::{char_value, CHARSET};
+use currency::Currency;
+use encoder::{number_u5_len, u5_len, write_error, Bech32Writer};
+use features::Features;
+use hashes::{DescriptionHash, PaymentHash, PaymentSecret};
+use node_id::NodeId;
+use short_channel_id::ShortChannelId;
 use types::Error;
-use utils::{U5, U5Conversions, U64VecU5Conversions, U8Conversions};
-use std::collections::HashMap;
+use utils::{convert_bits_buffer, Padding, U5, U5Conversions, U64VecU5Conversions, U8Conversions};
+use std::fmt;
+use std::ops::Range;
 use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use itertools::Itertools;
 
-/// Bech32 alphabet.
-lazy_static! {
-    static ref BECH32_ALPHABET: HashMap<char, u8> =
-        hashmap!['q' => 0,'p' => 1,'z' => 2,'r' => 3,'y' => 4,'9' => 5,'x' => 6,'8' => 7,'g' => 8,
-        'f' => 9,'2' => 10,'t' => 11,'v' => 12,'d' => 13,'w' => 14,'0' => 15,'s' => 16,'3' => 17,
-        'j' => 18,'n' => 19,'5' => 20,'4' => 21,'k' => 22,'h' => 23, 'c' => 24, 'e' => 25,'6' => 26,
-        'm' => 27,'u' => 28,'a' => 29,'7' => 30,'l' => 31];
+// Types of the tagged fields known to this crate.
+const PAYMENT_HASH: U5 = tag_type('p');
+const PAYMENT_SECRET: U5 = tag_type('s');
+const DESCRIPTION: U5 = tag_type('d');
+const DESCRIPTION_HASH: U5 = tag_type('h');
+const FALLBACK_ADDRESS: U5 = tag_type('f');
+const EXPIRY: U5 = tag_type('x');
+const MIN_FINAL_CLTV_EXPIRY: U5 = tag_type('c');
+const ROUTING_INFO: U5 = tag_type('r');
+const PAYEE_NODE_ID: U5 = tag_type('n');
+const FEATURES: U5 = tag_type('9');
+const PAYMENT_METADATA: U5 = tag_type('m');
+
+// Helper for the tagged field types, value of a character of the bech32 alphabet.
+const fn tag_type(c: char) -> U5 {
+    match char_value(c as u8) {
+        Some(value) => value,
+        None => panic!("not a bech32 character"),
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 /// PaymentRequest tagged fields.
 pub enum Tag {
     /// `'p'`  256-bit SHA256 payment_hash. Preimage of this provides proof of payment.
     PaymentHash {
         /// `hash` payment hash.
-        hash: Vec<u8>,
+        hash: PaymentHash,
+    },
+
+    /// `'s'`  256-bit secret preventing forwarding nodes from probing the payment recipient.
+    PaymentSecret {
+        /// `secret` payment secret.
+        secret: PaymentSecret,
     },
 
     /// `'d'`  Short description of purpose of payment (UTF-8), e.g. '1 cup of coffee' or
@@ -39,7 +68,7 @@ pub enum Tag {
     DescriptionHash {
         /// `hash` Hash that will be included in the payment request, and can be checked against
         ///  the hash of a long description, an invoice.
-        hash: Vec<u8>,
+        hash: DescriptionHash,
     },
 
     /// `'f'`  Fallback on-chain address: for bitcoin, this starts with a 5-bit version and
@@ -48,9 +77,11 @@ pub enum Tag {
         /// `version` Address version; valid values are: <br>
         ///               - 17 (pubkey hash) <br>
         ///               - 18 (script hash) <br>
-        ///               - 0 (segwit hash: p2wpkh (20 bytes) or p2wsh (32 bytes))
+        ///               - 0 (segwit hash: p2wpkh (20 bytes) or p2wsh (32 bytes)) <br>
+        ///               - 1 to 16 (segwit witness program of 2 to 40 bytes, e.g. p2tr)
         version: u8,
         /// `hash`    Address hash
+        #[cfg_attr(feature = "serde", serde(with = "::serde_utils::hex"))]
         hash: Vec<u8>,
     },
 
@@ -60,7 +91,7 @@ pub enum Tag {
         seconds: u64,
     },
 
-    /// `'c'`  min_final_cltv_expiry to use for the last HTLC in the route. Default is 9
+    /// `'c'`  min_final_cltv_expiry to use for the last HTLC in the route. Default is 18
     /// if not specified.
     MinFinalCltvExpiry {
         /// `blocks` min_final_cltv_expiry, in blocks.
@@ -74,11 +105,44 @@ pub enum Tag {
         path: Vec<ExtraHop>,
     },
 
-    /// Unknown tag.
+    /// `'n'`  33-byte public key of the payee node.
+    PayeeNodeId {
+        /// `pub_key` Compressed public key of the payee node.
+        pub_key: NodeId,
+    },
+
+    /// `'9'`  One or more 5-bit values containing features supported or required for receiving
+    /// this payment.
+    Features {
+        /// `bits` Feature bit vector.
+        bits: Features,
+    },
+
+    /// `'m'`  Additional metadata to attach to the payment, it must be sent in the onion.
+    PaymentMetadata {
+        /// `bytes` Opaque metadata bytes.
+        #[cfg_attr(feature = "serde", serde(with = "::serde_utils::hex"))]
+        bytes: Vec<u8>,
+    },
+
+    /// Field skipped by the reader, e.g. a payment hash with an unexpected length. <br>
+    /// *Note*: the data is kept as received to be re-encoded exactly.
+    Skipped {
+        /// `tag` Tag type.
+        tag: U5,
+        /// `bytes` Raw u5 data.
+        bytes: Vec<U5>,
+        /// `reason` Why the field was skipped.
+        reason: String,
+    },
+
+    /// Unknown tag. <br>
+    /// *Note*: the data is kept as received, the declared length being the number of u5, so it
+    /// is re-encoded exactly and the signature still verifies.
     UnknownTag {
         /// `tag` Unknown tag.
         tag: U5,
-        /// `bytes` Content bytes.
+        /// `bytes` Raw u5 data.
         bytes: Vec<U5>,
     },
 }
@@ -86,68 +150,196 @@ pub enum Tag {
 impl Tag {
     /// Convert to a u5 vector.
     pub fn to_vec_u5(&self) -> Result<Vec<U5>, Error> {
-        match &self {
-            &&Tag::PaymentHash { ref hash } => {
-                let bytes = hash.to_u5_vec(true);
-                let p = BECH32_ALPHABET[&'p'];
-                Tag::vec_u5_aux(p, bytes)
-            }
-            &&Tag::Description { ref description } => {
-                let bytes = description.as_bytes().to_vec().to_u5_vec(true);
-                let d = BECH32_ALPHABET[&'d'];
-                Tag::vec_u5_aux(d, bytes)
-            }
-            &&Tag::DescriptionHash { ref hash } => {
-                let bytes = hash.to_u5_vec(true);
-                let h = BECH32_ALPHABET[&'h'];
-                Tag::vec_u5_aux(h, bytes)
-            }
-            &&Tag::FallbackAddress { version, ref hash } => {
-                let bytes = hash.to_u5_vec(true).map(|b| {
+        match *self {
+            Tag::PaymentHash { ref hash } => {
+                let bytes = hash.to_vec().to_u5_vec(Padding::Add);
+                Tag::vec_u5_aux(PAYMENT_HASH, bytes)
+            }
+            Tag::PaymentSecret { ref secret } => {
+                let bytes = secret.to_vec().to_u5_vec(Padding::Add);
+                Tag::vec_u5_aux(PAYMENT_SECRET, bytes)
+            }
+            Tag::Description { ref description } => {
+                let bytes = description.as_bytes().to_vec().to_u5_vec(Padding::Add);
+                Tag::vec_u5_aux(DESCRIPTION, bytes)
+            }
+            Tag::DescriptionHash { ref hash } => {
+                let bytes = hash.to_vec().to_u5_vec(Padding::Add);
+                Tag::vec_u5_aux(DESCRIPTION_HASH, bytes)
+            }
+            Tag::FallbackAddress { version, ref hash } => {
+                let bytes = hash.to_u5_vec(Padding::Add).map(|b| {
                     let mut data = vec![version];
                     data.extend(b);
                     data
                 });
-                let f = BECH32_ALPHABET[&'f'];
-                Tag::vec_u5_aux(f, bytes)
+                Tag::vec_u5_aux(FALLBACK_ADDRESS, bytes)
             }
-            &&Tag::Expiry { seconds } => {
+            Tag::Expiry { seconds } => {
                 let bytes = seconds.to_u5_vec();
-                let x = BECH32_ALPHABET[&'x'];
-                Tag::write_size(bytes.len()).map(|size| [vec![x], size, bytes].concat())
+                Tag::write_size(bytes.len()).map(|size| [vec![EXPIRY], size, bytes].concat())
             }
-            &&Tag::MinFinalCltvExpiry { blocks } => {
+            Tag::MinFinalCltvExpiry { blocks } => {
                 let bytes = blocks.to_u5_vec();
-                let c = BECH32_ALPHABET[&'c'];
-                Tag::write_size(bytes.len()).map(|size| [vec![c], size, bytes].concat())
+                Tag::write_size(bytes.len())
+                    .map(|size| [vec![MIN_FINAL_CLTV_EXPIRY], size, bytes].concat())
             }
-            &&Tag::RoutingInfo { ref path } => {
+            Tag::RoutingInfo { ref path } => {
                 let bytes = path.iter()
                     .map(|hop| hop.pack())
                     .fold_results(Vec::<u8>::new(), |mut acc, hop| {
                         acc.extend(hop);
                         acc
                     })
-                    .and_then(|v| v.to_u5_vec(true));
+                    .and_then(|v| v.to_u5_vec(Padding::Add));
+                Tag::vec_u5_aux(ROUTING_INFO, bytes)
+            }
+            Tag::PayeeNodeId { ref pub_key } => {
+                let bytes = pub_key.to_vec().to_u5_vec(Padding::Add);
+                Tag::vec_u5_aux(PAYEE_NODE_ID, bytes)
+            }
+            Tag::Features { ref bits } => {
+                let bytes = bits.to_words();
+                Tag::write_size(bytes.len()).map(|size| [vec![FEATURES], size, bytes].concat())
+            }
+            Tag::PaymentMetadata { ref bytes } => {
+                let bytes = bytes.to_u5_vec(Padding::Add);
+                Tag::vec_u5_aux(PAYMENT_METADATA, bytes)
+            }
+            Tag::Skipped { tag, ref bytes, .. } | Tag::UnknownTag { tag, ref bytes } => {
+                Tag::write_size(bytes.len()).map(|size| [vec![tag], size, bytes.to_owned()].concat())
+            }
+        }
+    }
+
+    /// Write the tag type, the length and the data to a bech32 writer, without the intermediate
+    /// vectors of `to_vec_u5`.
+    pub fn write_u5<W: fmt::Write>(&self, writer: &mut Bech32Writer<W>) -> Result<(), Error> {
+        let (tag, len) = match *self {
+            Tag::PaymentHash { .. } => (PAYMENT_HASH, u5_len(32)),
+            Tag::PaymentSecret { .. } => (PAYMENT_SECRET, u5_len(32)),
+            Tag::Description { ref description } => (DESCRIPTION, u5_len(description.len())),
+            Tag::DescriptionHash { .. } => (DESCRIPTION_HASH, u5_len(32)),
+            Tag::FallbackAddress { ref hash, .. } => (FALLBACK_ADDRESS, 1 + u5_len(hash.len())),
+            Tag::Expiry { seconds } => (EXPIRY, number_u5_len(seconds)),
+            Tag::MinFinalCltvExpiry { blocks } => (MIN_FINAL_CLTV_EXPIRY, number_u5_len(blocks)),
+            Tag::RoutingInfo { ref path } => {
+                (ROUTING_INFO, u5_len(path.len() * ExtraHop::CHUNK_LENGTH))
+            }
+            Tag::PayeeNodeId { .. } => (PAYEE_NODE_ID, u5_len(33)),
+            Tag::Features { ref bits } => (FEATURES, bits.words().len()),
+            Tag::PaymentMetadata { ref bytes } => (PAYMENT_METADATA, u5_len(bytes.len())),
+            Tag::Skipped { tag, ref bytes, .. } | Tag::UnknownTag { tag, ref bytes } => {
+                (tag, bytes.len())
+            }
+        };
+        if len >= 1024 {
+            return Err(Error::InvalidLength(String::from(
+                "tag data length field must be encoded on 2 5-bits u8",
+            )));
+        }
+        writer.write_u5(tag).map_err(write_error)?;
+        writer.write_number(len as u64, 2).map_err(write_error)?;
+        match *self {
+            Tag::PaymentHash { ref hash } => writer.write_bytes(hash.as_bytes()),
+            Tag::PaymentSecret { ref secret } => writer.write_bytes(secret.as_bytes()),
+            Tag::Description { ref description } => writer.write_bytes(description.as_bytes()),
+            Tag::DescriptionHash { ref hash } => writer.write_bytes(hash.as_bytes()),
+            Tag::FallbackAddress { version, ref hash } => writer
+                .write_u5(version)
+                .and_then(|_| writer.write_bytes(hash)),
+            Tag::Expiry { seconds } => writer.write_number(seconds, len),
+            Tag::MinFinalCltvExpiry { blocks } => writer.write_number(blocks, len),
+            Tag::RoutingInfo { ref path } => path.iter().try_for_each(|hop| {
+                writer.write_bytes(hop.pub_key.as_bytes())?;
+                writer.write_bytes(&u64::from(hop.short_channel_id).to_be_bytes())?;
+                writer.write_bytes(&hop.fee_base_msat.to_be_bytes())?;
+                writer.write_bytes(&hop.fee_proportional_millionths.to_be_bytes())?;
+                writer.write_bytes(&hop.cltv_expiry_delta.to_be_bytes())
+            }),
+            Tag::PayeeNodeId { ref pub_key } => writer.write_bytes(pub_key.as_bytes()),
+            Tag::Features { ref bits } => writer.write_u5_slice(bits.words()),
+            Tag::PaymentMetadata { ref bytes } => writer.write_bytes(bytes),
+            Tag::Skipped { ref bytes, .. } | Tag::UnknownTag { ref bytes, .. } => {
+                writer.write_u5_slice(bytes)
+            }
+        }
+        .and_then(|_| writer.flush_bytes())
+        .map_err(write_error)
+    }
+
+    /// Return the bech32 character of the tag type, e.g. `p` for the payment hash.
+    pub fn tag_char(&self) -> char {
+        match *self {
+            Tag::PaymentHash { .. } => 'p',
+            Tag::PaymentSecret { .. } => 's',
+            Tag::Description { .. } => 'd',
+            Tag::DescriptionHash { .. } => 'h',
+            Tag::FallbackAddress { .. } => 'f',
+            Tag::Expiry { .. } => 'x',
+            Tag::MinFinalCltvExpiry { .. } => 'c',
+            Tag::RoutingInfo { .. } => 'r',
+            Tag::PayeeNodeId { .. } => 'n',
+            Tag::Features { .. } => '9',
+            Tag::PaymentMetadata { .. } => 'm',
+            Tag::Skipped { tag, .. } | Tag::UnknownTag { tag, .. } => CHARSET[(tag & 31) as usize],
+        }
+    }
+
+    /// Return the name of the tag, e.g. `payment_hash`.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Tag::PaymentHash { .. } => "payment_hash",
+            Tag::PaymentSecret { .. } => "payment_secret",
+            Tag::Description { .. } => "description",
+            Tag::DescriptionHash { .. } => "description_hash",
+            Tag::FallbackAddress { .. } => "fallback_address",
+            Tag::Expiry { .. } => "expiry",
+            Tag::MinFinalCltvExpiry { .. } => "min_final_cltv_expiry",
+            Tag::RoutingInfo { .. } => "routing_info",
+            Tag::PayeeNodeId { .. } => "payee_node_id",
+            Tag::Features { .. } => "features",
+            Tag::PaymentMetadata { .. } => "payment_metadata",
+            Tag::Skipped { .. } => "skipped",
+            Tag::UnknownTag { .. } => "unknown",
+        }
+    }
 
-                let r = BECH32_ALPHABET[&'r'];
-                Tag::vec_u5_aux(r, bytes)
+    /// Create a fallback address tag from an address string.
+    ///
+    /// # Arguments
+    /// `address` base58check P2PKH or P2SH address, or bech32/bech32m segwit address.
+    /// `currency` the network the address must belong to.
+    pub fn from_fallback_address(address: &str, currency: &Currency) -> Result<Tag, Error> {
+        address::from_address(address, currency).map(|(version, hash)| Tag::FallbackAddress {
+            version,
+            hash,
+        })
+    }
+
+    /// Return the address string of a fallback address tag for the network, None for the other
+    /// tags.
+    ///
+    /// # Arguments
+    /// `currency` the network of the address.
+    pub fn fallback_address(&self, currency: &Currency) -> Option<Result<String, Error>> {
+        match *self {
+            Tag::FallbackAddress { version, ref hash } => {
+                Some(address::to_address(version, hash, currency))
             }
-            &&Tag::UnknownTag { tag, ref bytes } => Tag::write_size(bytes.len())
-                .map(|size| [vec![tag], size, bytes.to_owned()].concat()),
+            _ => None,
         }
     }
+
+    /// Return the number of u5 of the tag data, without the type and the length.
+    pub fn data_len(&self) -> Result<usize, Error> {
+        self.to_vec_u5().map(|v| v.len() - 3)
+    }
+
     // Helper for to_vec_u5.
     fn vec_u5_aux(value: u8, data: Result<Vec<u8>, Error>) -> Result<Vec<U5>, Error> {
-        match data {
-            Ok(bytes) => {
-                let len = bytes.len();
-                let mut vec = vec![value, (len / 32) as u8, (len % 32) as u8];
-                vec.extend(bytes);
-                Ok(vec)
-            }
-            Err(err) => Err(err),
-        }
+        let bytes = data?;
+        Tag::write_size(bytes.len()).map(|size| [vec![value], size, bytes].concat())
     }
 
     // Write the size into u5 vector
@@ -165,38 +357,76 @@ impl Tag {
 }
 
 impl Tag {
-    /// Parse a Tag from a u5 vector.
-    pub fn parse(input: &Vec<U5>) -> Result<Tag, Error> {
+    /// Parse a Tag from its u5: the type, the 2 u5 length and the data.
+    pub fn parse(input: &[U5]) -> Result<Tag, Error> {
         let tag = *input
-            .get(0)
+            .first()
             .ok_or(Error::InvalidLength("invalid vector length".to_owned()))?;
         // declared data length
-        let len = input.as_slice().get(1..3)
-            .map(|v| (v[0] * 32 + v[1]) as usize)
+        let len = input.get(1..3)
+            .map(|v| v[0] as usize * 32 + v[1] as usize)
             // check if the vector has the declared lenght
-            .and_then(|len| if len <= input.len() + 3 {Some(len)} else {None})
+            .and_then(|len| if len + 3 <= input.len() {Some(len)} else {None})
             .ok_or(Error::InvalidLength("invalid declared length".to_owned()))?;
+        // values above 31 are not 5-bits values, the type would be out of the bech32 alphabet
+        if let Some(value) = input[..len + 3].iter().find(|value| **value > 31) {
+            return Err(Error::InvalidInputValue(*value));
+        }
+
+        // readers must skip the fields without the expected length: 32 bytes hashes
+        // (32 * 8 + 4) / 5 = 52 and 33 bytes public key (33 * 8 + 1) / 5 = 53
+        let expected_len = match CHARSET.get(tag as usize) {
+            Some(&'p') | Some(&'h') | Some(&'s') => Some(52),
+            Some(&'n') => Some(53),
+            _ => None,
+        };
+        match expected_len {
+            Some(expected_len) if expected_len != len => {
+                return Ok(Tag::Skipped {
+                    tag,
+                    bytes: input[3..len + 3].to_vec(),
+                    reason: format!(
+                        "{} field must be {} 5-bits u8",
+                        CHARSET[tag as usize], expected_len
+                    ),
+                })
+            }
+            _ => (),
+        }
 
-        match tag {
-            p if p == BECH32_ALPHABET[&'p'] => {
-                let hash_result = input[3..55].to_vec().to_u8_vec(false);
-                hash_result.map(|hash| Tag::PaymentHash { hash })
+        let field = match tag {
+            PAYMENT_HASH => {
+                let hash = convert_bits_buffer(&input[3..len + 3], 5, 8, Padding::Strict)?;
+                PaymentHash::from_slice(&hash).map(|hash| Tag::PaymentHash { hash })
             }
-            d if d == BECH32_ALPHABET[&'d'] => {
-                let description_result = input[3..len + 3].to_vec().to_u8_vec(false);
+            PAYMENT_SECRET => {
+                let secret = convert_bits_buffer(&input[3..len + 3], 5, 8, Padding::Strict)?;
+                PaymentSecret::from_slice(&secret).map(|secret| Tag::PaymentSecret { secret })
+            }
+            DESCRIPTION => {
+                let description_result = input[3..len + 3].to_u8_vec(Padding::Strict);
                 description_result
                     .and_then(|v| String::from_utf8(v).map_err(Error::FromUTF8Err))
                     .map(|description| Tag::Description { description })
             }
-            h if h == BECH32_ALPHABET[&'h'] => {
-                let hash_result = input[3..len + 3].to_vec().to_u8_vec(false);
-                hash_result.map(|hash| Tag::DescriptionHash { hash })
+            DESCRIPTION_HASH => {
+                let hash = convert_bits_buffer(&input[3..len + 3], 5, 8, Padding::Strict)?;
+                DescriptionHash::from_slice(&hash).map(|hash| Tag::DescriptionHash { hash })
             }
-            f if f == BECH32_ALPHABET[&'f'] => {
+            FALLBACK_ADDRESS if len == 0 => Ok(Tag::Skipped {
+                tag,
+                bytes: vec![],
+                reason: "f field must hold a version".to_owned(),
+            }),
+            FALLBACK_ADDRESS => {
                 let version = input[3];
-                let hash_result = input[4..len + 3].to_vec().to_u8_vec(false);
+                let hash_result = input[4..len + 3].to_u8_vec(Padding::Strict);
+                // a hash length not matching the version is a warning, see
+                // PaymentRequest::validate_fallback_addresses
                 match version {
-                    v if v <= 18u8 => {
+                    0..=address::MAX_WITNESS_VERSION
+                    | address::P2PKH_VERSION
+                    | address::P2SH_VERSION => {
                         hash_result.map(|hash| Tag::FallbackAddress { version, hash })
                     }
                     _ => Ok(Tag::UnknownTag {
@@ -205,54 +435,137 @@ impl Tag {
                     }),
                 }
             }
-            r if r == BECH32_ALPHABET[&'r'] => {
-                let data_result = input[3..len + 3].to_vec().to_u8_vec(false);
+            ROUTING_INFO => {
+                let data_result = input[3..len + 3].to_u8_vec(Padding::Strict);
                 data_result
-                    .map(ExtraHop::parse_all)
+                    .and_then(ExtraHop::parse_all)
                     .map(|path| Tag::RoutingInfo { path })
             }
-            x if x == BECH32_ALPHABET[&'x'] => {
-                let seconds = input[3..len + 3].to_vec().u5_vec_to_u64(len);
-                Ok(Tag::Expiry { seconds })
+            EXPIRY => {
+                input[3..len + 3]
+                    .u5_vec_to_u64(len)
+                    .map(|seconds| Tag::Expiry { seconds })
+            }
+            MIN_FINAL_CLTV_EXPIRY => {
+                input[3..len + 3]
+                    .u5_vec_to_u64(len)
+                    .map(|blocks| Tag::MinFinalCltvExpiry { blocks })
             }
-            c if c == BECH32_ALPHABET[&'c'] => {
-                let blocks = input[3..len + 3].to_vec().u5_vec_to_u64(len);
-                Ok(Tag::MinFinalCltvExpiry { blocks })
+            PAYEE_NODE_ID => {
+                let pub_key = convert_bits_buffer(&input[3..len + 3], 5, 8, Padding::Strict)?;
+                NodeId::from_slice(&pub_key).map(|pub_key| Tag::PayeeNodeId { pub_key })
+            }
+            FEATURES => {
+                let bits = Features::from_words(input[3..len + 3].to_vec());
+                Ok(Tag::Features { bits })
+            }
+            PAYMENT_METADATA => {
+                let bytes_result = input[3..len + 3].to_u8_vec(Padding::Strict);
+                bytes_result.map(|bytes| Tag::PaymentMetadata { bytes })
             }
             _ => Ok(Tag::UnknownTag {
                 tag,
                 bytes: input[3..len + 3].to_vec(),
             }),
-        }
+        };
+        field.map_err(|e| Error::TagError {
+            tag: CHARSET[tag as usize],
+            span: None,
+            kind: Box::new(e),
+        })
+    }
+
+    /// Parse the tagged fields section, in order, reporting the position (in u5) of any malformed
+    /// field, see `Error::span`.
+    /// # Arguments
+    /// `input` Tagged fields, each is a type, a 2 u5 length and the data.
+    pub fn parse_all(input: &[U5]) -> Result<Vec<Tag>, Error> {
+        Tag::parse_fields(input, false)
     }
-    /// Parse multiple tags from a u5 vector.
-    pub fn parse_all(input: &Vec<U5>) -> Result<Vec<Tag>, Error> {
-        let mut raw_tags = Vec::<Vec<U5>>::new();
-        let mut data = &input[..];
+
+    /// Parse the tagged fields section, in order, recording the malformed fields as skipped
+    /// rather than failing, so the rest of the payment request can still be read.
+    /// # Arguments
+    /// `input` Tagged fields, each is a type, a 2 u5 length and the data.
+    pub fn parse_all_lenient(input: &[U5]) -> Result<Vec<Tag>, Error> {
+        Tag::parse_fields(input, true)
+    }
+
+    // Helper for parse_all and parse_all_lenient.
+    fn parse_fields(input: &[U5], lenient: bool) -> Result<Vec<Tag>, Error> {
+        let mut tags = Vec::<Tag>::new();
+        let mut offset = 0;
         // iterate over the input getting each tag
         // the second and third byte declare the tag length
-        while data.len() > 3 {
+        while input.len() - offset >= 3 {
+            let data = &input[offset..];
             // get the declared length of the tag
-            let len = (data[1] * 32 + data[2] + 3) as usize;
-            let tag: &[U5] = data.get(..len)
-                .ok_or(Error::InvalidLength("invalid tag length".to_owned()))?;
-            // store the tag
-            raw_tags.push(tag.to_vec());
+            let len = data[1] as usize * 32 + data[2] as usize + 3;
+            let tag: &[U5] = data.get(..len).ok_or_else(|| {
+                Tag::field_error(
+                    Error::InvalidLength(format!("declared length {} exceeds the data", len - 3)),
+                    data[0],
+                    offset..input.len(),
+                )
+            })?;
+            let tag = match Tag::parse(tag) {
+                Ok(tag) => tag,
+                Err(e) if lenient => Tag::Skipped {
+                    tag: tag[0],
+                    bytes: tag[3..].to_vec(),
+                    reason: e.to_string(),
+                },
+                Err(e) => return Err(Tag::field_error(e, tag[0], offset..offset + len)),
+            };
+            tags.push(tag);
             // continue processing the vector
-            data = &data[len..]
+            offset += len;
+        }
+        // the remaining words can't hold a tag type and length
+        if offset < input.len() {
+            return Err(Tag::field_error(
+                Error::InvalidFormat("incomplete tagged field".to_owned()),
+                input[offset],
+                offset..input.len(),
+            ));
         }
-        let tags = raw_tags.iter().flat_map(Tag::parse).collect_vec();
         Ok(tags)
     }
+
+    // Helper for parse_fields, locating the error of a field.
+    fn field_error(error: Error, tag: U5, span: Range<usize>) -> Error {
+        let kind = match error {
+            Error::TagError { kind, .. } => kind,
+            error => Box::new(error),
+        };
+        Error::TagError {
+            tag: CHARSET[(tag & 31) as usize],
+            span: Some(span),
+            kind,
+        }
+    }
+}
+
+/// Routing policy of a channel, as announced in its `channel_update`.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChannelPolicy {
+    /// Base fee in millisatoshis required for routing along this channel.
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths of a satoshi required for routing along this channel.
+    pub fee_proportional_millionths: u32,
+    /// Is this channel's cltv expiry delta.
+    pub cltv_expiry_delta: u16,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Entries containing extra routing information for a private route.
 pub struct ExtraHop {
     /// Public key (264 bits).
-    pub pub_key: Vec<u8>,
+    pub pub_key: NodeId,
     /// Channel ID of the channel.
-    pub short_channel_id: u64,
+    pub short_channel_id: ShortChannelId,
     /// Base fee in millisatoshis required for routing along this channel.
     pub fee_base_msat: u32,
     /// Proportional fee in millionths of a satoshi required for routing along this channel.
@@ -265,40 +578,206 @@ impl ExtraHop {
     /// 33 + 8 + 4 + 4 + 2
     const CHUNK_LENGTH: usize = 51;
 
+    /// Create an ExtraHop.
+    /// # Arguments
+    /// `pub_key` Public key (264 bits).
+    /// `short_channel_id` Channel ID of the channel.
+    /// `fee_base_msat` Base fee in millisatoshis.
+    /// `fee_proportional_millionths` Proportional fee in millionths of a satoshi.
+    /// `cltv_expiry_delta` Channel's cltv expiry delta.
+    pub fn new(
+        pub_key: NodeId,
+        short_channel_id: ShortChannelId,
+        fee_base_msat: u32,
+        fee_proportional_millionths: u32,
+        cltv_expiry_delta: u16,
+    ) -> ExtraHop {
+        ExtraHop {
+            pub_key,
+            short_channel_id,
+            fee_base_msat,
+            fee_proportional_millionths,
+            cltv_expiry_delta,
+        }
+    }
+
+    /// Create an ExtraHop from the routing policy of the channel.
+    /// # Arguments
+    /// `pub_key` Public key (264 bits).
+    /// `short_channel_id` Channel ID of the channel.
+    /// `policy` Routing policy of the channel.
+    pub fn from_policy(
+        pub_key: NodeId,
+        short_channel_id: ShortChannelId,
+        policy: &ChannelPolicy,
+    ) -> ExtraHop {
+        ExtraHop::new(
+            pub_key,
+            short_channel_id,
+            policy.fee_base_msat,
+            policy.fee_proportional_millionths,
+            policy.cltv_expiry_delta,
+        )
+    }
+
+    /// Return the public key of the node.
+    pub fn pub_key(&self) -> &NodeId {
+        &self.pub_key
+    }
+
+    /// Return the channel ID of the channel.
+    pub fn short_channel_id(&self) -> ShortChannelId {
+        self.short_channel_id
+    }
+
+    /// Return the base fee in millisatoshis.
+    pub fn fee_base_msat(&self) -> u32 {
+        self.fee_base_msat
+    }
+
+    /// Return the proportional fee in millionths of a satoshi.
+    pub fn fee_proportional_millionths(&self) -> u32 {
+        self.fee_proportional_millionths
+    }
+
+    /// Return the channel's cltv expiry delta.
+    pub fn cltv_expiry_delta(&self) -> u16 {
+        self.cltv_expiry_delta
+    }
+
+    /// Return the routing policy of the channel.
+    pub fn policy(&self) -> ChannelPolicy {
+        ChannelPolicy {
+            fee_base_msat: self.fee_base_msat,
+            fee_proportional_millionths: self.fee_proportional_millionths,
+            cltv_expiry_delta: self.cltv_expiry_delta,
+        }
+    }
+
+    /// Return the fee in millisatoshis charged by the node for forwarding the amount over the
+    /// channel: `fee_base_msat + amount_msat * fee_proportional_millionths / 1_000_000`.
+    /// # Arguments
+    /// `amount_msat` Amount to forward in millisatoshis.
+    pub fn fee_msat(&self, amount_msat: u64) -> u64 {
+        let proportional =
+            amount_msat.saturating_mul(self.fee_proportional_millionths as u64) / 1_000_000;
+        (self.fee_base_msat as u64).saturating_add(proportional)
+    }
+
+    /// Create an ExtraHop from a short channel id formatted as `block:tx:output`.
+    /// # Arguments
+    /// `pub_key` Public key (264 bits).
+    /// `short_channel_id` Channel ID of the channel, formatted as `block:tx:output` or `BxTxO`.
+    /// `fee_base_msat` Base fee in millisatoshis.
+    /// `fee_proportional_millionths` Proportional fee in millionths of a satoshi.
+    /// `cltv_expiry_delta` Channel's cltv expiry delta.
+    pub fn from_scid_string(
+        pub_key: NodeId,
+        short_channel_id: &str,
+        fee_base_msat: u32,
+        fee_proportional_millionths: u32,
+        cltv_expiry_delta: u16,
+    ) -> Result<ExtraHop, Error> {
+        Ok(ExtraHop::new(
+            pub_key,
+            short_channel_id.parse()?,
+            fee_base_msat,
+            fee_proportional_millionths,
+            cltv_expiry_delta,
+        ))
+    }
+
+    /// Parse a short channel id formatted as `block:tx:output` or `BxTxO` into its u64
+    /// representation. The block height and transaction index take 24 bits and the output
+    /// index 16 bits.
+    pub fn scid_from_string(s: &str) -> Result<u64, Error> {
+        s.parse::<ShortChannelId>().map(u64::from)
+    }
+
     /// Pack into Vec<u8>.
     pub fn pack(&self) -> Result<Vec<u8>, Error> {
         let mut wtr: Vec<u8> = vec![];
-        wtr.write_u64::<BigEndian>(self.short_channel_id)?;
+        wtr.write_u64::<BigEndian>(u64::from(self.short_channel_id))?;
         wtr.write_u32::<BigEndian>(self.fee_base_msat)?;
         wtr.write_u32::<BigEndian>(self.fee_proportional_millionths)?;
         wtr.write_u16::<BigEndian>(self.cltv_expiry_delta)?;
-        Ok([self.pub_key.to_owned(), wtr].concat())
+        Ok([self.pub_key.to_vec(), wtr].concat())
     }
 
-    /// Parse a u8 slice into an ExtraHop.
-    pub fn parse(data: &[u8]) -> ExtraHop {
-        let pub_key = data[0..33].to_owned();
-        let short_channel_id = BigEndian::read_u64(&data[33..41]);
+    /// Parse a u8 slice into an ExtraHop, the public key must be a valid node id.
+    pub fn parse(data: &[u8]) -> Result<ExtraHop, Error> {
+        if data.len() < ExtraHop::CHUNK_LENGTH {
+            return Err(Error::InvalidLength(format!(
+                "extra hop of {} bytes, expected {}",
+                data.len(),
+                ExtraHop::CHUNK_LENGTH
+            )));
+        }
+        let pub_key = NodeId::from_slice(&data[0..33])?;
+        let short_channel_id = ShortChannelId::from(BigEndian::read_u64(&data[33..41]));
         let fee_base_msat = BigEndian::read_u32(&data[41..45]);
         let fee_proportional_millionths = BigEndian::read_u32(&data[45..49]);
         let cltv_expiry_delta = BigEndian::read_u16(&data[49..ExtraHop::CHUNK_LENGTH]);
-        ExtraHop {
+        Ok(ExtraHop {
             pub_key,
             short_channel_id,
             fee_base_msat,
             fee_proportional_millionths,
             cltv_expiry_delta,
-        }
+        })
     }
 
     /// Parse a vec<u8> into a vec<ExtraHop>.
-    pub fn parse_all(data: Vec<u8>) -> Vec<ExtraHop> {
-        data
-            .chunks(ExtraHop::CHUNK_LENGTH)
-            // the last chunk may be shorter if there's not enough elements
-            .filter(|c| c.len() == ExtraHop::CHUNK_LENGTH)
-            .map(ExtraHop::parse)
-            .collect_vec()
+    /// *Note*: data that is not a multiple of 51 bytes is rejected, rather than dropping the
+    /// last incomplete hop.
+    pub fn parse_all(data: Vec<u8>) -> Result<Vec<ExtraHop>, Error> {
+        match data.len() % ExtraHop::CHUNK_LENGTH {
+            0 => data.chunks(ExtraHop::CHUNK_LENGTH)
+                .map(ExtraHop::parse)
+                .collect(),
+            trailing => Err(Error::TrailingRouteHintBytes(trailing)),
+        }
+    }
+}
+
+/// Return the total fee in millisatoshis charged by the hops for routing the amount.
+/// # Arguments
+/// `hops` Extra routing information.
+/// `amount_msat` Amount to route in millisatoshis.
+/// *Note*: every hop is charged on the amount itself, see `RouteHint::total_fee_msat` for the
+/// fees of the hops of a single route.
+pub fn route_fee_for_amount(hops: &[ExtraHop], amount_msat: u64) -> u64 {
+    hops.iter()
+        .fold(0u64, |fee, hop| fee.saturating_add(hop.fee_msat(amount_msat)))
+}
+
+/// Private route to the payee, the extra hops of a routing info field.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RouteHint(pub Vec<ExtraHop>);
+
+impl RouteHint {
+    /// Return the extra hops of the route.
+    pub fn hops(&self) -> &[ExtraHop] {
+        &self.0
+    }
+
+    /// Return the total fee in millisatoshis for delivering the amount to the payee through
+    /// the route. <br>
+    /// *Note*: each node also forwards the fees of the following nodes, so the fees are
+    /// computed from the last hop back to the first one.
+    /// # Arguments
+    /// `amount_msat` Amount to deliver to the payee in millisatoshis.
+    pub fn total_fee_msat(&self, amount_msat: u64) -> u64 {
+        self.0.iter().rev().fold(0u64, |fee, hop| {
+            fee.saturating_add(hop.fee_msat(amount_msat.saturating_add(fee)))
+        })
+    }
+}
+
+impl From<Vec<ExtraHop>> for RouteHint {
+    fn from(path: Vec<ExtraHop>) -> RouteHint {
+        RouteHint(path)
     }
 }
 
@@ -307,6 +786,17 @@ mod test {
     use super::*;
     use utils::from_hex;
 
+    #[test]
+    fn tag_types() {
+        let types = [PAYMENT_HASH, DESCRIPTION, DESCRIPTION_HASH, FALLBACK_ADDRESS, EXPIRY];
+        assert_eq!(types.iter().map(|&t| CHARSET[t as usize]).collect::<String>(), "pdhfx");
+        let types = [MIN_FINAL_CLTV_EXPIRY, ROUTING_INFO, PAYEE_NODE_ID, FEATURES, PAYMENT_METADATA];
+        assert_eq!(types.iter().map(|&t| CHARSET[t as usize]).collect::<String>(), "crn9m");
+        assert_eq!(char_value(b'P'), Some(PAYMENT_HASH));
+        assert_eq!(char_value(b'b'), None);
+        assert_eq!(char_value(200), None);
+    }
+
     #[test]
     fn payment_hash_tag() {
         let u5_payment_hash_tag = vec![
@@ -317,13 +807,242 @@ mod test {
         assert_eq!(
             Tag::parse(&u5_payment_hash_tag).unwrap(),
             Tag::PaymentHash {
-                hash: ::utils::from_hex(
-                    "0001020304050607080900010203040506070809000102030405060708090102"
-                ).unwrap(),
+                hash: "0001020304050607080900010203040506070809000102030405060708090102"
+                    .parse()
+                    .unwrap(),
             }
         );
     }
 
+    #[test]
+    fn payment_hash_tag_with_unexpected_length() {
+        // 51 and 53 u5 payment hashes are skipped
+        for len in &[51u8, 53] {
+            let u5_tag = [vec![1u8, len / 32, len % 32], vec![0u8; *len as usize]].concat();
+            let tag = Tag::parse(&u5_tag).unwrap();
+
+            assert!(!matches!(tag, Tag::PaymentHash { .. }));
+            assert_eq!(tag.to_vec_u5().unwrap(), u5_tag);
+        }
+
+        // non-zero padding bits
+        let u5_tag = [vec![1u8, 1, 20], vec![0u8; 51], vec![1]].concat();
+        assert!(Tag::parse(&u5_tag).is_err());
+    }
+
+    #[test]
+    fn skipped_tags() {
+        let expiry = Tag::Expiry { seconds: 60 }.to_vec_u5().unwrap();
+        // 51 u5 description hash, 0 u5 fallback address and invalid description
+        let u5_tags = [
+            vec![23u8, 1, 19],
+            vec![0u8; 51],
+            vec![9, 0, 0],
+            vec![13, 0, 2, 31, 31],
+            expiry,
+        ].concat();
+
+        assert!(Tag::parse_all(&u5_tags).is_err());
+
+        let tags = Tag::parse_all_lenient(&u5_tags).unwrap();
+        let skipped = tags.iter()
+            .filter_map(|tag| match *tag {
+                Tag::Skipped { tag, ref reason, .. } => Some((CHARSET[tag as usize], reason)),
+                _ => None,
+            })
+            .collect_vec();
+
+        assert_eq!(tags.len(), 4);
+        assert_eq!(tags[3], Tag::Expiry { seconds: 60 });
+        assert_eq!(skipped.len(), 3);
+        assert_eq!(skipped[0].0, 'h');
+        assert_eq!(skipped[1].0, 'f');
+        assert_eq!(skipped[2].0, 'd');
+        assert_eq!(
+            tags.iter()
+                .map(|tag| tag.to_vec_u5().unwrap())
+                .collect_vec()
+                .concat(),
+            u5_tags
+        );
+    }
+
+    #[test]
+    fn parse_all_trailing_data() {
+        let u5_tags = vec![24u8, 0, 1, 12, 0, 1];
+
+        let error = Tag::parse_all(&u5_tags).unwrap_err();
+
+        assert!(matches!(error, Error::TagError { tag: 'q', .. }));
+        assert_eq!(error.span(), Some(4..6));
+        assert_eq!(
+            Tag::parse_all(&u5_tags[..4]).unwrap(),
+            vec![Tag::MinFinalCltvExpiry { blocks: 12 }]
+        );
+    }
+
+    #[test]
+    fn parse_all_offsets() {
+        let expiry = Tag::Expiry { seconds: 60 }.to_vec_u5().unwrap();
+        let description = Tag::Description {
+            description: "ナンセンス 1杯".to_owned(),
+        }.to_vec_u5()
+            .unwrap();
+        let u5_tags = [expiry.clone(), description.clone(), expiry.clone()].concat();
+
+        assert_eq!(
+            Tag::parse_all(&u5_tags).unwrap(),
+            vec![
+                Tag::Expiry { seconds: 60 },
+                Tag::Description {
+                    description: "ナンセンス 1杯".to_owned(),
+                },
+                Tag::Expiry { seconds: 60 },
+            ]
+        );
+
+        // malformed description after the expiry
+        let invalid = [expiry.clone(), vec![13, 0, 2, 31, 31]].concat();
+        let error = Tag::parse_all(&invalid).unwrap_err();
+
+        assert!(matches!(error, Error::TagError { tag: 'd', .. }));
+        assert_eq!(error.span(), Some(5..10));
+
+        // declared length past the end of the data
+        let truncated = [expiry.clone(), vec![6, 1, 0, 1]].concat();
+        let error = Tag::parse_all(&truncated).unwrap_err();
+
+        assert!(matches!(error, Error::TagError { tag: 'x', .. }));
+        assert_eq!(error.span(), Some(5..9));
+    }
+
+    #[test]
+    fn long_description_tag() {
+        let tag = Tag::Description {
+            description: "x".repeat(600),
+        };
+        let u5_tag = tag.to_vec_u5().unwrap();
+
+        assert_eq!(u5_tag.len(), 963);
+        assert_eq!(Tag::parse_all(&u5_tag).unwrap(), vec![tag]);
+    }
+
+    #[test]
+    fn unknown_tag_round_trip() {
+        for len in &[0usize, 1, 31, 32, 33, 1023] {
+            // `v` tag, not defined by BOLT #11
+            let u5_tag = [
+                vec![12u8, (len / 32) as u8, (len % 32) as u8],
+                (0..*len).map(|i| (i % 32) as u8).collect(),
+            ].concat();
+            let tag = Tag::parse(&u5_tag).unwrap();
+
+            assert_eq!(tag.tag_char(), 'v');
+            assert_eq!(tag.to_vec_u5().unwrap(), u5_tag);
+        }
+    }
+
+    #[test]
+    fn empty_routing_info_tag() {
+        let u5_routing_info_tag = vec![3u8, 0, 0];
+
+        assert_eq!(
+            Tag::parse(&u5_routing_info_tag).unwrap(),
+            Tag::RoutingInfo { path: vec![] }
+        );
+        assert_eq!(
+            Tag::parse_all(&u5_routing_info_tag).unwrap(),
+            vec![Tag::RoutingInfo { path: vec![] }]
+        );
+        assert_eq!(
+            Tag::RoutingInfo { path: vec![] }.to_vec_u5().unwrap(),
+            u5_routing_info_tag
+        );
+    }
+
+    #[test]
+    fn payee_node_id_tag() {
+        let pub_key = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+            .parse::<NodeId>()
+            .unwrap();
+        let tag = Tag::PayeeNodeId { pub_key };
+        let u5_tag = tag.to_vec_u5().unwrap();
+
+        assert_eq!(u5_tag.len(), 56);
+        assert_eq!(Tag::parse(&u5_tag).unwrap(), tag);
+
+        let mut u5_short_tag = u5_tag[..55].to_vec();
+        u5_short_tag[2] = 20;
+        assert!(matches!(
+            Tag::parse(&u5_short_tag).unwrap(),
+            Tag::Skipped { tag: 19, .. }
+        ));
+    }
+
+    #[test]
+    fn features_tag() {
+        let mut bits = Features::new();
+        bits.set_bit(Features::VAR_ONION_OPTIN);
+        bits.set_bit(Features::PAYMENT_SECRET);
+        let tag = Tag::Features { bits };
+        let u5_tag = tag.to_vec_u5().unwrap();
+
+        assert_eq!(u5_tag, vec![5, 0, 3, 16, 8, 0]);
+        assert_eq!(Tag::parse(&u5_tag).unwrap(), tag);
+    }
+
+    #[test]
+    fn payment_secret_tag() {
+        let u5_payment_secret_tag = format!("sp5{}zygs", "zyg3".repeat(12))
+            .bytes()
+            .map(|c| char_value(c).unwrap())
+            .collect::<Vec<_>>();
+        let tag = Tag::PaymentSecret {
+            secret: PaymentSecret::from([0x11; 32]),
+        };
+
+        assert_eq!(Tag::parse(&u5_payment_secret_tag).unwrap(), tag);
+        assert_eq!(tag.to_vec_u5().unwrap(), u5_payment_secret_tag);
+        assert_eq!(tag.name(), "payment_secret");
+    }
+
+    #[test]
+    fn payment_metadata_tag() {
+        let u5_payment_metadata_tag = vec![27u8, 0, 7, 0, 7, 29, 15, 21, 28, 0];
+        let tag = Tag::PaymentMetadata {
+            bytes: from_hex("01fafaf0").unwrap(),
+        };
+
+        assert_eq!(Tag::parse(&u5_payment_metadata_tag).unwrap(), tag);
+        assert_eq!(tag.to_vec_u5().unwrap(), u5_payment_metadata_tag);
+    }
+
+    #[test]
+    fn tag_metadata() {
+        let tag = Tag::PaymentHash {
+            hash: PaymentHash::from([0u8; 32]),
+        };
+
+        assert_eq!(tag.tag_char(), 'p');
+        assert_eq!(tag.name(), "payment_hash");
+        assert_eq!(tag.data_len().unwrap(), 52);
+
+        let tag = Tag::Expiry { seconds: 60 };
+
+        assert_eq!(tag.tag_char(), 'x');
+        assert_eq!(tag.name(), "expiry");
+        assert_eq!(tag.data_len().unwrap(), 2);
+
+        let tag = Tag::UnknownTag {
+            tag: 17,
+            bytes: vec![1, 2, 3],
+        };
+
+        assert_eq!(tag.tag_char(), '3');
+        assert_eq!(tag.name(), "unknown");
+        assert_eq!(tag.data_len().unwrap(), 3);
+    }
+
     #[test]
     fn description_tag() {
         let u5_description_tag = vec![
@@ -340,6 +1059,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn description_tag_with_padding() {
+        // "1 cup coffee": 12 bytes encoded on 20 u5, the last 4 bits are padding
+        let u5_description_tag = vec![
+            13u8, 0, 20, 6, 4, 16, 6, 6, 29, 11, 16, 4, 1, 17, 22, 30, 25, 19, 6, 12, 21, 18, 16,
+        ];
+
+        assert_eq!(
+            Tag::parse(&u5_description_tag).unwrap(),
+            Tag::Description {
+                description: "1 cup coffee".to_owned(),
+            }
+        );
+    }
+
     #[test]
     fn description_hash_tag() {
         let u5_description_hash_tag = vec![
@@ -351,7 +1085,8 @@ mod test {
         assert_eq!(
             Tag::parse(&u5_description_hash_tag).unwrap(),
             Tag::DescriptionHash {
-                hash: from_hex("3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1")
+                hash: "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1"
+                    .parse()
                     .unwrap(),
             }
         );
@@ -373,6 +1108,56 @@ mod test {
         );
     }
 
+    #[test]
+    fn fallback_address_tag_witness_program_length() {
+        let tag = |version: u8, len: usize| {
+            let mut data = vec![version];
+            data.extend(vec![0u8; len].to_u5_vec(Padding::Add).unwrap());
+            [vec![9u8, (data.len() / 32) as u8, (data.len() % 32) as u8], data].concat()
+        };
+
+        assert!(Tag::parse(&tag(0, 20)).is_ok());
+        // kept, the length is checked by PaymentRequest::validate_fallback_addresses
+        assert_eq!(
+            Tag::parse(&tag(0, 25)).unwrap(),
+            Tag::FallbackAddress {
+                version: 0,
+                hash: vec![0u8; 25],
+            }
+        );
+        assert_eq!(
+            Tag::parse(&tag(1, 32)).unwrap(),
+            Tag::FallbackAddress {
+                version: 1,
+                hash: vec![0u8; 32],
+            }
+        );
+        assert!(matches!(
+            Tag::parse(&tag(16, 41)).unwrap(),
+            Tag::FallbackAddress { version: 16, .. }
+        ));
+        assert!(matches!(
+            Tag::parse(&tag(19, 20)).unwrap(),
+            Tag::UnknownTag { .. }
+        ));
+    }
+
+    #[test]
+    fn fallback_address_tag_conversion() {
+        let address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+        let tag = Tag::from_fallback_address(address, &Currency::Bitcoin).unwrap();
+
+        assert!(matches!(tag, Tag::FallbackAddress { version: 1, .. }));
+        assert_eq!(
+            tag.fallback_address(&Currency::Bitcoin).unwrap().unwrap(),
+            address
+        );
+        assert!(Tag::from_fallback_address(address, &Currency::Testnet).is_err());
+        assert!(Tag::Expiry { seconds: 60 }
+            .fallback_address(&Currency::Bitcoin)
+            .is_none());
+    }
+
     #[test]
     fn expiry_tag() {
         let u5_expiry_tag = vec![6u8, 0, 2, 1, 28];
@@ -382,6 +1167,46 @@ mod test {
         );
     }
 
+    #[test]
+    fn expiry_tag_overflow() {
+        // 13 u5 hold up to 65 bits, leading zeroes are accepted
+        let max = [vec![6u8, 0, 13, 15], vec![31; 12]].concat();
+        assert_eq!(
+            Tag::parse(&max).unwrap(),
+            Tag::Expiry { seconds: u64::MAX }
+        );
+        let padded = [vec![6u8, 0, 20], vec![0; 19], vec![1]].concat();
+        assert_eq!(Tag::parse(&padded).unwrap(), Tag::Expiry { seconds: 1 });
+
+        let overflow = [vec![6u8, 0, 13, 16], vec![0; 12]].concat();
+        assert!(matches!(
+            Tag::parse(&overflow),
+            Err(Error::TagError { tag: 'x', .. })
+        ));
+        let overflow = [vec![24u8, 0, 31], vec![31; 31]].concat();
+        assert!(matches!(
+            Tag::parse(&overflow),
+            Err(Error::TagError { tag: 'c', .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_u5() {
+        assert!(matches!(
+            Tag::parse(&[32u8, 0, 0]),
+            Err(Error::InvalidInputValue(32))
+        ));
+        assert!(matches!(
+            Tag::parse(&[6u8, 0, 1, 255]),
+            Err(Error::InvalidInputValue(255))
+        ));
+        // values past the field are not read
+        assert_eq!(
+            Tag::parse(&[6u8, 0, 1, 1, 255]).unwrap(),
+            Tag::Expiry { seconds: 1 }
+        );
+    }
+
     #[test]
     fn min_final_cltv_expiry_tag() {
         let u5_min_final_cltv_expiry_tag = vec![24u8, 0, 1, 12];
@@ -409,19 +1234,21 @@ mod test {
             Tag::RoutingInfo {
                 path: vec![
                     ExtraHop {
-                        pub_key: from_hex(
-                            "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                        ).unwrap(),
-                        short_channel_id: 72623859790382856,
+                        pub_key: "029e03a901b85534ff1e92c43c74431f7c\
+                            e72046060fcf7a95c37e148f78c77255"
+                            .parse::<NodeId>()
+                            .unwrap(),
+                        short_channel_id: ShortChannelId::from(72623859790382856),
                         fee_base_msat: 1,
                         fee_proportional_millionths: 20,
                         cltv_expiry_delta: 3,
                     },
                     ExtraHop {
-                        pub_key: from_hex(
-                            "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                        ).unwrap(),
-                        short_channel_id: 217304205466536202,
+                        pub_key: "039e03a901b85534ff1e92c43c74431f7c\
+                            e72046060fcf7a95c37e148f78c77255"
+                            .parse::<NodeId>()
+                            .unwrap(),
+                        short_channel_id: ShortChannelId::from(217304205466536202),
                         fee_base_msat: 2,
                         fee_proportional_millionths: 30,
                         cltv_expiry_delta: 4,
@@ -430,4 +1257,164 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn routing_info_tag_with_trailing_bytes() {
+        let hop = ExtraHop::new(
+            "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+                .parse()
+                .unwrap(),
+            ShortChannelId::from(1),
+            2,
+            30,
+            4,
+        );
+        let data = [hop.pack().unwrap(), vec![0u8; 10]].concat();
+
+        assert_eq!(ExtraHop::parse_all(data[..51].to_vec()).unwrap(), vec![hop]);
+        assert!(matches!(
+            ExtraHop::parse_all(data.clone()),
+            Err(Error::TrailingRouteHintBytes(10))
+        ));
+        assert!(matches!(
+            ExtraHop::parse(&data[..50]),
+            Err(Error::InvalidLength(_))
+        ));
+
+        let data = data.to_u5_vec(Padding::Add).unwrap();
+        let len = data.len() as u8;
+        let u5_routing_info_tag = [vec![3u8, len / 32, len % 32], data].concat();
+
+        match Tag::parse(&u5_routing_info_tag) {
+            Err(Error::TagError { tag, kind, .. }) => {
+                assert_eq!(tag, 'r');
+                assert!(matches!(*kind, Error::TrailingRouteHintBytes(10)));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn routing_info_tag_with_invalid_pub_key() {
+        let data = [vec![5u8; 33], vec![0u8; 18]].concat().to_u5_vec(Padding::Add).unwrap();
+        let len = data.len() as u8;
+        let u5_routing_info_tag = [vec![3u8, len / 32, len % 32], data].concat();
+
+        match Tag::parse(&u5_routing_info_tag) {
+            Err(Error::TagError { tag, kind, .. }) => {
+                assert_eq!(tag, 'r');
+                assert!(matches!(*kind, Error::InvalidValue(_)));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn route_fee() {
+        let hops = vec![
+            ExtraHop {
+                pub_key: "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+                    .parse::<NodeId>()
+                    .unwrap(),
+                short_channel_id: ShortChannelId::from(72623859790382856),
+                fee_base_msat: 1,
+                fee_proportional_millionths: 20,
+                cltv_expiry_delta: 3,
+            },
+            ExtraHop {
+                pub_key: "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+                    .parse::<NodeId>()
+                    .unwrap(),
+                short_channel_id: ShortChannelId::from(217304205466536202),
+                fee_base_msat: 2,
+                fee_proportional_millionths: 30,
+                cltv_expiry_delta: 4,
+            },
+        ];
+
+        // (1 + 2) + (2 + 3)
+        assert_eq!(route_fee_for_amount(&hops, 100_000), 8);
+        assert_eq!(route_fee_for_amount(&[], 100_000), 0);
+        // the proportional fee of each hop saturates
+        assert_eq!(
+            route_fee_for_amount(&hops, u64::MAX),
+            2 * (u64::MAX / 1_000_000) + 3
+        );
+    }
+
+    #[test]
+    fn extra_hop_from_policy() {
+        let pub_key = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+            .parse::<NodeId>()
+            .unwrap();
+        let policy = ChannelPolicy {
+            fee_base_msat: 1,
+            fee_proportional_millionths: 20,
+            cltv_expiry_delta: 3,
+        };
+        let short_channel_id = ShortChannelId::from(72623859790382856);
+        let hop = ExtraHop::from_policy(pub_key, short_channel_id, &policy);
+
+        assert_eq!(hop, ExtraHop::new(pub_key, short_channel_id, 1, 20, 3));
+        assert_eq!(hop.pub_key(), &pub_key);
+        assert_eq!(hop.short_channel_id(), short_channel_id);
+        assert_eq!(hop.fee_base_msat(), 1);
+        assert_eq!(hop.fee_proportional_millionths(), 20);
+        assert_eq!(hop.cltv_expiry_delta(), 3);
+        assert_eq!(hop.policy(), policy);
+    }
+
+    #[test]
+    fn route_hint_fee() {
+        let pub_key = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+            .parse::<NodeId>()
+            .unwrap();
+        let first = ExtraHop::new(pub_key, ShortChannelId::from(1), 1, 20, 3);
+        let second = ExtraHop::new(pub_key, ShortChannelId::from(2), 1000, 100_000, 4);
+
+        assert_eq!(first.fee_msat(100_000), 3);
+        assert_eq!(second.fee_msat(100_000), 11_000);
+        assert_eq!(first.fee_msat(u64::MAX), 1 + u64::MAX / 1_000_000);
+
+        let route = RouteHint::from(vec![first, second]);
+
+        assert_eq!(route.hops().len(), 2);
+        // the first hop forwards 100_000 + 11_000
+        assert_eq!(route.total_fee_msat(100_000), 11_000 + 3);
+        assert_eq!(RouteHint::default().total_fee_msat(100_000), 0);
+    }
+
+    #[test]
+    fn scid_from_string() {
+        assert_eq!(
+            ExtraHop::scid_from_string("100:200:3").unwrap(),
+            (100 << 40) | (200 << 16) | 3
+        );
+        assert_eq!(
+            ExtraHop::scid_from_string("66051:263430:1800").unwrap(),
+            72623859790382856
+        );
+        assert!(ExtraHop::scid_from_string("16777216:200:3").is_err());
+        assert!(ExtraHop::scid_from_string("100:200:65536").is_err());
+        assert!(ExtraHop::scid_from_string("100:200").is_err());
+        assert!(ExtraHop::scid_from_string("100:x:3").is_err());
+    }
+
+    #[test]
+    fn extra_hop_from_scid_string() {
+        let pub_key = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+            .parse::<NodeId>()
+            .unwrap();
+
+        assert_eq!(
+            ExtraHop::from_scid_string(pub_key, "66051:263430:1800", 1, 20, 3).unwrap(),
+            ExtraHop {
+                pub_key,
+                short_channel_id: ShortChannelId::from(72623859790382856),
+                fee_base_msat: 1,
+                fee_proportional_millionths: 20,
+                cltv_expiry_delta: 3,
+            }
+        );
+    }
 }
@@ -0,0 +1,121 @@
+//! Deterministic fixtures for the unit tests of the crates built on this one: canned signing
+//! keys, preimages, payment hashes and signed payment requests, so payment flows can be tested
+//! without copying hex strings around. <br>
+//! The same index always gives the same key, preimage and payment hash, and `fake_invoice` always
+//! gives the same payment request for the same arguments, signing being deterministic. <br>
+//! *Note*: the module is only built with the `test-utils` feature, the keys are public and must
+//! never hold funds.
+
+use currency::Currency;
+use hashes::{PaymentHash, PaymentPreimage};
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use secp256k1::{PublicKey, SecretKey};
+
+/// Secret key of the node signing the examples of the BOLT 11 appendix.
+pub const SECRET_KEY: [u8; 32] = [
+    0xe1, 0x26, 0xf6, 0x8f, 0x7e, 0xaf, 0xcc, 0x8b, 0x74, 0xf5, 0x4d, 0x26, 0x9f, 0xe2, 0x06, 0xbe,
+    0x71, 0x50, 0x00, 0xf9, 0x4d, 0xac, 0x06, 0x7d, 0x1c, 0x04, 0xa8, 0xca, 0x3b, 0x2d, 0xb7, 0x34,
+];
+
+/// Node id of `SECRET_KEY`, hex encoded.
+pub const NODE_ID: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+
+/// Timestamp of the payment requests of `fake_invoice`, the one of the BOLT 11 appendix.
+pub const TIMESTAMP: u64 = 1496314658;
+
+/// Return the secret key of `SECRET_KEY`.
+pub fn secret_key() -> SecretKey {
+    SecretKey::parse(&SECRET_KEY).expect("the canned secret key is valid")
+}
+
+/// Return a canned secret key, every byte being 1 but the last one, `index`, e.g. to give a key
+/// to each node of a test.
+/// # Arguments
+/// * `index` - Index of the key.
+pub fn signing_key(index: u8) -> SecretKey {
+    let mut key = [1u8; 32];
+    key[31] = index;
+    SecretKey::parse(&key).expect("the canned secret keys are valid")
+}
+
+/// Return the node id of a canned secret key.
+/// # Arguments
+/// * `index` - Index of the key, see `signing_key`.
+pub fn node_id(index: u8) -> NodeId {
+    NodeId::from(&PublicKey::from_secret_key(&signing_key(index)))
+}
+
+/// Return a canned preimage, every byte being `index`.
+/// # Arguments
+/// * `index` - Index of the preimage.
+pub fn preimage(index: u8) -> [u8; 32] {
+    [index; 32]
+}
+
+/// Return the payment hash of a canned preimage, its SHA256.
+/// # Arguments
+/// * `index` - Index of the preimage, see `preimage`.
+pub fn payment_hash(index: u8) -> PaymentHash {
+    PaymentPreimage::from(preimage(index)).payment_hash()
+}
+
+/// Return a bitcoin payment request signed with `SECRET_KEY`, paying the payment hash of
+/// `preimage(0)` and timestamped with `TIMESTAMP`.
+/// # Arguments
+/// * `amount` - Amount in millisatoshis, `None` for a donation.
+/// * `description` - Description of the payment.
+pub fn fake_invoice(amount: Option<u64>, description: &str) -> PaymentRequest {
+    PaymentRequest::new(
+        Currency::Bitcoin.prefix().to_owned(),
+        amount,
+        payment_hash(0),
+        &secret_key(),
+        description.to_owned(),
+        None,
+        None,
+        vec![],
+        Some(TIMESTAMP),
+        None,
+    ).expect("the amount of a fake invoice must not exceed the bitcoin supply")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::to_hex;
+
+    #[test]
+    fn keys() {
+        assert_eq!(NodeId::from(&PublicKey::from_secret_key(&secret_key())).to_string(), NODE_ID);
+        assert_eq!(node_id(1), node_id(1));
+        assert!(node_id(1) != node_id(2));
+        assert!(node_id(0) != node_id(255));
+    }
+
+    #[test]
+    fn payment_hashes() {
+        // SHA256 of 32 zero bytes
+        assert_eq!(
+            payment_hash(0).to_string(),
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925"
+        );
+        assert_eq!(to_hex(&preimage(7)), "07".repeat(32));
+    }
+
+    #[test]
+    fn fake_invoices() {
+        let invoice = fake_invoice(Some(250_000_000), "1 cup coffee");
+        let encoded = invoice.encode().unwrap();
+        let decoded = PaymentRequest::decode(&encoded).unwrap();
+
+        assert_eq!(decoded, invoice);
+        assert_eq!(decoded.amount, Some(250_000_000));
+        assert_eq!(decoded.timestamp, TIMESTAMP);
+        assert_eq!(decoded.payment_hash(), Some(payment_hash(0)));
+        assert_eq!(decoded.description(), Some("1 cup coffee".to_owned()));
+        assert_eq!(NodeId::from(&decoded.node_id).to_string(), NODE_ID);
+        assert_eq!(fake_invoice(Some(250_000_000), "1 cup coffee").encode().unwrap(), encoded);
+        assert_eq!(fake_invoice(None, "donation").amount, None);
+    }
+}
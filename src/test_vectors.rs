@@ -0,0 +1,477 @@
+//! Examples of the BOLT 11 appendix, as a conformance suite for the crate and the applications
+//! built on it. <br>
+//! The valid examples are signed by the spec node, carry a payment secret and must decode, verify
+//! and encode back to the same string, the invalid ones must be rejected for the reason given by
+//! the spec. <br>
+//! *Note*: the module is only built with the `test-vectors` feature. The "too short" and "missing
+//! `s`" examples are built with the spec node key from the donation example, their description
+//! says how.
+
+use features::Features;
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use types::{AmountError, Error, ValidationError};
+use bech32;
+
+/// Node signing the examples.
+pub const NODE_ID: &str = "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad";
+
+/// Timestamp of the examples, but the pico-BTC one.
+pub const TIMESTAMP: u64 = 1496314658;
+
+/// Reason of the spec for rejecting an invalid example.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidReason {
+    /// Bech32 checksum is invalid.
+    InvalidChecksum,
+    /// Malformed bech32 string, no `1` separator.
+    MissingSeparator,
+    /// Malformed bech32 string, mixed case.
+    MixedCase,
+    /// Signature is not recoverable.
+    UnrecoverableSignature,
+    /// Invalid amount multiplier.
+    UnknownMultiplier,
+    /// Invalid sub-millisatoshi precision.
+    SubMillisatoshiPrecision,
+    /// Unknown required feature, holding its bit.
+    UnknownRequiredFeature(usize),
+    /// String is too short to hold the timestamp and the signature.
+    TooShort,
+    /// Missing required `s` field.
+    MissingPaymentSecret,
+}
+
+/// Expected outcome of an example.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// Valid payment request of the given amount in millisatoshis, if any.
+    Valid {
+        /// Amount in millisatoshis.
+        amount: Option<u64>,
+        /// Timestamp, `TIMESTAMP` for most examples.
+        timestamp: u64,
+    },
+    /// Payment request to reject.
+    Invalid(InvalidReason),
+}
+
+/// Example of the BOLT 11 appendix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// Description of the example in the spec.
+    pub description: &'static str,
+    /// Encoded payment request.
+    pub invoice: &'static str,
+    /// Expected outcome.
+    pub expected: Expected,
+}
+
+/// Valid examples of the appendix.
+pub const VALID: &[TestVector] = &[
+    TestVector {
+        description: "Please make a donation of any amount using payment_hash \
+                      0001020304050607080900010203040506070809000102030405060708090102 to me \
+                      @03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad",
+        invoice: "lnbc1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5r\
+                  qwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g\
+                  6twvus8g6rfwvs8qun0dfjkxaq9qrsgq357wnc5r2ueh7ck6q93dj32dlqnls087fxdwk8qakdyafkq3y\
+                  ap9us6v52vjjsrvywa6rt52cm9r9zqt8r2t7mlcwspyetp5h2tztugp9lfyql",
+        expected: Expected::Valid {
+            amount: None,
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Please send $3 for a cup of coffee to the same peer, within one minute",
+        invoice: "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyq\
+                  cyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgqu\
+                  k0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ym\
+                  jpf39tuven09sam30g4vgpfna3rh",
+        expected: Expected::Valid {
+            amount: Some(250_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Please send 0.0025 BTC for a cup of nonsense (ナンセンス 1杯) to the same \
+                      peer, within one minute",
+        invoice: "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyq\
+                  cyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4curk03c9wlrswe78q4eyqc7d8d0\
+                  xqzpu9qrsgqhtjpauu9ur7fw2thcl4y9vfvh4m9wlfyz2gem29g5ghe2aak2pm3ps8fdhtceqsaagty2v\
+                  ph7utlgj48u0ged6a337aewvraedendscp573dxr",
+        expected: Expected::Valid {
+            amount: Some(250_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Now send $24 for an entire list of things (hashed)",
+        invoice: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqs9qrsgq7ea976txfraylvgzuxs8kgcw23ezlrszfnh8r6qtfpr6cxga50aj6txm9\
+                  rxrydzd06dfeawfk6swupvz4erwnyutnjq7x39ymw6j38gp7ynn44",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "The same, on testnet, with a fallback address mk2QpYatsKicvFVuTAQLBryyccRXMUaGHP",
+        invoice: "lntb20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqsfpp3x9et2e20v6pu37c5d9vax37wxq72un989qrsgqrzgvherr3khd4dwn6qw0c\
+                  jc8zgp7d86h8x45yyamutvqy2jy9sp47s5dmhgj02p5nlr8an6tx7us22ck02wxm4qgg7a25hxsrd2cak\
+                  cqfktzgt",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "On mainnet, with fallback address 1RustyRX2oai4EYYDpQGWvEL62BBGqN9T with \
+                      extra routing info to go via nodes \
+                      029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255 then \
+                      039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
+        invoice: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq20q82gphp2nflc7jtzrcazr\
+                  ra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu92d8lr6fvg0r5g\
+                  v0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzq9qrsgqdfjcdk6w3ak5pca9\
+                  hwfwfh63zrrz06wwfya0ydlzpgzxkn5xagsqz7x9j4jwe7yj7vaf2k9lqsdk45kts2fd0fkr28am0u4w9\
+                  5tt2nsq76cqw0",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "On mainnet, with fallback (P2SH) address 3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX",
+        invoice: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqsfppj3a24vwu6r8ejrss3axul8rxldph2q7z99qrsgqucgxqdqv0tka4d934he3s\
+                  79qwn7uty2wtnfpvtnet6vrv5qzts4qn7gqe5r2ptexmqgevp0td9k664szwdg2q5sczsrhypuww8htee\
+                  sqr3g7sj",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "On mainnet, with fallback (P2WPKH) address \
+                      bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        invoice: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqsfppqw508d6qejxtdg4y5r3zarvary0c5xw7k9qrsgqda2u999esmftp45gj9r68\
+                  v8ejthwvgrm57pqsr57reyrxhj8rmfj9mucgyv83zr9j39jh9k4ryy9hntu2e9t0w862at59h7epfjy4j\
+                  cp3sl9pc",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "On mainnet, with fallback (P2WSH) address \
+                      bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+        invoice: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqsfp4qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q9qrsgq6\
+                  fucydh6k5553z9tu9s9r9ljk562yrf45gzjr7sc7px4hy2hkpjz7rcdr3p0glm3gycupl39p50zjjy37u\
+                  czf003r59p6wz84xhuqqqpqtr65n",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "On mainnet, with fallback (P2TR) address \
+                      bc1pptdvg0d2nj99568qn6ssdy4cygnwuxgw2ukmnwgwz7jpqjz2kszse2s3lm",
+        invoice: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+                  q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5f\
+                  k98klysy043l2ahrqsfp4pptdvg0d2nj99568qn6ssdy4cygnwuxgw2ukmnwgwz7jpqjz2kszs9qrsgqy\
+                  606dznq28exnydt2r4c29y56xjtn3sk4mhgjtl4pg2y4ar3249rq4ajlmj9jy8zvlzw7cr8mggqzm842x\
+                  fr0v72rswzq9xvr4hknfsqwmn6xd",
+        expected: Expected::Valid {
+            amount: Some(2_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Please send $30 for coffee beans to the same peer, which supports \
+                      features 8, 14 and 99, using secret \
+                      0x1111111111111111111111111111111111111111111111111111111111111111",
+        invoice: "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9\
+                  v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqq\
+                  qqqqqqsgq2a25dxl5hrntdtn6zvydt7d66hyzsyhqs4wdynavys42xgl6sgx9c4g7me86a27t07mdtfry\
+                  458rtjr0v92cnmswpsjscgt2vcse3sgpz3uapa",
+        expected: Expected::Valid {
+            amount: Some(2_500_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Same, but all upper case",
+        invoice: "LNBC25M1PVJLUEZPP5QQQSYQCYQ5RQWZQFQQQSYQCYQ5RQWZQFQQQSYQCYQ5RQWZQFQYPQDQ5VDHKVEN9\
+                  V5SXYETPDEESSP5ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYGS9Q5SQQQQQQQQQQ\
+                  QQQQQQSGQ2A25DXL5HRNTDTN6ZVYDT7D66HYZSYHQS4WDYNAVYS42XGL6SGX9C4G7ME86A27T07MDTFRY\
+                  458RTJR0V92CNMSWPSJSCGT2VCSE3SGPZ3UAPA",
+        expected: Expected::Valid {
+            amount: Some(2_500_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Please send 0.01 BTC with payment metadata 0x01fafaf0",
+        invoice: "lnbc10m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdp9wpshjmt9\
+                  de6zqmt9w3skgct5vysxjmnnd9jx2mq8q8a04uqsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg\
+                  3zyg3zyg3zygs9q2gqqqqqqsgq7hf8he7ecf7n4ffphs6awl9t6676rrclv9ckg3d3ncn7fct63p6s365\
+                  duk5wrk202cfy3aj5xnnp5gs3vrdvruverwwq7yzhkf5a3xqpd05wjc",
+        expected: Expected::Valid {
+            amount: Some(1_000_000_000),
+            timestamp: TIMESTAMP,
+        },
+    },
+    TestVector {
+        description: "Please send 0.00967878534 BTC for a list of items within one week, amount in \
+                      pico-BTC",
+        invoice: "lnbc9678785340p1pwmna7lpp5gc3xfm08u9qy06djf8dfflhugl6p7lgza6dsjxq454gxhj9t7a0sd8d\
+                  gfkx7cmtwd68yetpd5s9xar0wfjn5gpc8qhrsdfq24f5ggrxdaezqsnvda3kkum5wfjkzmfqf3jkgem9w\
+                  gsyuctwdus9xgrcyqcjcgpzgfskx6eqf9hzqnteypzxz7fzypfhg6trddjhygrcyqezcgpzfysywmm5yp\
+                  xxjemgw3hxjmn8yptk7untd9hxwg3q2d6xjcmtv4ezq7pqxgsxzmnyyqcjqmt0wfjjq6t5v4khxsp5zyg\
+                  3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygsxqyjw5qcqp2rzjq0gxwkzc8w6323m55m\
+                  4jyxcjwmy7stt9hwkwe2qxmy8zpsgg7jcuwz87fcqqeuqqqyqqqqlgqqqqn3qq9q9qrsgqrvgkpnmps66\
+                  4wgkp43l22qsgdw4ve24aca4nymnxddlnp8vh9v2sdxlu5ywdxefsfvm0fq3sesf08uf6q9a2ke0hc9j6\
+                  z6wlxg5z5kqpu2v9wz",
+        expected: Expected::Valid {
+            amount: Some(967_878_534),
+            timestamp: 1572468703,
+        },
+    },
+];
+
+/// Invalid examples of the appendix.
+pub const INVALID: &[TestVector] = &[
+    TestVector {
+        description: "Same, but adding invalid unknown feature 100",
+        invoice: "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9\
+                  v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q4psqqqqqqqqq\
+                  qqqqqqqsgqtqyx5vggfcsll4wu246hz02kp85x4katwsk9639we5n5yngc3yhqkm35jnjw4len8vrnqnf\
+                  5ejh0mzj9n3vz2px97evektfm2l6wqccp3y7372",
+        expected: Expected::Invalid(InvalidReason::UnknownRequiredFeature(100)),
+    },
+    TestVector {
+        description: "Bech32 checksum is invalid",
+        invoice: "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4c\
+                  urk03c9wlrswe78q4eyqc7d8d0xqzpuyk0sg5g70me25alkluzd2x62aysf2pyy8edtjeevuv4p2d5p76\
+                  r4zkmneet7uvyakky2zr4cusd45tftc9c5fh0nnqpnl2jfll544esqchsrnt",
+        expected: Expected::Invalid(InvalidReason::InvalidChecksum),
+    },
+    TestVector {
+        description: "Malformed bech32 string (no 1)",
+        invoice: "pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmw\
+                  wd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9rn449d9\
+                  p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w",
+        expected: Expected::Invalid(InvalidReason::MissingSeparator),
+    },
+    TestVector {
+        description: "Malformed bech32 string (mixed case)",
+        invoice: "LNBC2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4c\
+                  urk03c9wlrswe78q4eyqc7d8d0xqzpuyk0sg5g70me25alkluzd2x62aysf2pyy8edtjeevuv4p2d5p76\
+                  r4zkmneet7uvyakky2zr4cusd45tftc9c5fh0nnqpnl2jfll544esqchsrny",
+        expected: Expected::Invalid(InvalidReason::MixedCase),
+    },
+    TestVector {
+        description: "Signature is not recoverable",
+        invoice: "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxa\
+                  tsyp3k7enxv4jsxqzpuaxtrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77\
+                  w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspk28uwq",
+        expected: Expected::Invalid(InvalidReason::UnrecoverableSignature),
+    },
+    TestVector {
+        description: "String is too short, a timestamp with a valid checksum",
+        invoice: "lnbc1pvjluezhsv8me",
+        expected: Expected::Invalid(InvalidReason::TooShort),
+    },
+    TestVector {
+        description: "Invalid multiplier",
+        invoice: "lnbc2500x1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxa\
+                  tsyp3k7enxv4jsxqzpusp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9qrsgqr\
+                  rzc4cvfue4zp3hggxp47ag7xnrlr8vgcmkjxk3j5jqethnumgkpqp23z9jclu3v0a7e0aruz366e9wqdy\
+                  kw6dxhdzcjjhldxq0w6wgqcnu43j",
+        expected: Expected::Invalid(InvalidReason::UnknownMultiplier),
+    },
+    TestVector {
+        description: "Invalid sub-millisatoshi precision",
+        invoice: "lnbc2500000001p1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5\
+                  xysxxatsyp3k7enxv4jsxqzpusp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9\
+                  qrsgq0lzc236j96a95uv0m3umg28gclm5lqxtqqwk32uuk4k6673k6n5kfvx3d2h8s295fad45fdhmusm\
+                  8sjudfhlf6dcsxmfvkeywmjdkxcp99202x",
+        expected: Expected::Invalid(InvalidReason::SubMillisatoshiPrecision),
+    },
+    TestVector {
+        description: "Missing required `s` field, the donation example without its secret",
+        invoice: "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5s\
+                  xxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq9qrsgq9n7xuxqekadvlqh60lcrtlk\
+                  u62kkk2fpjwp5yahtslkqarpustfx44rpnswyejlxk5uangyfl5a8grkpevx05wt9gy9afhcj24we44qq\
+                  6zac46",
+        expected: Expected::Invalid(InvalidReason::MissingPaymentSecret),
+    },
+];
+
+/// Outcome of an example.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorResult {
+    /// The example.
+    pub vector: &'static TestVector,
+    /// Why the crate doesn't match the spec on this example, `None` when it does.
+    pub failure: Option<String>,
+}
+
+impl VectorResult {
+    /// Whether the crate matches the spec on this example.
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Check the crate against every example of the appendix, valid ones first.
+///
+/// # Examples
+/// ```
+/// use bolt11::test_vectors::run_conformance;
+///
+/// for result in run_conformance() {
+///     assert!(result.passed(), "{}: {:?}", result.vector.description, result.failure);
+/// }
+/// ```
+pub fn run_conformance() -> Vec<VectorResult> {
+    VALID.iter().chain(INVALID).map(check).collect()
+}
+
+/// Check the crate against an example.
+/// # Arguments
+/// `vector` Example, e.g. from `VALID` or `INVALID`.
+pub fn check(vector: &'static TestVector) -> VectorResult {
+    let decoded = PaymentRequest::decode(vector.invoice);
+    let failure = match vector.expected {
+        Expected::Valid { amount, timestamp } => decoded
+            .map_err(|e| format!("decoding failed: {}", e))
+            .and_then(|pr| check_valid(&pr, vector.invoice, amount, timestamp))
+            .err(),
+        Expected::Invalid(reason) => match (reason, decoded) {
+            (InvalidReason::UnknownRequiredFeature(bit), Ok(pr)) => {
+                match pr.validate_for_payment(&Features::known()) {
+                    Err(ValidationError::UnknownRequiredFeatures(ref bits))
+                        if bits.contains(&bit) =>
+                    {
+                        None
+                    }
+                    result => Some(format!("unexpected validation {:?}", result)),
+                }
+            }
+            (InvalidReason::MissingPaymentSecret, Ok(pr)) => match pr.validate_features() {
+                Err(ref violations)
+                    if violations.contains(&ValidationError::MissingPaymentSecret) =>
+                {
+                    None
+                }
+                result => Some(format!("unexpected validation {:?}", result)),
+            },
+            (_, Ok(_)) => Some("decoded an invalid payment request".to_owned()),
+            (reason, Err(e)) => if invalid_reason(&e) == Some(reason) {
+                None
+            } else {
+                Some(format!("unexpected error: {}", e))
+            },
+        },
+    };
+    VectorResult { vector, failure }
+}
+
+// Helper for check, compare a decoded valid example with the spec.
+fn check_valid(
+    pr: &PaymentRequest,
+    invoice: &str,
+    amount: Option<u64>,
+    timestamp: u64,
+) -> Result<(), String> {
+    let node_id = NodeId::from(&pr.node_id).to_string();
+    if node_id != NODE_ID {
+        return Err(format!("unexpected node id {}", node_id));
+    }
+    if pr.amount != amount {
+        return Err(format!("unexpected amount {:?}", pr.amount));
+    }
+    if pr.timestamp != timestamp {
+        return Err(format!("unexpected timestamp {}", pr.timestamp));
+    }
+    if pr.payment_secret().is_none() {
+        return Err("no payment secret".to_owned());
+    }
+    pr.validate_for_payment(&Features::known())
+        .map_err(|e| format!("validation failed: {}", e))?;
+    // the upper case example is encoded in lower case
+    match pr.encode() {
+        Ok(ref encoded) if *encoded == invoice.to_lowercase() => Ok(()),
+        Ok(encoded) => Err(format!("encoded as {}", encoded)),
+        Err(e) => Err(format!("encoding failed: {}", e)),
+    }
+}
+
+// Helper for check, reason of the spec matching a decoding error, if any.
+fn invalid_reason(error: &Error) -> Option<InvalidReason> {
+    match *error {
+        Error::Bech32Error(bech32::Error::InvalidChecksum) => Some(InvalidReason::InvalidChecksum),
+        Error::Bech32Error(bech32::Error::MissingSeparator) => Some(InvalidReason::MissingSeparator),
+        Error::Bech32Error(bech32::Error::MixedCase) => Some(InvalidReason::MixedCase),
+        Error::SignatureError(_) => Some(InvalidReason::UnrecoverableSignature),
+        Error::AmountError(AmountError::UnknownMultiplier(_)) => {
+            Some(InvalidReason::UnknownMultiplier)
+        }
+        Error::AmountError(AmountError::SubMillisatoshiPrecision) => {
+            Some(InvalidReason::SubMillisatoshiPrecision)
+        }
+        Error::InvalidLength(_) => Some(InvalidReason::TooShort),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn conformance() {
+        let results = run_conformance();
+        assert_eq!(VALID.len() + INVALID.len(), results.len());
+        for result in results {
+            assert!(
+                result.passed(),
+                "{}: {:?}",
+                result.vector.description,
+                result.failure
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_expectation() {
+        static VECTOR: TestVector = TestVector {
+            description: "valid example expected to be rejected",
+            invoice: VALID[1].invoice,
+            expected: Expected::Invalid(InvalidReason::MixedCase),
+        };
+        assert!(!check(&VECTOR).passed());
+        static AMOUNT: TestVector = TestVector {
+            expected: Expected::Valid {
+                amount: None,
+                timestamp: TIMESTAMP,
+            },
+            ..VECTOR
+        };
+        assert_eq!(
+            check(&AMOUNT).failure,
+            Some("unexpected amount Some(250000000)".to_owned())
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Generators of payment requests and of their parts, for property tests and fuzzing. <br>
+//! The strategies of this module, also used by the `proptest::arbitrary::Arbitrary`
+//! implementations, and the `arbitrary::Arbitrary` implementations only produce values which
+//! encode and decode back to themselves, e.g. `Tag::parse(&tag.to_vec_u5()?)` returns the tag and
+//! `PaymentRequest::decode(&payment_request.encode()?)` returns the payment request. <br>
+//! *Note*: the module is only built with the `testing` feature.
+
+use amount::{Amount, MilliSatoshi, MAX_AMOUNT_MSAT};
+use arbitrary;
+use arbitrary::Unstructured;
+use bech32::CHARSET;
+use currency::Currency;
+use features::Features;
+use node_id::NodeId;
+use payment_request::PaymentRequest;
+use proptest::prelude::*;
+use proptest::sample;
+use secp256k1::{PublicKey, SecretKey};
+use short_channel_id::ShortChannelId;
+use tag::{ExtraHop, Tag};
+use timestamp::Timestamp;
+use utils::U5;
+
+/// Maximum number of bytes of the generated descriptions and metadata.
+pub const MAX_FIELD_BYTES: usize = 256;
+
+/// Maximum number of u5 of the generated feature bits and unknown fields.
+pub const MAX_FIELD_WORDS: usize = 64;
+
+/// Maximum number of hops of the generated routes.
+pub const MAX_ROUTE_HOPS: usize = 5;
+
+/// Maximum number of optional tagged fields of the generated payment requests, 4 routes of 5
+/// hops stay within the 20 hops and the 7089 characters of `ParseOptions::default()`.
+pub const MAX_OPTIONAL_TAGS: usize = 4;
+
+// Types of the tagged fields read by `Tag::parse`.
+const KNOWN_TAGS: &str = "pdhfxcrn9ms";
+
+/// Strategy of the node ids, any x coordinate with either prefix.
+pub fn node_id() -> impl Strategy<Value = NodeId> {
+    (any::<bool>(), any::<[u8; 32]>()).prop_map(|(odd, x)| node_id_from(odd, x))
+}
+
+/// Strategy of the extra hops.
+pub fn extra_hop() -> impl Strategy<Value = ExtraHop> {
+    (
+        node_id(),
+        any::<u64>(),
+        any::<u32>(),
+        any::<u32>(),
+        any::<u16>(),
+    )
+        .prop_map(
+            |(pub_key, scid, fee_base_msat, fee_proportional_millionths, cltv_expiry_delta)| {
+                ExtraHop::new(
+                    pub_key,
+                    ShortChannelId::from(scid),
+                    fee_base_msat,
+                    fee_proportional_millionths,
+                    cltv_expiry_delta,
+                )
+            },
+        )
+}
+
+/// Strategy of the amounts in millisatoshis, from 1 millisatoshi to the bitcoin supply.
+pub fn amount_msat() -> impl Strategy<Value = u64> {
+    1..=MAX_AMOUNT_MSAT
+}
+
+/// Strategy of the tagged fields, except `Tag::Skipped` which is only produced by the readers.
+pub fn tag() -> impl Strategy<Value = Tag> {
+    prop_oneof![
+        any::<[u8; 32]>().prop_map(|hash| Tag::PaymentHash { hash: hash.into() }),
+        any::<[u8; 32]>().prop_map(|secret| Tag::PaymentSecret { secret: secret.into() }),
+        description().prop_map(|description| Tag::Description { description }),
+        any::<[u8; 32]>().prop_map(|hash| Tag::DescriptionHash { hash: hash.into() }),
+        (any::<u8>(), any::<usize>()).prop_flat_map(|(version, len)| {
+            let version = fallback_version(version);
+            prop::collection::vec(any::<u8>(), fallback_len(version, len))
+                .prop_map(move |hash| Tag::FallbackAddress { version, hash })
+        }),
+        any::<u64>().prop_map(|seconds| Tag::Expiry { seconds }),
+        any::<u64>().prop_map(|blocks| Tag::MinFinalCltvExpiry { blocks }),
+        prop::collection::vec(extra_hop(), 1..=MAX_ROUTE_HOPS)
+            .prop_map(|path| Tag::RoutingInfo { path }),
+        node_id().prop_map(|pub_key| Tag::PayeeNodeId { pub_key }),
+        words().prop_map(|words| Tag::Features {
+            bits: Features::from_words(words),
+        }),
+        prop::collection::vec(any::<u8>(), 0..=MAX_FIELD_BYTES)
+            .prop_map(|bytes| Tag::PaymentMetadata { bytes }),
+        (sample::select(unknown_tags()), words())
+            .prop_map(|(tag, bytes)| Tag::UnknownTag { tag, bytes }),
+    ]
+}
+
+/// Strategy of the signed payment requests of the Bitcoin networks, with a payment hash, a
+/// description or a description hash, and optional tagged fields. <br>
+/// *Note*: a payee node id field always holds the node id of the signing key.
+pub fn payment_request() -> impl Strategy<Value = PaymentRequest> {
+    (
+        sample::select(Currency::all()),
+        proptest::option::of(amount_msat()),
+        0..=Timestamp::MAX,
+        any::<[u8; 32]>().prop_filter_map("invalid secret key", |key| SecretKey::parse(&key).ok()),
+        any::<[u8; 32]>(),
+        prop_oneof![
+            description().prop_map(|description| Tag::Description { description }),
+            any::<[u8; 32]>().prop_map(|hash| Tag::DescriptionHash { hash: hash.into() }),
+        ],
+        any::<bool>(),
+        prop::collection::vec(
+            tag().prop_filter("mandatory or payee field", |tag| {
+                !"pdhn".contains(tag.tag_char())
+            }),
+            0..=MAX_OPTIONAL_TAGS,
+        ),
+    )
+        .prop_map(
+            |(currency, amount, timestamp, key, hash, description, payee, optional)| {
+                let tags = [
+                    vec![Tag::PaymentHash { hash: hash.into() }, description],
+                    optional,
+                ];
+                signed_payment_request(&currency, amount, timestamp, &key, tags.concat(), payee)
+            },
+        )
+}
+
+impl proptest::arbitrary::Arbitrary for ExtraHop {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<ExtraHop>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<ExtraHop> {
+        extra_hop().boxed()
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for Tag {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Tag>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<Tag> {
+        tag().boxed()
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for MilliSatoshi {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MilliSatoshi>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<MilliSatoshi> {
+        amount_msat().prop_map(MilliSatoshi).boxed()
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for Amount {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Amount>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<Amount> {
+        amount_msat().prop_map(Amount::from_msat).boxed()
+    }
+}
+
+impl proptest::arbitrary::Arbitrary for PaymentRequest {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<PaymentRequest>;
+
+    fn arbitrary_with(_: ()) -> BoxedStrategy<PaymentRequest> {
+        payment_request().boxed()
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for NodeId {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<NodeId> {
+        Ok(node_id_from(u.arbitrary()?, u.arbitrary()?))
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for ExtraHop {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<ExtraHop> {
+        Ok(ExtraHop::new(
+            u.arbitrary()?,
+            ShortChannelId::from(u.arbitrary::<u64>()?),
+            u.arbitrary()?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+        ))
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for MilliSatoshi {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<MilliSatoshi> {
+        u.int_in_range(1..=MAX_AMOUNT_MSAT).map(MilliSatoshi)
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for Amount {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Amount> {
+        u.int_in_range(1..=MAX_AMOUNT_MSAT).map(Amount::from_msat)
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for Tag {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Tag> {
+        let tag = match u.int_in_range(0..=11u8)? {
+            0 => Tag::PaymentHash {
+                hash: u.arbitrary::<[u8; 32]>()?.into(),
+            },
+            1 => Tag::Description {
+                description: arbitrary_description(u)?,
+            },
+            2 => Tag::DescriptionHash {
+                hash: u.arbitrary::<[u8; 32]>()?.into(),
+            },
+            3 => {
+                let version = fallback_version(u.arbitrary()?);
+                let len = fallback_len(version, u.arbitrary()?);
+                Tag::FallbackAddress {
+                    version,
+                    hash: u.bytes(len)?.to_vec(),
+                }
+            }
+            4 => Tag::Expiry {
+                seconds: u.arbitrary()?,
+            },
+            5 => Tag::MinFinalCltvExpiry {
+                blocks: u.arbitrary()?,
+            },
+            6 => {
+                let len = u.int_in_range(1..=MAX_ROUTE_HOPS)?;
+                Tag::RoutingInfo {
+                    path: (0..len)
+                        .map(|_| u.arbitrary())
+                        .collect::<arbitrary::Result<Vec<ExtraHop>>>()?,
+                }
+            }
+            7 => Tag::PayeeNodeId {
+                pub_key: u.arbitrary()?,
+            },
+            8 => Tag::Features {
+                bits: Features::from_words(arbitrary_words(u)?),
+            },
+            9 => {
+                let len = u.int_in_range(0..=MAX_FIELD_BYTES)?;
+                Tag::PaymentMetadata {
+                    bytes: u.bytes(len)?.to_vec(),
+                }
+            }
+            10 => Tag::PaymentSecret {
+                secret: u.arbitrary::<[u8; 32]>()?.into(),
+            },
+            _ => Tag::UnknownTag {
+                tag: *u.choose(&unknown_tags())?,
+                bytes: arbitrary_words(u)?,
+            },
+        };
+        Ok(tag)
+    }
+}
+
+impl<'a> arbitrary::Arbitrary<'a> for PaymentRequest {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<PaymentRequest> {
+        let currency = *u.choose(Currency::all())?;
+        let amount = if u.arbitrary()? {
+            Some(u.int_in_range(1..=MAX_AMOUNT_MSAT)?)
+        } else {
+            None
+        };
+        let timestamp = u.int_in_range(0..=Timestamp::MAX)?;
+        let key =
+            SecretKey::parse(&u.arbitrary()?).map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        let mut tags = vec![Tag::PaymentHash {
+            hash: u.arbitrary::<[u8; 32]>()?.into(),
+        }];
+        tags.push(if u.arbitrary()? {
+            Tag::Description {
+                description: arbitrary_description(u)?,
+            }
+        } else {
+            Tag::DescriptionHash {
+                hash: u.arbitrary::<[u8; 32]>()?.into(),
+            }
+        });
+        let payee = u.arbitrary()?;
+        for _ in 0..u.int_in_range(0..=MAX_OPTIONAL_TAGS)? {
+            let tag: Tag = u.arbitrary()?;
+            if !"pdhn".contains(tag.tag_char()) {
+                tags.push(tag);
+            }
+        }
+        Ok(signed_payment_request(
+            &currency, amount, timestamp, &key, tags, payee,
+        ))
+    }
+}
+
+// Helper for the generators, node id of a 32 bytes x coordinate.
+fn node_id_from(odd: bool, x: [u8; 32]) -> NodeId {
+    let mut bytes = [0u8; 33];
+    bytes[0] = if odd { 0x03 } else { 0x02 };
+    bytes[1..].copy_from_slice(&x);
+    NodeId::from_slice(&bytes).expect("33 bytes with a valid prefix")
+}
+
+// Helper for the generators, types of the tagged fields read as unknown fields.
+fn unknown_tags() -> Vec<U5> {
+    (0..32u8)
+        .filter(|tag| !KNOWN_TAGS.contains(CHARSET[*tag as usize]))
+        .collect()
+}
+
+// Helper for the generators, fallback address version among the witness versions, P2PKH and
+// P2SH.
+fn fallback_version(choice: u8) -> u8 {
+    choice % 19
+}
+
+// Helper for the generators, hash length valid for the fallback address version: 20 or 32 bytes
+// for the version 0, 2 to 40 bytes for the other witness versions, 20 bytes for P2PKH and P2SH.
+fn fallback_len(version: u8, choice: usize) -> usize {
+    match version {
+        0 => [20, 32][choice % 2],
+        1..=16 => 2 + choice % 39,
+        _ => 20,
+    }
+}
+
+// Helper for the strategies, descriptions of at most `MAX_FIELD_BYTES` bytes.
+fn description() -> impl Strategy<Value = String> {
+    ".{0,64}"
+}
+
+// Helper for the strategies, u5 of the feature bits and the unknown fields.
+fn words() -> impl Strategy<Value = Vec<U5>> {
+    prop::collection::vec(0..32u8, 0..=MAX_FIELD_WORDS)
+}
+
+// Helper for the arbitrary implementations, description cut to `MAX_FIELD_BYTES` bytes on a
+// character boundary.
+fn arbitrary_description(u: &mut Unstructured) -> arbitrary::Result<String> {
+    let mut description: String = u.arbitrary()?;
+    let mut len = description.len().min(MAX_FIELD_BYTES);
+    while !description.is_char_boundary(len) {
+        len -= 1;
+    }
+    description.truncate(len);
+    Ok(description)
+}
+
+// Helper for the arbitrary implementations, u5 of the feature bits and the unknown fields.
+fn arbitrary_words(u: &mut Unstructured) -> arbitrary::Result<Vec<U5>> {
+    let len = u.int_in_range(0..=MAX_FIELD_WORDS)?;
+    (0..len).map(|_| u.int_in_range(0..=31u8)).collect()
+}
+
+// Helper for the generators, sign the payment request built from the generated values, adding
+// the payee node id field after the payment hash and the description when asked.
+fn signed_payment_request(
+    currency: &Currency,
+    amount: Option<u64>,
+    timestamp: u64,
+    key: &SecretKey,
+    mut tags: Vec<Tag>,
+    payee: bool,
+) -> PaymentRequest {
+    let node_id = PublicKey::from_secret_key(key);
+    if payee {
+        tags.insert(
+            2,
+            Tag::PayeeNodeId {
+                pub_key: NodeId::from(&node_id),
+            },
+        );
+    }
+    let unsigned = PaymentRequest {
+        prefix: currency.prefix().to_owned(),
+        amount,
+        timestamp,
+        node_id,
+        tags,
+        signature: vec![],
+    };
+    unsigned
+        .sign(key)
+        .expect("generated payment requests can be signed")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn tag_round_trip(tag in tag()) {
+            prop_assert_eq!(Tag::parse(&tag.to_vec_u5().unwrap()).unwrap(), tag);
+        }
+
+        #[test]
+        fn payment_request_round_trip(payment_request in payment_request()) {
+            let encoded = payment_request.encode().unwrap();
+            prop_assert_eq!(PaymentRequest::decode(&encoded).unwrap(), payment_request);
+        }
+
+        #[test]
+        fn amount_round_trip(amount in any::<MilliSatoshi>()) {
+            prop_assert_eq!(MilliSatoshi::from_bolt11(&amount.to_bolt11()).unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn arbitrary_round_trip() {
+        // pseudo random input, every 1024 bytes window yields a payment request or an error
+        let data = (0..8192u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<u8>>();
+        let mut generated = 0;
+        for window in data.chunks(1024) {
+            let mut u = Unstructured::new(window);
+            if let Ok(payment_request) = u.arbitrary::<PaymentRequest>() {
+                let encoded = payment_request.encode().unwrap();
+                assert_eq!(PaymentRequest::decode(&encoded).unwrap(), payment_request);
+                generated += 1;
+            }
+        }
+        assert!(generated > 0);
+    }
+}
@@ -1,24 +1,94 @@
+//! Creation timestamp of a payment request.
+
+use types::Error;
 use utils::U5;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// seconds-since-1970 (35 bits, big-endian)
 pub struct Timestamp;
 
 impl Timestamp {
+    /// Largest timestamp fitting the 35 bits of a payment request.
+    pub const MAX: u64 = (1 << 35) - 1;
+
     /// decode timestamp from u5 vector
-    pub fn decode(data: &Vec<U5>) -> u64 {
+    pub fn decode(data: &[U5]) -> u64 {
         data.iter().take(7).fold(0, |a, b| a * 32u64 + *b as u64)
     }
-    /// encode timestamp
-    pub fn encode(timestamp: u64) -> Vec<U5> {
+    /// encode timestamp, which must fit 35 bits
+    pub fn encode(timestamp: u64) -> Result<Vec<U5>, Error> {
         let mut acc: Vec<U5> = Vec::new();
-        let mut time_acc = timestamp;
+        let mut time_acc = Timestamp::check(timestamp)?;
         // 35 bits, big-endian
         while acc.len() < 7 {
             acc.push((time_acc % 32) as U5);
             time_acc /= 32;
         }
         acc.reverse();
-        acc
+        Ok(acc)
+    }
+    /// current timestamp (UNIX format)
+    pub fn now() -> Result<u64, Error> {
+        Timestamp::from_system_time(SystemTime::now())
+    }
+    /// Check the timestamp fits the 35 bits of a payment request.
+    /// # Arguments
+    /// `timestamp` Seconds since epoch (UNIX format).
+    pub fn check(timestamp: u64) -> Result<u64, Error> {
+        if timestamp > Timestamp::MAX {
+            return Err(Error::InvalidValue(format!(
+                "timestamp {} does not fit 35 bits",
+                timestamp
+            )));
+        }
+        Ok(timestamp)
+    }
+    /// Return the timestamp of the system time, in seconds precision.
+    /// # Arguments
+    /// `time` System time, which must be after the epoch and fit 35 bits.
+    pub fn from_system_time(time: SystemTime) -> Result<u64, Error> {
+        time.duration_since(UNIX_EPOCH)
+            .map_err(|_| Error::InvalidValue("invalid system time".to_owned()))
+            .and_then(|duration| Timestamp::check(duration.as_secs()))
+    }
+    /// Return the system time of the timestamp, or an error if the system time can't represent
+    /// it.
+    /// # Arguments
+    /// `timestamp` Seconds since epoch (UNIX format).
+    pub fn as_system_time(timestamp: u64) -> Result<SystemTime, Error> {
+        UNIX_EPOCH
+            .checked_add(Duration::from_secs(timestamp))
+            .ok_or_else(|| Error::InvalidValue(format!("invalid timestamp {}", timestamp)))
+    }
+}
+
+/// Source of the current time, for the expiry checks.
+pub trait Clock {
+    /// Return the current time (UNIX format).
+    fn now(&self) -> u64;
+}
+
+/// Clock of the system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        // a system time before the epoch is clamped to the epoch
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Clock always returning the same time, e.g. for tests or targets without a system time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
     }
 }
 
@@ -32,6 +102,34 @@ mod test {
         let timestamp = 1496314658;
 
         assert_eq!(Timestamp::decode(&data), timestamp);
-        assert!(data.eq(&Timestamp::encode(timestamp)));
+        assert!(data.eq(&Timestamp::encode(timestamp).unwrap()));
+    }
+
+    #[test]
+    fn timestamp_bounds() {
+        assert_eq!(Timestamp::encode(Timestamp::MAX).unwrap(), vec![31; 7]);
+        assert!(matches!(
+            Timestamp::encode(Timestamp::MAX + 1),
+            Err(Error::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn clocks() {
+        assert_eq!(FixedClock(1496314658).now(), 1496314658);
+        assert!(SystemClock.now() >= Timestamp::now().unwrap());
+    }
+
+    #[test]
+    fn system_time() {
+        let time = Timestamp::as_system_time(1496314658).unwrap();
+
+        assert_eq!(Timestamp::from_system_time(time).unwrap(), 1496314658);
+        assert!(Timestamp::from_system_time(UNIX_EPOCH - Duration::from_secs(1)).is_err());
+        assert!(
+            Timestamp::from_system_time(Timestamp::as_system_time(Timestamp::MAX + 1).unwrap())
+                .is_err()
+        );
+        assert!(Timestamp::as_system_time(u64::MAX).is_err());
     }
 }
@@ -3,8 +3,10 @@
 use std::{error, fmt};
 use std::io;
 use std::num;
+use std::ops::Range;
 use std::string;
 use bech32;
+use options::Limit;
 use secp256k1;
 
 /// Result of vector base conversion
@@ -12,6 +14,7 @@ pub type ConvertResult = Result<Vec<u8>, Error>;
 
 /// Error types
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Invalid parameter.
     InvalidParameter(String),
@@ -23,6 +26,12 @@ pub enum Error {
     InvalidPadding,
     /// Invalid input length.
     InvalidLength(String),
+    /// Invalid payment request structure.
+    InvalidFormat(String),
+    /// Payment request for an unexpected network.
+    WrongNetwork(String),
+    /// Invalid amount.
+    AmountError(AmountError),
     /// Wraps an io error produced when reading or writing.
     IOErr(io::Error),
     /// Wraps parse float error.
@@ -32,9 +41,273 @@ pub enum Error {
     /// Wraps string from utf8 error.
     FromUTF8Err(string::FromUtf8Error),
     /// Wraps bech32 error.
-    Bech32Err(bech32::Error),
+    Bech32Error(bech32::Error),
     /// Wraps secp256k1 error.
     SignatureError(secp256k1::Error),
+    /// Malformed tagged field.
+    TagError {
+        /// Type of the field, e.g. `'p'`.
+        tag: char,
+        /// Position of the field if known: characters of the payment request when decoding it,
+        /// u5 of the tagged fields when parsing them.
+        span: Option<Range<usize>>,
+        /// Error of the field data.
+        kind: Box<Error>,
+    },
+    /// Payment request exceeding a size limit, holding the limit and its maximum.
+    LimitExceeded(Limit, usize),
+    /// Routing info whose length is not a multiple of a hop, holding the number of trailing
+    /// bytes.
+    TrailingRouteHintBytes(usize),
+    /// Wraps payment request validation error.
+    Validation(ValidationError),
+    /// Wraps signer error.
+    Signer(SignError),
+}
+
+/// Invalid amount of a payment request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AmountError {
+    /// Amount that is not a positive integer without leading zeroes followed by an optional
+    /// multiplier.
+    InvalidFormat(String),
+    /// Multiplier letter other than `m`, `u`, `n` and `p`.
+    UnknownMultiplier(char),
+    /// Pico-bitcoin amount that is not a multiple of 10, finer than a millisatoshi.
+    SubMillisatoshiPrecision,
+    /// Amount in millisatoshis above the 21 million bitcoin supply.
+    ExceedsSupply(u64),
+    /// Amount too large to be represented in millisatoshis.
+    TooLarge(String),
+    /// Amount not written with its shortest representation, e.g. `2500000n` rather than
+    /// `2500u`.
+    NotShortest(String),
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AmountError::InvalidFormat(ref amount) => write!(f, "invalid amount {}", amount),
+            AmountError::UnknownMultiplier(c) => write!(f, "unknown multiplier {}", c),
+            AmountError::SubMillisatoshiPrecision => {
+                write!(f, "amount has sub-millisatoshi precision")
+            }
+            AmountError::ExceedsSupply(msat) => {
+                write!(f, "amount of {} msat exceeds the bitcoin supply", msat)
+            }
+            AmountError::TooLarge(ref amount) => write!(f, "amount {} is too large", amount),
+            AmountError::NotShortest(ref amount) => write!(
+                f,
+                "amount {} is not written with its shortest representation",
+                amount
+            ),
+        }
+    }
+}
+
+impl error::Error for AmountError {}
+
+/// Payment request violating the BOLT #11 rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Neither a description nor a description hash.
+    MissingDescription,
+    /// Both a description and a description hash.
+    DescriptionAndHash,
+    /// Required feature bits unknown to the payer.
+    UnknownRequiredFeatures(Vec<usize>),
+    /// Feature set without the feature it depends on, holding their even bits.
+    MissingFeatureDependency {
+        /// Feature set.
+        feature: usize,
+        /// Feature it depends on.
+        dependency: usize,
+    },
+    /// `payment_secret` feature set without the `s` field.
+    MissingPaymentSecret,
+    /// Fallback address whose hash length does not match its version.
+    InvalidFallbackAddress {
+        /// Version of the `f` field.
+        version: u8,
+        /// Length of the hash in bytes.
+        len: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::MissingDescription => {
+                write!(f, "missing description or description hash")
+            }
+            ValidationError::DescriptionAndHash => {
+                write!(f, "both description and description hash are present")
+            }
+            ValidationError::UnknownRequiredFeatures(ref bits) => {
+                write!(f, "unknown required feature bits {:?}", bits)
+            }
+            ValidationError::MissingFeatureDependency {
+                feature,
+                dependency,
+            } => write!(
+                f,
+                "feature bit {} is set without the feature bit {} it depends on",
+                feature, dependency
+            ),
+            ValidationError::MissingPaymentSecret => {
+                write!(f, "payment secret feature is set without a payment secret")
+            }
+            ValidationError::InvalidFallbackAddress { version, len } => write!(
+                f,
+                "fallback address of version {} has an invalid hash length {}",
+                version, len
+            ),
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+/// Issue of a payment request which does not prevent its decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// Field of a type unknown to this crate, holding its type.
+    UnknownField(char),
+    /// Field skipped for its unexpected length or data.
+    SkippedField {
+        /// Type of the field.
+        tag: char,
+        /// Why the field was skipped.
+        reason: String,
+    },
+    /// Field `p`, `d` or `h` appearing more than once, holding its type.
+    DuplicateField(char),
+    /// Amount not written with its shortest representation, e.g. `2500000n` rather than `2500u`.
+    NonShortestAmount(String),
+    /// Amount in millisatoshis that is not a whole number of satoshis, which can't be paid on
+    /// chain, e.g. to a fallback address.
+    SubSatoshiAmount(u64),
+    /// Payment request violating a BOLT #11 rule.
+    Validation(ValidationError),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::UnknownField(tag) => write!(f, "unknown {} field", tag),
+            Warning::SkippedField { tag, ref reason } => {
+                write!(f, "skipped {} field: {}", tag, reason)
+            }
+            Warning::DuplicateField(tag) => write!(f, "duplicate {} field", tag),
+            Warning::NonShortestAmount(ref amount) => write!(
+                f,
+                "amount {} is not written with its shortest representation",
+                amount
+            ),
+            Warning::SubSatoshiAmount(msat) => {
+                write!(f, "amount of {} msat is not a whole number of satoshis", msat)
+            }
+            Warning::Validation(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Error of the allocation free decoding, see `view::RawInvoiceView`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ViewError {
+    /// Both lowercase and uppercase characters.
+    MixedCase,
+    /// Character outside of the bech32 character set, holding its position.
+    InvalidChar(usize),
+    /// Missing `1` separator.
+    MissingSeparator,
+    /// Invalid bech32 checksum.
+    InvalidChecksum,
+    /// Data part too short for the timestamp, signature and checksum.
+    TooShort,
+    /// Human readable part not starting with `ln`.
+    InvalidPrefix,
+    /// Invalid or unrepresentable amount.
+    InvalidAmount,
+    /// More tagged fields than the capacity, holding the capacity.
+    TooManyTags(usize),
+    /// Tagged field whose length runs past the data part, holding its position.
+    TagOverflow(usize),
+    /// Field data of an unexpected length.
+    InvalidLength,
+    /// Output buffer too small, holding the needed length.
+    BufferTooSmall(usize),
+    /// More route hops than the capacity, holding the capacity.
+    TooManyHops(usize),
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ViewError::MixedCase => write!(f, "mixed case"),
+            ViewError::InvalidChar(position) => write!(f, "invalid character at {}", position),
+            ViewError::MissingSeparator => write!(f, "missing separator"),
+            ViewError::InvalidChecksum => write!(f, "invalid checksum"),
+            ViewError::TooShort => write!(f, "data part is too short"),
+            ViewError::InvalidPrefix => write!(f, "invalid prefix"),
+            ViewError::InvalidAmount => write!(f, "invalid amount"),
+            ViewError::TooManyTags(capacity) => {
+                write!(f, "more than {} tagged fields", capacity)
+            }
+            ViewError::TagOverflow(position) => {
+                write!(f, "tagged field at {} runs past the data part", position)
+            }
+            ViewError::InvalidLength => write!(f, "field data has an invalid length"),
+            ViewError::BufferTooSmall(len) => write!(f, "buffer is too small, {} bytes needed", len),
+            ViewError::TooManyHops(capacity) => write!(f, "more than {} route hops", capacity),
+        }
+    }
+}
+
+impl error::Error for ViewError {}
+
+/// Error of an `InvoiceSigner`, see `signer::InvoiceSigner`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignError {
+    /// Signing backend that can't be reached, e.g. a KMS or a remote signer.
+    Unavailable(String),
+    /// Signing backend refusing to sign, e.g. a policy denying the payment request.
+    Rejected(String),
+    /// Wraps secp256k1 error.
+    Secp256k1(secp256k1::Error),
+}
+
+impl fmt::Display for SignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SignError::Unavailable(ref e) => write!(f, "signer unavailable: {}", e),
+            SignError::Rejected(ref e) => write!(f, "signer rejected the payment request: {}", e),
+            SignError::Secp256k1(ref e) => write!(f, "{:?}", e),
+        }
+    }
+}
+
+impl error::Error for SignError {}
+
+impl From<secp256k1::Error> for SignError {
+    fn from(e: secp256k1::Error) -> SignError {
+        SignError::Secp256k1(e)
+    }
+}
+
+impl Error {
+    /// Return the position of the error if known, e.g. to highlight it: characters of the payment
+    /// request when decoding it.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match *self {
+            Error::TagError { ref span, .. } => span.clone(),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -45,44 +318,47 @@ impl fmt::Display for Error {
             Error::InvalidValue(ref e) => write!(f, "{}", e),
             Error::InvalidPadding => write!(f, "invalid padding"),
             Error::InvalidLength(ref e) => write!(f, "{}", e),
+            Error::InvalidFormat(ref e) => write!(f, "{}", e),
+            Error::WrongNetwork(ref e) => write!(f, "{}", e),
+            Error::AmountError(ref e) => write!(f, "{}", e),
             Error::IOErr(ref e) => write!(f, "{}", e),
             Error::ParseFloatErr(ref e) => write!(f, "{}", e),
             Error::ParseIntErr(ref e) => write!(f, "{}", e),
             Error::FromUTF8Err(ref e) => write!(f, "{}", e),
-            Error::Bech32Err(ref e) => write!(f, "{}", e),
+            Error::Bech32Error(ref e) => write!(f, "{}", e),
             Error::SignatureError(ref e) => write!(f, "{:?}", e),
+            Error::TagError {
+                tag,
+                span: Some(ref span),
+                ref kind,
+            } => write!(
+                f,
+                "invalid {} field at {}..{}: {}",
+                tag, span.start, span.end, kind
+            ),
+            Error::TagError { tag, ref kind, .. } => write!(f, "invalid {} field: {}", tag, kind),
+            Error::LimitExceeded(limit, max) => write!(f, "{} exceeds {}", limit, max),
+            Error::TrailingRouteHintBytes(len) => {
+                write!(f, "routing info has {} trailing bytes", len)
+            }
+            Error::Validation(ref e) => write!(f, "{}", e),
+            Error::Signer(ref e) => write!(f, "{}", e),
         }
     }
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::InvalidParameter(ref e) => e,
-            Error::InvalidInputValue(_) => "invalid input value",
-            Error::InvalidValue(ref e) => e,
-            Error::InvalidPadding => "invalid padding",
-            Error::InvalidLength(ref e) => e,
-            Error::IOErr(ref e) => error::Error::description(e),
-            Error::ParseFloatErr(ref e) => error::Error::description(e),
-            Error::ParseIntErr(ref e) => error::Error::description(e),
-            Error::FromUTF8Err(ref e) => error::Error::description(e),
-            Error::Bech32Err(ref e) => error::Error::description(e),
-            Error::SignatureError(ref e) => match *e {
-                secp256k1::Error::InvalidSignature => "invalid signature",
-                secp256k1::Error::InvalidPublicKey => "invalid public key",
-                secp256k1::Error::InvalidSecretKey => "invalid secret key",
-                secp256k1::Error::InvalidRecoveryId => "invalid recovery id",
-                secp256k1::Error::InvalidMessage => "invalid message",
-            },
-        }
-    }
-    fn cause(&self) -> Option<&error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
+            Error::AmountError(ref e) => Some(e),
             Error::IOErr(ref e) => Some(e),
             Error::ParseFloatErr(ref e) => Some(e),
             Error::ParseIntErr(ref e) => Some(e),
             Error::FromUTF8Err(ref e) => Some(e),
+            Error::Bech32Error(ref e) => Some(e),
+            Error::TagError { ref kind, .. } => Some(kind.as_ref()),
+            Error::Validation(ref e) => Some(e),
+            Error::Signer(ref e) => Some(e),
             _ => None,
         }
     }
@@ -114,7 +390,7 @@ impl From<string::FromUtf8Error> for Error {
 
 impl From<bech32::Error> for Error {
     fn from(e: bech32::Error) -> Error {
-        Error::Bech32Err(e)
+        Error::Bech32Error(e)
     }
 }
 
@@ -123,3 +399,67 @@ impl From<secp256k1::Error> for Error {
         Error::SignatureError(e)
     }
 }
+
+impl From<AmountError> for Error {
+    fn from(e: AmountError) -> Error {
+        Error::AmountError(e)
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(e: ValidationError) -> Error {
+        Error::Validation(e)
+    }
+}
+
+impl From<SignError> for Error {
+    fn from(e: SignError) -> Error {
+        Error::Signer(e)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn source() {
+        let error = Error::TagError {
+            tag: 'r',
+            span: None,
+            kind: Box::new(Error::TrailingRouteHintBytes(10)),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "invalid r field: routing info has 10 trailing bytes"
+        );
+        assert!(matches!(
+            error.source().and_then(|e| e.downcast_ref::<Error>()),
+            Some(&Error::TrailingRouteHintBytes(10))
+        ));
+
+        let error = Error::from(AmountError::UnknownMultiplier('x'));
+
+        assert_eq!(error.to_string(), "unknown multiplier x");
+        assert_eq!(
+            error.source().and_then(|e| e.downcast_ref::<AmountError>()),
+            Some(&AmountError::UnknownMultiplier('x'))
+        );
+        assert!(Error::InvalidPadding.source().is_none());
+    }
+
+    #[test]
+    fn span() {
+        let error = Error::TagError {
+            tag: 'd',
+            span: Some(17..22),
+            kind: Box::new(Error::InvalidPadding),
+        };
+
+        assert_eq!(error.span(), Some(17..22));
+        assert_eq!(error.to_string(), "invalid d field at 17..22: invalid padding");
+        assert_eq!(Error::InvalidPadding.span(), None);
+    }
+}
@@ -0,0 +1,363 @@
+//! `lightning:` URIs, e.g. `lightning:lnbc2500u1...` or `LIGHTNING://LNBC2500U1...?label=a`, and
+//! BIP-21 unified URIs, e.g. `bitcoin:bc1q...?amount=0.001&lightning=lnbc1m1...`, as found in QR
+//! codes and links.
+
+use amount::Amount;
+use encoder;
+use payment_request::PaymentRequest;
+use types::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Scheme of the lightning URIs, matched case-insensitively.
+pub const LIGHTNING_SCHEME: &str = "lightning:";
+
+/// Scheme of the BIP-21 URIs, matched case-insensitively.
+pub const BITCOIN_SCHEME: &str = "bitcoin:";
+
+/// Payment request with the query parameters of its `lightning:` URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LightningUri {
+    /// Payment request of the URI.
+    pub invoice: PaymentRequest,
+    /// Query parameters, percent-decoded, in order.
+    pub params: Vec<(String, String)>,
+}
+
+impl LightningUri {
+    /// Create a URI for a payment request, without query parameters.
+    /// # Arguments
+    /// `invoice` Payment request of the URI.
+    pub fn new(invoice: PaymentRequest) -> LightningUri {
+        LightningUri {
+            invoice,
+            params: vec![],
+        }
+    }
+
+    /// Add a query parameter, passed through as is.
+    /// # Arguments
+    /// `key` Name of the parameter. <br>
+    /// `value` Value of the parameter, percent-encoded in the URI.
+    pub fn with_param(mut self, key: &str, value: &str) -> LightningUri {
+        self.params.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Return the value of the first query parameter with this name, if any.
+    /// # Arguments
+    /// `key` Name of the parameter.
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|param| param.0 == key)
+            .map(|param| param.1.as_str())
+    }
+
+    /// Return the URI, failing if the payment request can't be encoded, e.g. it isn't signed.
+    pub fn to_uri(&self) -> Result<String, Error> {
+        let mut uri = format!("{}{}", LIGHTNING_SCHEME, self.invoice.encode()?);
+        write_query(&mut uri, &self.params, '?').map_err(encoder::write_error)?;
+        Ok(uri)
+    }
+}
+
+/// Parse a `lightning:` URI: the surrounding whitespace is ignored, the scheme is
+/// case-insensitive and may be followed by `//`.
+impl FromStr for LightningUri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<LightningUri, Error> {
+        let rest = strip_scheme(s.trim(), LIGHTNING_SCHEME).ok_or_else(|| {
+            Error::InvalidFormat(format!("missing {} scheme", LIGHTNING_SCHEME))
+        })?;
+        let rest = rest.strip_prefix("//").unwrap_or(rest);
+        let (invoice, query) = match rest.find('?') {
+            Some(index) => (&rest[..index], &rest[index + 1..]),
+            None => (rest, ""),
+        };
+        Ok(LightningUri {
+            invoice: PaymentRequest::decode(invoice)?,
+            params: parse_query(query)?,
+        })
+    }
+}
+
+/// BIP-21 URI offering an on-chain address and, with the `lightning` parameter, a payment
+/// request, the payer choosing either.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnifiedUri {
+    /// On-chain address, None for a `bitcoin:?lightning=...` URI.
+    pub address: Option<String>,
+    /// Amount in millisatoshis, the `amount` parameter in bitcoin.
+    pub amount: Option<u64>,
+    /// Label of the recipient.
+    pub label: Option<String>,
+    /// Message describing the payment.
+    pub message: Option<String>,
+    /// Payment request, the `lightning` parameter.
+    pub lightning: Option<PaymentRequest>,
+    /// Other query parameters, percent-decoded, in order.
+    pub params: Vec<(String, String)>,
+}
+
+impl UnifiedUri {
+    /// Create a unified URI for an address and a payment request, with the amount of the
+    /// payment request when it is a whole number of satoshis.
+    /// # Arguments
+    /// `address` On-chain address. <br>
+    /// `invoice` Payment request.
+    pub fn new(address: &str, invoice: PaymentRequest) -> UnifiedUri {
+        UnifiedUri {
+            address: Some(address.to_owned()),
+            amount: invoice.amount.filter(|amount| amount % 1000 == 0),
+            label: None,
+            message: None,
+            lightning: Some(invoice),
+            params: vec![],
+        }
+    }
+
+    /// Return the URI, failing if the payment request can't be encoded, e.g. it isn't signed.
+    pub fn to_uri(&self) -> Result<String, Error> {
+        let mut uri = format!(
+            "{}{}",
+            BITCOIN_SCHEME,
+            self.address.as_ref().map_or("", |a| a.as_str())
+        );
+        let mut params = vec![];
+        if let Some(amount) = self.amount {
+            params.push(("amount".to_owned(), Amount::from_msat(amount).to_plain_btc_string()));
+        }
+        if let Some(ref label) = self.label {
+            params.push(("label".to_owned(), label.to_owned()));
+        }
+        if let Some(ref message) = self.message {
+            params.push(("message".to_owned(), message.to_owned()));
+        }
+        if let Some(ref invoice) = self.lightning {
+            params.push(("lightning".to_owned(), invoice.encode()?));
+        }
+        params.extend(self.params.iter().cloned());
+        write_query(&mut uri, &params, '?').map_err(encoder::write_error)?;
+        Ok(uri)
+    }
+}
+
+/// Parse a BIP-21 URI: the surrounding whitespace is ignored, the scheme and the parameter
+/// names are case-insensitive; an unknown `req-` parameter is an error, as BIP-21 requires.
+impl FromStr for UnifiedUri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<UnifiedUri, Error> {
+        let rest = strip_scheme(s.trim(), BITCOIN_SCHEME).ok_or_else(|| {
+            Error::InvalidFormat(format!("missing {} scheme", BITCOIN_SCHEME))
+        })?;
+        let (address, query) = match rest.find('?') {
+            Some(index) => (&rest[..index], &rest[index + 1..]),
+            None => (rest, ""),
+        };
+        let mut uri = UnifiedUri {
+            address: if address.is_empty() {
+                None
+            } else {
+                Some(address.to_owned())
+            },
+            amount: None,
+            label: None,
+            message: None,
+            lightning: None,
+            params: vec![],
+        };
+        for (key, value) in parse_query(query)? {
+            match key.to_lowercase().as_str() {
+                "amount" => uri.amount = Some(Amount::from_plain_btc_string(&value)?),
+                "label" => uri.label = Some(value),
+                "message" => uri.message = Some(value),
+                "lightning" => uri.lightning = Some(PaymentRequest::decode(&value)?),
+                name if name.starts_with("req-") => {
+                    return Err(Error::InvalidParameter(format!(
+                        "unsupported required parameter {}",
+                        key
+                    )));
+                }
+                _ => uri.params.push((key, value)),
+            }
+        }
+        Ok(uri)
+    }
+}
+
+/// Return what follows the scheme, matched case-insensitively, or None if it is missing.
+/// # Arguments
+/// `uri` The URI. <br>
+/// `scheme` The scheme, including the colon.
+pub fn strip_scheme<'a>(uri: &'a str, scheme: &str) -> Option<&'a str> {
+    match uri.get(..scheme.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(scheme) => Some(&uri[scheme.len()..]),
+        _ => None,
+    }
+}
+
+/// Parse the query of a URI, without the `?`, into percent-decoded key and value pairs.
+/// # Arguments
+/// `query` The query, e.g. `amount=0.001&label=coffee`.
+pub fn parse_query(query: &str) -> Result<Vec<(String, String)>, Error> {
+    query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (key, value) = match param.find('=') {
+                Some(index) => (&param[..index], &param[index + 1..]),
+                None => (param, ""),
+            };
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect()
+}
+
+/// Write the query parameters, percent-encoded, the first one preceded by `separator`.
+/// # Arguments
+/// `f` Sink to write to, e.g. a formatter or a string. <br>
+/// `params` Key and value pairs. <br>
+/// `separator` `?` to start the query, `&` to continue it.
+pub fn write_query<W: fmt::Write>(
+    f: &mut W,
+    params: &[(String, String)],
+    separator: char,
+) -> fmt::Result {
+    for (i, (key, value)) in params.iter().enumerate() {
+        let separator = if i == 0 { separator } else { '&' };
+        write!(f, "{}{}={}", separator, percent_encode(key), percent_encode(value))?;
+    }
+    Ok(())
+}
+
+/// Percent-encode every byte but the unreserved characters of RFC 3986.
+/// # Arguments
+/// `s` String to encode.
+pub fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Decode the `%XX` escapes of a URI component, which must give UTF-8.
+/// # Arguments
+/// `s` String to decode.
+pub fn percent_decode(s: &str) -> Result<String, Error> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let escape = s.get(i + 1..i + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| Error::InvalidFormat(format!("invalid percent escape in {}", s)))?;
+            decoded.push(escape);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(decoded)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TX_REF: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+    #[test]
+    fn parse() {
+        let invoice = PaymentRequest::decode(TX_REF).unwrap();
+
+        for uri in &[
+            format!("lightning:{}", TX_REF),
+            format!("  Lightning://{}\n", TX_REF),
+            format!("LIGHTNING:{}", TX_REF.to_uppercase()),
+        ] {
+            assert_eq!(uri.parse::<LightningUri>().unwrap(), LightningUri::new(invoice.to_owned()));
+        }
+
+        let uri = format!("lightning:{}?label=cup%20of%20coffee&flag", TX_REF)
+            .parse::<LightningUri>()
+            .unwrap();
+        assert_eq!(uri.param("label"), Some("cup of coffee"));
+        assert_eq!(uri.param("flag"), Some(""));
+        assert_eq!(uri.param("amount"), None);
+
+        assert!(matches!(TX_REF.parse::<LightningUri>(), Err(Error::InvalidFormat(_))));
+        assert!(format!("lightning:{}?label=%2", TX_REF).parse::<LightningUri>().is_err());
+        assert!(percent_decode("%+1").is_err());
+        assert!("lightning:lnbc1".parse::<LightningUri>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        let invoice = PaymentRequest::decode(TX_REF).unwrap();
+
+        assert_eq!(invoice.to_uri().unwrap(), format!("lightning:{}", TX_REF));
+        let uri = LightningUri::new(invoice).with_param("label", "café & co");
+        assert_eq!(
+            uri.to_uri().unwrap(),
+            format!("lightning:{}?label=caf%C3%A9%20%26%20co", TX_REF)
+        );
+        assert_eq!(uri.to_uri().unwrap().parse::<LightningUri>().unwrap(), uri);
+
+        let mut unsigned = uri;
+        unsigned.invoice.signature = vec![];
+        assert!(matches!(unsigned.to_uri(), Err(Error::InvalidLength(_))));
+    }
+
+    #[test]
+    fn unified() {
+        let invoice = PaymentRequest::decode(TX_REF).unwrap();
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+        let uri = UnifiedUri::new(address, invoice.to_owned());
+
+        assert_eq!(
+            uri.to_uri().unwrap(),
+            format!("bitcoin:{}?amount=0.0025&lightning={}", address, TX_REF)
+        );
+        assert_eq!(uri.to_uri().unwrap().parse::<UnifiedUri>().unwrap(), uri);
+        assert_eq!(invoice.to_unified_uri(address).unwrap(), uri.to_uri().unwrap());
+
+        let mut unsigned = uri.clone();
+        unsigned.lightning.as_mut().unwrap().signature = vec![];
+        assert!(matches!(unsigned.to_uri(), Err(Error::InvalidLength(_))));
+
+        let parsed = format!(
+            "BITCOIN:{}?amount=0.0025&label=Coffee%20shop&LIGHTNING={}&pj=https",
+            address.to_uppercase(),
+            TX_REF.to_uppercase()
+        ).parse::<UnifiedUri>()
+            .unwrap();
+        assert_eq!(parsed.address, Some(address.to_uppercase()));
+        assert_eq!(parsed.amount, Some(250_000_000));
+        assert_eq!(parsed.label, Some("Coffee shop".to_owned()));
+        assert_eq!(parsed.lightning, Some(invoice));
+        assert_eq!(parsed.params, vec![("pj".to_owned(), "https".to_owned())]);
+
+        let lightning_only = format!("bitcoin:?lightning={}", TX_REF)
+            .parse::<UnifiedUri>()
+            .unwrap();
+        assert_eq!(lightning_only.address, None);
+        assert!(lightning_only.lightning.is_some());
+
+        assert!(matches!(
+            format!("bitcoin:{}?req-somethingyoudontunderstand=50", address).parse::<UnifiedUri>(),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(format!("bitcoin:{}?amount=1e3", address).parse::<UnifiedUri>().is_err());
+        assert!(format!("lightning:{}", TX_REF).parse::<UnifiedUri>().is_err());
+    }
+}
@@ -3,90 +3,60 @@
 use std::fmt::Write;
 use std::num;
 use types::{ConvertResult, Error};
-use num::bigint::BigUint;
 
 /// Alias for u8 that contains 5-bit values
 pub type U5 = u8;
 
+/// Buffer of the values of a tagged field, e.g. the u5 of a field or the bytes of a hash. <br>
+/// With the `smallvec` feature the first 128 values are stored inline, which covers most fields
+/// and spares their heap allocation.
+#[cfg(feature = "smallvec")]
+pub type FieldBuffer = ::smallvec::SmallVec<[u8; 128]>;
+/// Buffer of the values of a tagged field, e.g. the u5 of a field or the bytes of a hash.
+#[cfg(not(feature = "smallvec"))]
+pub type FieldBuffer = Vec<u8>;
+
+/// Handling of the trailing bits of a bit size conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// Complete the last value with zero bits, e.g. when encoding
+    Add,
+    /// Drop the trailing bits, which must be fewer than the input bit size and all zeros
+    Strict,
+}
+
 pub trait U5Conversions {
-    /// convert a vector of 5-bit values to hex-string
-    fn u5_to_hex(&self) -> String;
     /// Convert a vector containing u5 values to u8
-    fn to_u8_vec(&self, padding: bool) -> ConvertResult;
-    /// Convert a vector of u5 values to u64
-    fn u5_vec_to_u64(&self, length: usize) -> u64;
+    fn to_u8_vec(&self, padding: Padding) -> ConvertResult;
+    /// Convert a vector of u5 values to u64, fails if the value exceeds 64 bits
+    fn u5_vec_to_u64(&self, length: usize) -> Result<u64, Error>;
 }
 
-impl U5Conversions for Vec<U5> {
-    /// convert a vector of 5-bit values to hex-string
-    fn u5_to_hex(&self) -> String {
-        let u5 = self.iter().fold(BigUint::from(0u64), |mut s, b| {
-            s <<= 5;
-            s |= BigUint::from(*b);
-            s
-        });
-        u5.to_str_radix(16)
-    }
+impl U5Conversions for [U5] {
     /// Convert a vector containing u5 values to u8
-    fn to_u8_vec(&self, padding: bool) -> ConvertResult {
+    fn to_u8_vec(&self, padding: Padding) -> ConvertResult {
         convert_bits(self, 5, 8, padding)
     }
-    /// Convert a vector of u5 values to u64
-    fn u5_vec_to_u64(&self, length: usize) -> u64 {
-        self.iter()
-            .take(length)
-            .fold(0u64, |acc, i| acc * 32u64 + *i as u64)
+    /// Convert a vector of u5 values to u64, fails if the value exceeds 64 bits
+    fn u5_vec_to_u64(&self, length: usize) -> Result<u64, Error> {
+        self.iter().take(length).try_fold(0u64, |acc, i| {
+            acc.checked_mul(32)
+                .map(|acc| acc | u64::from(*i))
+                .ok_or(Error::InvalidValue("value exceeds 64 bits".to_owned()))
+        })
     }
 }
 
 pub trait U8Conversions {
     /// Convert a vector containing u8 values to u5
-    fn to_u5_vec(&self, padding: bool) -> ConvertResult;
-    /// Convert a vector of u8 to hex-string
-    fn to_hex_string(&self) -> String;
+    fn to_u5_vec(&self, padding: Padding) -> ConvertResult;
 }
 
-impl U8Conversions for Vec<u8> {
+impl U8Conversions for [u8] {
     /// Convert a vector containing u8 values to u5
-    fn to_u5_vec(&self, padding: bool) -> ConvertResult {
+    fn to_u5_vec(&self, padding: Padding) -> ConvertResult {
         convert_bits(self, 8, 5, padding)
     }
-    /// Convert a vector of u8 to hex-string
-    fn to_hex_string(&self) -> String {
-        self.iter().fold(String::new(), |mut acc: String, b: &u8| {
-            write!(&mut acc, "{:02x}", b).expect("Unable to write");
-            acc
-        })
-    }
-}
-
-pub trait StringConversions {
-    /// Convert a hex string to bytes
-    fn hex_to_bytes(&self) -> Result<Vec<u8>, num::ParseIntError>;
-}
-
-impl StringConversions for String {
-    fn hex_to_bytes(&self) -> Result<Vec<u8>, num::ParseIntError> {
-        /// split a string in chunks, the length of the string must be even
-        fn split_n(s: &str, n: usize) -> Vec<&str> {
-            (0..(s.len() - n + 1) / 2 + 1)
-                .map(|i| &s[2 * i..2 * i + n])
-                .collect()
-        }
-
-        let padded: String = if self.len() % 2 != 0 {
-            let mut s = String::from("0");
-            s.push_str(self);
-            s
-        } else {
-            self.to_owned()
-        };
-
-        split_n(&padded.trim()[..], 2)
-            .iter()
-            .map(|b| u8::from_str_radix(b, 16))
-            .collect::<Result<Vec<u8>, _>>()
-    }
 }
 
 pub trait U64VecU5Conversions {
@@ -124,48 +94,75 @@ pub fn from_hex(hex_str: &str) -> Result<Vec<u8>, num::ParseIntError> {
     } else {
         hex_str.to_owned()
     };
-    split_n(&padded.trim()[..], 2)
+    split_n(padded.trim(), 2)
         .iter()
         .map(|b| u8::from_str_radix(b, 16))
         .collect::<Result<Vec<u8>, _>>()
 }
 /// split a string in chunks, the length of the string must be even
 fn split_n(s: &str, n: usize) -> Vec<&str> {
-    (0..(s.len() - n + 1) / 2 + 1)
+    (0..(s.len() - n).div_ceil(2) + 1)
         .map(|i| &s[2 * i..2 * i + n])
         .collect()
 }
 
-/// Convert between bit sizes
-pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> ConvertResult {
-    if from > 8 || to > 8 {
+/// Convert between bit sizes, up to 8 bits. <br>
+/// The values are accumulated in a u64 a chunk at a time, at most 7 values of 8 bits being added
+/// to the at most 7 bits left by the previous chunk.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, padding: Padding) -> ConvertResult {
+    let mut ret: Vec<u8> = Vec::with_capacity((data.len() * from as usize).div_ceil(to as usize));
+    convert_bits_into(data, from, to, padding, &mut ret)?;
+    Ok(ret)
+}
+
+/// Convert between bit sizes into a field buffer, see `convert_bits`.
+pub fn convert_bits_buffer(data: &[u8], from: u32, to: u32, padding: Padding) -> Result<FieldBuffer, Error> {
+    let mut ret = FieldBuffer::new();
+    convert_bits_into(data, from, to, padding, &mut ret)?;
+    Ok(ret)
+}
+
+// Helper for convert_bits and convert_bits_buffer.
+fn convert_bits_into<B: Extend<u8>>(
+    data: &[u8],
+    from: u32,
+    to: u32,
+    padding: Padding,
+    ret: &mut B,
+) -> Result<(), Error> {
+    if from == 0 || to == 0 || from > 8 || to > 8 {
         return Err(Error::InvalidParameter(
-            "convert_bits `from` and `to` parameters greater than 8".to_owned(),
+            "convert_bits `from` and `to` parameters must be between 1 and 8".to_owned(),
         ));
     }
-    let mut acc: u32 = 0;
+    let mut acc: u64 = 0;
     let mut bits: u32 = 0;
-    let mut ret: Vec<u8> = Vec::new();
-    let maxv: u32 = (1 << to) - 1;
-    for value in data {
-        let v: u32 = *value as u32;
-        if (v >> from) != 0 {
+    let maxv: u64 = (1 << to) - 1;
+    for chunk in data.chunks(7) {
+        if u32::from(chunk.iter().fold(0, |all, &v| all | v)) >> from != 0 {
             // Input value exceeds `from` bit size
-            return Err(Error::InvalidInputValue(v as u8));
+            let v = chunk.iter().find(|&&v| u32::from(v) >> from != 0);
+            return Err(Error::InvalidInputValue(v.cloned().unwrap_or_default()));
+        }
+        for &v in chunk {
+            acc = (acc << from) | u64::from(v);
         }
-        acc = (acc << from) | v;
-        bits += from;
+        bits += from * chunk.len() as u32;
         while bits >= to {
             bits -= to;
-            ret.push(((acc >> bits) & maxv) as u8);
+            ret.extend(Some(((acc >> bits) & maxv) as u8));
         }
+        acc &= (1 << bits) - 1;
     }
-    if pad {
-        if bits > 0 {
-            ret.push(((acc << (to - bits)) & maxv) as u8);
+    match padding {
+        Padding::Add if bits > 0 => ret.extend(Some(((acc << (to - bits)) & maxv) as u8)),
+        Padding::Strict if bits >= from || acc != 0 => {
+            // Padding must be shorter than `from` bits and all zeros
+            return Err(Error::InvalidPadding);
         }
+        Padding::Add | Padding::Strict => (),
     }
-    Ok(ret)
+    Ok(())
 }
 
 #[cfg(test)]
@@ -174,9 +171,9 @@ mod test {
 
     #[test]
     fn test_to_hex() {
-        assert_eq!(to_hex(&vec![0, 0, 0, 0]), "00000000");
-        assert_eq!(to_hex(&vec![10, 11, 12, 13]), "0a0b0c0d");
-        assert_eq!(to_hex(&vec![0, 0, 0, 255]), "000000ff");
+        assert_eq!(to_hex(&[0, 0, 0, 0]), "00000000");
+        assert_eq!(to_hex(&[10, 11, 12, 13]), "0a0b0c0d");
+        assert_eq!(to_hex(&[0, 0, 0, 255]), "000000ff");
     }
 
     #[test]
@@ -186,6 +183,20 @@ mod test {
         assert_eq!(from_hex("000000ff").unwrap(), vec![0, 0, 0, 255]);
     }
 
+    #[test]
+    fn u5_vec_to_u64_overflow() {
+        let max = [vec![15u8], vec![31; 12]].concat();
+        assert_eq!(max.u5_vec_to_u64(max.len()).unwrap(), u64::MAX);
+        // only the first `length` values are read
+        assert_eq!([1u8, 2, 31, 31].u5_vec_to_u64(2).unwrap(), 34);
+
+        let overflow = [vec![16u8], vec![0; 12]].concat();
+        assert!(matches!(
+            overflow.u5_vec_to_u64(overflow.len()),
+            Err(Error::InvalidValue(_))
+        ));
+    }
+
     #[test]
     fn u5_test() {
         let u5_vec = vec![
@@ -197,7 +208,42 @@ mod test {
             214,
         ];
 
-        assert!(u5_vec.to_u8_vec(false).unwrap().eq(&u8_vec));
-        assert!(u8_vec.to_u5_vec(true).unwrap().eq(&u5_vec));
+        assert!(u5_vec.to_u8_vec(Padding::Strict).unwrap().eq(&u8_vec));
+        assert!(u8_vec.to_u5_vec(Padding::Add).unwrap().eq(&u5_vec));
+    }
+
+    #[test]
+    fn u5_padding_test() {
+        // 10 bits, the 2 padding bits are dropped
+        assert_eq!([31u8, 28].to_u8_vec(Padding::Strict).unwrap(), vec![255]);
+        // non zero padding bits
+        assert!(matches!(
+            [31u8, 29].to_u8_vec(Padding::Strict),
+            Err(Error::InvalidPadding)
+        ));
+        // 15 bits, 7 padding bits
+        assert!(matches!(
+            [31u8, 28, 0].to_u8_vec(Padding::Strict),
+            Err(Error::InvalidPadding)
+        ));
+    }
+
+    #[test]
+    fn convert_bits_round_trip() {
+        // spans several chunks with bits left between them
+        let bytes = (0..=255u8).collect::<Vec<u8>>();
+        let u5 = bytes.to_u5_vec(Padding::Add).unwrap();
+
+        assert_eq!(u5.len(), (256 * 8usize).div_ceil(5));
+        assert!(u5.iter().all(|&v| v < 32));
+        assert_eq!(u5.to_u8_vec(Padding::Strict).unwrap(), bytes);
+        assert!(matches!(
+            convert_bits(&[1, 2, 3, 4, 5, 6, 7, 32], 5, 8, Padding::Strict),
+            Err(Error::InvalidInputValue(32))
+        ));
+        assert_eq!(
+            &convert_bits_buffer(&u5[..52], 5, 8, Padding::Add).unwrap()[..],
+            &convert_bits(&u5[..52], 5, 8, Padding::Add).unwrap()[..]
+        );
     }
 }
@@ -0,0 +1,490 @@
+//! Decoding without heap allocations for embedded targets, e.g. signers running on a
+//! microcontroller. <br>
+//! `RawInvoiceView` borrows the payment request instead of copying it: the checksum is verified
+//! on the fly, the tagged fields are slices of the input and their data is read into buffers of
+//! the caller. The numbers of tagged fields and of route hops are bounded at compile time by
+//! const generic capacities. <br>
+//! *Note*: the view doesn't interpret the tagged fields beyond their type and length, see
+//! `PaymentRequest` for the full decoding. The crate still depends on `std` though, so it can't
+//! be built for the targets without one yet.
+
+use bech32::{char_value, polymod_str, CHARSET};
+use std::ops::Deref;
+use std::str::Bytes;
+use types::ViewError;
+
+/// Default capacity of tagged fields of `RawInvoiceView`.
+pub const DEFAULT_MAX_TAGS: usize = 16;
+
+/// Length of a hop of a routing tag in bytes.
+const HOP_LEN: usize = 51;
+
+/// Length of the timestamp in u5.
+const TIMESTAMP_LEN: usize = 7;
+
+/// Length of the signature in u5.
+const SIGNATURE_LEN: usize = 104;
+
+/// Length of the checksum in u5.
+const CHECKSUM_LEN: usize = 6;
+
+/// Vector of fixed capacity, stored inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedVec<T: Copy + Default, const N: usize> {
+    items: [T; N],
+    len: usize,
+}
+
+/// Tagged field borrowed from a payment request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RawTagView<'a> {
+    /// Type of the field, e.g. `'p'`.
+    pub tag: char,
+    /// Data of the field, as bech32 characters of the payment request.
+    pub data: &'a str,
+}
+
+/// Hop of a routing tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HopView {
+    /// Compressed node id of the start of the channel.
+    pub node_id: [u8; 33],
+    /// Short channel id as a 64-bit integer.
+    pub short_channel_id: u64,
+    /// Base fee in millisatoshis.
+    pub fee_base_msat: u32,
+    /// Proportional fee in millionths.
+    pub fee_proportional_millionths: u32,
+    /// Channel's cltv expiry delta.
+    pub cltv_expiry_delta: u16,
+}
+
+/// Payment request borrowed from its encoding, with at most `TAGS` tagged fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawInvoiceView<'a, const TAGS: usize = DEFAULT_MAX_TAGS> {
+    /// Prefix, e.g. `lnbc`, in the case of the input.
+    pub prefix: &'a str,
+    /// Amount part of the human readable part, empty if missing.
+    pub raw_amount: &'a str,
+    /// Amount in millisatoshis, if any.
+    pub amount_msat: Option<u64>,
+    /// Request timestamp (UNIX format).
+    pub timestamp: u64,
+    /// Tagged fields, in order.
+    pub tags: FixedVec<RawTagView<'a>, TAGS>,
+    /// Compact signature.
+    pub signature: [u8; 64],
+    /// Recovery id of the signature.
+    pub recovery_id: u8,
+}
+
+/// Bytes of bech32 characters, 8 bits at a time, the trailing padding bits being dropped.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    chars: Bytes<'a>,
+    acc: u32,
+    bits: u32,
+}
+
+impl<T: Copy + Default, const N: usize> FixedVec<T, N> {
+    /// Create an empty vector.
+    pub fn new() -> FixedVec<T, N> {
+        FixedVec {
+            items: [T::default(); N],
+            len: 0,
+        }
+    }
+
+    /// Append an item, return it back if the vector is full.
+    pub fn push(&mut self, item: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(item);
+        }
+        self.items[self.len] = item;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the vector empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Maximum number of items.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Items of the vector.
+    pub fn as_slice(&self) -> &[T] {
+        &self.items[..self.len]
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for FixedVec<T, N> {
+    fn default() -> FixedVec<T, N> {
+        FixedVec::new()
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Deref for FixedVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl Default for HopView {
+    fn default() -> HopView {
+        HopView {
+            node_id: [0u8; 33],
+            short_channel_id: 0,
+            fee_base_msat: 0,
+            fee_proportional_millionths: 0,
+            cltv_expiry_delta: 0,
+        }
+    }
+}
+
+impl<'a> ByteReader<'a> {
+    /// Create a reader of bech32 characters, already checked to be in the character set.
+    /// # Arguments
+    /// `data` Bech32 characters in either case.
+    pub fn new(data: &'a str) -> ByteReader<'a> {
+        ByteReader {
+            chars: data.bytes(),
+            acc: 0,
+            bits: 0,
+        }
+    }
+
+    // Helper for the readers, fill the output buffer.
+    fn read_into(&mut self, out: &mut [u8]) -> Result<(), ViewError> {
+        for byte in out.iter_mut() {
+            *byte = self.next().ok_or(ViewError::InvalidLength)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for ByteReader<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.bits < 8 {
+            let value = char_value(self.chars.next()?).unwrap_or_default();
+            self.acc = (self.acc << 5) | u32::from(value);
+            self.bits += 5;
+        }
+        self.bits -= 8;
+        Some((self.acc >> self.bits) as u8)
+    }
+}
+
+impl<'a> RawTagView<'a> {
+    /// Length of the data in u5.
+    pub fn len_u5(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Length of the data in bytes, the padding bits being dropped.
+    pub fn len_bytes(&self) -> usize {
+        self.data.len() * 5 / 8
+    }
+
+    /// Bytes of the data, the padding bits being dropped.
+    pub fn bytes(&self) -> ByteReader<'a> {
+        ByteReader::new(self.data)
+    }
+
+    /// Read the bytes of the data into a buffer, e.g. the UTF-8 description of a `d` field.
+    /// # Arguments
+    /// `out` Buffer of at least `len_bytes` bytes.
+    pub fn read_bytes<'b>(&self, out: &'b mut [u8]) -> Result<&'b [u8], ViewError> {
+        let len = self.len_bytes();
+        if out.len() < len {
+            return Err(ViewError::BufferTooSmall(len));
+        }
+        ByteReader::new(self.data).read_into(&mut out[..len])?;
+        Ok(&out[..len])
+    }
+
+    /// Read a 32 bytes hash, e.g. of a `p` or `h` field.
+    pub fn hash(&self) -> Result<[u8; 32], ViewError> {
+        if self.len_u5() != 52 {
+            return Err(ViewError::InvalidLength);
+        }
+        let mut hash = [0u8; 32];
+        ByteReader::new(self.data).read_into(&mut hash)?;
+        Ok(hash)
+    }
+
+    /// Read the hops of a routing tag, at most `HOPS`.
+    pub fn route_hops<const HOPS: usize>(&self) -> Result<FixedVec<HopView, HOPS>, ViewError> {
+        let len = self.len_bytes();
+        if self.tag != 'r' || len == 0 || len % HOP_LEN != 0 {
+            return Err(ViewError::InvalidLength);
+        }
+        if len / HOP_LEN > HOPS {
+            return Err(ViewError::TooManyHops(HOPS));
+        }
+        let mut reader = ByteReader::new(self.data);
+        let mut hops = FixedVec::new();
+        for _ in 0..len / HOP_LEN {
+            let mut bytes = [0u8; HOP_LEN];
+            reader.read_into(&mut bytes)?;
+            let mut hop = HopView::default();
+            hop.node_id.copy_from_slice(&bytes[..33]);
+            hop.short_channel_id = be_u64(&bytes[33..41]);
+            hop.fee_base_msat = be_u64(&bytes[41..45]) as u32;
+            hop.fee_proportional_millionths = be_u64(&bytes[45..49]) as u32;
+            hop.cltv_expiry_delta = be_u64(&bytes[49..51]) as u16;
+            hops.push(hop).map_err(|_| ViewError::TooManyHops(HOPS))?;
+        }
+        Ok(hops)
+    }
+
+    /// Read the data as a big-endian integer, e.g. of an `x` or `c` field.
+    pub fn number(&self) -> Result<u64, ViewError> {
+        if self.len_u5() > 12 {
+            return Err(ViewError::InvalidLength);
+        }
+        Ok(self.data
+            .bytes()
+            .fold(0, |n, c| n << 5 | u64::from(char_value(c).unwrap_or_default())))
+    }
+}
+
+impl<'a, const TAGS: usize> RawInvoiceView<'a, TAGS> {
+    /// Decode a payment request without allocating, checking its checksum and the bounds of its
+    /// tagged fields.
+    /// # Arguments
+    /// `input` Payment request, all lowercase or all uppercase.
+    pub fn parse(input: &'a str) -> Result<RawInvoiceView<'a, TAGS>, ViewError> {
+        let has_lower = input.bytes().any(|b| b.is_ascii_lowercase());
+        let has_upper = input.bytes().any(|b| b.is_ascii_uppercase());
+        if has_lower && has_upper {
+            return Err(ViewError::MixedCase);
+        }
+        let separator = input.rfind('1').ok_or(ViewError::MissingSeparator)?;
+        let (hrp, data) = (&input[..separator], &input[separator + 1..]);
+        if let Some(position) = hrp.bytes().position(|b| !(33..=126).contains(&b)) {
+            return Err(ViewError::InvalidChar(position));
+        }
+        if let Some(position) = data.bytes().position(|b| char_value(b).is_none()) {
+            return Err(ViewError::InvalidChar(separator + 1 + position));
+        }
+        if data.len() < TIMESTAMP_LEN + SIGNATURE_LEN + CHECKSUM_LEN {
+            return Err(ViewError::TooShort);
+        }
+
+        if polymod_str(hrp, data) != 1 {
+            return Err(ViewError::InvalidChecksum);
+        }
+
+        if !hrp.get(..2).is_some_and(|ln| ln.eq_ignore_ascii_case("ln")) {
+            return Err(ViewError::InvalidPrefix);
+        }
+        let amount_start = hrp
+            .bytes()
+            .position(|b| b.is_ascii_digit())
+            .unwrap_or(hrp.len());
+        let (prefix, raw_amount) = hrp.split_at(amount_start);
+        let amount_msat = parse_amount(raw_amount)?;
+
+        let data = &data[..data.len() - CHECKSUM_LEN];
+        let (timestamp, rest) = data.split_at(TIMESTAMP_LEN);
+        let (mut fields, signature_data) = rest.split_at(rest.len() - SIGNATURE_LEN);
+        let timestamp = RawTagView {
+            tag: 't',
+            data: timestamp,
+        }.number()?;
+
+        let mut tags = FixedVec::new();
+        let mut position = separator + 1 + TIMESTAMP_LEN;
+        while !fields.is_empty() {
+            if fields.len() < 3 {
+                return Err(ViewError::TagOverflow(position));
+            }
+            let values = fields
+                .bytes()
+                .take(3)
+                .map(|b| char_value(b).unwrap_or_default() as usize)
+                .collect::<FixedVec<usize, 3>>();
+            let len = values[1] * 32 + values[2];
+            if fields.len() < 3 + len {
+                return Err(ViewError::TagOverflow(position));
+            }
+            let tag = RawTagView {
+                tag: CHARSET[values[0]],
+                data: &fields[3..3 + len],
+            };
+            tags.push(tag)
+                .map_err(|_| ViewError::TooManyTags(TAGS))?;
+            fields = &fields[3 + len..];
+            position += 3 + len;
+        }
+
+        let mut signature = [0u8; 65];
+        ByteReader::new(signature_data).read_into(&mut signature)?;
+        let mut compact = [0u8; 64];
+        compact.copy_from_slice(&signature[..64]);
+
+        Ok(RawInvoiceView {
+            prefix,
+            raw_amount,
+            amount_msat,
+            timestamp,
+            tags,
+            signature: compact,
+            recovery_id: signature[64],
+        })
+    }
+
+    /// First tagged field of the type, if any.
+    /// # Arguments
+    /// `tag` Type of the field, e.g. `'p'`.
+    pub fn tag(&self, tag: char) -> Option<&RawTagView<'a>> {
+        self.tags.iter().find(|field| field.tag == tag)
+    }
+
+    /// Payment hash of the `p` field, if any.
+    pub fn payment_hash(&self) -> Option<Result<[u8; 32], ViewError>> {
+        self.tag('p').map(RawTagView::hash)
+    }
+}
+
+impl<T: Copy + Default, const N: usize> ::std::iter::FromIterator<T> for FixedVec<T, N> {
+    /// Collect at most `N` items, the extra ones being dropped.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> FixedVec<T, N> {
+        let mut items = FixedVec::new();
+        for item in iter.into_iter().take(N) {
+            let _ = items.push(item);
+        }
+        items
+    }
+}
+
+// Helper for route_hops, a big-endian integer of at most 8 bytes.
+fn be_u64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |n, &b| n << 8 | u64::from(b))
+}
+
+// Helper for parse, the amount in millisatoshis of the amount part.
+fn parse_amount(raw_amount: &str) -> Result<Option<u64>, ViewError> {
+    if raw_amount.is_empty() {
+        return Ok(None);
+    }
+    let digits_len = raw_amount
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .count();
+    let (digits, multiplier) = raw_amount.split_at(digits_len);
+    if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return Err(ViewError::InvalidAmount);
+    }
+    let value = digits
+        .bytes()
+        .try_fold(0u64, |n, d| n.checked_mul(10)?.checked_add(u64::from(d - b'0')))
+        .ok_or(ViewError::InvalidAmount)?;
+    let msat = match multiplier.as_bytes() {
+        b"" => value.checked_mul(100_000_000_000),
+        b"m" | b"M" => value.checked_mul(100_000_000),
+        b"u" | b"U" => value.checked_mul(100_000),
+        b"n" | b"N" => value.checked_mul(100),
+        b"p" | b"P" if value % 10 == 0 => Some(value / 10),
+        _ => None,
+    };
+    msat.map(Some).ok_or(ViewError::InvalidAmount)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use payment_request::PaymentRequest;
+
+    const TX_REF: &str = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+        58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+        9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+        qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+        f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+        dhhwkj";
+
+    #[test]
+    fn parse() {
+        let view = RawInvoiceView::<8>::parse(TX_REF).unwrap();
+        let pay_request = PaymentRequest::decode(TX_REF).unwrap();
+
+        assert_eq!(view.prefix, "lnbc");
+        assert_eq!(view.raw_amount, "20m");
+        assert_eq!(view.amount_msat, pay_request.amount);
+        assert_eq!(view.timestamp, pay_request.timestamp);
+        assert_eq!(
+            view.tags.iter().map(|tag| tag.tag).collect::<String>(),
+            pay_request.tags.iter().map(|tag| tag.tag_char()).collect::<String>()
+        );
+        assert_eq!(
+            &view.payment_hash().unwrap().unwrap(),
+            pay_request.payment_hash().unwrap().as_bytes()
+        );
+        assert_eq!(&view.signature[..], &pay_request.signature[..64]);
+        assert_eq!(view.recovery_id, pay_request.signature[64]);
+        assert_eq!(
+            RawInvoiceView::<8>::parse(&TX_REF.to_uppercase()).unwrap().timestamp,
+            view.timestamp
+        );
+    }
+
+    #[test]
+    fn route_hops() {
+        let view: RawInvoiceView = RawInvoiceView::parse(TX_REF).unwrap();
+        let routing_info = view.tag('r').unwrap();
+        let hops = routing_info.route_hops::<4>().unwrap();
+        let expected = PaymentRequest::decode(TX_REF).unwrap().routing_info();
+
+        assert_eq!(hops.len(), 2);
+        assert_eq!(hops[0].node_id, *expected[0].pub_key.as_bytes());
+        assert_eq!(hops[1].short_channel_id, 217304205466536202);
+        assert_eq!(hops[1].fee_base_msat, expected[1].fee_base_msat);
+        assert_eq!(hops[1].cltv_expiry_delta, expected[1].cltv_expiry_delta);
+        assert_eq!(
+            routing_info.route_hops::<1>(),
+            Err(ViewError::TooManyHops(1))
+        );
+        let mut buffer = [0u8; 8];
+        assert_eq!(
+            view.tag('h').unwrap().read_bytes(&mut buffer),
+            Err(ViewError::BufferTooSmall(32))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            RawInvoiceView::<8>::parse(&TX_REF.replacen("lnbc", "LNbc", 1)),
+            Err(ViewError::MixedCase)
+        );
+        assert_eq!(
+            RawInvoiceView::<8>::parse(&TX_REF.replacen("dhhwkj", "dhhwkk", 1)),
+            Err(ViewError::InvalidChecksum)
+        );
+        assert_eq!(
+            RawInvoiceView::<2>::parse(TX_REF),
+            Err(ViewError::TooManyTags(2))
+        );
+        assert_eq!(
+            RawInvoiceView::<8>::parse("lnbc1qqqqqq"),
+            Err(ViewError::TooShort)
+        );
+        assert_eq!(parse_amount("2500u"), Ok(Some(250_000_000)));
+        assert_eq!(parse_amount("10p"), Ok(Some(1)));
+        assert_eq!(parse_amount("1p"), Err(ViewError::InvalidAmount));
+        assert_eq!(parse_amount("99999999999999m"), Err(ViewError::InvalidAmount));
+    }
+}
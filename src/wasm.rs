@@ -0,0 +1,252 @@
+//! JavaScript bindings built with wasm-bindgen, behind the `wasm` feature, e.g.
+//! `cargo rustc --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`
+//! then `wasm-bindgen --target web --out-dir pkg
+//! target/wasm32-unknown-unknown/release/bolt11.wasm`. <br>
+//! *Note*: the decoded payment requests have the shape of Core Lightning's `decodepay`, see
+//! `cln::DecodePay`, and the signing is left to a JavaScript callback so the node key never
+//! enters the module.
+
+use builder::InvoiceBuilder;
+use cln::DecodePay;
+use currency::Currency;
+use hashes::{DescriptionHash, PaymentHash};
+use js_sys::{Date, Function, Uint8Array};
+use payment_request::PaymentRequest;
+use secp256k1::{self, Message, RecoveryId, Signature};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::time::Duration;
+use types::Error;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPESCRIPT_DEFINITIONS: &str = r#"
+export interface EncodeFields {
+    /** Prefix of the payment request, `lnbc` if missing. */
+    prefix?: string;
+    /** Amount in millisatoshis, any amount if missing. */
+    amountMsat?: number;
+    /** Payment hash, hex encoded. */
+    paymentHash: string;
+    /** Description, either it or the description hash is required. */
+    description?: string;
+    /** Description hash, hex encoded. */
+    descriptionHash?: string;
+    /** Expiry in seconds. */
+    expiry?: number;
+    /** min_final_cltv_expiry in blocks. */
+    minFinalCltvExpiry?: number;
+    /** Request timestamp (UNIX format), now if missing. */
+    timestamp?: number;
+}
+
+export interface Fallback {
+    type?: string;
+    addr?: string;
+    hex: string;
+}
+
+export interface Route {
+    pubkey: string;
+    short_channel_id: string;
+    fee_base_msat: number;
+    fee_proportional_millionths: number;
+    cltv_expiry_delta: number;
+}
+
+export interface DecodedInvoice {
+    currency: string;
+    created_at: number;
+    expiry: number;
+    payee: string;
+    msatoshi?: number;
+    amount_msat?: string;
+    description?: string;
+    description_hash?: string;
+    min_final_cltv_expiry: number;
+    features?: string;
+    fallbacks?: Fallback[];
+    routes?: Route[][];
+    payment_metadata?: string;
+    payment_hash: string;
+    signature: string;
+}
+
+/** Signs the 32 bytes message, returns the 64 bytes compact signature followed by the recovery id. */
+export type SignCallback = (message: Uint8Array) => Uint8Array;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Fields of a payment request to encode, `EncodeFields`.
+    #[wasm_bindgen(typescript_type = "EncodeFields")]
+    pub type JsEncodeFields;
+
+    /// Decoded payment request, `DecodedInvoice`.
+    #[wasm_bindgen(typescript_type = "DecodedInvoice")]
+    pub type JsDecodedInvoice;
+
+    /// Signing function, `SignCallback`.
+    #[wasm_bindgen(typescript_type = "SignCallback")]
+    pub type JsSignCallback;
+}
+
+/// Fields of a payment request to encode, deserialized from `EncodeFields`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeFields {
+    /// Prefix of the payment request, `lnbc` if missing.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Amount in millisatoshis, any amount if missing.
+    #[serde(default)]
+    pub amount_msat: Option<u64>,
+    /// Payment hash, hex encoded.
+    pub payment_hash: String,
+    /// Description, either it or the description hash is required.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Description hash, hex encoded.
+    #[serde(default)]
+    pub description_hash: Option<String>,
+    /// Expiry in seconds.
+    #[serde(default)]
+    pub expiry: Option<u64>,
+    /// min_final_cltv_expiry in blocks.
+    #[serde(default)]
+    pub min_final_cltv_expiry: Option<u64>,
+    /// Request timestamp (UNIX format), now if missing.
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+}
+
+impl EncodeFields {
+    /// Build and sign the payment request.
+    /// # Arguments
+    /// `now` Timestamp used if the fields don't have one, the system clock isn't available in the
+    /// browser.<br>
+    /// `sign` Signing function of the message.
+    pub fn build_signed<F>(&self, now: u64, sign: F) -> Result<String, Error>
+    where
+        F: FnOnce(&Message) -> Result<(Signature, RecoveryId), secp256k1::Error>,
+    {
+        let prefix = self.prefix.as_ref().map_or("lnbc", String::as_str);
+        let currency = Currency::from_prefix(prefix)
+            .ok_or_else(|| Error::InvalidParameter(format!("invalid prefix {}", prefix)))?;
+        let builder = InvoiceBuilder::new(currency)
+            .payment_hash(self.payment_hash.parse::<PaymentHash>()?)
+            .timestamp(self.timestamp.unwrap_or(now));
+        let mut builder = match (&self.description, &self.description_hash) {
+            (Some(description), None) => builder.description(description),
+            (None, Some(hash)) => builder.description_hash(hash.parse::<DescriptionHash>()?),
+            _ => {
+                return Err(Error::InvalidParameter(
+                    "either description or descriptionHash is required".to_owned(),
+                ))
+            }
+        };
+        if let Some(amount) = self.amount_msat {
+            builder = builder.amount_msat(amount);
+        }
+        if let Some(seconds) = self.expiry {
+            builder = builder.expiry(Duration::from_secs(seconds));
+        }
+        if let Some(blocks) = self.min_final_cltv_expiry {
+            builder = builder.min_final_cltv_expiry(blocks);
+        }
+        builder.build_signed_with(sign)
+    }
+}
+
+/// Decode a payment request, throws if it's invalid.
+/// # Arguments
+/// `invoice` Payment request, with or without the `lightning:` scheme.
+#[wasm_bindgen]
+pub fn decode(invoice: &str) -> Result<JsDecodedInvoice, JsValue> {
+    let payment_request = invoice.parse::<PaymentRequest>().map_err(js_error)?;
+    DecodePay::new(&payment_request)
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map(JsCast::unchecked_into)
+        .map_err(JsValue::from)
+}
+
+/// Encode and sign a payment request, throws if the fields are invalid or the callback fails.
+/// # Arguments
+/// `fields` Fields of the payment request.<br>
+/// `sign` Callback signing the 32 bytes message, returning the 64 bytes compact signature
+/// followed by the recovery id.
+#[wasm_bindgen]
+pub fn encode(fields: JsEncodeFields, sign: JsSignCallback) -> Result<String, JsValue> {
+    let fields: EncodeFields = serde_wasm_bindgen::from_value(fields.into())?;
+    let sign: Function = sign.unchecked_into();
+    // the error thrown by the callback, reported instead of the secp256k1 one
+    let thrown = RefCell::new(None);
+    let now = (Date::now() / 1000.0) as u64;
+    fields
+        .build_signed(now, |message| {
+            sign_with(&sign, message).map_err(|e| {
+                *thrown.borrow_mut() = Some(e);
+                secp256k1::Error::InvalidSignature
+            })
+        })
+        .map_err(|e| thrown.borrow_mut().take().unwrap_or_else(|| js_error(e)))
+}
+
+// Helper for encode, call the JavaScript signing function.
+fn sign_with(sign: &Function, message: &Message) -> Result<(Signature, RecoveryId), JsValue> {
+    let message = Uint8Array::from(&message.serialize()[..]);
+    let signed = sign.call1(&JsValue::NULL, &message)?.dyn_into::<Uint8Array>()?;
+    if signed.length() != 65 {
+        return Err(js_sys::Error::new(&format!(
+            "the signature must be 65 bytes, got {}",
+            signed.length()
+        )).into());
+    }
+    let mut bytes = [0u8; 64];
+    signed.subarray(0, 64).copy_to(&mut bytes);
+    let recovery_id = RecoveryId::parse(signed.get_index(64))
+        .map_err(|e| js_sys::Error::new(&format!("invalid recovery id: {:?}", e)))?;
+    Ok((Signature::parse(&bytes), recovery_id))
+}
+
+// Helper for decode and encode, a JavaScript error from an error.
+fn js_error(e: Error) -> JsValue {
+    js_sys::Error::new(&e.to_string()).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1::SecretKey;
+    use utils::from_hex;
+
+    #[test]
+    fn build_signed() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&from_hex(
+            "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734",
+        ).unwrap());
+        let key = SecretKey::parse(&key).unwrap();
+        let fields = EncodeFields {
+            amount_msat: Some(250_000_000),
+            payment_hash: "0001020304050607080900010203040506070809000102030405060708090102"
+                .to_owned(),
+            description: Some("1 cup coffee".to_owned()),
+            expiry: Some(60),
+            ..EncodeFields::default()
+        };
+
+        let encoded = fields
+            .build_signed(1496314658, |message| secp256k1::sign(message, &key))
+            .unwrap();
+
+        assert_eq!(encoded, tx_ref);
+        assert!(EncodeFields {
+            description_hash: Some(fields.payment_hash.to_owned()),
+            ..fields.to_owned()
+        }.build_signed(1496314658, |message| secp256k1::sign(message, &key))
+            .is_err());
+    }
+}
@@ -0,0 +1,504 @@
+//! Differential testing against other implementations: a corpus is decoded with this crate and
+//! with `lightning-invoice`, or with `lncli` and `lightning-cli`, and the structured results are
+//! diffed to catch semantic divergences rather than only crashes. <br>
+//! *Note*: the command line tools are compared through the JSON of `PaymentRequest::to_lnd` and
+//! `PaymentRequest::to_cln`, only the fields of these views are diffed. Run them with
+//! `cargo test --features "ldk-interop cli" --test differential -- --ignored`.
+#![cfg(all(feature = "ldk-interop", feature = "cli"))]
+
+extern crate bolt11;
+extern crate lightning_invoice;
+extern crate serde_json;
+
+use bolt11::hashes::PaymentSecret;
+use bolt11::node_id::NodeId;
+use bolt11::payment_request::PaymentRequest;
+use bolt11::tag::ExtraHop;
+use bolt11::types::Error;
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::fmt;
+use std::process::Command;
+
+/// Implementation compared with this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Implementation {
+    /// The `lightning-invoice` crate.
+    Ldk,
+    /// lnd's `decodepayreq`, compared with `PaymentRequest::to_lnd`.
+    Lncli(CliCommand),
+    /// Core Lightning's `decodepay`, compared with `PaymentRequest::to_cln`.
+    LightningCli(CliCommand),
+}
+
+impl Implementation {
+    /// `lncli decodepayreq`, from the `PATH`.
+    fn lncli() -> Implementation {
+        Implementation::Lncli(CliCommand::new("lncli", &["decodepayreq"]))
+    }
+
+    /// `lightning-cli decodepay`, from the `PATH`.
+    fn lightning_cli() -> Implementation {
+        Implementation::LightningCli(CliCommand::new("lightning-cli", &["decodepay"]))
+    }
+
+    /// Decode the payment request with the implementation and diff the result with this crate.
+    /// <br>
+    /// *Note*: fails only if the command can't be run or its output is not JSON, a rejected
+    /// payment request is an `Outcome`.
+    /// # Arguments
+    /// * `invoice` - Encoded payment request.
+    fn compare(&self, invoice: &str) -> Result<Outcome, Error> {
+        let ours = PaymentRequest::decode(invoice);
+        match *self {
+            Implementation::Ldk => Ok(compare_ldk(ours, invoice)),
+            Implementation::Lncli(ref command) => command.compare(ours, invoice, |payment_request| {
+                serde_json::to_value(payment_request.to_lnd())
+            }),
+            Implementation::LightningCli(ref command) => {
+                command.compare(ours, invoice, |payment_request| {
+                    serde_json::to_value(payment_request.to_cln())
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Implementation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Implementation::Ldk => write!(f, "lightning-invoice"),
+            Implementation::Lncli(ref command) | Implementation::LightningCli(ref command) => {
+                write!(f, "{}", command.program)
+            }
+        }
+    }
+}
+
+/// Command decoding a payment request, given as its last argument, and printing JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CliCommand {
+    /// Program to run, e.g. `lncli`.
+    program: String,
+    /// Arguments before the payment request, e.g. `["--network=regtest", "decodepayreq"]`.
+    args: Vec<String>,
+}
+
+impl CliCommand {
+    /// Create a command.
+    /// # Arguments
+    /// * `program` - Program to run.
+    /// * `args` - Arguments before the payment request.
+    fn new(program: &str, args: &[&str]) -> CliCommand {
+        CliCommand {
+            program: program.to_owned(),
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+        }
+    }
+
+    // Helper for the command line tools, runs the command and diffs its JSON with our view.
+    fn compare<F>(
+        &self,
+        ours: Result<PaymentRequest, Error>,
+        invoice: &str,
+        view: F,
+    ) -> Result<Outcome, Error>
+    where
+        F: Fn(&PaymentRequest) -> Result<Value, serde_json::Error>,
+    {
+        let output = Command::new(&self.program).args(&self.args).arg(invoice).output()?;
+        let json_error = |e: serde_json::Error| {
+            Error::InvalidFormat(format!("{}: {}", self.program, e))
+        };
+        let theirs = if output.status.success() {
+            Ok(serde_json::from_slice::<Value>(&output.stdout).map_err(json_error)?)
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_owned())
+        };
+        Ok(match (ours, theirs) {
+            (Ok(payment_request), Ok(theirs)) => {
+                let ours = view(&payment_request).map_err(json_error)?;
+                Outcome::from_differences(diff_json(&ours, &theirs))
+            }
+            (Ok(_), Err(reason)) => Outcome::OnlyTheirsRejected(reason),
+            (Err(e), Ok(_)) => Outcome::OnlyOursRejected(e.to_string()),
+            (Err(_), Err(_)) => Outcome::BothRejected,
+        })
+    }
+}
+
+/// Field decoded differently by this crate and another implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Difference {
+    /// Name of the field.
+    field: String,
+    /// Value decoded by this crate.
+    ours: String,
+    /// Value decoded by the other implementation.
+    theirs: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} != {}", self.field, self.ours, self.theirs)
+    }
+}
+
+/// Result of decoding a payment request with both implementations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    /// Both decoded the same fields.
+    Agree,
+    /// Both rejected the payment request.
+    BothRejected,
+    /// Only this crate rejected the payment request, with the error.
+    OnlyOursRejected(String),
+    /// Only the other implementation rejected the payment request, with its error.
+    OnlyTheirsRejected(String),
+    /// Both decoded the payment request, with different fields.
+    Diverge(Vec<Difference>),
+}
+
+impl Outcome {
+    /// Check whether the implementations disagree, on the validity or on a field.
+    fn diverged(&self) -> bool {
+        *self != Outcome::Agree && *self != Outcome::BothRejected
+    }
+
+    // Helper for the comparisons, agreeing if nothing differs.
+    fn from_differences(differences: Vec<Difference>) -> Outcome {
+        if differences.is_empty() {
+            Outcome::Agree
+        } else {
+            Outcome::Diverge(differences)
+        }
+    }
+}
+
+/// Outcome for a payment request of the corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DifferentialResult {
+    /// Encoded payment request.
+    invoice: String,
+    /// Implementation compared with this crate.
+    implementation: String,
+    /// Outcome, or the error running the implementation.
+    outcome: Result<Outcome, String>,
+}
+
+impl DifferentialResult {
+    /// Check whether the implementations disagree, an error running the implementation being a
+    /// disagreement too.
+    fn diverged(&self) -> bool {
+        self.outcome.as_ref().map(Outcome::diverged).unwrap_or(true)
+    }
+}
+
+/// Decode every payment request of the corpus with this crate and with each implementation.
+/// # Arguments
+/// * `corpus` - Encoded payment requests.
+/// * `implementations` - Implementations compared with this crate.
+fn run(corpus: &[&str], implementations: &[Implementation]) -> Vec<DifferentialResult> {
+    corpus
+        .iter()
+        .flat_map(|invoice| {
+            implementations.iter().map(move |implementation| DifferentialResult {
+                invoice: (*invoice).to_owned(),
+                implementation: implementation.to_string(),
+                outcome: implementation.compare(invoice).map_err(|e| e.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Fields compared with `lightning-invoice`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Summary {
+    /// Node id of the payee, given or recovered, hex encoded.
+    payee: String,
+    /// Payment hash, hex encoded.
+    payment_hash: Option<String>,
+    /// Payment secret, hex encoded.
+    payment_secret: Option<String>,
+    /// Amount in millisatoshis.
+    amount_msat: Option<u64>,
+    /// Request timestamp (UNIX format).
+    timestamp: u64,
+    /// Expiry in seconds, the default if not specified.
+    expiry: u64,
+    /// min_final_cltv_expiry, the default if not specified.
+    min_final_cltv_expiry: u64,
+    /// Description.
+    description: Option<String>,
+    /// Description hash, hex encoded.
+    description_hash: Option<String>,
+    /// Fallback addresses.
+    fallback_addresses: Vec<String>,
+    /// Route hints, one per routing tag.
+    route_hints: Vec<Vec<ExtraHop>>,
+    /// Feature bits set.
+    features: Vec<usize>,
+}
+
+impl Summary {
+    /// Fields of a payment request decoded by this crate.
+    fn new(payment_request: &PaymentRequest) -> Summary {
+        Summary {
+            payee: NodeId::from(&payment_request.node_id).to_string(),
+            payment_hash: payment_request.payment_hash().map(|hash| hash.to_string()),
+            payment_secret: payment_request.payment_secret().map(|secret| secret.to_string()),
+            amount_msat: payment_request.amount,
+            timestamp: payment_request.timestamp,
+            expiry: payment_request.expiry_seconds(),
+            min_final_cltv_expiry: payment_request.min_final_cltv_expiry(),
+            description: payment_request.description(),
+            description_hash: payment_request.description_hash().map(|hash| hash.to_string()),
+            fallback_addresses: payment_request
+                .currency()
+                .map(|currency| payment_request.fallback_addresses(&currency))
+                .unwrap_or_default(),
+            route_hints: payment_request.route_hints(),
+            features: payment_request
+                .features()
+                .map(|features| features.bits())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Fields of a payment request decoded by `lightning-invoice`. <br>
+    /// *Note*: fails if a route hint hop can't be converted.
+    fn from_ldk(invoice: &Bolt11Invoice) -> Result<Summary, Error> {
+        let (description, description_hash) = match invoice.description() {
+            Bolt11InvoiceDescription::Direct(description) => {
+                (Some(description.clone().into_inner().0), None)
+            }
+            Bolt11InvoiceDescription::Hash(hash) => (None, Some(hash.0.to_string())),
+        };
+        Ok(Summary {
+            payee: invoice.get_payee_pub_key().to_string(),
+            payment_hash: Some(invoice.payment_hash().to_string()),
+            payment_secret: Some(PaymentSecret::from(invoice.payment_secret().0).to_string()),
+            amount_msat: invoice.amount_milli_satoshis(),
+            timestamp: invoice.duration_since_epoch().as_secs(),
+            expiry: invoice.expiry_time().as_secs(),
+            min_final_cltv_expiry: invoice.min_final_cltv_expiry_delta(),
+            description,
+            description_hash,
+            fallback_addresses: invoice
+                .fallback_addresses()
+                .iter()
+                .map(|address| address.to_string())
+                .collect(),
+            route_hints: invoice
+                .route_hints()
+                .iter()
+                .map(|route_hint| {
+                    route_hint
+                        .0
+                        .iter()
+                        .map(ExtraHop::try_from)
+                        .collect::<Result<Vec<_>, Error>>()
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            features: invoice
+                .features()
+                .map(|features| {
+                    features
+                        .le_flags()
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(i, byte)| {
+                            (0..8)
+                                .filter(move |bit| byte & (1 << bit) != 0)
+                                .map(move |bit| i * 8 + bit)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Fields differing from another summary.
+    fn diff(&self, theirs: &Summary) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        diff_field(&mut differences, "payee", &self.payee, &theirs.payee);
+        diff_field(&mut differences, "payment_hash", &self.payment_hash, &theirs.payment_hash);
+        diff_field(
+            &mut differences,
+            "payment_secret",
+            &self.payment_secret,
+            &theirs.payment_secret,
+        );
+        diff_field(&mut differences, "amount_msat", &self.amount_msat, &theirs.amount_msat);
+        diff_field(&mut differences, "timestamp", &self.timestamp, &theirs.timestamp);
+        diff_field(&mut differences, "expiry", &self.expiry, &theirs.expiry);
+        diff_field(
+            &mut differences,
+            "min_final_cltv_expiry",
+            &self.min_final_cltv_expiry,
+            &theirs.min_final_cltv_expiry,
+        );
+        diff_field(&mut differences, "description", &self.description, &theirs.description);
+        diff_field(
+            &mut differences,
+            "description_hash",
+            &self.description_hash,
+            &theirs.description_hash,
+        );
+        diff_field(
+            &mut differences,
+            "fallback_addresses",
+            &self.fallback_addresses,
+            &theirs.fallback_addresses,
+        );
+        diff_field(&mut differences, "route_hints", &self.route_hints, &theirs.route_hints);
+        diff_field(&mut differences, "features", &self.features, &theirs.features);
+        differences
+    }
+}
+
+// Helper for `Implementation::compare`, decodes with `lightning-invoice` and diffs the summaries.
+fn compare_ldk(ours: Result<PaymentRequest, Error>, invoice: &str) -> Outcome {
+    let theirs = invoice.parse::<Bolt11Invoice>().map_err(|e| e.to_string());
+    match (ours, theirs) {
+        (Ok(payment_request), Ok(invoice)) => match Summary::from_ldk(&invoice) {
+            Ok(theirs) => Outcome::from_differences(Summary::new(&payment_request).diff(&theirs)),
+            Err(e) => Outcome::OnlyOursRejected(format!("lightning-invoice conversion: {}", e)),
+        },
+        (Ok(_), Err(reason)) => Outcome::OnlyTheirsRejected(reason),
+        (Err(e), Ok(_)) => Outcome::OnlyOursRejected(e.to_string()),
+        (Err(_), Err(_)) => Outcome::BothRejected,
+    }
+}
+
+// Helper for `Summary::diff`, records the field if the values differ.
+fn diff_field<T: fmt::Debug + PartialEq>(
+    differences: &mut Vec<Difference>,
+    field: &str,
+    ours: &T,
+    theirs: &T,
+) {
+    if ours != theirs {
+        differences.push(Difference {
+            field: field.to_owned(),
+            ours: format!("{:?}", ours),
+            theirs: format!("{:?}", theirs),
+        });
+    }
+}
+
+/// Fields of our JSON view differing in the JSON of another implementation. <br>
+/// *Note*: only the fields of our view are compared, a field missing from theirs is `null`.
+/// # Arguments
+/// * `ours` - JSON object of our view.
+/// * `theirs` - JSON object printed by the other implementation.
+fn diff_json(ours: &Value, theirs: &Value) -> Vec<Difference> {
+    match *ours {
+        Value::Object(ref fields) => fields
+            .iter()
+            .filter_map(|(field, value)| {
+                let other = theirs.get(field).unwrap_or(&Value::Null);
+                if value == other {
+                    None
+                } else {
+                    Some(Difference {
+                        field: field.to_owned(),
+                        ours: value.to_string(),
+                        theirs: other.to_string(),
+                    })
+                }
+            })
+            .collect(),
+        _ if ours == theirs => Vec::new(),
+        _ => vec![Difference {
+            field: String::new(),
+            ours: ours.to_string(),
+            theirs: theirs.to_string(),
+        }],
+    }
+}
+
+const TX_REF: &str = "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5\
+    qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk9\
+    8klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u\
+    7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y\
+    00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzq9qrsgqdfjcdk6w3ak5pca9hwfwfh63zrrz06wwfya0ydlzpgzxkn5x\
+    agsqz7x9j4jwe7yj7vaf2k9lqsdk45kts2fd0fkr28am0u4w95tt2nsq76cqw0";
+
+#[test]
+fn ldk() {
+    let results = run(&[TX_REF, "lnbc1invalid"], &[Implementation::Ldk]);
+
+    assert_eq!(results[0].outcome, Ok(Outcome::Agree));
+    assert_eq!(results[1].outcome, Ok(Outcome::BothRejected));
+    assert!(results.iter().all(|result| !result.diverged()));
+    assert_eq!(results[0].implementation, "lightning-invoice");
+}
+
+#[test]
+fn summary_diff() {
+    let payment_request = PaymentRequest::decode(TX_REF).unwrap();
+    let ours = Summary::new(&payment_request);
+    let mut theirs = ours.clone();
+    theirs.min_final_cltv_expiry += 1;
+
+    assert_eq!(ours.payment_secret, Some("11".repeat(32)));
+    assert!(ours.diff(&ours).is_empty());
+    assert_eq!(
+        ours.diff(&theirs),
+        vec![Difference {
+            field: "min_final_cltv_expiry".to_owned(),
+            ours: ours.min_final_cltv_expiry.to_string(),
+            theirs: theirs.min_final_cltv_expiry.to_string(),
+        }]
+    );
+    assert!(Outcome::Diverge(ours.diff(&theirs)).diverged());
+}
+
+#[test]
+fn json_diff() {
+    let ours = serde_json::json!({ "expiry": "3600", "description": "coffee" });
+    let theirs = serde_json::json!({ "expiry": "60", "description": "coffee", "extra": 1 });
+
+    assert!(diff_json(&ours, &ours).is_empty());
+    assert_eq!(
+        diff_json(&ours, &theirs),
+        vec![Difference {
+            field: "expiry".to_owned(),
+            ours: "\"3600\"".to_owned(),
+            theirs: "\"60\"".to_owned(),
+        }]
+    );
+    assert_eq!(
+        diff_json(&ours, &serde_json::json!({}))
+            .iter()
+            .map(|difference| difference.theirs.as_str())
+            .collect::<Vec<_>>(),
+        vec!["null", "null"]
+    );
+}
+
+#[test]
+fn missing_command() {
+    let implementation = Implementation::Lncli(CliCommand::new("bolt11-missing-command", &[]));
+    let results = run(&[TX_REF], &[implementation]);
+
+    assert!(results[0].outcome.is_err());
+    assert!(results[0].diverged());
+}
+
+#[test]
+#[ignore]
+fn cli_tools() {
+    let implementations = [Implementation::lncli(), Implementation::lightning_cli()];
+    for result in run(&[TX_REF], &implementations) {
+        match result.outcome {
+            Ok(Outcome::Diverge(ref differences)) => {
+                let differences = differences.iter().map(Difference::to_string);
+                panic!("{}: {}", result.implementation, differences.collect::<Vec<_>>().join(", "))
+            }
+            ref outcome => assert!(!result.diverged(), "{}: {:?}", result.implementation, outcome),
+        }
+    }
+}